@@ -1,6 +1,9 @@
 use std::io::Result;
 
 fn main() -> Result<()> {
+    // NOTE: this unconditionally requires `protoc` on `PATH` regardless of
+    // the `pure-rust-wire` feature - see `src/wire/pb.rs`'s module docs for
+    // why that feature doesn't (yet) make this conditional.
     prost_build::compile_protos(
         &["wire.proto", "perun-remote.proto", "errors.proto"],
         &(["go-perun/wire/protobuf/", "src/wire/"]),
@@ -1,19 +1,26 @@
-//! State machine for the application logic. We need to do networking during the
-//! setup and application logic, so we need to do it while the main loop is
-//! running. Additionally, some steps cannot finish immediately. Since we
-//! (currently at least) don't have an async runtime in this demo the easiest
-//! way to do this is to have a state machine for the setup and application
-//! logic, too, which is contained in this module.
+//! Application logic for the demo: fetch blockchain info from the go-side
+//! config dealer, open a TCP connection to the other participant, propose or
+//! accept a channel, then run it. This needs to interleave with the main
+//! loop's `iface.poll` and some steps can't finish immediately (a TCP
+//! connect, waiting for a message), so the logic below runs as a single
+//! long-lived task on the [scheduler]. Blocking-looking calls like
+//! [recv_envelope_or_timeout] internally yield back to the scheduler until
+//! their data is ready (or a deadline passes), letting the setup/channel
+//! lifecycle read top-to-bottom instead of being spread across an explicit
+//! state enum.
+//!
+//! [scheduler]: crate::scheduler
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 
-use alloc::vec::Vec;
+use alloc::{collections::VecDeque, rc::Rc, string::String, vec::Vec};
 use perun::{
-    abiencode::types::U256,
+    abiencode::types::{Signature, U256},
     channel::{
-        fixed_size_payment::{Allocation, Balances, ParticipantBalances},
+        fixed_size_payment::{Allocation, Balances, ParticipantBalances, ProtocolVersion},
         Asset, ProposalBuildError,
     },
+    client::{ChannelFeatures, HandshakeError},
     messages::{
         ConversionError, FunderReplyMessage, LedgerChannelProposal, ParticipantMessage,
         WatcherReplyMessage,
@@ -23,7 +30,8 @@ use perun::{
         envelope::{self, Msg},
         Envelope,
     },
-    wire::ProtoBufEncodingLayer,
+    sig::{Signer, SigningError},
+    wire::{Identity, ProtoBufEncodingLayer},
     Address, Hash, InvalidProposal, PerunClient,
 };
 use prost::{DecodeError, Message};
@@ -38,14 +46,34 @@ use smoltcp::{
 
 use crate::{
     bus::Bus,
-    channel::{self, Channel},
+    channel::{self, Channel, Retry, UpdateId},
+    scheduler::{OwnedStack, Scheduler, WaitRequest, WaitResult},
+    tls::{self, Role, TlsSocket},
 };
 
-/// We are currently copying from the rx-buffer to a slice for decoding
-/// protobuf, because that needs a single consecutive area of memory (see
-/// comments in [`try_recv`] for details).
+/// Cap on a single frame's size (2-byte length prefix included), so that a
+/// peer claiming an enormous length can't make us grow [Reassembly::rec_buf]
+/// without bound. This does not need to match any buffer smoltcp itself
+/// allocates - see [`try_recv`] for details.
 pub const MAX_MESSAGE_SIZE: usize = 510;
 
+/// How long a blocking step (TCP connect, TLS handshake, waiting for a
+/// specific reply) is allowed to take before we give up on it and return
+/// [Error::Timeout], so a peer that never answers can't wedge this task
+/// forever.
+const CONNECT_TIMEOUT_MILLIS: u64 = 10_000;
+const HANDSHAKE_TIMEOUT_MILLIS: u64 = 10_000;
+const PROPOSAL_TIMEOUT_MILLIS: u64 = 15_000;
+
+/// How many `run_active_channel` ticks (one per loop iteration, same unit
+/// [Retry::deadline_ticks] uses) of not hearing anything from the
+/// participant - including their own keepalive traffic - before we send a
+/// `PingMsg` to check whether they're still there.
+const KEEPALIVE_IDLE_TICKS: u64 = 200;
+/// How many ticks to wait for a `PongMsg` reply to our `PingMsg` before
+/// treating the peer as disconnected and attempting [reconnect_and_resync].
+const KEEPALIVE_PONG_TIMEOUT_TICKS: u64 = 50;
+
 /// Configuration for the demo: Peers and where to find the
 /// participant/watcher/funder.
 pub struct Config {
@@ -54,79 +82,312 @@ pub struct Config {
     pub service_server: (IpAddress, u16),
     pub listen_port: u16,
     pub participants: [&'static str; 2],
+    /// The on-chain [Address] the participant handshake (see
+    /// [PerunClient::send_handshake_msg][perun::PerunClient::send_handshake_msg])
+    /// expects the other participant to control.
+    pub peer_address: Address,
 }
 
-/// State machine for the demo logic: Fetch information about the blockchain
-/// from the go-side, create TCP socket with participant and propose channel.
-pub struct Application<'cl, DeviceT>
+/// Coarse phase the demo is in, tracked only so [Application::update],
+/// [Application::force_close], [Application::shutdown] and
+/// [Application::propose_channel] can reject calls that make no sense right
+/// now, the way they used to by matching on `ApplicationState`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Setup,
+    Listening,
+    Active,
+}
+
+/// A request queued by [Application::update]/[force_close]/[propose_channel]
+/// for the running task to pick up. Queued rather than acted on directly
+/// because those methods are called from the main loop's button handling,
+/// not from inside the task.
+#[derive(Clone, Copy)]
+enum Command {
+    Update {
+        id: UpdateId,
+        amount: U256,
+        is_final: bool,
+        retry: Retry,
+    },
+    ForceClose,
+    ProposeChannel,
+    Shutdown,
+}
+
+/// A notable channel-lifecycle occurrence, queued by the task for the host to
+/// drain via [Application::pop_event] instead of having to infer what
+/// happened from `Phase`/[Application::withdraw_ready]. Mirrors LDK's
+/// `Event`/`EventsProvider` model.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// An incoming `LedgerChannelProposalMsg` arrived and was auto-accepted.
+    ChannelProposalReceived,
+    /// The peer accepted the channel proposal we sent.
+    ProposalAccepted,
+    /// The peer rejected the channel proposal we sent.
+    ProposalRejected { reason: String },
+    /// Both participants funded the channel; it's now active.
+    Funded,
+    /// The peer proposed a channel update we've applied.
+    UpdateReceived,
+    /// An update we proposed was rejected, or timed out retrying, and
+    /// [Channel::update]'s [Retry] budget is exhausted.
+    UpdateRejected { reason: String },
+    /// [Channel::force_close] registered an on-chain dispute, either because
+    /// we called it or because the peer did and the Watcher notified us.
+    DisputeRaised,
+    /// The channel closed, cooperatively (see [Channel::shutdown]) or via
+    /// dispute (see [Channel::force_close]).
+    Closed,
+    /// A disputed channel's challenge window has elapsed (see
+    /// [Application::notify_block_height]). Named to match what the host
+    /// actually wants to know - that funds can be withdrawn - even though
+    /// this demo has no feed for the withdrawal transaction itself
+    /// completing, only for its dispute timeout passing.
+    WithdrawComplete,
+}
+
+/// A [Channel::force_close] registered on-chain, tracked so
+/// [Application::notify_block_height] knows once its challenge window has
+/// elapsed and withdrawal is safe. See [Application::withdraw_ready].
+///
+/// [Channel::force_close]: crate::channel::Channel::force_close
+#[derive(Debug, Clone, Copy)]
+struct Dispute {
+    /// Block height as of [run_active_channel] noticing the channel
+    /// force-closed.
+    registered_at_block: u64,
+    challenge_duration: u64,
+    eth_holder: Address,
+    withdraw_receiver: Address,
+}
+
+/// Channel-proposal parameters read from the config dealer at startup (see
+/// [connect_config_dealer_and_read_config]), rather than hard-coded into the
+/// firmware image.
+pub struct ChannelParams {
+    pub eth_holder: Address,
+    pub withdraw_receiver: Address,
+    pub chain_id: U256,
+    pub challenge_duration: u64,
+    pub init_balances: [U256; 2],
+}
+
+impl ChannelParams {
+    const FLAG_CHAIN_ID: u8 = 1 << 0;
+    const FLAG_CHALLENGE_DURATION: u8 = 1 << 1;
+    const FLAG_INIT_BALANCES: u8 = 1 << 2;
+
+    /// Decode the config dealer's message: a required 20-byte `eth_holder`
+    /// and 20-byte `withdraw_receiver`, followed by a flags byte and then
+    /// only the fields whose flag bit is set, each falling back to this
+    /// demo's previous hard-coded value when absent - mirroring how a config
+    /// node resolves optional keys with fallbacks. This is a small ad-hoc
+    /// layout rather than a generated `perunwire` message: the dealer is a
+    /// tool internal to this demo, not part of the go-perun wire protocol,
+    /// so there's no `.proto` schema for it to stay compatible with.
+    fn decode(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() < 41 {
+            return Err(Error::InvalidState);
+        }
+        let eth_holder = Address(buf[..20].try_into().unwrap());
+        let withdraw_receiver = Address(buf[20..40].try_into().unwrap());
+        let flags = buf[40];
+        let mut offset = 41;
+
+        let chain_id = if flags & Self::FLAG_CHAIN_ID != 0 {
+            let bytes = buf.get(offset..offset + 32).ok_or(Error::InvalidState)?;
+            offset += 32;
+            U256::from_big_endian(bytes)
+        } else {
+            1337.into() // Default chainID when using a SimulatedBackend from go-ethereum or Ganache
+        };
+
+        let challenge_duration = if flags & Self::FLAG_CHALLENGE_DURATION != 0 {
+            let bytes = buf.get(offset..offset + 8).ok_or(Error::InvalidState)?;
+            offset += 8;
+            u64::from_be_bytes(bytes.try_into().unwrap())
+        } else {
+            25
+        };
+
+        let init_balances = if flags & Self::FLAG_INIT_BALANCES != 0 {
+            let bytes = buf.get(offset..offset + 64).ok_or(Error::InvalidState)?;
+            [
+                U256::from_big_endian(&bytes[..32]),
+                U256::from_big_endian(&bytes[32..64]),
+            ]
+        } else {
+            [100_000.into(), 100_000.into()]
+        };
+
+        Ok(Self {
+            eth_holder,
+            withdraw_receiver,
+            chain_id,
+            challenge_duration,
+            init_balances,
+        })
+    }
+}
+
+/// Per-socket state for reassembling a length-prefixed frame out of however
+/// many decrypted bytes happen to be queued on any given call to
+/// [try_recv]. `rec_size` is the total frame size (2-byte length prefix
+/// included) we're currently collecting, or 0 if we haven't seen a complete
+/// length prefix yet.
+#[derive(Default)]
+struct Reassembly {
+    rec_size: usize,
+    rec_buf: Vec<u8>,
+}
+
+/// Per-socket token bucket bounding how much a remote peer can throw at us
+/// before we've even decoded anything. Tracked per socket rather than per
+/// remote address: each of this demo's sockets only ever has one remote
+/// connected to it at a time (a fresh connection attempt replaces whatever
+/// was there, see [abort_socket]'s callers), so a per-socket bucket already
+/// means "per currently-connected remote".
+struct RateLimiter {
+    window_start: u64,
+    connection_attempts: u32,
+    bytes_received: usize,
+}
+
+impl RateLimiter {
+    const WINDOW_MILLIS: u64 = 1000;
+    const MAX_CONNECTION_ATTEMPTS_PER_WINDOW: u32 = 5;
+    const MAX_BYTES_PER_WINDOW: usize = 8 * MAX_MESSAGE_SIZE;
+
+    fn new(now_millis: u64) -> Self {
+        Self {
+            window_start: now_millis,
+            connection_attempts: 0,
+            bytes_received: 0,
+        }
+    }
+
+    fn roll_window(&mut self, now_millis: u64) {
+        if now_millis.saturating_sub(self.window_start) >= Self::WINDOW_MILLIS {
+            self.window_start = now_millis;
+            self.connection_attempts = 0;
+            self.bytes_received = 0;
+        }
+    }
+
+    fn note_connection_attempt(&mut self, now_millis: u64) -> Result<(), Error> {
+        self.roll_window(now_millis);
+        self.connection_attempts += 1;
+        if self.connection_attempts > Self::MAX_CONNECTION_ATTEMPTS_PER_WINDOW {
+            return Err(Error::RateLimited);
+        }
+        Ok(())
+    }
+
+    fn note_bytes_received(&mut self, now_millis: u64, count: usize) -> Result<(), Error> {
+        self.roll_window(now_millis);
+        self.bytes_received += count;
+        if self.bytes_received > Self::MAX_BYTES_PER_WINDOW {
+            return Err(Error::RateLimited);
+        }
+        Ok(())
+    }
+}
+
+/// Everything the long-running task needs, shared via `Rc` so it can be
+/// cloned into the task's closures without fighting the borrow checker over
+/// `Application`'s own lifetime (the same trick [TlsSocket] already uses, see
+/// [crate::tls]).
+struct Ctx<'cl, DeviceT>
 where
     DeviceT: for<'d> Device<'d>,
 {
-    state: ApplicationState<'cl, DeviceT>,
     iface: &'cl RefCell<Interface<'cl, DeviceT>>,
     participant_handle: SocketHandle,
+    /// Dedicated socket for dialing the other participant, used alongside
+    /// `participant_handle` (which stays listening) so we can propose a
+    /// channel without giving up our ability to accept the other side's
+    /// dial-back. See [propose_to_peer] for the simultaneous-open handling
+    /// this makes possible.
+    participant_dial_handle: SocketHandle,
     service_handle: SocketHandle,
-    config: Config,
-    rng: StdRng,
-    client: &'cl PerunClient<ProtoBufEncodingLayer<Bus<'cl, DeviceT>>>,
+    /// Whichever of `participant_handle`/`participant_dial_handle` carries
+    /// the conversation the rest of the code should treat as "the"
+    /// participant socket right now. Shared with `Bus`, which has no socket
+    /// handle of its own to pick from in `send_to_participant`.
+    active_participant: Rc<Cell<SocketHandle>>,
+    tls_participant: TlsSocket,
+    tls_participant_dial: TlsSocket,
+    tls_service: TlsSocket,
+    client: &'cl PerunClient<ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>,
     addr: Address,
+    config: Config,
+    rng: RefCell<StdRng>,
+    commands: RefCell<VecDeque<Command>>,
+    phase: Cell<Phase>,
+    reassembly_participant: RefCell<Reassembly>,
+    reassembly_participant_dial: RefCell<Reassembly>,
+    reassembly_service: RefCell<Reassembly>,
+    rate_limiter_participant: RefCell<RateLimiter>,
+    rate_limiter_participant_dial: RefCell<RateLimiter>,
+    rate_limiter_service: RefCell<RateLimiter>,
+    /// Latches "we've already counted this listen round's inbound
+    /// connection", so repeatedly observing the same still-open connection
+    /// in [listen_for_peer]'s poll loop doesn't inflate its attempt count.
+    /// Reset every time `listen_for_peer` starts a fresh listening round.
+    incoming_connection_counted: Cell<bool>,
+    /// Wall-clock time (matching `Application::poll`'s `now_millis`) as of
+    /// the start of the current tick, used by [wait_until_or_timeout] and
+    /// [RateLimiter] to measure deadlines/windows without threading time
+    /// through every call.
+    now_millis: Cell<u64>,
+    /// Ticks elapsed since [run_active_channel] started, used to drive the
+    /// keepalive timers below (same unit [Retry::deadline_ticks] uses).
+    active_tick: Cell<u64>,
+    /// `active_tick` as of the last time we heard anything from the
+    /// participant (including their own keepalive traffic). Compared against
+    /// [KEEPALIVE_IDLE_TICKS] to decide when to send our own `PingMsg`.
+    last_participant_activity_tick: Cell<u64>,
+    /// `active_tick` at which we sent our most recent `PingMsg` still
+    /// awaiting a `PongMsg`, or `None` if no ping is currently outstanding.
+    /// Compared against [KEEPALIVE_PONG_TIMEOUT_TICKS] to decide when to
+    /// treat the peer as disconnected.
+    ping_sent_tick: Cell<Option<u64>>,
+    /// Current on-chain block height, as last pushed in via
+    /// [Application::notify_block_height]. `0` until the host calls it at
+    /// least once. Mirrors a chain `Confirm`-style feed: the host pushes new
+    /// block heights in as it observes them, rather than this demo polling a
+    /// chain client itself (there is none here).
+    block_height: Cell<u64>,
+    /// The dispute (if any) [run_active_channel] is currently timing out,
+    /// populated when it notices a channel force-close. Cleared by
+    /// [Application::notify_block_height] once the challenge window elapses
+    /// and `withdraw_ready` below is set, or immediately if the channel
+    /// instead closed cooperatively.
+    dispute: Cell<Option<Dispute>>,
+    /// `Some((eth_holder, withdraw_receiver))` once a disputed channel's
+    /// challenge window has elapsed - see [Application::withdraw_ready].
+    /// Reset to `None` as soon as a new dispute starts being tracked.
+    withdraw_ready: Cell<Option<(Address, Address)>>,
+    /// Queued for the host to drain via [Application::pop_event].
+    events: RefCell<VecDeque<Event>>,
+    /// Set by the task if it returns an `Err`, surfaced to the caller of
+    /// [Application::poll] the next time it's called (the task itself has
+    /// already ended by then, same as the old code effectively stopping
+    /// making progress once `poll()` started returning `Err` every call).
+    error: RefCell<Option<Error>>,
 }
 
-/// Enum to represent the states the Application can be in.
-enum ApplicationState<'cl, DeviceT>
+/// State machine for the demo logic: Fetch information about the blockchain
+/// from the go-side, create TCP socket with participant and propose channel.
+pub struct Application<'cl, DeviceT>
 where
     DeviceT: for<'d> Device<'d>,
 {
-    /// Initial state, nothing has been done yet, the application was just
-    /// started. Immediately transition to `ConnectingToConfigDealer`
-    InitialState,
-    /// Setting up the TCP connection to get info about the blockchain this demo
-    /// is using (eth-holder and withdraw_receiver). As soon as the connection is
-    /// established we read from it and go to `ClosingParticipantSocket`.
-    ConnectingToConfigDealer,
-    /// We have everything we need, wait until the setup connection is closed,
-    /// then setup TCP listening and transition to `Listening`
-    ClosingSockets {
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    },
-    /// Wait and do nothing until someone presses a button or we receive a tcp
-    /// connection attempt, then transition into `Connecting` or
-    /// `WaitForProposal` respectively. In both cases connect to the
-    /// funder/watcher.
-    Listening {
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    },
-    /// We have received a connection and gotten a handshake (and sent a
-    /// response handshake). Wait until we have connected to the watcher/funder
-    /// and receive a channel proposal, then accept it and transition into
-    /// `Active`.
-    WaitForProposal {
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    },
-    /// Setting up the TCP connections to other participant (p2p) and remote
-    /// funder/watcher. Once the connections are both established send the
-    /// handshake message and transition to `WaitForHandshake`.
-    Connecting {
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    },
-    /// Wait until we receive the handshake response, then propose a channel and
-    /// transition to `Active`.
-    WaitForHandshake {
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    },
-    /// We have an open channel, the logic of which is handled in a separate
-    /// state machine. If the channel closes transition to
-    /// `ClosingParticipantSocket`.
-    Active {
-        eth_holder: Address,
-        withdraw_receiver: Address,
-        channel: Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>>,
-    },
+    ctx: Rc<Ctx<'cl, DeviceT>>,
+    scheduler: Scheduler<'cl>,
 }
 
 #[derive(Debug)]
@@ -141,6 +402,19 @@ pub enum Error {
     InvalidState,
     MessageLargerThanRxBuffer(usize),
     ProposalBuildError(ProposalBuildError),
+    Tls(tls::Error),
+    /// A blocking step (connect, handshake, proposal exchange, ...) didn't
+    /// complete before its deadline; the offending socket has already been
+    /// aborted by the time this is returned.
+    Timeout,
+    /// A peer exceeded its connection-attempt or byte-rate budget; its
+    /// socket has already been aborted by the time this is returned.
+    RateLimited,
+    /// The participant handshake failed - either we couldn't trust the
+    /// peer's [ParticipantMessage::AuthResponse] ([HandshakeError]), or
+    /// signing our own ([SigningError]).
+    HandshakeFailed(HandshakeError),
+    SigningFailed(SigningError),
 }
 
 impl From<smoltcp::Error> for Error {
@@ -173,6 +447,21 @@ impl From<ProposalBuildError> for Error {
         Self::ProposalBuildError(e)
     }
 }
+impl From<tls::Error> for Error {
+    fn from(e: tls::Error) -> Self {
+        Self::Tls(e)
+    }
+}
+impl From<HandshakeError> for Error {
+    fn from(e: HandshakeError) -> Self {
+        Self::HandshakeFailed(e)
+    }
+}
+impl From<SigningError> for Error {
+    fn from(e: SigningError) -> Self {
+        Self::SigningFailed(e)
+    }
+}
 
 enum ServiceReplyMessage {
     Watcher(WatcherReplyMessage),
@@ -185,641 +474,1231 @@ where
 {
     pub fn new(
         participant_handle: SocketHandle,
+        participant_dial_handle: SocketHandle,
         service_handle: SocketHandle,
         config: Config,
         rng: StdRng,
         addr: Address,
-        client: &'cl PerunClient<ProtoBufEncodingLayer<Bus<'cl, DeviceT>>>,
+        client: &'cl PerunClient<ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>,
         iface: &'cl RefCell<Interface<'cl, DeviceT>>,
+        tls_participant: TlsSocket,
+        tls_participant_dial: TlsSocket,
+        tls_service: TlsSocket,
+        active_participant_handle: Rc<Cell<SocketHandle>>,
     ) -> Self {
-        Self {
-            state: ApplicationState::InitialState,
+        let ctx = Rc::new(Ctx {
+            iface,
             participant_handle,
+            participant_dial_handle,
             service_handle,
-            config,
-            rng,
+            active_participant: active_participant_handle,
+            tls_participant,
+            tls_participant_dial,
+            tls_service,
             client,
             addr,
-            iface,
-        }
+            config,
+            rng: RefCell::new(rng),
+            commands: RefCell::new(VecDeque::new()),
+            phase: Cell::new(Phase::Setup),
+            reassembly_participant: RefCell::new(Reassembly::default()),
+            reassembly_participant_dial: RefCell::new(Reassembly::default()),
+            reassembly_service: RefCell::new(Reassembly::default()),
+            rate_limiter_participant: RefCell::new(RateLimiter::new(0)),
+            rate_limiter_participant_dial: RefCell::new(RateLimiter::new(0)),
+            rate_limiter_service: RefCell::new(RateLimiter::new(0)),
+            incoming_connection_counted: Cell::new(false),
+            now_millis: Cell::new(0),
+            active_tick: Cell::new(0),
+            last_participant_activity_tick: Cell::new(0),
+            ping_sent_tick: Cell::new(None),
+            block_height: Cell::new(0),
+            dispute: Cell::new(None),
+            withdraw_ready: Cell::new(None),
+            events: RefCell::new(VecDeque::new()),
+            error: RefCell::new(None),
+        });
+
+        let mut scheduler = Scheduler::new();
+        let task_ctx = ctx.clone();
+        scheduler.spawn(OwnedStack::new(), move |yielder| {
+            if let Err(e) = run(&task_ctx, yielder) {
+                *task_ctx.error.borrow_mut() = Some(e);
+            }
+        });
+
+        Self { ctx, scheduler }
     }
 
-    fn connect_config_dealer(&mut self) -> Result<(), Error> {
-        let mut iface = self.iface.borrow_mut();
-        let (socket, cx) = iface.get_socket_and_context::<TcpSocket>(self.participant_handle);
-        socket.connect(
-            cx,
-            self.config.config_server,
-            (IpAddress::Unspecified, self.get_ethemeral_port()),
-        )?;
+    /// Main polling function. Call this regularly, for example always after
+    /// polling the network interface. `now_millis` should be a monotonic
+    /// clock (the same one driving `iface.poll`'s `Instant`).
+    pub fn poll(&mut self, now_millis: u64) -> Result<(), Error> {
+        self.ctx.now_millis.set(now_millis);
+        {
+            let mut iface = self.ctx.iface.borrow_mut();
+            self.ctx.tls_participant.poll(
+                &mut iface,
+                self.ctx.participant_handle,
+                &mut self.ctx.rng.borrow_mut(),
+            )?;
+            self.ctx.tls_participant_dial.poll(
+                &mut iface,
+                self.ctx.participant_dial_handle,
+                &mut self.ctx.rng.borrow_mut(),
+            )?;
+            self.ctx.tls_service.poll(
+                &mut iface,
+                self.ctx.service_handle,
+                &mut self.ctx.rng.borrow_mut(),
+            )?;
+        }
 
-        self.state = ApplicationState::ConnectingToConfigDealer;
-        Ok(())
+        self.scheduler.poll(now_millis);
+
+        match self.ctx.error.borrow_mut().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
 
-    fn wait_connected_and_read_config(&mut self) -> Result<(), Error> {
-        let mut iface = self.iface.borrow_mut();
-        let socket = iface.get_socket::<TcpSocket>(self.participant_handle);
-        if socket.is_active() && socket.can_recv() {
-            // Try reading from the socket. Returns Err if there is something
-            // wrong with the socket (unexpected tcp state). Returns None if not
-            // enough bytes are available (we only received partial data for
-            // some reason).
-            //
-            // Note that this will fail if we are at a ringbuffer boundry, see
-            // `try_recv` for details. In this demo this is not a problem
-            // because the rx_buffer is always empty when this function is
-            // called and can thus always fit 40 bytes in a consecutive slice.
-            if let Some((eth_holder, withdraw_receiver)) = socket.recv(|x| {
-                if x.len() >= 40 {
-                    let eth_holder = Address(x[..20].try_into().unwrap());
-                    let withdraw_receiver = Address(x[20..40].try_into().unwrap());
-                    (40, Some((eth_holder, withdraw_receiver)))
-                } else {
-                    (0, None)
-                }
-            })? {
-                self.state = ApplicationState::ClosingSockets {
-                    eth_holder,
-                    withdraw_receiver,
-                };
-                socket.close();
+    /// Feed in the current on-chain block height, so a registered dispute's
+    /// challenge window (see [Channel::force_close]) can be timed out and
+    /// [Application::withdraw_ready] becomes observable once it's safe to
+    /// withdraw. Call this whenever the host learns of a new block, the same
+    /// way [Application::poll] is called whenever it learns of a new
+    /// `now_millis` - independent of `Phase`, since a dispute can outlive the
+    /// channel that raised it.
+    pub fn notify_block_height(&mut self, height: u64) {
+        self.ctx.block_height.set(height);
+        if let Some(dispute) = self.ctx.dispute.get() {
+            if height >= dispute.registered_at_block + dispute.challenge_duration {
+                self.ctx
+                    .withdraw_ready
+                    .set(Some((dispute.eth_holder, dispute.withdraw_receiver)));
+                self.ctx.dispute.set(None);
+                self.ctx
+                    .events
+                    .borrow_mut()
+                    .push_back(Event::WithdrawComplete);
             }
         }
-        Ok(())
     }
 
-    fn wait_connections_closed(
+    /// `Some((eth_holder, withdraw_receiver))` once a channel that disputed
+    /// via [Channel::force_close] has had its challenge window demonstrably
+    /// elapse (per [Application::notify_block_height]), rather than assuming
+    /// it on the first `DisputeAck`. `None` before that, and again once a
+    /// later dispute starts being tracked.
+    pub fn withdraw_ready(&self) -> Option<(Address, Address)> {
+        self.ctx.withdraw_ready.get()
+    }
+
+    /// Pop the oldest queued [Event], or `None` if there isn't one. Call this
+    /// in a loop (e.g. after [Application::poll]) to drain everything that
+    /// happened since the last drain - mirrors LDK's
+    /// `EventsProvider::process_pending_events`.
+    pub fn pop_event(&mut self) -> Option<Event> {
+        self.ctx.events.borrow_mut().pop_front()
+    }
+
+    /// Mint a fresh caller-chosen [UpdateId] for a new logical payment.
+    /// Reuse the same id across repeated `update()` calls (e.g. after a
+    /// dropped connection, if it's unclear whether the earlier call went
+    /// through) to get [Channel::update]'s idempotent no-op/prior-result
+    /// behavior instead of risking a double payment.
+    pub fn generate_update_id(&self) -> UpdateId {
+        UpdateId(self.ctx.rng.borrow_mut().gen())
+    }
+
+    /// Send `amount` WEI to the other channel participant to demonstrate
+    /// channel updates, automatically retried per `retry` if rejected or
+    /// unacknowledged. If the channel is not currently active it will return
+    /// an error. See [generate_update_id][Application::generate_update_id]
+    /// for how to pick `id`.
+    pub fn update(
         &mut self,
-        eth_holder: Address,
-        withdraw_receiver: Address,
+        id: UpdateId,
+        amount: U256,
+        is_final: bool,
+        retry: Retry,
     ) -> Result<(), Error> {
-        // Only continue if the sockets are free (i.e. closed) and avaliable.
-        // Alternatively we could use `socket.abort()`, resulting in a
-        // non-graceful shutdown but slightly faster transition times. One
-        // downside of doing it this way is that a malicious config dealer could
-        // DoS us by never sending a Fin, but since the config dealer is only
-        // necessary for the demo (which can't use hard-coded addresses) this is
-        // not a problem.
-        //
-        // We have to get the socket multiple times because of the lifetimes in
-        // `iface.get_socket` and we can only start the connection if both
-        // sockets are free.
-        let mut iface = self.iface.borrow_mut();
-        let ssocket_active = iface
-            .get_socket::<TcpSocket>(self.service_handle)
-            .is_active();
-        let psocket = iface.get_socket::<TcpSocket>(self.participant_handle);
-        if !ssocket_active && !psocket.is_active() {
-            psocket.listen(self.config.listen_port)?;
-            self.state = ApplicationState::Listening {
-                eth_holder,
-                withdraw_receiver,
-            };
+        if self.ctx.phase.get() != Phase::Active {
+            return Err(Error::InvalidState);
         }
+        self.ctx.commands.borrow_mut().push_back(Command::Update {
+            id,
+            amount,
+            is_final,
+            retry,
+        });
         Ok(())
     }
 
-    fn check_incomming_connection(
-        &mut self,
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    ) -> Result<(), Error> {
-        // Scope iface because `try_recv_participant_msg` needs to borrow it, too.
-        {
-            let mut iface = self.iface.borrow_mut();
-            let psocket = iface.get_socket::<TcpSocket>(self.participant_handle);
-            if !psocket.is_open() || !psocket.may_recv() {
-                // We don't have a connection, yet
-                return Ok(());
-            }
+    /// Force close the channel by sending a DisputeRequest to the Watcher.
+    pub fn force_close(&mut self) -> Result<(), Error> {
+        if self.ctx.phase.get() != Phase::Active {
+            return Err(Error::InvalidState);
         }
+        self.ctx
+            .commands
+            .borrow_mut()
+            .push_back(Command::ForceClose);
+        Ok(())
+    }
 
-        let env: Envelope = match self.try_recv(self.participant_handle)? {
-            Some(env) => env,
-            None => return Ok(()),
-        };
+    /// Cooperatively close the channel: agree a final state with the other
+    /// participant and exchange `ShutdownMsg`s, falling back to
+    /// [Application::force_close] if the peer stops responding partway
+    /// through. See [Channel::shutdown].
+    pub fn shutdown(&mut self) -> Result<(), Error> {
+        if self.ctx.phase.get() != Phase::Active {
+            return Err(Error::InvalidState);
+        }
+        self.ctx.commands.borrow_mut().push_back(Command::Shutdown);
+        Ok(())
+    }
 
-        match env.msg {
-            Some(envelope::Msg::AuthResponseMsg(_)) => {}
-            Some(_) => return Err(Error::UnexpectedMsg),
-            None => return Err(Error::InvalidState),
+    /// Propose a new channel to the other participant.
+    pub fn propose_channel(&mut self) -> Result<(), Error> {
+        if self.ctx.phase.get() != Phase::Listening {
+            return Err(Error::InvalidState);
         }
+        self.ctx
+            .commands
+            .borrow_mut()
+            .push_back(Command::ProposeChannel);
+        Ok(())
+    }
+}
 
-        let my_wire_address = self.config.participants[0].into();
+/// Suspend the current task until the next scheduler tick, unconditionally.
+/// Used by the blocking IO helpers below, which re-check their own condition
+/// every time they're resumed instead of expressing it as an `event`
+/// predicate (simpler to write, at the cost of polling every tick instead of
+/// only when something changed - fine at this scale).
+fn yield_tick<'cl>(yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>) -> WaitResult {
+    yielder.suspend(WaitRequest {
+        event: None,
+        timeout: None,
+    })
+}
 
-        if env.recipient[..] != self.config.participants[0].as_bytes()[..] {
-            return Err(Error::UnexpectedMsg);
+/// Suspend the current task until `event` returns true (or `timeout_millis`
+/// elapses, if given).
+fn wait_until<'cl>(
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    timeout_millis: Option<u64>,
+    event: impl Fn() -> bool + 'cl,
+) -> WaitResult {
+    yielder.suspend(WaitRequest {
+        event: Some(alloc::boxed::Box::new(event)),
+        timeout: timeout_millis,
+    })
+}
+
+/// Like [wait_until], but bounded to `timeout_millis` from now (on
+/// [Ctx::now_millis]) rather than waiting forever, returning
+/// [Error::Timeout] if the deadline passes first.
+fn wait_until_or_timeout<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    timeout_millis: u64,
+    event: impl Fn() -> bool + 'cl,
+) -> Result<(), Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let deadline = ctx.now_millis.get() + timeout_millis;
+    match wait_until(yielder, Some(deadline), event) {
+        WaitResult::Completed => Ok(()),
+        WaitResult::TimedOut => Err(Error::Timeout),
+    }
+}
+
+fn get_ethemeral_port<DeviceT: for<'d> Device<'d>>(ctx: &Ctx<DeviceT>) -> u16 {
+    const MIN: u16 = 49152;
+    const MAX: u16 = 65535;
+    // Note: This is not evenly distributed but sufficient for what we need.
+    MIN + (ctx.rng.borrow_mut().next_u32() as u16) % (MAX - MIN)
+}
+
+/// Start connecting `handle` to `remote` and block until the TCP handshake
+/// completes.
+fn connect<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    handle: SocketHandle,
+    remote: (IpAddress, u16),
+) -> Result<(), Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    {
+        let port = get_ethemeral_port(ctx);
+        let mut iface = ctx.iface.borrow_mut();
+        let (socket, cx) = iface.get_socket_and_context::<TcpSocket>(handle);
+        if socket.is_listening() {
+            socket.abort();
         }
+        socket.connect(cx, remote, (IpAddress::Unspecified, port))?;
+    }
 
-        self.client
-            .send_handshake_msg(&my_wire_address, &env.sender);
+    let wait_ctx = ctx.clone();
+    let result = wait_until_or_timeout(ctx, yielder, CONNECT_TIMEOUT_MILLIS, move || {
+        wait_ctx
+            .iface
+            .borrow_mut()
+            .get_socket::<TcpSocket>(handle)
+            .is_active()
+    });
+    if result.is_err() {
+        abort_socket(ctx, handle);
+    }
+    result
+}
 
-        let mut iface = self.iface.borrow_mut();
-        let (ssocket, cx) = iface.get_socket_and_context::<TcpSocket>(self.service_handle);
-        ssocket.connect(
-            cx,
-            self.config.service_server,
-            (IpAddress::Unspecified, self.get_ethemeral_port()),
-        )?;
+/// Block until both the participant and watcher/funder sockets are closed.
+///
+/// Alternatively we could use `socket.abort()`, resulting in a
+/// non-graceful shutdown but slightly faster transition times. One
+/// downside of doing it this way is that a malicious config dealer could
+/// DoS us by never sending a Fin, but since the config dealer is only
+/// necessary for the demo (which can't use hard-coded addresses) this is
+/// not a problem.
+fn wait_sockets_closed<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+) where
+    DeviceT: for<'d> Device<'d>,
+{
+    let wait_ctx = ctx.clone();
+    wait_until(yielder, None, move || {
+        let mut iface = wait_ctx.iface.borrow_mut();
+        let ssocket_active = iface
+            .get_socket::<TcpSocket>(wait_ctx.service_handle)
+            .is_active();
+        let psocket_active = iface
+            .get_socket::<TcpSocket>(wait_ctx.participant_handle)
+            .is_active();
+        let pdialsocket_active = iface
+            .get_socket::<TcpSocket>(wait_ctx.participant_dial_handle)
+            .is_active();
+        !ssocket_active && !psocket_active && !pdialsocket_active
+    });
+}
 
-        self.state = ApplicationState::WaitForProposal {
-            eth_holder,
-            withdraw_receiver,
-        };
-        Ok(())
+/// The `TlsSocket` sharing a session with the given handle's socket.
+fn tls_for<'cl, DeviceT>(ctx: &Ctx<'cl, DeviceT>, handle: SocketHandle) -> &TlsSocket
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    if handle == ctx.service_handle {
+        &ctx.tls_service
+    } else if handle == ctx.participant_dial_handle {
+        &ctx.tls_participant_dial
+    } else {
+        &ctx.tls_participant
     }
+}
 
-    fn wait_connected_and_proposal_msg(
-        &mut self,
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    ) -> Result<(), Error> {
-        {
-            let mut iface = self.iface.borrow_mut();
-            let ssocket = iface.get_socket::<TcpSocket>(self.service_handle);
-            if !ssocket.is_open() {
-                // We don't have a connection, yet
-                return Ok(());
-            }
-        }
+fn reassembly_for<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+    handle: SocketHandle,
+) -> &RefCell<Reassembly>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    if handle == ctx.service_handle {
+        &ctx.reassembly_service
+    } else if handle == ctx.participant_dial_handle {
+        &ctx.reassembly_participant_dial
+    } else {
+        &ctx.reassembly_participant
+    }
+}
 
-        match self.try_recv_participant_msg()? {
-            Some(ParticipantMessage::ChannelProposal(prop)) => {
-                let mut channel = self.client.handle_proposal(prop, withdraw_receiver)?;
-                // This cannot panic because we have just created the channel
-                // and thus cannot have accepted it already.
-                channel.accept(self.rng.gen(), self.addr).unwrap();
-                let channel = channel.build().map_err(|(_, e)| e)?;
-                self.state = ApplicationState::Active {
-                    eth_holder,
-                    withdraw_receiver,
-                    channel: Channel::new_agreed_upon(channel),
-                };
-                Ok(())
-            }
-            Some(_) => Err(Error::InvalidState),
-            None => Ok(()),
+/// The [RateLimiter] tracking the remote connected to `handle`.
+fn rate_limiter_for<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+    handle: SocketHandle,
+) -> &RefCell<RateLimiter>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    if handle == ctx.service_handle {
+        &ctx.rate_limiter_service
+    } else if handle == ctx.participant_dial_handle {
+        &ctx.rate_limiter_participant_dial
+    } else {
+        &ctx.rate_limiter_participant
+    }
+}
+
+fn try_recv<'cl, DeviceT, T: Message + Default>(
+    ctx: &Ctx<'cl, DeviceT>,
+    handle: SocketHandle,
+) -> Result<Option<T>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    // This reads from the TlsSocket's decrypted queue rather than straight
+    // off the TcpSocket: `Application::poll` drives the TLS
+    // handshake/decryption every tick before the task below ever runs, so by
+    // the time we get here any data is already plaintext.
+    let tls = tls_for(ctx, handle);
+    let mut reassembly = reassembly_for(ctx, handle).borrow_mut();
+
+    // Dequeue as much as is currently available and append it to rec_buf. A
+    // single call here may not have the whole frame yet (or even the whole
+    // 2-byte length prefix), so the loop below and the rest of this function
+    // have to work correctly no matter how the bytes of a frame ended up
+    // split across calls.
+    let mut chunk = [0u8; 256];
+    loop {
+        let queued = tls.recv_queue();
+        if queued == 0 {
+            break;
         }
+        let to_read = queued.min(chunk.len());
+        let bytes_read = tls.recv_slice(&mut chunk[..to_read]);
+        reassembly.rec_buf.extend_from_slice(&chunk[..bytes_read]);
+        rate_limiter_for(ctx, handle)
+            .borrow_mut()
+            .note_bytes_received(ctx.now_millis.get(), bytes_read)?;
     }
 
-    /// Connect to both participant and watcher/funder, then propose a channel
-    /// in a later state.
-    fn connect(&mut self, eth_holder: Address, withdraw_receiver: Address) -> Result<(), Error> {
-        let mut iface = self.iface.borrow_mut();
+    if reassembly.rec_size == 0 {
+        if reassembly.rec_buf.len() < 2 {
+            return Ok(None); // We don't have 2 bytes of length, yet.
+        }
+        let length: usize = u16::from_be_bytes(reassembly.rec_buf[..2].try_into().unwrap()).into();
 
-        let (psocket, cx) = iface.get_socket_and_context::<TcpSocket>(self.participant_handle);
-        if psocket.is_listening() {
-            psocket.abort();
+        // Make sure it is even possible to receive the message. Returning an
+        // error here (rather than e.g. discarding and resyncing) drops the
+        // connection, but that's fine: such messages won't happen under
+        // normal protocol operation as long as MAX_MESSAGE_SIZE is large
+        // enough to hold the largest possible message type (it is, for
+        // channels with 2 participants and 1 asset).
+        if 2 + length > MAX_MESSAGE_SIZE {
+            reassembly.rec_buf.clear();
+            return Err(Error::MessageLargerThanRxBuffer(2 + length));
         }
-        psocket.connect(
-            cx,
-            self.config.other_participant,
-            (IpAddress::Unspecified, self.get_ethemeral_port()),
-        )?;
 
-        let (ssocket, cx) = iface.get_socket_and_context::<TcpSocket>(self.service_handle);
-        ssocket.connect(
-            cx,
-            self.config.service_server,
-            (IpAddress::Unspecified, self.get_ethemeral_port()),
-        )?;
+        reassembly.rec_size = 2 + length;
+    }
 
-        self.state = ApplicationState::Connecting {
-            eth_holder,
-            withdraw_receiver,
-        };
-        Ok(())
+    // Only continue if the message is complete.
+    if reassembly.rec_buf.len() < reassembly.rec_size {
+        return Ok(None);
     }
 
-    fn wait_connected_and_send_handshake(
-        &mut self,
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    ) -> Result<(), Error> {
-        let mut iface = self.iface.borrow_mut();
-
-        // Wait for the participant socket and send handshake (only transition
-        // if both are ready)
-        let psocket = iface.get_socket::<TcpSocket>(self.participant_handle);
-        if psocket.is_active() && psocket.may_recv() && psocket.may_send() {
-            // propose_channel neeeds to be able to borrow the interface to send
-            // things on the network. Because of this we need to drop the
-            // interface first. Alternatively we could have moved
-            // propose_channel to a new state or restructured this function to
-            // automatically drop it before calling propose_channel.
-            drop(psocket);
-            drop(iface);
-
-            // Handshake
-            let peers: Vec<Vec<u8>> = self
-                .config
-                .participants
-                .map(|p| p.as_bytes().to_vec())
-                .into();
-            self.client.send_handshake_msg(&peers[0], &peers[1]);
-
-            self.state = ApplicationState::WaitForHandshake {
-                eth_holder,
-                withdraw_receiver,
-            }
+    let env = T::decode(&reassembly.rec_buf[2..reassembly.rec_size])?;
+    reassembly.rec_buf.drain(..reassembly.rec_size);
+    reassembly.rec_size = 0;
+    Ok(Some(env))
+}
+
+/// Block until a full `T` is available on `handle` and return it, aborting
+/// `handle` and returning [Error::Timeout] if none shows up within
+/// `timeout_millis`.
+fn recv_envelope_or_timeout<'cl, DeviceT, T: Message + Default>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    handle: SocketHandle,
+    timeout_millis: u64,
+) -> Result<T, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let deadline = ctx.now_millis.get() + timeout_millis;
+    loop {
+        if let Some(env) = try_recv::<_, T>(ctx, handle)? {
+            return Ok(env);
         }
-        Ok(())
+        if ctx.now_millis.get() >= deadline {
+            abort_socket(ctx, handle);
+            return Err(Error::Timeout);
+        }
+        yield_tick(yielder);
     }
+}
 
-    fn try_recv<T: Message + Default>(&mut self, handle: SocketHandle) -> Result<Option<T>, Error> {
-        // Yes, this function is long when including comments. When not
-        // including them it is still complex, but I have not found a way to do
-        // this without reading everything into a heap-allocated buffer or
-        // storing some information between calls to try_recv using the API
-        // smoltcp currently provides.
-        let mut iface = self.iface.borrow_mut();
-        let socket = iface.get_socket::<TcpSocket>(handle);
-
-        let recv_queue = socket.recv_queue();
-        if recv_queue < 2 {
-            return Ok(None); // We don't have 2 bytes of length
-        }
-
-        // Peek at the message length (keeping length and message in the
-        // rx-buffer if it is not completely received)
-        let mut buf_msg_length = [0u8; 2];
-        let bytes_peeked = socket.peek_slice(&mut buf_msg_length)?;
-        if bytes_peeked < 2 {
-            // smoltcp currently does not provide the capability to peek
-            // over the edge of the (internal) rx ringbuffer. the current
-            // peek cannot have this ability without copying data
-            // internally, peek_slice does however, at least based on its
-            // API design and comment. Unfortunately (likely due to a bug in
-            // smoltcp) it does not do so, which makes it impossible to read
-            // the length if we are at the end of the ringbuffer. This can
-            // be solved in one of the following ways:
-            // - Change peek_slice to do what the comment says: Do the same
-            //   as recv_slice, which does look over the ringbuffer boundry.
-            // - Add a `peek_offset(&mut self, size: usize, offset: usize)`
-            //   to smoltcp which allows us to do option 1 ourselves
-            // - Read and dequeue the message length, then store it
-            //   somewhere in the application.
-            // - Read and dequeue the message length, then immediately
-            //   follow with the message and panic if it is not complete,
-            //   yet. This would likely happen more often than panicing if
-            //   we are exactly at the ringbuffer border.
-            //
-            // Technical debt: Because this is likely a bug in smoltcp and
-            // option 3 would require a lot of changes we're panicking in
-            // this case for now (at least until we have this fixed in a
-            // separate branch on smoltcp or a fork).
-            //
-            // The probability that this happens is `1/rx_buffer.len()`,
-            // which is currently < 1/512.
-            panic!("Bug/Limitation in smoltcp");
-        }
-        let length: usize = u16::from_be_bytes(buf_msg_length).into();
-
-        // Make sure it is even possible to receive the message.
-        if (2 + length) > socket.recv_capacity() {
-            // To handle messages larger than the rx_buffer size requires one of
-            // the following:
-            // - Partial protobuf decoding and storing the partial data
-            //   somewhere. Difficult if not impossible with the Protobuf
-            //   library (although it should in theory be possible)
-            // - Copying the data into a separate buffer that can hold it over
-            //   multiple poll calls. Difficult to do, especially since that
-            //   would require a heap large enough to store the data which could
-            //   be up to 64KiB of space, which would be near impossible on a
-            //   device with just low ram.
-            // - Keep a counter of the remaining message size and discard a
-            //   message over multiple calls to `try_recv` (with calls to
-            //   `iface.poll` in between). This would allow keeping the
-            //   connection open even if someone sends a too big message. The
-            //   problem with this approach is that it may break some
-            //   assumptions on the other side.
-            // - Panic or return an error, thus effectively dropping the
-            //   connection as there is no way to handle such big messages. This
-            //   is the option implemented below.
-            //
-            // Note that such messages won't happen under normal protocol
-            // completion as long as the rx_buffer is large enough to hold the
-            // largest possible message type (512 is sufficient for channels
-            // with 2 participants and 1 asset).
-            return Err(Error::MessageLargerThanRxBuffer(2 + length));
+fn try_recv_participant_msg<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+) -> Result<Option<ParticipantMessage>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    try_recv_participant_msg_on(ctx, ctx.active_participant.get())
+}
+
+/// Same as [try_recv_participant_msg], but on an explicit socket rather than
+/// [Ctx::active_participant] - used while racing `participant_handle` against
+/// `participant_dial_handle` in [propose_to_peer], before either has become
+/// "the" active one.
+fn try_recv_participant_msg_on<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+    handle: SocketHandle,
+) -> Result<Option<ParticipantMessage>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let env: Envelope = match try_recv(ctx, handle)? {
+        Some(env) => env,
+        None => return Ok(None),
+    };
+    let msg = match env.msg {
+        Some(m) => m,
+        None => return Err(Error::EnvelopeHasNoMsg),
+    };
+    let msg = match msg {
+        Msg::PingMsg(_) => ParticipantMessage::Ping,
+        Msg::PongMsg(_) => ParticipantMessage::Pong,
+        Msg::ShutdownMsg(m) => ParticipantMessage::Shutdown(m.try_into()?),
+        Msg::AuthChallengeMsg(m) => {
+            ParticipantMessage::AuthChallenge(Hash(m.nonce.try_into().unwrap()))
+        }
+        Msg::AuthResponseMsg(m) => ParticipantMessage::AuthResponse {
+            nonce: Hash(m.nonce.try_into().unwrap()),
+            sig: Signature(m.sig.try_into().unwrap()),
+            // The wire message carries no feature bits.
+            features: ChannelFeatures::empty(),
+        },
+        Msg::LedgerChannelProposalMsg(m) => ParticipantMessage::ChannelProposal(m.try_into()?), // Accepted right after an inbound handshake, see `finish_incoming_proposal`.
+        Msg::LedgerChannelProposalAccMsg(m) => ParticipantMessage::ProposalAccepted(m.try_into()?),
+        Msg::SubChannelProposalMsg(_) => unimplemented!(),
+        Msg::SubChannelProposalAccMsg(_) => unimplemented!(),
+        Msg::VirtualChannelProposalMsg(m) => {
+            ParticipantMessage::VirtualChannelProposal(m.try_into()?)
+        } // Possible in the library but this Application does not support acting as an intermediary yet.
+        Msg::VirtualChannelProposalAccMsg(m) => {
+            ParticipantMessage::VirtualChannelProposalAccepted(m.try_into()?)
+        }
+        Msg::ChannelProposalRejMsg(m) => ParticipantMessage::ProposalRejected {
+            id: Hash(m.proposal_id.try_into().unwrap()),
+            reason: m.reason,
+        },
+        Msg::ChannelUpdateMsg(m) => ParticipantMessage::ChannelUpdate(m.try_into()?),
+        Msg::VirtualChannelFundingProposalMsg(m) => {
+            ParticipantMessage::VirtualChannelFundingProposal(m.try_into()?)
         }
+        Msg::VirtualChannelSettlementProposalMsg(m) => {
+            ParticipantMessage::VirtualChannelSettlementProposal(m.try_into()?)
+        }
+        Msg::ChannelUpdateAccMsg(m) => ParticipantMessage::ChannelUpdateAccepted(m.try_into()?),
+        Msg::ChannelUpdateRejMsg(m) => ParticipantMessage::ChannelUpdateRejected {
+            id: Hash(m.channel_id.try_into().unwrap()),
+            version: m.version,
+            reason: m.reason,
+        },
+        Msg::ChannelSyncMsg(m) => ParticipantMessage::ChannelSync(m.try_into()?),
+    };
+    Ok(Some(msg))
+}
 
-        // Only continue if the message is complete.
-        if socket.recv_queue() < 2 + length {
-            return Ok(None); // We don't have all the data
-        }
-
-        // Read the entire message and decode it.
-        //
-        // Technical debt: We're currently creating a copy of the bytes in
-        // memory for decoding. It should be possible to do this without
-        // creating a copy (in a local variable) by implementing a custom buffer
-        // to decode from. This would also eliminate the need for the
-        // MAX_MESSAGE_SIZE local array.
-        //
-        // unsized local variables are currently unstable rust, see
-        // https://doc.rust-lang.org/unstable-book/language-features/unsized-locals.html.
-        // Therefore we need to specify a size. We cannot take it from socket or
-        // self.config because neither is constant => MAX_MESSAGE_SIZE
-        //
-        // Discard 2 bytes of length information.
-        let read = socket.recv(|x| {
-            let len = x.len().min(2);
-            (len, len)
-        })?;
-        if read != 2 {
-            // At the moment this cannot happen because we're panicking earlier
-            // if we are at the bingbuffer boundry (the only situation where
-            // this could happen). I've nevertheless added the logic to handle
-            // this case as a defensive mechanism (i.e. we won't panic here) in
-            // case someone fixes the panic above but doesn't change this part.
-            socket.recv(|_| (2 - read, ()))?;
-        }
-        let mut buf = [0u8; MAX_MESSAGE_SIZE];
-        let bytes_read = socket.recv_slice(&mut buf[..length])?;
-        if bytes_read != length {
-            // This can only happen if the rx_buffer runs out, which can't
-            // happen because we have queued bytes. Note that this only holds
-            // true as long as smoltcp does not queue out-of-order packets.
-            unreachable!("We previously checked for queue size, did smoltcp add storage for out-of-order packets?")
-        }
-        let env = T::decode(&buf[..length])?;
-        Ok(Some(env))
-    }
-
-    fn wait_handshake_and_propose_channel(
-        &mut self,
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    ) -> Result<(), Error> {
-        // Only continue if we have a complete package and there was no decoding
-        // error. Note that we currently do not check the addresses in the
-        // envelope.
-        match self.try_recv_participant_msg()? {
-            Some(ParticipantMessage::Auth) => {
-                self.send_channel_proposal(eth_holder, withdraw_receiver)
-            }
-            Some(_) => Err(Error::UnexpectedMsg),
-            None => Ok(()),
+fn try_recv_service_msg<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+) -> Result<Option<ServiceReplyMessage>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let env: perunwire::Message = match try_recv(ctx, ctx.service_handle)? {
+        Some(env) => env,
+        None => return Ok(None),
+    };
+    let msg = match env.msg {
+        Some(m) => m,
+        None => return Err(Error::EnvelopeHasNoMsg),
+    };
+    let msg = match msg {
+        perunwire::message::Msg::FundingRequest(_) => unimplemented!(),
+        perunwire::message::Msg::FundingResponse(m) => {
+            ServiceReplyMessage::Funder(FunderReplyMessage::Funded {
+                id: Hash(m.channel_id.try_into().unwrap()),
+            })
+        }
+        perunwire::message::Msg::WatchRequest(_) => unimplemented!(),
+        perunwire::message::Msg::WatchResponse(m) => {
+            ServiceReplyMessage::Watcher(WatcherReplyMessage::Ack {
+                id: Hash(m.channel_id.try_into().unwrap()),
+                version: m.version,
+            })
+        }
+        perunwire::message::Msg::ForceCloseRequest(_) => unimplemented!(),
+        perunwire::message::Msg::ForceCloseResponse(m) => {
+            ServiceReplyMessage::Watcher(WatcherReplyMessage::DisputeAck {
+                id: Hash(m.channel_id.try_into().unwrap()),
+            })
+        }
+        perunwire::message::Msg::DisputeNotification(m) => {
+            ServiceReplyMessage::Watcher(WatcherReplyMessage::DisputeNotification {
+                id: Hash(m.channel_id.try_into().unwrap()),
+            })
+        }
+    };
+
+    Ok(Some(msg))
+}
+
+fn send_channel_proposal<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+    channel_params: &ChannelParams,
+) -> Result<Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let init_balance = Balances([ParticipantBalances(channel_params.init_balances)]);
+    let peers = ctx
+        .config
+        .participants
+        .map(|p| p.as_bytes().to_vec())
+        .into();
+    let prop = LedgerChannelProposal {
+        proposal_id: ctx.rng.borrow_mut().gen(),
+        challenge_duration: channel_params.challenge_duration,
+        nonce_share: ctx.rng.borrow_mut().gen(),
+        init_bals: Allocation::new(
+            [Asset {
+                chain_id: channel_params.chain_id,
+                holder: channel_params.eth_holder,
+            }],
+            init_balance,
+        ),
+        funding_agreement: init_balance,
+        participant: ctx.addr,
+        peers,
+        protocol_version: ProtocolVersion::CURRENT,
+        app: Address([0u8; 20]),
+        init_data: alloc::vec![],
+    };
+    let channel = ctx
+        .client
+        .propose_channel(prop, channel_params.withdraw_receiver)?;
+    Ok(Channel::new(channel))
+}
+
+fn abort_socket<'cl, DeviceT>(ctx: &Ctx<'cl, DeviceT>, handle: SocketHandle)
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    ctx.iface
+        .borrow_mut()
+        .get_socket::<TcpSocket>(handle)
+        .abort();
+}
+
+/// Non-blocking step of the mutual handshake on `handle`, used while racing
+/// `participant_dial_handle` against an inbound connection in
+/// [propose_to_peer]. Like [try_recv_participant_msg], this never yields.
+///
+/// Answers an inbound `AuthChallenge` from `peer_id` immediately (so both
+/// directions make progress regardless of poll order), and reports `true`
+/// once `peer_id`'s `AuthResponse` to our own outstanding challenge has been
+/// verified against `peer_addr`.
+fn poll_handshake<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+    handle: SocketHandle,
+    my_id: &Identity,
+    peer_id: &Identity,
+    peer_addr: Address,
+) -> Result<bool, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    match try_recv_participant_msg_on(ctx, handle)? {
+        Some(ParticipantMessage::AuthChallenge(nonce)) => {
+            ctx.client
+                .handle_auth_challenge(my_id, peer_id, nonce, ChannelFeatures::empty())?;
+            Ok(false)
         }
+        Some(ParticipantMessage::AuthResponse {
+            nonce,
+            sig,
+            features,
+        }) => {
+            ctx.client.handle_auth_response(
+                my_id,
+                peer_id,
+                nonce,
+                sig,
+                peer_addr,
+                ChannelFeatures::empty(),
+                features,
+            )?;
+            Ok(true)
+        }
+        Some(_) => Err(Error::UnexpectedMsg),
+        None => Ok(false),
     }
+}
 
-    fn send_channel_proposal(
-        &mut self,
-        eth_holder: Address,
-        withdraw_receiver: Address,
-    ) -> Result<(), Error> {
-        // Channel Proposal
-        let init_balance = Balances([ParticipantBalances([100_000.into(), 100_000.into()])]);
-        let peers = self
-            .config
-            .participants
-            .map(|p| p.as_bytes().to_vec())
-            .into();
-        let prop = LedgerChannelProposal {
-            proposal_id: self.rng.gen(),
-            challenge_duration: 25,
-            nonce_share: self.rng.gen(),
-            init_bals: Allocation::new(
-                [Asset {
-                    chain_id: 1337.into(), // Default chainID when using a SimulatedBackend from go-ethereum or Ganache
-                    holder: eth_holder,
-                }],
-                init_balance,
-            ),
-            funding_agreement: init_balance,
-            participant: self.addr,
-            peers,
-        };
-        let channel = self.client.propose_channel(prop, withdraw_receiver)?;
-        // Setup sub-state-machine for handling the channel
-        let channel = Channel::new(channel);
-        self.state = ApplicationState::Active {
-            channel,
-            eth_holder,
-            withdraw_receiver,
-        };
-        Ok(())
+/// Non-blocking check for an inbound handshake on `participant_handle`,
+/// returning the raw [Envelope] (its `sender` is needed to reply) and the
+/// nonce of its `AuthChallenge`. Split out of what used to be the start of
+/// `wait_for_incoming_proposal` so [propose_to_peer] can poll it without
+/// blocking while it's also racing its own dial attempt.
+fn peek_incoming_auth<'cl, DeviceT>(
+    ctx: &Ctx<'cl, DeviceT>,
+) -> Result<Option<(Envelope, Hash)>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let env: Envelope = match try_recv(ctx, ctx.participant_handle)? {
+        Some(env) => env,
+        None => return Ok(None),
+    };
+    let nonce = match env.msg {
+        Some(envelope::Msg::AuthChallengeMsg(ref m)) => Hash(m.nonce.clone().try_into().unwrap()),
+        Some(_) => return Err(Error::UnexpectedMsg),
+        None => return Err(Error::InvalidState),
+    };
+    if env.recipient[..] != ctx.config.participants[0].as_bytes()[..] {
+        return Err(Error::UnexpectedMsg);
     }
+    Ok(Some((env, nonce)))
+}
 
-    fn try_recv_participant_msg(&mut self) -> Result<Option<ParticipantMessage>, Error> {
-        let env: Envelope = match self.try_recv(self.participant_handle)? {
-            Some(env) => env,
-            None => return Ok(None),
-        };
-        let msg = match env.msg {
-            Some(m) => m,
-            None => return Err(Error::EnvelopeHasNoMsg),
-        };
-        let msg = match msg {
-            Msg::PingMsg(_) => unimplemented!(),
-            Msg::PongMsg(_) => unimplemented!(),
-            Msg::ShutdownMsg(_) => unimplemented!(),
-            Msg::AuthResponseMsg(_) => ParticipantMessage::Auth,
-            Msg::LedgerChannelProposalMsg(m) => ParticipantMessage::ChannelProposal(m.try_into()?), // Possible in the library but this Application does not support incoming requests.
-            Msg::LedgerChannelProposalAccMsg(m) => {
-                ParticipantMessage::ProposalAccepted(m.try_into()?)
-            }
-            Msg::SubChannelProposalMsg(_) => unimplemented!(),
-            Msg::SubChannelProposalAccMsg(_) => unimplemented!(),
-            Msg::VirtualChannelProposalMsg(_) => unimplemented!(),
-            Msg::VirtualChannelProposalAccMsg(_) => unimplemented!(),
-            Msg::ChannelProposalRejMsg(m) => ParticipantMessage::ProposalRejected {
-                id: Hash(m.proposal_id.try_into().unwrap()),
-                reason: m.reason,
-            },
-            Msg::ChannelUpdateMsg(m) => ParticipantMessage::ChannelUpdate(m.try_into()?),
-            Msg::VirtualChannelFundingProposalMsg(_) => unimplemented!(),
-            Msg::VirtualChannelSettlementProposalMsg(_) => unimplemented!(),
-            Msg::ChannelUpdateAccMsg(m) => ParticipantMessage::ChannelUpdateAccepted(m.try_into()?),
-            Msg::ChannelUpdateRejMsg(m) => ParticipantMessage::ChannelUpdateRejected {
-                id: Hash(m.channel_id.try_into().unwrap()),
-                version: m.version,
-                reason: m.reason,
-            },
-            Msg::ChannelSyncMsg(_) => unimplemented!(),
-        };
-        Ok(Some(msg))
+/// Reply to an inbound handshake (`env`), connect to the watcher/funder, and
+/// accept whatever channel proposal follows. Shared by the plain inbound path
+/// ([wait_for_incoming_proposal]) and the losing side of a simultaneous open
+/// ([propose_to_peer]).
+fn finish_incoming_proposal<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    channel_params: &ChannelParams,
+    env: Envelope,
+    their_nonce: Hash,
+) -> Result<Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let my_wire_address: Identity = ctx.config.participants[0].into();
+    ctx.client.handle_auth_challenge(
+        &my_wire_address,
+        &env.sender,
+        their_nonce,
+        ChannelFeatures::empty(),
+    )?;
+    ctx.client
+        .send_handshake_msg(&my_wire_address, &env.sender, ctx.rng.borrow_mut().gen())?;
+
+    let deadline = ctx.now_millis.get() + HANDSHAKE_TIMEOUT_MILLIS;
+    loop {
+        if poll_handshake(
+            ctx,
+            ctx.participant_handle,
+            &my_wire_address,
+            &env.sender,
+            ctx.config.peer_address,
+        )? {
+            break;
+        }
+        if ctx.now_millis.get() >= deadline {
+            abort_socket(ctx, ctx.participant_handle);
+            return Err(Error::Timeout);
+        }
+        yield_tick(yielder);
     }
 
-    fn try_recv_service_msg(&mut self) -> Result<Option<ServiceReplyMessage>, Error> {
-        let env: perunwire::Message = match self.try_recv(self.service_handle)? {
-            Some(env) => env,
-            None => return Ok(None),
-        };
-        let msg = match env.msg {
-            Some(m) => m,
-            None => return Err(Error::EnvelopeHasNoMsg),
-        };
-        let msg = match msg {
-            perunwire::message::Msg::FundingRequest(_) => unimplemented!(),
-            perunwire::message::Msg::FundingResponse(m) => {
-                ServiceReplyMessage::Funder(FunderReplyMessage::Funded {
-                    id: Hash(m.channel_id.try_into().unwrap()),
-                })
-            }
-            perunwire::message::Msg::WatchRequest(_) => unimplemented!(),
-            perunwire::message::Msg::WatchResponse(m) => {
-                ServiceReplyMessage::Watcher(WatcherReplyMessage::Ack {
-                    id: Hash(m.channel_id.try_into().unwrap()),
-                    version: m.version,
-                })
-            }
-            perunwire::message::Msg::ForceCloseRequest(_) => unimplemented!(),
-            perunwire::message::Msg::ForceCloseResponse(m) => {
-                ServiceReplyMessage::Watcher(WatcherReplyMessage::DisputeAck {
-                    id: Hash(m.channel_id.try_into().unwrap()),
-                })
-            }
-            perunwire::message::Msg::DisputeNotification(m) => {
-                ServiceReplyMessage::Watcher(WatcherReplyMessage::DisputeNotification {
-                    id: Hash(m.channel_id.try_into().unwrap()),
-                })
+    ctx.tls_service.reset(Role::Client);
+    connect(ctx, yielder, ctx.service_handle, ctx.config.service_server)?;
+
+    match recv_envelope_or_timeout(
+        ctx,
+        yielder,
+        ctx.participant_handle,
+        PROPOSAL_TIMEOUT_MILLIS,
+    )? {
+        ParticipantMessage::ChannelProposal(prop) => {
+            ctx.events
+                .borrow_mut()
+                .push_back(Event::ChannelProposalReceived);
+            let mut channel = ctx
+                .client
+                .handle_proposal(prop, channel_params.withdraw_receiver)?;
+            // This cannot panic because we have just created the channel
+            // and thus cannot have accepted it already.
+            channel
+                .accept(ctx.rng.borrow_mut().gen(), ctx.addr)
+                .unwrap();
+            let channel = channel.build().map_err(|(_, e)| e)?;
+            Ok(Channel::new_agreed_upon(channel))
+        }
+        _ => Err(Error::InvalidState),
+    }
+}
+
+/// Dial the other participant and watcher/funder, handshake, and propose a
+/// channel. Collapses what used to be
+/// `connect`→`wait_connected_and_send_handshake`→`WaitForHandshake`→
+/// `wait_handshake_and_propose_channel` into one function.
+///
+/// Dialing happens on `participant_dial_handle`, a separate socket from
+/// `participant_handle` (which [listen_for_peer] leaves listening), because
+/// the other side may dial us back on their own initiative at the same time
+/// - a simultaneous open. Once our handshake is acked we therefore keep
+/// polling both our own dial attempt and the listen socket for an inbound
+/// handshake; if only ours completes we're the proposer, if only theirs does
+/// we accept, and if both complete we break the tie the same way this demo
+/// already does for the go-side's proposer selection (see the comment where
+/// `Config::participants` is built in `main.rs`): the larger wire address
+/// proposes. We don't thread a dedicated tie-break field through the wire
+/// messages themselves because the protobuf schema here mirrors go-perun's
+/// and isn't ours to extend.
+fn propose_to_peer<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    channel_params: &ChannelParams,
+) -> Result<Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    ctx.tls_participant_dial.reset(Role::Client);
+    connect(
+        ctx,
+        yielder,
+        ctx.participant_dial_handle,
+        ctx.config.other_participant,
+    )?;
+
+    let wait_ctx = ctx.clone();
+    if wait_until_or_timeout(ctx, yielder, HANDSHAKE_TIMEOUT_MILLIS, move || {
+        wait_ctx.tls_participant_dial.is_established()
+    })
+    .is_err()
+    {
+        abort_socket(ctx, ctx.participant_dial_handle);
+        return Err(Error::Timeout);
+    }
+
+    let peers: Vec<Vec<u8>> = ctx
+        .config
+        .participants
+        .map(|p| p.as_bytes().to_vec())
+        .into();
+    ctx.client
+        .send_handshake_msg(&peers[0], &peers[1], ctx.rng.borrow_mut().gen())?;
+
+    let deadline = ctx.now_millis.get() + HANDSHAKE_TIMEOUT_MILLIS;
+    loop {
+        let dial_acked = poll_handshake(
+            ctx,
+            ctx.participant_dial_handle,
+            &peers[0],
+            &peers[1],
+            ctx.config.peer_address,
+        )?;
+        let incoming = peek_incoming_auth(ctx)?;
+        let we_are_larger = ctx.config.participants[0] > ctx.config.participants[1];
+        let dial_wins = dial_acked && (incoming.is_none() || we_are_larger);
+
+        if dial_wins {
+            if incoming.is_some() {
+                abort_socket(ctx, ctx.participant_handle);
             }
-        };
+            ctx.active_participant.set(ctx.participant_dial_handle);
+            ctx.tls_service.reset(Role::Client);
+            connect(ctx, yielder, ctx.service_handle, ctx.config.service_server)?;
+            return send_channel_proposal(ctx, channel_params);
+        } else if let Some((env, nonce)) = incoming {
+            abort_socket(ctx, ctx.participant_dial_handle);
+            ctx.active_participant.set(ctx.participant_handle);
+            return finish_incoming_proposal(ctx, yielder, channel_params, env, nonce);
+        }
+
+        if ctx.now_millis.get() >= deadline {
+            abort_socket(ctx, ctx.participant_dial_handle);
+            return Err(Error::Timeout);
+        }
 
-        Ok(Some(msg))
+        yield_tick(yielder);
+    }
+}
+
+/// Accept an inbound TCP connection from the other participant, handshake,
+/// and accept their channel proposal. Collapses what used to be
+/// `check_incomming_connection`→`WaitForProposal`→
+/// `wait_connected_and_proposal_msg` into one function.
+fn wait_for_incoming_proposal<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    channel_params: &ChannelParams,
+) -> Result<Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let deadline = ctx.now_millis.get() + HANDSHAKE_TIMEOUT_MILLIS;
+    loop {
+        if let Some((env, nonce)) = peek_incoming_auth(ctx)? {
+            return finish_incoming_proposal(ctx, yielder, channel_params, env, nonce);
+        }
+        if ctx.now_millis.get() >= deadline {
+            abort_socket(ctx, ctx.participant_handle);
+            return Err(Error::Timeout);
+        }
+        yield_tick(yielder);
     }
+}
 
-    /// Helper function to not duplicate code. We have to process a message
-    /// before we can continue with the second one, otherwise we might loose a
-    /// message. The same goes for checking if the channel was closed.
-    fn forward_messages<T, F1, F2>(&mut self, recv_fn: F1, process_fn: F2) -> Result<bool, Error>
-    where
-        F1: Fn(&mut Self) -> Result<Option<T>, Error>,
-        F2: Fn(&mut Channel<ProtoBufEncodingLayer<Bus<DeviceT>>>, T) -> Result<(), Error>,
+/// Wait and do nothing until someone presses the "propose" button or we
+/// receive a TCP connection attempt, then handle whichever happened first.
+fn listen_for_peer<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    channel_params: &ChannelParams,
+) -> Result<Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
     {
-        let msg: Option<T> = recv_fn(self)?;
-
-        if let Some(msg) = msg {
-            // Now get the (mutable) channel object so we don't get issues with mutability.
-            let (channel, eth_holder, withdraw_receiver) = match self.state {
-                ApplicationState::Active {
-                    ref mut channel,
-                    eth_holder,
-                    withdraw_receiver,
-                } => (channel, eth_holder, withdraw_receiver),
-                _ => unreachable!("This function is only called when in Active"),
-            };
+        let mut iface = ctx.iface.borrow_mut();
+        iface
+            .get_socket::<TcpSocket>(ctx.participant_handle)
+            .listen(ctx.config.listen_port)?;
+    }
+    // Whoever connects to us next is the TLS client, we're the server that
+    // waits for their hello (see `tls::Role`).
+    ctx.tls_participant.reset(Role::Server);
+    ctx.active_participant.set(ctx.participant_handle);
+    ctx.phase.set(Phase::Listening);
+    ctx.incoming_connection_counted.set(false);
 
-            process_fn(channel, msg)?;
-
-            if channel.is_closed() {
-                let mut iface = self.iface.borrow_mut();
-                iface
-                    .get_socket::<TcpSocket>(self.participant_handle)
-                    .close();
-                iface.get_socket::<TcpSocket>(self.service_handle).close();
-                self.state = ApplicationState::ClosingSockets {
-                    eth_holder,
-                    withdraw_receiver,
-                };
+    loop {
+        let has_incoming_connection = {
+            let mut iface = ctx.iface.borrow_mut();
+            let psocket = iface.get_socket::<TcpSocket>(ctx.participant_handle);
+            psocket.is_open() && psocket.may_recv()
+        };
+        if has_incoming_connection {
+            if !ctx.incoming_connection_counted.get() {
+                ctx.incoming_connection_counted.set(true);
+                rate_limiter_for(ctx, ctx.participant_handle)
+                    .borrow_mut()
+                    .note_connection_attempt(ctx.now_millis.get())?;
             }
-            Ok(true)
-        } else {
-            Ok(false)
+            return wait_for_incoming_proposal(ctx, yielder, channel_params);
         }
+
+        let propose_requested =
+            matches!(ctx.commands.borrow().front(), Some(Command::ProposeChannel));
+        if propose_requested {
+            ctx.commands.borrow_mut().pop_front();
+            return propose_to_peer(ctx, yielder, channel_params);
+        }
+
+        yield_tick(yielder);
     }
+}
 
-    fn forward_messages_to_channel(&mut self) -> Result<(), Error> {
-        let has_participant_msg = self.forward_messages(
-            |s| s.try_recv_participant_msg(),
-            |ch, msg| {
-                ch.process_participant_msg(msg)?;
-                Ok(())
-            },
-        )?;
+/// Queue [Event::UpdateRejected] with `e`'s reason if `e` is a
+/// [channel::Error::UpdateFailed] - the one case [Channel::poll_retries]/
+/// [Channel::process_participant_msg] report by returning an `Err` instead of
+/// letting [run_active_channel] carry on, so it's also worth telling the host
+/// about through the event queue before the `?` this wraps propagates it.
+fn record_update_failure<'cl, DeviceT>(ctx: &Ctx<'cl, DeviceT>, e: channel::Error) -> Error
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    if let channel::Error::UpdateFailed(ref reason) = e {
+        ctx.events.borrow_mut().push_back(Event::UpdateRejected {
+            reason: reason.clone(),
+        });
+    }
+    e.into()
+}
 
-        // Only process one message, as that could have changed the channels
-        // state to Closed, which would mean we don't forward messages anymore.
-        // I don't know if that is really necessary but it has the additional
-        // benefit of allowing Interface.poll calls in between.
-        if has_participant_msg {
-            return Ok(());
+/// The two participant wire addresses, in the fixed "us, then peer" order
+/// [finish_incoming_proposal]/[propose_to_peer] already use for
+/// `send_handshake_msg` - used by the keepalive ping/pong below, which
+/// addresses messages the same way.
+fn participant_identities<'cl, DeviceT>(ctx: &Ctx<'cl, DeviceT>) -> (Vec<u8>, Vec<u8>)
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    (
+        ctx.config.participants[0].as_bytes().to_vec(),
+        ctx.config.participants[1].as_bytes().to_vec(),
+    )
+}
+
+/// Re-dial the other participant, handshake, and resend `channel`'s
+/// [ChannelSync] message to resynchronize after the keepalive timer in
+/// [run_active_channel] decided the peer was gone. Returns once the peer's
+/// own `ChannelSyncMsg` reply has been applied to `channel`.
+fn reconnect_and_resync<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    channel: &mut Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>,
+) -> Result<(), Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    abort_socket(ctx, ctx.active_participant.get());
+
+    ctx.tls_participant_dial.reset(Role::Client);
+    connect(
+        ctx,
+        yielder,
+        ctx.participant_dial_handle,
+        ctx.config.other_participant,
+    )?;
+
+    let (my_id, peer_id) = participant_identities(ctx);
+    ctx.client
+        .send_handshake_msg(&my_id, &peer_id, ctx.rng.borrow_mut().gen())?;
+
+    let deadline = ctx.now_millis.get() + HANDSHAKE_TIMEOUT_MILLIS;
+    loop {
+        if poll_handshake(
+            ctx,
+            ctx.participant_dial_handle,
+            &my_id,
+            &peer_id,
+            ctx.config.peer_address,
+        )? {
+            break;
+        }
+        if ctx.now_millis.get() >= deadline {
+            abort_socket(ctx, ctx.participant_dial_handle);
+            return Err(Error::Timeout);
         }
+        yield_tick(yielder);
+    }
+    ctx.active_participant.set(ctx.participant_dial_handle);
 
-        self.forward_messages(
-            |s| s.try_recv_service_msg(),
-            |ch, msg| {
-                match msg {
-                    ServiceReplyMessage::Funder(msg) => ch.process_funder_reply(msg)?,
-                    ServiceReplyMessage::Watcher(msg) => ch.process_watcher_reply(msg)?,
-                };
-                Ok(())
-            },
-        )?;
-        Ok(())
+    if channel.sync_msg().is_none() {
+        // Nothing to reestablish (the channel isn't in a state that has a
+        // signed state to reconcile); the reconnected socket is enough.
+        return Ok(());
     }
+    channel.send_sync_msg();
 
-    /// Main polling function transitioning between states. Call this regularly,
-    /// for example always after polling the network interface.
-    pub fn poll(&mut self) -> Result<(), Error> {
-        match self.state {
-            ApplicationState::InitialState => self.connect_config_dealer(),
-            ApplicationState::ConnectingToConfigDealer => self.wait_connected_and_read_config(),
-            ApplicationState::ClosingSockets {
-                eth_holder,
-                withdraw_receiver,
-            } => self.wait_connections_closed(eth_holder, withdraw_receiver),
-            ApplicationState::Listening {
-                eth_holder,
-                withdraw_receiver,
-            } => self.check_incomming_connection(eth_holder, withdraw_receiver),
-            ApplicationState::WaitForProposal {
-                eth_holder,
-                withdraw_receiver,
-            } => self.wait_connected_and_proposal_msg(eth_holder, withdraw_receiver),
-            ApplicationState::Connecting {
-                eth_holder,
-                withdraw_receiver,
-            } => self.wait_connected_and_send_handshake(eth_holder, withdraw_receiver),
-            ApplicationState::WaitForHandshake {
-                eth_holder,
-                withdraw_receiver,
-            } => self.wait_handshake_and_propose_channel(eth_holder, withdraw_receiver),
-            ApplicationState::Active { .. } => self.forward_messages_to_channel(),
-        }
-    }
-
-    /// Send 100 WEI to the other channel participant to demonstrate channel
-    /// updates. If the channel is not currently active it will return an error.
-    pub fn update(&mut self, amount: U256, is_final: bool) -> Result<(), Error> {
-        match &mut self.state {
-            ApplicationState::Active { channel, .. } => {
-                channel.update(amount, is_final)?;
-                Ok(())
-            }
-            _ => Err(Error::InvalidState),
+    let deadline = ctx.now_millis.get() + PROPOSAL_TIMEOUT_MILLIS;
+    loop {
+        if let Some(msg) = try_recv_participant_msg_on(ctx, ctx.participant_dial_handle)? {
+            return match msg {
+                ParticipantMessage::ChannelSync(_) => Ok(channel.process_participant_msg(msg)?),
+                _ => Err(Error::UnexpectedMsg),
+            };
+        }
+        if ctx.now_millis.get() >= deadline {
+            abort_socket(ctx, ctx.participant_dial_handle);
+            return Err(Error::Timeout);
         }
+        yield_tick(yielder);
     }
+}
 
-    /// Force close the channel by sending a DisputeRequest to the Watcher.
-    pub fn force_close(&mut self) -> Result<(), Error> {
-        match &mut self.state {
-            ApplicationState::Active { channel, .. } => {
-                channel.force_close()?;
-                Ok(())
+/// Run the channel's sub-state-machine until it closes, forwarding
+/// participant/watcher/funder messages and queued `update`/`force_close`
+/// commands. Also runs a Ping/Pong keepalive: once
+/// [KEEPALIVE_IDLE_TICKS] pass without hearing anything from the
+/// participant we send a `PingMsg`, and if no `PongMsg` answers it within
+/// [KEEPALIVE_PONG_TIMEOUT_TICKS] we treat the peer as disconnected, try to
+/// reconnect and resynchronize via [reconnect_and_resync], and fall back to
+/// `channel.force_close()` if that doesn't work either. If the channel
+/// force-closes, starts tracking its dispute's challenge window (see
+/// [Application::notify_block_height]) before returning. Returns once the
+/// channel is closed and both sockets are closed.
+fn run_active_channel<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+    mut channel: Channel<'cl, ProtoBufEncodingLayer<Bus<'cl, DeviceT>>, Signer>,
+    channel_params: &ChannelParams,
+) -> Result<(), Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    ctx.phase.set(Phase::Active);
+    ctx.active_tick.set(0);
+    ctx.last_participant_activity_tick.set(0);
+    ctx.ping_sent_tick.set(None);
+    loop {
+        // Drive the in-flight update's retry clock every tick, regardless of
+        // whether a message/command is also processed this iteration, so a
+        // missing `ChannelUpdateAccMsg` is noticed even while nothing new
+        // arrives.
+        channel
+            .poll_retries()
+            .map_err(|e| record_update_failure(ctx, e))?;
+        ctx.active_tick.set(ctx.active_tick.get() + 1);
+
+        // Only process one message/command per tick, as handling one could
+        // have closed the channel, which would mean we shouldn't keep
+        // forwarding. This also gives `iface.poll` a chance to run in
+        // between.
+        if let Some(msg) = try_recv_participant_msg(ctx)? {
+            ctx.last_participant_activity_tick
+                .set(ctx.active_tick.get());
+            match msg {
+                ParticipantMessage::Ping => {
+                    let (my_id, peer_id) = participant_identities(ctx);
+                    ctx.client.send_pong(&my_id, &peer_id);
+                }
+                ParticipantMessage::Pong => ctx.ping_sent_tick.set(None),
+                msg => {
+                    let event = match &msg {
+                        ParticipantMessage::ChannelUpdate(_) => Some(Event::UpdateReceived),
+                        ParticipantMessage::ProposalAccepted(_) => Some(Event::ProposalAccepted),
+                        ParticipantMessage::ProposalRejected { reason, .. } => {
+                            Some(Event::ProposalRejected {
+                                reason: reason.clone(),
+                            })
+                        }
+                        _ => None,
+                    };
+                    let result = channel.process_participant_msg(msg);
+                    // A rejected proposal comes back as `Err(Rejected)`, so
+                    // its event is queued before that error propagates.
+                    if result.is_ok() || matches!(result, Err(channel::Error::Rejected { .. })) {
+                        if let Some(event) = event {
+                            ctx.events.borrow_mut().push_back(event);
+                        }
+                    }
+                    result.map_err(|e| record_update_failure(ctx, e))?;
+                }
+            }
+        } else if let Some(msg) = try_recv_service_msg(ctx)? {
+            let was_active = channel.is_active();
+            match msg {
+                ServiceReplyMessage::Funder(msg) => channel.process_funder_reply(msg)?,
+                ServiceReplyMessage::Watcher(msg) => channel.process_watcher_reply(msg)?,
+            }
+            if !was_active && channel.is_active() {
+                ctx.events.borrow_mut().push_back(Event::Funded);
+            }
+        } else if let Some(cmd) = ctx.commands.borrow_mut().pop_front() {
+            match cmd {
+                Command::Update {
+                    id,
+                    amount,
+                    is_final,
+                    retry,
+                } => channel.update(id, amount, is_final, retry)?,
+                Command::ForceClose => channel.force_close()?,
+                Command::Shutdown => channel.shutdown()?,
+                // Not valid while a channel is already active; drop it.
+                Command::ProposeChannel => {}
             }
-            _ => Err(Error::InvalidState),
+        } else if let Some(sent_at) = ctx.ping_sent_tick.get() {
+            // Nothing else happened this tick and we're waiting on a pong -
+            // check whether it's overdue.
+            if ctx.active_tick.get().saturating_sub(sent_at) >= KEEPALIVE_PONG_TIMEOUT_TICKS {
+                ctx.ping_sent_tick.set(None);
+                if reconnect_and_resync(ctx, yielder, &mut channel).is_err() {
+                    channel.force_close()?;
+                } else {
+                    ctx.last_participant_activity_tick
+                        .set(ctx.active_tick.get());
+                }
+            }
+        } else if ctx
+            .active_tick
+            .get()
+            .saturating_sub(ctx.last_participant_activity_tick.get())
+            >= KEEPALIVE_IDLE_TICKS
+        {
+            let (my_id, peer_id) = participant_identities(ctx);
+            ctx.client.send_ping(&my_id, &peer_id);
+            ctx.ping_sent_tick.set(Some(ctx.active_tick.get()));
         }
-    }
 
-    /// Propose a new channel to the other participant.
-    pub fn propose_channel(&mut self) -> Result<(), Error> {
-        match self.state {
-            ApplicationState::Listening {
-                eth_holder,
-                withdraw_receiver,
-            } => self.connect(eth_holder, withdraw_receiver),
-            _ => Err(Error::InvalidState),
+        if channel.is_closed() {
+            if channel.is_disputed() {
+                ctx.dispute.set(Some(Dispute {
+                    registered_at_block: ctx.block_height.get(),
+                    challenge_duration: channel_params.challenge_duration,
+                    eth_holder: channel_params.eth_holder,
+                    withdraw_receiver: channel_params.withdraw_receiver,
+                }));
+                ctx.withdraw_ready.set(None);
+                ctx.events.borrow_mut().push_back(Event::DisputeRaised);
+            }
+            ctx.events.borrow_mut().push_back(Event::Closed);
+            let mut iface = ctx.iface.borrow_mut();
+            iface
+                .get_socket::<TcpSocket>(ctx.active_participant.get())
+                .close();
+            iface.get_socket::<TcpSocket>(ctx.service_handle).close();
+            return Ok(());
         }
+
+        yield_tick(yielder);
     }
+}
 
-    fn get_ethemeral_port(&mut self) -> u16 {
-        const MIN: u16 = 49152;
-        const MAX: u16 = 65535;
-        // Note: This is not evenly distributed but sufficient for what we need.
-        MIN + (self.rng.next_u32() as u16) % (MAX - MIN)
+/// Connect to the config dealer, read the one-time, length-prefixed
+/// [ChannelParams] message it sends, and close the connection.
+fn connect_config_dealer_and_read_config<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+) -> Result<ChannelParams, Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    ctx.tls_participant.reset(Role::Client);
+    connect(
+        ctx,
+        yielder,
+        ctx.participant_handle,
+        ctx.config.config_server,
+    )?;
+
+    let wait_ctx = ctx.clone();
+    if wait_until_or_timeout(ctx, yielder, HANDSHAKE_TIMEOUT_MILLIS, move || {
+        wait_ctx.tls_participant.recv_queue() >= 2
+    })
+    .is_err()
+    {
+        abort_socket(ctx, ctx.participant_handle);
+        return Err(Error::Timeout);
+    }
+    let mut len_buf = [0u8; 2];
+    ctx.tls_participant.recv_slice(&mut len_buf);
+    let len = u16::from_be_bytes(len_buf) as usize;
+
+    let wait_ctx = ctx.clone();
+    if wait_until_or_timeout(ctx, yielder, HANDSHAKE_TIMEOUT_MILLIS, move || {
+        wait_ctx.tls_participant.recv_queue() >= len
+    })
+    .is_err()
+    {
+        abort_socket(ctx, ctx.participant_handle);
+        return Err(Error::Timeout);
+    }
+    let mut buf = alloc::vec![0u8; len];
+    ctx.tls_participant.recv_slice(&mut buf);
+    let channel_params = ChannelParams::decode(&buf)?;
+
+    ctx.iface
+        .borrow_mut()
+        .get_socket::<TcpSocket>(ctx.participant_handle)
+        .close();
+    Ok(channel_params)
+}
+
+/// The whole demo lifecycle, run as a single task: fetch config once, then
+/// repeatedly listen for/propose a channel and run it until closed.
+fn run<'cl, DeviceT>(
+    ctx: &Rc<Ctx<'cl, DeviceT>>,
+    yielder: &corosensei::Yielder<WaitResult, WaitRequest<'cl>>,
+) -> Result<(), Error>
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    let channel_params = connect_config_dealer_and_read_config(ctx, yielder)?;
+
+    loop {
+        wait_sockets_closed(ctx, yielder);
+        let channel = match listen_for_peer(ctx, yielder, &channel_params) {
+            Ok(channel) => channel,
+            // A wedged handshake/proposal shouldn't take the whole demo down;
+            // go back to a safe, known state (closed sockets, Listening) and
+            // give the next peer a fresh chance.
+            Err(Error::Timeout) => continue,
+            Err(e) => return Err(e),
+        };
+        run_active_channel(ctx, yielder, channel, &channel_params)?;
     }
 }
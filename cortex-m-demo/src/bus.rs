@@ -1,33 +1,55 @@
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 
+use alloc::rc::Rc;
 use perun::wire::BytesBus;
 use smoltcp::{
     iface::{Interface, SocketHandle},
     phy::Device,
-    socket::TcpSocket,
 };
 
+use crate::tls::TlsSocket;
+
 pub struct Bus<'iface, DeviceT>
 where
     DeviceT: for<'d> Device<'d>,
 {
     pub iface: &'iface RefCell<Interface<'iface, DeviceT>>,
     pub participant_handle: SocketHandle,
+    pub participant_dial_handle: SocketHandle,
     pub service_handle: SocketHandle,
+    /// Which of `participant_handle`/`participant_dial_handle` carries the
+    /// live participant conversation right now. `Application` owns this; see
+    /// `application::propose_to_peer` for why there are two candidate sockets.
+    pub active_participant_handle: Rc<Cell<SocketHandle>>,
+    /// Shared with the `Application`'s receive path, see the module docs in
+    /// [crate::tls] for why.
+    pub tls_participant: TlsSocket,
+    pub tls_participant_dial: TlsSocket,
+    pub tls_service: TlsSocket,
 }
 
 impl<'iface, DeviceT> Bus<'iface, DeviceT>
 where
     DeviceT: for<'d> Device<'d>,
 {
+    fn tls_for(&self, handle: SocketHandle) -> &TlsSocket {
+        if handle == self.service_handle {
+            &self.tls_service
+        } else if handle == self.participant_dial_handle {
+            &self.tls_participant_dial
+        } else {
+            &self.tls_participant
+        }
+    }
+
     fn send(&self, handle: SocketHandle, msg: &[u8]) {
+        let tls = self.tls_for(handle);
         let mut iface = self.iface.borrow_mut();
-        let socket = iface.get_socket::<TcpSocket>(handle);
         // Note: In this implementation the entire message has to fit into the
         // tx buffer. To loosen that requirement you'd need some way to queue
         // half the data and resume later, which is not easily doable without
         // async afaict.
-        let count_written = socket.send_slice(msg).unwrap();
+        let count_written = tls.send_slice(&mut iface, handle, msg).unwrap();
         if count_written != msg.len() {
             panic!(
                 "Could not send message, wrote {count_written}/{} bytes",
@@ -55,6 +77,6 @@ where
         _recipient: &perun::wire::Identity,
         msg: &[u8],
     ) {
-        self.send(self.participant_handle, msg)
+        self.send(self.active_participant_handle.get(), msg)
     }
 }
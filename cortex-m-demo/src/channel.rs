@@ -5,24 +5,112 @@
 //! - Easier handling because it is just a single struct instead of having
 //!   things in the type system for compile time errors.
 
-use alloc::string::String;
+use alloc::{collections::VecDeque, string::String};
 use perun::{
     abiencode::types::U256,
     channel::{self, ProposedChannel},
     messages::{FunderReplyMessage, ParticipantMessage, WatcherReplyMessage},
+    sig::EthSigner,
     wire::MessageBus,
 };
 
-pub struct Channel<'cl, B: MessageBus> {
-    inner: ChannelInner<'cl, B>,
+/// Caller-chosen identifier for an [Channel::update] call. Borrowed from
+/// LDK's payment-idempotency model: resubmitting the same `UpdateId` (e.g.
+/// because the caller isn't sure whether an earlier call actually went
+/// through after a reconnect) is a no-op that returns the original call's
+/// outcome instead of risking a second, duplicate payment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateId(pub [u8; 32]);
+
+/// How eagerly [Channel::update] retries an update that was rejected or
+/// whose `ChannelUpdateAccMsg` never arrived, before giving up and
+/// surfacing [Error::UpdateFailed] to the caller.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    /// Total number of times to submit the update, including the first
+    /// attempt. A rejection/timeout on the last attempt is terminal.
+    pub max_attempts: u32,
+    /// How many [Channel::poll_retries] ticks (one per `Application::poll`
+    /// call while this channel is active) to wait for a
+    /// `ChannelUpdateAccMsg`/`ChannelUpdateRejMsg` before treating the
+    /// attempt as lost and retrying.
+    pub deadline_ticks: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            deadline_ticks: 50,
+        }
+    }
+}
+
+/// Outcome of an update we've finished retrying, kept around so a
+/// resubmission of the same [UpdateId] can return it instead of re-sending.
+#[derive(Debug, Clone)]
+enum UpdateOutcome {
+    Accepted,
+    Failed(String),
+}
+
+/// Bookkeeping for the `update()` call currently in flight, so it can be
+/// resent on rejection/timeout without the caller having to ask again.
+#[derive(Clone)]
+struct PendingUpdate {
+    /// `None` for the final update [Channel::shutdown] proposes internally -
+    /// it isn't a caller-visible `update()` call, so it has no [UpdateId] to
+    /// record a completion under.
+    id: Option<UpdateId>,
+    amount: U256,
+    is_final: bool,
+    retry: Retry,
+    /// Attempts made so far, including the one currently in flight.
+    attempts: u32,
+    /// [Channel]'s tick counter as of this attempt's submission, see
+    /// [Retry::deadline_ticks].
+    submitted_at_tick: u64,
+    /// Set when this is the final update [Channel::shutdown] proposed, so
+    /// its acceptance starts the `ShutdownMsg` handshake instead of just
+    /// completing like an app-level payment, and exhausting its `retry`
+    /// budget falls back to [Channel::force_close] instead of surfacing
+    /// [Error::UpdateFailed].
+    shutdown: bool,
+}
+
+/// How many finished updates' outcomes [Channel] remembers for idempotent
+/// resubmission. This demo only ever has one caller retrying one update at a
+/// time, so a small ring is enough to survive "rejected/timed out, caller
+/// resubmits the same id" without growing unbounded.
+const MAX_COMPLETED_UPDATES: usize = 16;
+
+/// The [Channel::shutdown] handshake in progress: our final update has been
+/// accepted and our own `ShutdownMsg` sent, waiting for the peer's.
+struct Closing {
+    /// [Channel]'s tick counter value at which to give up on the peer's
+    /// `ShutdownMsg` and fall back to [Channel::force_close].
+    deadline_tick: u64,
+}
+
+/// How many [Channel::poll_retries] ticks to wait for the peer's
+/// `ShutdownMsg` after sending our own, before falling back to
+/// [Channel::force_close].
+const SHUTDOWN_DEADLINE_TICKS: u64 = 100;
+
+pub struct Channel<'cl, B: MessageBus, S: EthSigner> {
+    inner: ChannelInner<'cl, B, S>,
+    pending: Option<PendingUpdate>,
+    completed: VecDeque<(UpdateId, UpdateOutcome)>,
+    closing: Option<Closing>,
+    tick: u64,
 }
 
-enum ChannelInner<'cl, B: MessageBus> {
-    Proposed(channel::ProposedChannel<'cl, B>),
-    AgreedUpon(channel::AgreedUponChannel<'cl, B>),
-    Signed(channel::SignedChannel<'cl, B>, bool, bool),
+enum ChannelInner<'cl, B: MessageBus, S: EthSigner> {
+    Proposed(channel::ProposedChannel<'cl, B, S>),
+    AgreedUpon(channel::AgreedUponChannel<'cl, B, S>),
+    Signed(channel::SignedChannel<'cl, B, S>, bool, bool),
     Active(
-        channel::ActiveChannel<'cl, B>,
+        channel::ActiveChannel<'cl, B, S>,
         Option<channel::ChannelUpdate>,
     ),
     // We store owned values in this enum and need to move the channel out of
@@ -43,6 +131,10 @@ enum ChannelInner<'cl, B: MessageBus> {
     TemporaryInvalidState,
 
     ForceClosed,
+    /// Both sides signed a final state and exchanged `ShutdownMsg`s, so the
+    /// channel is ready to be settled on-chain without going through the
+    /// Watcher's dispute process. See [Channel::shutdown].
+    CooperativelyClosed,
 }
 
 #[derive(Debug)]
@@ -59,6 +151,16 @@ pub enum Error {
     Accept(channel::AcceptError),
     ApplyUpdate(channel::ApplyError),
     NotEnoughFunds,
+    Reestablish(channel::ReestablishError),
+    /// An `update()`'s [Retry] budget was exhausted, or a resubmission of an
+    /// [UpdateId] that previously failed this way. Carries the reason the
+    /// last attempt was rejected (or a fixed message for a timeout).
+    UpdateFailed(String),
+    /// `update()` was called with an [UpdateId] different from the one
+    /// currently in flight; this demo only tracks one outstanding update at
+    /// a time, matching [Channel::update]'s existing single-pending-update
+    /// limitation.
+    UpdateInFlight,
 }
 impl From<channel::HandleAcceptError> for Error {
     fn from(e: channel::HandleAcceptError) -> Self {
@@ -105,11 +207,20 @@ impl From<channel::ApplyError> for Error {
         Self::ApplyUpdate(e)
     }
 }
+impl From<channel::ReestablishError> for Error {
+    fn from(e: channel::ReestablishError) -> Self {
+        Self::Reestablish(e)
+    }
+}
 
-impl<'cl, B: MessageBus> Channel<'cl, B> {
-    pub fn new(channel: ProposedChannel<'cl, B>) -> Self {
+impl<'cl, B: MessageBus, S: EthSigner> Channel<'cl, B, S> {
+    pub fn new(channel: ProposedChannel<'cl, B, S>) -> Self {
         Self {
             inner: ChannelInner::Proposed(channel),
+            pending: None,
+            completed: VecDeque::new(),
+            closing: None,
+            tick: 0,
         }
     }
 
@@ -123,8 +234,8 @@ impl<'cl, B: MessageBus> Channel<'cl, B> {
     fn progress<F>(&mut self, f: F) -> Result<(), Error>
     where
         F: FnOnce(
-            ChannelInner<'cl, B>,
-        ) -> Result<ChannelInner<'cl, B>, (ChannelInner<'cl, B>, Error)>,
+            ChannelInner<'cl, B, S>,
+        ) -> Result<ChannelInner<'cl, B, S>, (ChannelInner<'cl, B, S>, Error)>,
     {
         // Move ChannelInner out of self so we get ownership of the variant.
         let mut inner = ChannelInner::TemporaryInvalidState;
@@ -150,11 +261,59 @@ impl<'cl, B: MessageBus> Channel<'cl, B> {
         }
     }
 
-    pub fn update(&mut self, amount: U256, is_final: bool) -> Result<(), Error> {
-        // This function does not use self.progress because it was written at a
-        // time where using it was a pain, given the reference to ch inside of
-        // update. This can probably be implemented in a cleaner way using
-        // self.progress by now.
+    /// Propose sending `amount` to the other participant (or closing the
+    /// channel, if `is_final`), tagged with a caller-chosen `id`.
+    ///
+    /// Calling this again with an `id` that's already completed is a no-op
+    /// that returns the original outcome instead of re-sending - safe to do
+    /// after a reconnect when the caller isn't sure whether the first call
+    /// got through. Calling it again with the `id` that's currently in
+    /// flight is also a no-op (the existing attempt is left to run); `retry`
+    /// only takes effect when the id is first submitted. A different `id`
+    /// while one is already in flight is rejected with
+    /// [Error::UpdateInFlight], since this abstraction only tracks one
+    /// outstanding update at a time (see the note on incoming updates
+    /// below).
+    pub fn update(
+        &mut self,
+        id: UpdateId,
+        amount: U256,
+        is_final: bool,
+        retry: Retry,
+    ) -> Result<(), Error> {
+        if let Some((_, outcome)) = self.completed.iter().find(|(i, _)| *i == id) {
+            return match outcome {
+                UpdateOutcome::Accepted => Ok(()),
+                UpdateOutcome::Failed(reason) => Err(Error::UpdateFailed(reason.clone())),
+            };
+        }
+        if let Some(pending) = &self.pending {
+            return if pending.id == Some(id) {
+                Ok(())
+            } else {
+                Err(Error::UpdateInFlight)
+            };
+        }
+        self.submit_update(Some(id), amount, is_final, retry, 1, false)
+    }
+
+    /// Build and send the `ChannelUpdate` wire message for `amount`/
+    /// `is_final`, recording it as the in-flight [PendingUpdate] so
+    /// [Channel::poll_retries] and the accept/reject handlers can track it.
+    /// This function does not use self.progress because it was written at a
+    /// time where using it was a pain, given the reference to ch inside of
+    /// update. This can probably be implemented in a cleaner way using
+    /// self.progress by now.
+    #[allow(clippy::too_many_arguments)]
+    fn submit_update(
+        &mut self,
+        id: Option<UpdateId>,
+        amount: U256,
+        is_final: bool,
+        retry: Retry,
+        attempts: u32,
+        shutdown: bool,
+    ) -> Result<(), Error> {
         match self.inner {
             ChannelInner::Active(ref mut ch, ref mut update) => {
                 let mut new_state = ch.state().make_next_state();
@@ -167,6 +326,15 @@ impl<'cl, B: MessageBus> Channel<'cl, B> {
                 match ch.update(new_state) {
                     Ok(u) => {
                         *update = Some(u);
+                        self.pending = Some(PendingUpdate {
+                            id,
+                            amount,
+                            is_final,
+                            retry,
+                            attempts,
+                            submitted_at_tick: self.tick,
+                            shutdown,
+                        });
                         Ok(())
                     }
                     Err(e) => Err(e.into()),
@@ -177,6 +345,227 @@ impl<'cl, B: MessageBus> Channel<'cl, B> {
         }
     }
 
+    /// Record `id` as finished with `outcome`, evicting the oldest entry
+    /// first if [MAX_COMPLETED_UPDATES] is already reached.
+    fn complete_update(&mut self, id: UpdateId, outcome: UpdateOutcome) {
+        if self.completed.len() >= MAX_COMPLETED_UPDATES {
+            self.completed.pop_front();
+        }
+        self.completed.push_back((id, outcome));
+    }
+
+    /// Advance the retry clock by one `Application::poll` tick. If we're
+    /// waiting for the peer's `ShutdownMsg` (see [Channel::shutdown]) and
+    /// [SHUTDOWN_DEADLINE_TICKS] has elapsed, give up on the cooperative
+    /// handshake and force-close instead. Otherwise, if the pending update's
+    /// [Retry::deadline_ticks] has elapsed without an accept/reject arriving,
+    /// either resend it or give up, depending on [Retry::max_attempts]. A
+    /// no-op outside of an in-flight update/handshake.
+    pub fn poll_retries(&mut self) -> Result<(), Error> {
+        self.tick += 1;
+        if let Some(closing) = &self.closing {
+            if self.tick >= closing.deadline_tick {
+                self.closing = None;
+                return self.force_close();
+            }
+            return Ok(());
+        }
+        let expired = matches!(
+            &self.pending,
+            Some(p) if self.tick.saturating_sub(p.submitted_at_tick) >= p.retry.deadline_ticks
+        );
+        if !expired {
+            return Ok(());
+        }
+        let pending = self.pending.take().unwrap();
+        if pending.attempts < pending.retry.max_attempts {
+            self.submit_update(
+                pending.id,
+                pending.amount,
+                pending.is_final,
+                pending.retry,
+                pending.attempts + 1,
+                pending.shutdown,
+            )
+        } else if pending.shutdown {
+            self.force_close()
+        } else {
+            let reason = String::from("timed out waiting for ChannelUpdateAccMsg");
+            self.complete_update(pending.id.unwrap(), UpdateOutcome::Failed(reason.clone()));
+            Err(Error::UpdateFailed(reason))
+        }
+    }
+
+    /// Handle a `ChannelUpdateAccMsg` for the update we have in flight. If it
+    /// was the final update [Channel::shutdown] proposed, this starts the
+    /// `ShutdownMsg` handshake; otherwise it's recorded as accepted so a
+    /// resubmission of the same [UpdateId] returns `Ok(())` instead of
+    /// re-sending.
+    fn handle_update_accepted(&mut self, msg: ParticipantMessage) -> Result<(), Error> {
+        let result = self.progress(|inner| match (inner, msg) {
+            (
+                ChannelInner::Active(mut ch, Some(mut update)),
+                ParticipantMessage::ChannelUpdateAccepted(msg),
+            ) => {
+                match update.participant_accepted(&ch, 1, msg) {
+                    Ok(_) => {}
+                    Err(e) => return Err((ChannelInner::Active(ch, Some(update)), e.into())),
+                }
+                match update.apply(&mut ch) {
+                    Ok(_) => Ok(ChannelInner::Active(ch, None)),
+                    Err(e) => Err((ChannelInner::Active(ch, Some(update)), e.into())),
+                }
+            }
+            (ChannelInner::TemporaryInvalidState, _) => unreachable!(),
+            (inner, _) => Err((inner, Error::InvalidState)),
+        });
+        if result.is_ok() {
+            if let Some(pending) = self.pending.take() {
+                if pending.shutdown {
+                    return self.begin_shutdown_handshake();
+                }
+                if let Some(id) = pending.id {
+                    self.complete_update(id, UpdateOutcome::Accepted);
+                }
+            }
+        }
+        result
+    }
+
+    /// Handle a `ChannelUpdateRejMsg` for the update we have in flight: retry
+    /// it if its [Retry] budget allows. Otherwise, if it was the final update
+    /// [Channel::shutdown] proposed, fall back to [Channel::force_close];
+    /// for a regular `update()` call, record it as failed so a resubmission
+    /// of the same [UpdateId] returns this rejection instead of retrying
+    /// forever.
+    fn handle_update_rejected(&mut self, reason: String) -> Result<(), Error> {
+        self.progress(|inner| match inner {
+            ChannelInner::Active(ch, Some(_)) => Ok(ChannelInner::Active(ch, None)),
+            ChannelInner::TemporaryInvalidState => unreachable!(),
+            inner => Err((inner, Error::InvalidState)),
+        })?;
+        match self.pending.take() {
+            Some(pending) if pending.attempts < pending.retry.max_attempts => self.submit_update(
+                pending.id,
+                pending.amount,
+                pending.is_final,
+                pending.retry,
+                pending.attempts + 1,
+                pending.shutdown,
+            ),
+            Some(pending) if pending.shutdown => self.force_close(),
+            Some(pending) => {
+                self.complete_update(pending.id.unwrap(), UpdateOutcome::Failed(reason.clone()));
+                Err(Error::UpdateFailed(reason))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Propose a final update settling the channel (if one hasn't already
+    /// been agreed upon) and, once the peer accepts it, exchange
+    /// `ShutdownMsg`s to settle the channel on-chain directly instead of
+    /// going through the Watcher's dispute process. Falls back to
+    /// [Channel::force_close] if the peer stops responding to either step
+    /// within its [Retry]/[SHUTDOWN_DEADLINE_TICKS] budget.
+    ///
+    /// Technical debt: this transitions to [ChannelInner::CooperativelyClosed]
+    /// as soon as both `ShutdownMsg`s are exchanged, without waiting for an
+    /// on-chain withdraw confirmation - [FunderReplyMessage] doesn't have a
+    /// variant for one in this demo yet.
+    pub fn shutdown(&mut self) -> Result<(), Error> {
+        if self.pending.is_some() || self.closing.is_some() {
+            return Ok(());
+        }
+        match &self.inner {
+            ChannelInner::Active(ch, _) if ch.state().is_final => self.begin_shutdown_handshake(),
+            ChannelInner::Active(_, _) => {
+                self.submit_update(None, 0.into(), true, Retry::default(), 1, true)
+            }
+            _ => Err(Error::InvalidState),
+        }
+    }
+
+    /// Broadcast our `ShutdownMsg` and start waiting for the peer's, see
+    /// [Channel::shutdown].
+    fn begin_shutdown_handshake(&mut self) -> Result<(), Error> {
+        match &self.inner {
+            ChannelInner::Active(ch, _) => {
+                ch.send_shutdown();
+                self.closing = Some(Closing {
+                    deadline_tick: self.tick + SHUTDOWN_DEADLINE_TICKS,
+                });
+                Ok(())
+            }
+            _ => Err(Error::InvalidState),
+        }
+    }
+
+    /// Handle an incoming `ShutdownMsg`: the peer has agreed the current
+    /// state is final and wants to settle on-chain directly. Replies with
+    /// our own `ShutdownMsg` unless we already sent one via
+    /// [Channel::shutdown]/[Channel::begin_shutdown_handshake].
+    fn handle_shutdown(&mut self) -> Result<(), Error> {
+        let already_closing = self.closing.is_some();
+        self.progress(|inner| match inner {
+            ChannelInner::Active(ch, None) if ch.state().is_final => {
+                if !already_closing {
+                    ch.send_shutdown();
+                }
+                Ok(ChannelInner::CooperativelyClosed)
+            }
+            ChannelInner::TemporaryInvalidState => unreachable!(),
+            inner => Err((inner, Error::InvalidState)),
+        })?;
+        self.closing = None;
+        Ok(())
+    }
+
+    /// Whether this channel has settled, either cooperatively (see
+    /// [Channel::shutdown]) or via the Watcher's dispute process (see
+    /// [Channel::force_close]).
+    pub fn is_closed(&self) -> bool {
+        matches!(
+            self.inner,
+            ChannelInner::ForceClosed | ChannelInner::CooperativelyClosed
+        )
+    }
+
+    /// Whether this channel closed via the Watcher's dispute process (see
+    /// [Channel::force_close]) rather than cooperatively (see
+    /// [Channel::shutdown]) - used by the caller to decide whether to start
+    /// timing out the dispute's challenge window.
+    pub fn is_disputed(&self) -> bool {
+        matches!(self.inner, ChannelInner::ForceClosed)
+    }
+
+    /// Whether both participants have funded the channel, i.e. it has
+    /// reached `ChannelInner::Active` - used by the caller to notice the
+    /// `Signed -> Active` transition [Channel::process_funder_reply]/
+    /// [Channel::process_watcher_reply] can cause.
+    pub fn is_active(&self) -> bool {
+        matches!(self.inner, ChannelInner::Active(_, _))
+    }
+
+    /// The [ParticipantMessage::ChannelSync] handshake to send the peer when
+    /// resuming this channel over a freshly (re-)established connection.
+    /// `None` outside of `ChannelInner::Active`, since only an active channel
+    /// has a signed state worth reconciling.
+    pub fn sync_msg(&self) -> Option<ParticipantMessage> {
+        match &self.inner {
+            ChannelInner::Active(ch, _) => Some(ParticipantMessage::ChannelSync(ch.sync_msg())),
+            _ => None,
+        }
+    }
+
+    /// Broadcast [Channel::sync_msg] to the peer, e.g. after reconnecting. A
+    /// no-op outside of `ChannelInner::Active`.
+    pub fn send_sync_msg(&self) {
+        if let ChannelInner::Active(ch, _) = &self.inner {
+            ch.send_sync_msg();
+        }
+    }
+
     pub fn force_close(&mut self) -> Result<(), Error> {
         self.progress(|inner| match inner {
             ChannelInner::Active(ch, update) => match ch.force_close() {
@@ -231,10 +620,26 @@ impl<'cl, B: MessageBus> Channel<'cl, B> {
 
     pub fn process_participant_msg(&mut self, msg: ParticipantMessage) -> Result<(), Error> {
         // Notes on invalid pairs:
-        // - `ParticipantMessage::Auth` is not for a single channel and doesn't
-        //   make sense in this context.
+        // - `ParticipantMessage::AuthChallenge`/`AuthResponse` are not for a
+        //   single channel and don't make sense in this context.
         // - Incomming proposals `ParticipantMessage::ChannelProposal` are not
         //   for a specific channel either
+
+        // Accept/reject for our own in-flight update needs `self.pending` to
+        // drive retries, which `self.progress`'s closure can't see - pulled
+        // out into their own handlers instead of being arms below.
+        if matches!(self.inner, ChannelInner::Active(_, Some(_))) {
+            if let ParticipantMessage::ChannelUpdateAccepted(_) = &msg {
+                return self.handle_update_accepted(msg);
+            }
+            if let ParticipantMessage::ChannelUpdateRejected { reason, .. } = &msg {
+                let reason = reason.clone();
+                return self.handle_update_rejected(reason);
+            }
+        }
+        if let ParticipantMessage::Shutdown(_) = &msg {
+            return self.handle_shutdown();
+        }
         self.progress(|inner| match (inner, msg) {
             // ProposedChannel
             (ChannelInner::Proposed(mut ch), ParticipantMessage::ProposalAccepted(msg)) => {
@@ -323,25 +728,36 @@ impl<'cl, B: MessageBus> Channel<'cl, B> {
                 }
                 Ok(ChannelInner::Active(ch, None))
             }
-            (
-                ChannelInner::Active(mut ch, Some(mut update)),
-                ParticipantMessage::ChannelUpdateAccepted(msg),
-            ) => {
-                match update.participant_accepted(&ch, 1, msg) {
-                    Ok(_) => {}
-                    Err(e) => return Err((ChannelInner::Active(ch, Some(update)), e.into())),
-                }
-                match update.apply(&mut ch) {
-                    Ok(_) => Ok(ChannelInner::Active(ch, None)),
-                    Err(e) => Err((ChannelInner::Active(ch, Some(update)), e.into())),
+            // `Active(_, Some(_))` + `ChannelUpdateAccepted`/`ChannelUpdateRejected` are
+            // handled above via `handle_update_accepted`/`handle_update_rejected`, which
+            // need `self.pending` to drive retries.
+
+            // Resuming after a reconnect: `Application`'s keepalive redials
+            // and sends our own `sync_msg()` (see `reconnect_and_resync`);
+            // this arm applies the peer's `ChannelSync`, whether it is the
+            // reply to ours or one the peer sent on its own.
+            (ChannelInner::Active(mut ch, update), ParticipantMessage::ChannelSync(msg)) => {
+                match ch.reestablish(msg) {
+                    Ok(channel::ReestablishOutcome::Applied) => Ok(ChannelInner::Active(ch, None)),
+                    Ok(_) => Ok(ChannelInner::Active(ch, update)),
+                    Err(channel::ReestablishError::VersionDiverged) => match ch.force_close() {
+                        Ok(_) => Ok(ChannelInner::ForceClosed),
+                        Err((ch, e)) => Err((ChannelInner::Active(ch, update), e.into())),
+                    },
+                    Err(e) => Err((ChannelInner::Active(ch, update), e.into())),
                 }
             }
-            (
-                ChannelInner::Active(ch, Some(_)),
-                ParticipantMessage::ChannelUpdateRejected { .. },
-            ) => Ok(ChannelInner::Active(ch, None)),
             (ChannelInner::TemporaryInvalidState, _) => unreachable!(),
             (inner, _) => Err((inner, Error::InvalidState)),
-        })
+        })?;
+        // `ChannelSync` reestablishment is the only other path (besides the
+        // accept/reject handlers above, which already clear it themselves)
+        // that can drop the in-flight `ChannelUpdate` back to `None` - keep
+        // `self.pending` from lingering on a proposal the reestablished
+        // state no longer has outstanding.
+        if matches!(self.inner, ChannelInner::Active(_, None)) {
+            self.pending = None;
+        }
+        Ok(())
     }
 }
@@ -6,15 +6,23 @@ mod application;
 mod bus;
 mod button;
 mod channel;
+mod scheduler;
+mod tls;
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 
+use alloc::rc::Rc;
 use application::{Application, Config, MAX_MESSAGE_SIZE};
 use bus::Bus;
 use button::DebouncedButton;
+use channel::Retry;
 use cortex_m::{interrupt::Mutex, peripheral::SYST};
 use cortex_m_rt::{entry, exception};
-use perun::{sig::Signer, wire::ProtoBufEncodingLayer, PerunClient};
+use perun::{
+    sig::Signer,
+    wire::{ProtoBufEncodingLayer, RateLimits, VersionRange},
+    Address, PerunClient,
+};
 use rand::{rngs::StdRng, SeedableRng};
 use rand_core::RngCore;
 use smoltcp::{
@@ -33,6 +41,7 @@ use stm32_eth::{
     stm32::{CorePeripherals, Peripherals},
     EthPins,
 };
+use tls::{Role, TlsSocket};
 
 // Panic handler
 // use panic_halt as _;
@@ -69,6 +78,16 @@ const CIDR_PREFIX_LEN: u8 = 24;
 const MAC_ADDRESS: EthernetAddress = EthernetAddress([0x00, 0x00, 0xDE, 0xAD, 0xBE, 0xEF]);
 const DEBOUNCE_THRESHHOLD: u64 = 100; // Milliseconds
 
+// Pre-shared key authenticating the TLS sessions to the config dealer and the
+// other participant, baked into the device like the demo's other addresses.
+// A real deployment would provision this per-device instead of hard-coding it.
+const TLS_PSK: [u8; 32] = [0x42; 32];
+
+// Ethereum address the participant handshake (see `application::Config::peer_address`)
+// expects the other participant to control, baked in the same way as
+// `TLS_PSK` - a real deployment would provision this per-device instead.
+const PEER_ADDRESS: Address = Address([0x00; 20]);
+
 static TIME: Mutex<RefCell<u64>> = Mutex::new(RefCell::new(0));
 
 type LedOutputPin<const N: u8> = gpio::Pin<'B', N, gpio::Output<gpio::PushPull>>;
@@ -147,7 +166,7 @@ fn main() {
     let mut ip_addrs = [ip_addr];
     let mut neighbor_storage = [None; 16];
     let neighbor_cache = NeighborCache::new(&mut neighbor_storage[..]);
-    let mut sockets: [_; 2] = Default::default();
+    let mut sockets: [_; 3] = Default::default();
     let mut iface = InterfaceBuilder::new(&mut ethernet.dma, &mut sockets[..])
         .random_seed(hw_rng.next_u64())
         .hardware_addr(HardwareAddress::Ethernet(MAC_ADDRESS))
@@ -177,6 +196,17 @@ fn main() {
         TcpSocketBuffer::new(&mut participant_tx_buffer[..]),
     );
     let participant_handle = iface.add_socket(participant_socket);
+    // Dedicated outbound socket for proposing a channel, kept separate from
+    // `participant_handle` (which stays listening) so we can dial the other
+    // participant while still accepting their dial-back - see
+    // `application::propose_to_peer` for why that matters.
+    let mut participant_dial_rx_buffer = [0; MAX_MESSAGE_SIZE + 2];
+    let mut participant_dial_tx_buffer = [0; MAX_MESSAGE_SIZE + 2];
+    let participant_dial_socket = TcpSocket::new(
+        TcpSocketBuffer::new(&mut participant_dial_rx_buffer[..]),
+        TcpSocketBuffer::new(&mut participant_dial_tx_buffer[..]),
+    );
+    let participant_dial_handle = iface.add_socket(participant_dial_socket);
     // Funder/Watcher communication
     let mut service_rx_buffer = [0; MAX_MESSAGE_SIZE + 2];
     // service_tx_buffer currently needs to have space for FundingRequestMsg
@@ -202,6 +232,7 @@ fn main() {
         service_server: (IpAddress::from(SERVER_IP_ADDRESS), SERVER_SERVICE_PORT),
         listen_port: DEVICE_LISTEN_PORT,
         participants: ["Bob", "Alice"],
+        peer_address: PEER_ADDRESS,
     };
 
     // Move the interface into a RefCell because we need a mutable reference in
@@ -211,10 +242,27 @@ fn main() {
     // on the bus and thus need to mutably borrow the interface, too).
     let iface = &RefCell::new(iface);
 
+    // Shared with `Application`'s receive path below, see the module docs in
+    // `tls` for why both sides need a clone of the same `TlsSocket`.
+    let tls_participant = TlsSocket::new(Role::Client, TLS_PSK);
+    let tls_participant_dial = TlsSocket::new(Role::Client, TLS_PSK);
+    let tls_service = TlsSocket::new(Role::Client, TLS_PSK);
+
+    // Which of `participant_handle`/`participant_dial_handle` currently
+    // carries the live participant conversation; shared with `Bus` so its
+    // sends go out on the right socket. See `application::propose_to_peer`
+    // for why there are two candidate sockets in the first place.
+    let active_participant_handle = Rc::new(Cell::new(participant_handle));
+
     let bus = Bus {
         iface,
         participant_handle,
+        participant_dial_handle,
         service_handle,
+        active_participant_handle: active_participant_handle.clone(),
+        tls_participant: tls_participant.clone(),
+        tls_participant_dial: tls_participant_dial.clone(),
+        tls_service: tls_service.clone(),
     };
     // We need/want randomness for signing and for generating the ephemeral
     // port numbers. Creating a new RNG from the one we got is the easiest
@@ -224,17 +272,26 @@ fn main() {
     // this is small, as initialization is fast and the RNGs internal state
     // is 136 bytes (StdRng currently uses ChaCha12).
     let mut rng2 = StdRng::seed_from_u64(hw_rng.next_u64());
-    let signer = Signer::new(&mut rng2);
+    let signer = Signer::random(&mut rng2);
     let addr = signer.address();
-    let client = PerunClient::new(ProtoBufEncodingLayer { bus }, signer);
+    let client = PerunClient::new(
+        ProtoBufEncodingLayer::new(bus, VersionRange { min: 1, max: 1 }, RateLimits::default()),
+        signer,
+        1337,
+    );
     let mut app = Application::new(
         participant_handle,
+        participant_dial_handle,
         service_handle,
         config,
         rng2,
         addr,
         &client,
         iface,
+        tls_participant,
+        tls_participant_dial,
+        tls_service,
+        active_participant_handle,
     );
 
     // main application loop
@@ -250,7 +307,7 @@ fn main() {
         }
 
         // Application state machine
-        app.poll().unwrap();
+        app.poll(time).unwrap();
 
         // Handle input buttons
         if propose_channel_btn.is_falling_edge(time) {
@@ -260,13 +317,14 @@ fn main() {
             }
         }
         if update_btn.is_rising_edge(time) {
-            match app.update(100.into(), false) {
+            let id = app.generate_update_id();
+            match app.update(id, 100.into(), false, Retry::default()) {
                 Ok(_) => green_led.toggle(),
                 Err(_) => red_led.toggle(),
             }
         }
         if normal_close_btn.is_falling_edge(time) {
-            match app.update(0.into(), true) {
+            match app.shutdown() {
                 Ok(_) => green_led.toggle(),
                 Err(_) => red_led.toggle(),
             }
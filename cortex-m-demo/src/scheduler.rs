@@ -0,0 +1,162 @@
+//! Cooperative, stackful-coroutine scheduler.
+//!
+//! [application] used to need an explicit `ApplicationState` enum purely
+//! because setup/channel logic has to run alongside the main loop's
+//! `iface.poll` and some steps (a TCP connect, waiting for a message) cannot
+//! finish immediately, and there is no async runtime here. This module
+//! replaces that enum with a small scheduler so the same logic can be
+//! written as straight-line blocking code instead.
+//!
+//! Each [Task] is a [ScopedCoroutine] running on its own fixed-size
+//! [OwnedStack], allocated once up front - no stack growth, no recursion
+//! across tasks. A task yields a [WaitRequest] to suspend itself, and
+//! [Scheduler::poll] resumes it with a [WaitResult] once the request's
+//! `event` predicate returns true or its `timeout` (compared against the
+//! monotonic millisecond clock passed into `poll`) elapses.
+//!
+//! [application]: crate::application
+
+use alloc::{boxed::Box, vec::Vec};
+use corosensei::{
+    stack::{Stack, StackPointer},
+    CoroutineResult, ScopedCoroutine, Yielder,
+};
+
+/// Size of each task's stack. All setup/channel logic in [application] runs
+/// on one of these, so it has to be generous enough for the deepest call
+/// chain (protobuf decode, signature verification, ...) that function uses.
+///
+/// [application]: crate::application
+pub const STACK_SIZE: usize = 4096;
+
+/// A fixed-size stack owned by a single [Task] for its whole lifetime.
+/// Allocated once on the heap when the task is spawned; unlike the OS thread
+/// stacks a hosted stackful-coroutine library would use, there is no paging
+/// or guard page here, just a flat buffer, so `STACK_SIZE` needs to be picked
+/// with headroom and isn't enforced at runtime if exceeded.
+pub struct OwnedStack(Box<[u8; STACK_SIZE]>);
+
+impl OwnedStack {
+    pub fn new() -> Self {
+        Self(Box::new([0u8; STACK_SIZE]))
+    }
+}
+
+// Safety: `base()`/`limit()` bound exactly the `STACK_SIZE` bytes owned by
+// `self.0`, which stays allocated (the `Box` never moves its heap
+// allocation) for as long as `self` is alive, which outlives every
+// coroutine built on top of it because `Task` owns both.
+unsafe impl Stack for OwnedStack {
+    fn base(&self) -> StackPointer {
+        // Stacks grow down, so `base` is the highest address.
+        let top = unsafe { self.0.as_ptr().add(STACK_SIZE) } as usize;
+        unsafe { StackPointer::new_unchecked(top) }
+    }
+
+    fn limit(&self) -> StackPointer {
+        let bottom = self.0.as_ptr() as usize;
+        unsafe { StackPointer::new_unchecked(bottom) }
+    }
+}
+
+/// What a parked [Task] is waiting for before the scheduler resumes it.
+///
+/// `event` is a closure rather than the bare `fn()` you might expect from a
+/// generic wait condition, because every predicate we actually need
+/// ("participant socket `may_recv()`", "a command was queued", ...) has to
+/// borrow the task's own context (socket handles, `Interface`, ...), which a
+/// capture-less `fn` pointer cannot do.
+pub struct WaitRequest<'a> {
+    /// Re-evaluated on every [Scheduler::poll]; `None` means "just resume me
+    /// again next tick", which is what the blocking IO helpers in
+    /// [application](crate::application) use instead of expressing socket
+    /// readiness as a pure predicate.
+    pub event: Option<Box<dyn Fn() -> bool + 'a>>,
+    /// Absolute deadline on the same millisecond clock passed to
+    /// [Scheduler::poll]. `None` means wait forever.
+    pub timeout: Option<u64>,
+}
+
+/// Why a parked task was resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitResult {
+    /// The `event` predicate returned true (or there wasn't one).
+    Completed,
+    /// `timeout` elapsed before `event` did.
+    TimedOut,
+}
+
+/// One cooperatively-scheduled task. See the module docs for the overall
+/// design; `'a` is the lifetime of whatever the task's closure borrows (in
+/// practice, the `Interface`/sockets/`PerunClient` it was spawned with).
+pub type Task<'a> = ScopedCoroutine<'a, WaitResult, WaitRequest<'a>, (), OwnedStack>;
+
+struct Parked<'a> {
+    task: Task<'a>,
+    wait: WaitRequest<'a>,
+}
+
+/// Round-robin scheduler for a handful of long-lived tasks.
+///
+/// There is no priority and no preemption: a task only ever gives up control
+/// by yielding a [WaitRequest], and [Scheduler::poll] resumes every task
+/// whose wait condition is satisfied, in spawn order, once per call. That
+/// mirrors the cooperative, single-threaded nature of the `ApplicationState`
+/// dispatch this replaces - nothing here runs concurrently with the main
+/// loop's own work, it just gets to interleave with it at yield points.
+pub struct Scheduler<'a> {
+    parked: Vec<Parked<'a>>,
+}
+
+impl<'a> Scheduler<'a> {
+    pub fn new() -> Self {
+        Self { parked: Vec::new() }
+    }
+
+    /// Start a new task. It runs until its first yield (or returns
+    /// immediately, if it never blocks) before this call returns.
+    pub fn spawn<F>(&mut self, stack: OwnedStack, body: F)
+    where
+        F: FnOnce(&Yielder<WaitResult, WaitRequest<'a>>) + 'a,
+    {
+        let task = ScopedCoroutine::new(stack, move |yielder, _first_resume: WaitResult| {
+            body(yielder)
+        });
+        self.resume_and_park(task, WaitResult::Completed);
+    }
+
+    fn resume_and_park(&mut self, mut task: Task<'a>, resume_with: WaitResult) {
+        match task.resume(resume_with) {
+            CoroutineResult::Yield(wait) => self.parked.push(Parked { task, wait }),
+            CoroutineResult::Return(()) => {} // Finished: nothing to reschedule.
+        }
+    }
+
+    /// Resume every task whose wait condition is satisfied. Call this once
+    /// per main-loop tick, right after `iface.poll`.
+    pub fn poll(&mut self, now_millis: u64) {
+        let mut still_parked = Vec::with_capacity(self.parked.len());
+        for mut parked in self.parked.drain(..) {
+            let event_ready = parked.wait.event.as_deref().map_or(true, |f| f());
+            let timed_out = !event_ready && parked.wait.timeout.map_or(false, |t| now_millis >= t);
+            if !event_ready && !timed_out {
+                still_parked.push(parked);
+                continue;
+            }
+
+            let result = if event_ready {
+                WaitResult::Completed
+            } else {
+                WaitResult::TimedOut
+            };
+            match parked.task.resume(result) {
+                CoroutineResult::Yield(wait) => {
+                    parked.wait = wait;
+                    still_parked.push(parked);
+                }
+                CoroutineResult::Return(()) => {}
+            }
+        }
+        self.parked = still_parked;
+    }
+}
@@ -0,0 +1,406 @@
+//! Lightweight TLS-style record layer wrapping the smoltcp `TcpSocket`s used
+//! for the participant and watcher/funder connections, so Perun traffic is
+//! encrypted and authenticated instead of assuming a trusted LAN.
+//!
+//! This is not a conformant TLS 1.3 stack - there is no certificate chain,
+//! cipher negotiation, or resumption - but it follows the same shape: a
+//! record layer (1-byte content type, 2-byte length, payload) carries an
+//! initial handshake flight before application data is allowed to flow, and
+//! callers on both sides only see cleartext once the handshake completes.
+//! Authentication is via a pre-shared secret baked into the device
+//! configuration, the same trust model `perun::wire::EncryptedLayer` uses for
+//! its `SharedSecret` mode - both peers derive the same session keys from it,
+//! so there is no separate trust-anchor/certificate step to configure.
+//!
+//! [TlsSocket] wraps a single smoltcp `TcpSocket`, identified by its
+//! `SocketHandle` (passed in on every call rather than stored, since the
+//! `Interface` is only ever borrowed for the duration of one call). It is
+//! driven from two places that need to share one session: [Application]'s
+//! poll loop owns the receive path and drives the handshake/decrypts
+//! incoming records via [TlsSocket::poll], while [Bus] only needs
+//! [TlsSocket::send_slice] to seal and write outgoing ones. Both hold a
+//! cheap clone of the same `TlsSocket` (backed by `Rc<RefCell<_>>`).
+//!
+//! [Application]: crate::application::Application
+//! [Bus]: crate::bus::Bus
+
+use alloc::{collections::VecDeque, rc::Rc, vec::Vec};
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use core::{cell::RefCell, mem};
+use rand::rngs::StdRng;
+use sha3::{Digest, Sha3_256};
+use smoltcp::{
+    iface::{Interface, SocketHandle},
+    phy::Device,
+    socket::TcpSocket,
+};
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
+
+#[derive(Debug)]
+pub enum Error {
+    Network(smoltcp::Error),
+    /// The AEAD tag did not verify, or a handshake record had the wrong size.
+    Decryption,
+    /// A record's declared length exceeded [MAX_RECORD_LEN].
+    RecordTooLarge(usize),
+}
+
+impl From<smoltcp::Error> for Error {
+    fn from(e: smoltcp::Error) -> Self {
+        Self::Network(e)
+    }
+}
+
+/// Which side sends the first handshake flight. The side that dials out
+/// (`connect`) is always the client, the side that was listening
+/// (`listen`/accept) is always the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+const CONTENT_HANDSHAKE: u8 = 0;
+const CONTENT_APPLICATION_DATA: u8 = 1;
+const RECORD_HEADER_LEN: usize = 3; // 1-byte content type + 2-byte length
+/// Largest ciphertext this demo ever needs in one record: sized for
+/// `MAX_MESSAGE_SIZE` plus the AEAD tag.
+const MAX_RECORD_LEN: usize = 600;
+
+enum Handshake {
+    /// Haven't sent our ephemeral hello yet (the server waits here until it
+    /// sees the client's).
+    Idle,
+    /// Sent our ephemeral public key, waiting for the peer's to derive keys.
+    SentHello(EphemeralSecret),
+    Established,
+}
+
+struct Session {
+    send_key: ChaCha20Poly1305,
+    recv_key: ChaCha20Poly1305,
+    send_counter: u64,
+    recv_counter: u64,
+}
+
+struct Inner {
+    role: Role,
+    psk: [u8; 32],
+    handshake: Handshake,
+    session: Option<Session>,
+    /// Bytes pulled off the rx ringbuffer that haven't formed a complete
+    /// record yet.
+    rx_raw: Vec<u8>,
+    /// Decrypted application data, in order, not yet consumed by the caller.
+    rx_plain: VecDeque<u8>,
+}
+
+/// Cheap-clone handle to a TLS session for one TCP connection. See the
+/// module docs for why this needs to be shared between [Bus](crate::bus::Bus)
+/// (send) and [Application](crate::application::Application) (receive/
+/// handshake driving).
+#[derive(Clone)]
+pub struct TlsSocket(Rc<RefCell<Inner>>);
+
+fn nonce_from_counter(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::clone_from_slice(&nonce)
+}
+
+/// Derive the two directional keys from the pre-shared secret and the
+/// ephemeral ECDH result. Mixing in `psk` is what provides authentication
+/// here (a passive attacker can complete the ECDH but doesn't know `psk`),
+/// since unlike `EncryptedLayer` this has no separate long-term identity key.
+fn derive_keys(psk: &[u8; 32], dh_ee: &[u8; 32]) -> (ChaCha20Poly1305, ChaCha20Poly1305) {
+    let kdf = |label: &[u8]| -> ChaCha20Poly1305 {
+        let mut hasher = Sha3_256::new();
+        hasher.update(psk);
+        hasher.update(dh_ee);
+        hasher.update(label);
+        let key: [u8; 32] = hasher.finalize().into();
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+    };
+    (kdf(b"client-to-server"), kdf(b"server-to-client"))
+}
+
+impl TlsSocket {
+    pub fn new(role: Role, psk: [u8; 32]) -> Self {
+        Self(Rc::new(RefCell::new(Inner {
+            role,
+            psk,
+            handshake: Handshake::Idle,
+            session: None,
+            rx_raw: Vec::new(),
+            rx_plain: VecDeque::new(),
+        })))
+    }
+
+    /// Start over for a new TCP connection on the wrapped socket, e.g. when
+    /// `TcpSocket::connect`/`listen` is about to be called again. `role` must
+    /// be set again every time because the same socket (and thus the same
+    /// `TlsSocket`) is reused for both roles over its lifetime: the
+    /// participant socket dials out as [Role::Client] (to the config
+    /// dealer) but listens/accepts as [Role::Server].
+    pub fn reset(&self, role: Role) {
+        let mut inner = self.0.borrow_mut();
+        inner.role = role;
+        inner.handshake = Handshake::Idle;
+        inner.session = None;
+        inner.rx_raw.clear();
+        inner.rx_plain.clear();
+    }
+
+    pub fn is_established(&self) -> bool {
+        matches!(self.0.borrow().handshake, Handshake::Established)
+    }
+
+    /// How many bytes of decrypted application data are ready to read, i.e.
+    /// the `TlsSocket` equivalent of `TcpSocket::recv_queue`.
+    pub fn recv_queue(&self) -> usize {
+        self.0.borrow().rx_plain.len()
+    }
+
+    /// Equivalent of `TcpSocket::peek_slice`, but over the decrypted
+    /// in-memory queue instead of smoltcp's ringbuffer, so (unlike the raw
+    /// socket) it never has trouble peeking across a buffer boundary.
+    pub fn peek_slice(&self, data: &mut [u8]) -> usize {
+        let inner = self.0.borrow();
+        let n = data.len().min(inner.rx_plain.len());
+        for (dst, src) in data.iter_mut().zip(inner.rx_plain.iter()).take(n) {
+            *dst = *src;
+        }
+        n
+    }
+
+    /// Equivalent of `TcpSocket::recv_slice`: copies out and dequeues up to
+    /// `data.len()` bytes of decrypted application data.
+    pub fn recv_slice(&self, data: &mut [u8]) -> usize {
+        let mut inner = self.0.borrow_mut();
+        let n = data.len().min(inner.rx_plain.len());
+        for dst in data.iter_mut().take(n) {
+            *dst = inner.rx_plain.pop_front().unwrap();
+        }
+        n
+    }
+
+    /// Seal `data` as a single application-data record and write it to the
+    /// socket, like `TcpSocket::send_slice` but encrypted. Like `Bus::send`,
+    /// this assumes the whole sealed record fits in one write.
+    pub fn send_slice<DeviceT: for<'d> Device<'d>>(
+        &self,
+        iface: &mut Interface<DeviceT>,
+        handle: SocketHandle,
+        data: &[u8],
+    ) -> Result<usize, Error> {
+        let record = {
+            let mut inner = self.0.borrow_mut();
+            let session = inner
+                .session
+                .as_mut()
+                .expect("send_slice called before the TLS handshake completed");
+            let counter = session.send_counter;
+            session.send_counter += 1;
+
+            let nonce = nonce_from_counter(counter);
+            let ciphertext = session
+                .send_key
+                .encrypt(&nonce, data)
+                .expect("ChaCha20Poly1305 encryption over a bounded buffer cannot fail");
+
+            let mut payload = Vec::with_capacity(8 + ciphertext.len());
+            payload.extend_from_slice(&counter.to_be_bytes());
+            payload.extend_from_slice(&ciphertext);
+            payload
+        };
+
+        let socket = iface.get_socket::<TcpSocket>(handle);
+        Self::write_record(socket, CONTENT_APPLICATION_DATA, &record)?;
+        Ok(data.len())
+    }
+
+    fn write_record(socket: &mut TcpSocket, content_type: u8, payload: &[u8]) -> Result<(), Error> {
+        if payload.len() > MAX_RECORD_LEN {
+            return Err(Error::RecordTooLarge(payload.len()));
+        }
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        header[0] = content_type;
+        header[1..].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+
+        let written = socket.send_slice(&header)? + socket.send_slice(payload)?;
+        if written != header.len() + payload.len() {
+            panic!(
+                "Could not write full TLS record, wrote {written}/{} bytes",
+                header.len() + payload.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Drive the handshake and drain/decrypt any records available on the rx
+    /// ringbuffer. Call this after every `iface.poll`, for both the listener
+    /// and dialer side, before checking [Self::is_established] or reading.
+    pub fn poll<DeviceT: for<'d> Device<'d>>(
+        &self,
+        iface: &mut Interface<DeviceT>,
+        handle: SocketHandle,
+        rng: &mut StdRng,
+    ) -> Result<(), Error> {
+        let socket = iface.get_socket::<TcpSocket>(handle);
+        if !socket.is_open() {
+            return Ok(());
+        }
+
+        self.maybe_send_client_hello(socket, rng)?;
+
+        // Drain everything currently queued. recv_slice (unlike peek_slice)
+        // is documented to cross the ringbuffer boundary correctly.
+        loop {
+            let queued = socket.recv_queue();
+            if queued == 0 {
+                break;
+            }
+            let mut chunk = [0u8; 256];
+            let to_read = queued.min(chunk.len());
+            let read = socket.recv_slice(&mut chunk[..to_read])?;
+            if read == 0 {
+                break;
+            }
+            self.0.borrow_mut().rx_raw.extend_from_slice(&chunk[..read]);
+        }
+
+        self.process_records(socket, rng)
+    }
+
+    /// The client side has to speak first: as soon as the TCP connection is
+    /// up, send our ephemeral public key if we haven't already. The server
+    /// side just waits for this in [Self::on_peer_hello].
+    fn maybe_send_client_hello(&self, socket: &mut TcpSocket, rng: &mut StdRng) -> Result<(), Error> {
+        let should_send = {
+            let inner = self.0.borrow();
+            inner.role == Role::Client && matches!(inner.handshake, Handshake::Idle) && socket.may_send()
+        };
+        if !should_send {
+            return Ok(());
+        }
+
+        let secret = EphemeralSecret::new(&mut *rng);
+        let public = X25519PublicKey::from(&secret);
+        Self::write_record(socket, CONTENT_HANDSHAKE, public.as_bytes())?;
+        self.0.borrow_mut().handshake = Handshake::SentHello(secret);
+        Ok(())
+    }
+
+    fn process_records(&self, socket: &mut TcpSocket, rng: &mut StdRng) -> Result<(), Error> {
+        loop {
+            let (content_type, payload) = {
+                let mut inner = self.0.borrow_mut();
+                if inner.rx_raw.len() < RECORD_HEADER_LEN {
+                    return Ok(());
+                }
+                let content_type = inner.rx_raw[0];
+                let length =
+                    u16::from_be_bytes([inner.rx_raw[1], inner.rx_raw[2]]) as usize;
+                if length > MAX_RECORD_LEN {
+                    return Err(Error::RecordTooLarge(length));
+                }
+                if inner.rx_raw.len() < RECORD_HEADER_LEN + length {
+                    return Ok(()); // Wait for the rest of the record.
+                }
+                let payload: Vec<u8> = inner
+                    .rx_raw
+                    .drain(..RECORD_HEADER_LEN + length)
+                    .skip(RECORD_HEADER_LEN)
+                    .collect();
+                (content_type, payload)
+            };
+
+            match content_type {
+                CONTENT_HANDSHAKE => self.on_peer_hello(socket, &payload, rng)?,
+                CONTENT_APPLICATION_DATA => self.on_application_data(&payload)?,
+                _ => return Err(Error::Decryption),
+            }
+        }
+    }
+
+    fn on_peer_hello(
+        &self,
+        socket: &mut TcpSocket,
+        payload: &[u8],
+        rng: &mut StdRng,
+    ) -> Result<(), Error> {
+        let peer_public_bytes: [u8; 32] = payload.try_into().map_err(|_| Error::Decryption)?;
+        let peer_public = X25519PublicKey::from(peer_public_bytes);
+
+        let handshake = mem::replace(&mut self.0.borrow_mut().handshake, Handshake::Idle);
+        match handshake {
+            Handshake::Idle => {
+                // We're the server, seeing the client's hello for the first
+                // time: generate our own ephemeral keypair, reply with it,
+                // then we both land on the same session keys.
+                let secret = EphemeralSecret::new(&mut *rng);
+                let public = X25519PublicKey::from(&secret);
+                let dh_ee: [u8; 32] = *secret.diffie_hellman(&peer_public).as_bytes();
+
+                Self::write_record(socket, CONTENT_HANDSHAKE, public.as_bytes())?;
+                self.install_session(dh_ee);
+            }
+            Handshake::SentHello(secret) => {
+                // We're the client, this completes the handshake we started.
+                let dh_ee: [u8; 32] = *secret.diffie_hellman(&peer_public).as_bytes();
+                self.install_session(dh_ee);
+            }
+            Handshake::Established => {
+                // Stray/duplicate hello after we're already set up - ignore
+                // it instead of erroring, there's nothing unsafe about it.
+                self.0.borrow_mut().handshake = Handshake::Established;
+            }
+        }
+        Ok(())
+    }
+
+    fn install_session(&self, dh_ee: [u8; 32]) {
+        let mut inner = self.0.borrow_mut();
+        let (key_client, key_server) = derive_keys(&inner.psk, &dh_ee);
+        let (send_key, recv_key) = match inner.role {
+            Role::Client => (key_client, key_server),
+            Role::Server => (key_server, key_client),
+        };
+        inner.session = Some(Session {
+            send_key,
+            recv_key,
+            send_counter: 0,
+            recv_counter: 0,
+        });
+        inner.handshake = Handshake::Established;
+    }
+
+    fn on_application_data(&self, payload: &[u8]) -> Result<(), Error> {
+        let mut inner = self.0.borrow_mut();
+        if payload.len() < 8 {
+            return Err(Error::Decryption);
+        }
+        let counter = u64::from_be_bytes(payload[..8].try_into().unwrap());
+        let ciphertext = &payload[8..];
+
+        let session = inner.session.as_mut().ok_or(Error::Decryption)?;
+        // No out-of-order tolerance: this is a single TCP byte stream, bytes
+        // only ever arrive in order.
+        if counter != session.recv_counter {
+            return Err(Error::Decryption);
+        }
+        session.recv_counter += 1;
+
+        let nonce = nonce_from_counter(counter);
+        let plaintext = session
+            .recv_key
+            .decrypt(&nonce, ciphertext)
+            .map_err(|_| Error::Decryption)?;
+
+        inner.rx_plain.extend(plaintext);
+        Ok(())
+    }
+}
@@ -7,12 +7,13 @@ use core::option::Option::{None, Some};
 use perun::channel::ActiveChannel;
 use perun::{
     channel::{
-        fixed_size_payment::{Allocation, Balances, ParticipantBalances},
+        fixed_size_payment::{Allocation, Balances, ParticipantBalances, ProtocolVersion},
         Asset, ChannelUpdate, LedgerChannelProposal,
     },
+    messages::{FunderReplyMessage, WatcherReplyMessage},
     perunwire::{self, envelope},
     sig::Signer,
-    wire::{BytesBus, Identity, MessageBus, ProtoBufEncodingLayer},
+    wire::{BytesBus, Identity, MessageBus, ProtoBufEncodingLayer, RateLimits, VersionRange},
     Address, PerunClient,
 };
 use prost::Message;
@@ -204,7 +205,7 @@ mod net {
 mod net {
     use perun::{
         abiencode::{self, types::Bytes32},
-        channel::fixed_size_payment::{Params, State},
+        channel::fixed_size_payment::{NoApp, Params, State},
         messages::{LedgerChannelProposalAcc, LedgerChannelUpdate, LedgerChannelUpdateAccepted},
         perunwire::{message, AuthResponseMsg, Envelope},
         sig::k256::Signer,
@@ -226,7 +227,7 @@ mod net {
         rng: StdRng,
         signer: Signer,
         proposal: Option<LedgerChannelProposal>,
-        state: Option<State<1, 2>>,
+        state: Option<State<NoApp, 1, 2>>,
     }
 
     #[derive(Debug)]
@@ -242,7 +243,7 @@ mod net {
             // Don't do that in production! For this example/demonstration this was the
             // easiest way to get a working (though deterministic) Rng.
             let mut rng = StdRng::seed_from_u64(666);
-            let signer = Signer::new(&mut rng);
+            let signer = Signer::random(&mut rng);
 
             let inner = InnerMutableData {
                 send_counter: 0,
@@ -264,7 +265,13 @@ mod net {
             let mut inner = self.inner.borrow_mut();
 
             let wiremsg = match inner.send_counter {
-                0 => envelope::Msg::AuthResponseMsg(AuthResponseMsg {}),
+                // Scripted reply to our AuthChallengeMsg; this mock doesn't
+                // implement the actual signing/verification, so it replies
+                // with an all-zero nonce and a dummy (all-zero) signature.
+                0 => envelope::Msg::AuthResponseMsg(AuthResponseMsg {
+                    nonce: vec![0u8; 32],
+                    sig: vec![0u8; 65],
+                }),
                 1 => {
                     let nonce_share: Bytes32 = inner.rng.gen();
                     let proposal = inner
@@ -280,6 +287,7 @@ mod net {
                         abiencode::types::U256::from_big_endian(hasher.finalize().as_slice());
 
                     let params = Params {
+                        chain_id: 1337.into(),
                         challenge_duration: proposal.challenge_duration,
                         nonce: nonce,
                         participants: [proposal.participant, inner.signer.address()],
@@ -287,7 +295,7 @@ mod net {
                         ledger_channel: true,
                         virtual_channel: false,
                     };
-                    inner.state = Some(State::new(params, proposal.init_bals).unwrap());
+                    inner.state = Some(State::new(params, proposal.init_bals, NoApp).unwrap());
 
                     envelope::Msg::LedgerChannelProposalAccMsg(
                         LedgerChannelProposalAcc {
@@ -412,15 +420,13 @@ fn get_rng() -> impl Rng + CryptoRng {
 
 fn get_peers() -> Vec<Vec<u8>> {
     const PEER0: [u8; 20] = [
-        0x7b, 0x7E, 0x21, 0x26, 0x52, 0xb9, 0xC3, 0x75,
-        0x5C, 0x4E, 0x1f, 0x17, 0x18, 0xa1, 0x42, 0xdD,
-        0xE3, 0x81, 0x75, 0x23,
+        0x7b, 0x7E, 0x21, 0x26, 0x52, 0xb9, 0xC3, 0x75, 0x5C, 0x4E, 0x1f, 0x17, 0x18, 0xa1, 0x42,
+        0xdD, 0xE3, 0x81, 0x75, 0x23,
     ];
 
     const PEER1: [u8; 20] = [
-        0xa6, 0x17, 0xfa, 0x2c, 0xc5, 0xeC, 0x8d, 0x72,
-        0xd4, 0xA6, 0x0b, 0x9F, 0x42, 0x46, 0x77, 0xe7,
-        0x4E, 0x6b, 0xef, 0x68,
+        0xa6, 0x17, 0xfa, 0x2c, 0xc5, 0xeC, 0x8d, 0x72, 0xd4, 0xA6, 0x0b, 0x9F, 0x42, 0x46, 0x77,
+        0xe7, 0x4E, 0x6b, 0xef, 0x68,
     ];
 
     vec![PEER0.to_vec(), PEER1.to_vec()]
@@ -438,9 +444,13 @@ fn main() {
     let peers = get_peers();
 
     // Signer, Addresses and Client
-    let signer = Signer::new(&mut rng);
+    let signer = Signer::random(&mut rng);
     let addr = signer.address();
-    let client = PerunClient::new(ProtoBufEncodingLayer { bus: &bus }, signer);
+    let client = PerunClient::new(
+        ProtoBufEncodingLayer::new(&bus, VersionRange { min: 1, max: 1 }, RateLimits::default()),
+        signer,
+        1337,
+    );
     client.send_handshake_msg(&peers[0], &peers[1]);
     bus.recv_envelope();
 
@@ -460,6 +470,9 @@ fn main() {
         funding_agreement: init_balance,
         participant: addr,
         peers,
+        protocol_version: ProtocolVersion::CURRENT,
+        app: Address([0u8; 20]),
+        init_data: vec![],
     };
     // Propose new channel and wait for responses
     let mut channel = client
@@ -497,18 +510,30 @@ fn main() {
 
     print_bold!("Bob: Received all signatures, send to watcher/funder");
 
-    let channel = channel.build().unwrap();
-    // Receive acknowledgements (currently not checked but we have to read them
-    // anyways).
+    let mut channel = channel.build().unwrap();
+    // Receive acknowledgements (content not decoded here - see
+    // lowlevel_basic_channel for an example that does - but their arrival is
+    // still fed into the funding/watch eventuality tracking, since we know
+    // both replies are for this channel).
     bus.recv_message();
     bus.recv_message();
+    channel
+        .on_funder_response(FunderReplyMessage::Funded {
+            id: channel.channel_id(),
+        })
+        .unwrap();
+    channel
+        .on_watcher_response(WatcherReplyMessage::Ack {
+            id: channel.channel_id(),
+            version: 0,
+        })
+        .unwrap();
 
-    let mut channel = channel.mark_funded();
+    let mut channel = channel.mark_funded().unwrap();
 
     print_user_interaction!("Bob: Propose Update");
     let mut new_state = channel.state().make_next_state();
-    new_state.outcome.balances.0[0].0[0] += 10.into();
-    new_state.outcome.balances.0[0].0[1] -= 10.into();
+    new_state.transfer(0, 1, 0, 10.into()).unwrap();
     let update = channel.update(new_state).unwrap();
     handle_update_response(&bus, &mut channel, update);
 
@@ -2,7 +2,7 @@
 
 use perun::{
     channel::{
-        fixed_size_payment::{Allocation, Balances, ParticipantBalances},
+        fixed_size_payment::{Allocation, Balances, ParticipantBalances, ProtocolVersion},
         Asset,
     },
     messages::{
@@ -10,7 +10,7 @@ use perun::{
         WatcherReplyMessage, WatcherRequestMessage,
     },
     sig::Signer,
-    wire::MessageBus,
+    wire::{Identity, MessageBus},
     Address, Hash, PerunClient,
 };
 use std::{fmt::Debug, sync::mpsc};
@@ -66,7 +66,12 @@ impl MessageBus for &Bus {
         self.service_tx.send(ServiceMsg::FunderReq(msg)).unwrap();
     }
 
-    fn send_to_participants(&self, msg: ParticipantMessage) {
+    fn send_to_participant(
+        &self,
+        _sender: &Identity,
+        _recipient: &Identity,
+        msg: ParticipantMessage,
+    ) {
         println!(
             "{}->{}: {:#?}",
             PARTICIPANTS[self.participant],
@@ -97,9 +102,9 @@ macro_rules! print_user_interaction {
 
 /// Alice: Proposes new channel.
 async fn alice(bus: Bus) {
-    let signer = Signer::new(&mut rand::thread_rng());
+    let signer = Signer::random(&mut rand::thread_rng());
     let addr = signer.address();
-    let client = PerunClient::new(&bus, signer);
+    let client = PerunClient::new(&bus, signer, 1);
 
     // Create channel proposal (user configuration)
     print_user_interaction!("Alice proposes a channel");
@@ -118,6 +123,9 @@ async fn alice(bus: Bus) {
         funding_agreement: init_balance,
         participant: addr,
         peers: vec!["Alice".as_bytes().to_vec(), "Bob".as_bytes().to_vec()],
+        protocol_version: ProtocolVersion::CURRENT,
+        app: Address([0u8; 20]),
+        init_data: vec![],
     };
     // Propose new channel and wait for responses
     // withdraw_receiver is the on-chain Address that will receive funds
@@ -169,14 +177,19 @@ async fn alice(bus: Bus) {
     }
 
     print_bold!("Alice: Received all signatures, send to watcher/funder");
-    let channel = channel.build().unwrap();
-    // Wait for Funded and WatchRequestAck messages (content not checked in this
-    // example)
-    bus.service_rx.recv().unwrap();
-    bus.service_rx.recv().unwrap();
+    let mut channel = channel.build().unwrap();
+    // Wait for the Funded and WatchRequestAck messages, feeding each into the
+    // channel's funding/watch eventuality tracking instead of discarding it.
+    for _ in 0..2 {
+        match bus.service_rx.recv().unwrap() {
+            ServiceMsg::FunderRepl(msg) => channel.on_funder_response(msg).unwrap(),
+            ServiceMsg::WatcherRepl(msg) => channel.on_watcher_response(msg).unwrap(),
+            _ => panic!("Unexpected service message"),
+        }
+    }
 
     print_bold!("Alice: Received Funded + WatchAck Message => Channel can be used");
-    let mut channel = channel.mark_funded();
+    let mut channel = channel.mark_funded().unwrap();
 
     // Wait until we receive an update proposal from bob (or whatever the
     // application wants to do in the meantime, Alice could also send update
@@ -236,9 +249,9 @@ async fn alice(bus: Bus) {
 
 /// Bob: Reacts to a proposed channel.
 async fn bob(bus: Bus) {
-    let signer = Signer::new(&mut rand::thread_rng());
+    let signer = Signer::random(&mut rand::thread_rng());
     let addr = signer.address();
-    let client = PerunClient::new(&bus, signer);
+    let client = PerunClient::new(&bus, signer, 1);
 
     // Wait for Channel Proposal, then accept it
     let mut channel = match bus.rx.recv().unwrap() {
@@ -287,24 +300,25 @@ async fn bob(bus: Bus) {
     }
 
     print_bold!("Bob: Received all signatures, send to watcher/funder");
-    let channel = channel.build().unwrap();
-    // Wait for Funded and WatchRequestAck messages (content not checked in this
-    // example)
-    bus.service_rx.recv().unwrap();
-    bus.service_rx.recv().unwrap();
+    let mut channel = channel.build().unwrap();
+    // Wait for the Funded and WatchRequestAck messages, feeding each into the
+    // channel's funding/watch eventuality tracking instead of discarding it.
+    for _ in 0..2 {
+        match bus.service_rx.recv().unwrap() {
+            ServiceMsg::FunderRepl(msg) => channel.on_funder_response(msg).unwrap(),
+            ServiceMsg::WatcherRepl(msg) => channel.on_watcher_response(msg).unwrap(),
+            _ => panic!("Unexpected service message"),
+        }
+    }
 
     print_bold!("Bob: Received Funded + WatchAck Message => Channel can be used");
-    let mut channel = channel.mark_funded();
+    let mut channel = channel.mark_funded().unwrap();
 
     print_user_interaction!("Bob: Propose Update");
     let mut new_state = channel.state().make_next_state();
     // Transfer 10 wei (assuming that's the channels currency) from Alice
     // (channel proposer) to Bob.
-    //
-    // There will be helper functions to do such simple changes and we'll most
-    // likely remove the `.0`.
-    new_state.outcome.balances.0[0].0[0] += 10.into();
-    new_state.outcome.balances.0[0].0[1] -= 10.into();
+    new_state.transfer(0, 1, 0, 10.into()).unwrap();
     let mut update = channel.update(new_state).unwrap();
     let accepted = match bus.rx.recv() {
         Ok(ParticipantMessage::ChannelUpdateAccepted(msg)) => {
@@ -0,0 +1,75 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use perun::abiencode::{as_bytes, to_writer, Writer};
+use serde::Serialize;
+
+/// Mirrors `abiencode::tests::bytescontainer`: a struct with static int
+/// fields followed by a single dynamic `bytes` field, the simplest shape that
+/// exercises both the offset/length bookkeeping and the zero-padding of the
+/// ABI encoder, now driven by arbitrary bytes instead of hand-picked ones.
+///
+/// Layout is fixed by construction: `[outer offset][a][b][data offset][data
+/// length][data, zero-padded]`, i.e. the length slot always starts at byte
+/// 128 (see the asserts below) - this only holds because `a`/`b` are
+/// single-slot statically-sized fields and `data` is the only dynamic one.
+#[derive(Arbitrary, Serialize, Debug)]
+struct FuzzStruct {
+    a: u8,
+    b: u32,
+    #[serde(with = "as_bytes")]
+    data: Vec<u8>,
+}
+
+/// Collects every 32-byte slot [to_writer] emits so the invariants below can
+/// be checked against the whole output, not just "it didn't panic".
+#[derive(Default)]
+struct VecWriter(Vec<u8>);
+
+impl Writer for VecWriter {
+    fn write(&mut self, slot: &[u8]) {
+        self.0.extend_from_slice(slot);
+    }
+}
+
+fuzz_target!(|value: FuzzStruct| {
+    let mut writer = VecWriter::default();
+    if to_writer(&value, &mut writer).is_err() {
+        return;
+    }
+
+    let out = &writer.0;
+    assert_eq!(
+        out.len() % 32,
+        0,
+        "abi encoding must be a whole number of 32-byte slots"
+    );
+
+    // Layout (see `bytescontainer.rs`): [outer offset][a][b][data
+    // offset][data length][data, zero-padded to a 32-byte boundary].
+    let len_slot = &out[128..160];
+    let encoded_len =
+        u32::from_be_bytes(len_slot[28..32].try_into().unwrap()) as usize;
+    assert_eq!(
+        encoded_len,
+        value.data.len(),
+        "dynamic bytes length prefix must match the actual data length"
+    );
+    assert!(
+        len_slot[..28].iter().all(|b| *b == 0),
+        "length prefix must be right-aligned/zero-padded like every other slot"
+    );
+
+    let padded_len = (value.data.len() + 31) / 32 * 32;
+    let data_slot = &out[160..160 + padded_len];
+    assert_eq!(
+        &data_slot[..value.data.len()],
+        value.data.as_slice(),
+        "encoded data must match the input bytes"
+    );
+    assert!(
+        data_slot[value.data.len()..].iter().all(|b| *b == 0),
+        "trailing bytes of the last (partial) data slot must be zero-padded"
+    );
+});
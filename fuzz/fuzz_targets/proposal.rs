@@ -0,0 +1,94 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use perun::{
+    abiencode::types::U256,
+    channel::{
+        fixed_size_payment::{Allocation, Balances, ParticipantBalances, ProtocolVersion},
+        Asset, LedgerChannelProposal, NonceShare,
+    },
+    messages::{FunderRequestMessage, ParticipantMessage, WatcherRequestMessage},
+    sig::Signer,
+    wire::{Identity, MessageBus},
+    Address, Hash, PerunClient,
+};
+
+/// Drops everything - this target only cares whether `propose_channel`/
+/// `handle_proposal` validate their (attacker-controlled) input without
+/// panicking, not about what ends up sent over the wire.
+struct NullBus;
+
+impl MessageBus for NullBus {
+    fn send_to_watcher(&self, _msg: WatcherRequestMessage) {}
+    fn send_to_funder(&self, _msg: FunderRequestMessage) {}
+    fn send_to_participant(
+        &self,
+        _sender: &Identity,
+        _recipient: &Identity,
+        _msg: ParticipantMessage,
+    ) {
+    }
+}
+
+/// Plain-data mirror of [LedgerChannelProposal] that [Arbitrary] can derive
+/// for, since the real type lives in another crate and has no `Arbitrary`
+/// impl of its own. One asset, two participants, matching the fixed
+/// dimensions `LedgerChannelProposal` itself is instantiated with.
+#[derive(Arbitrary, Debug)]
+struct ProposalInput {
+    proposal_id: [u8; 32],
+    challenge_duration: u64,
+    nonce_share: [u8; 32],
+    balances: [[u8; 32]; 2],
+    funding_agreement: [[u8; 32]; 2],
+    participant: [u8; 20],
+    peers: Vec<Vec<u8>>,
+    withdraw_receiver: [u8; 20],
+}
+
+fn build_proposal(input: &ProposalInput) -> LedgerChannelProposal {
+    let balances = Balances::<1, 2>([ParticipantBalances([
+        U256::from_big_endian(&input.balances[0]),
+        U256::from_big_endian(&input.balances[1]),
+    ])]);
+    let funding_agreement = Balances::<1, 2>([ParticipantBalances([
+        U256::from_big_endian(&input.funding_agreement[0]),
+        U256::from_big_endian(&input.funding_agreement[1]),
+    ])]);
+
+    LedgerChannelProposal {
+        proposal_id: Hash(input.proposal_id),
+        challenge_duration: input.challenge_duration,
+        nonce_share: NonceShare(input.nonce_share),
+        init_bals: Allocation::<1, 2>::new(
+            [Asset {
+                chain_id: U256::from(1u64),
+                holder: Address(input.participant),
+            }],
+            balances,
+        ),
+        funding_agreement,
+        participant: Address(input.participant),
+        peers: input.peers.clone(),
+        protocol_version: ProtocolVersion::CURRENT,
+        app: Address([0u8; 20]),
+        init_data: vec![],
+    }
+}
+
+fuzz_target!(|input: ProposalInput| {
+    // Fixed key: this target isn't exercising signing, just the proposal
+    // validation path, so a deterministic signer avoids spending entropy on
+    // key generation.
+    let signer = Signer::from_secret_bytes(&[0x42; 32]).expect("fixed test key is valid");
+    let client = PerunClient::new(NullBus, signer, 1);
+
+    let prop = build_proposal(&input);
+    let withdraw_receiver = Address(input.withdraw_receiver);
+
+    // Neither entry point should ever panic on attacker-controlled input -
+    // only return cleanly or fail with `InvalidProposal`.
+    let _ = client.propose_channel(prop.clone(), withdraw_receiver);
+    let _ = client.handle_proposal(prop, withdraw_receiver);
+});
@@ -25,7 +25,15 @@
 //! }
 //! ```
 
-use serde::ser::{Serialize, SerializeTuple, Serializer};
+extern crate alloc;
+
+use core::marker::PhantomData;
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::{Serialize, SerializeTuple, Serializer},
+    Deserialize, Deserializer,
+};
 
 pub fn serialize<S, T>(v: &[T], serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -42,3 +50,43 @@ where
     }
     s.end()
 }
+
+/// Reverses [serialize()] into a fixed-size `[T; N]` - unlike
+/// [super::as_dyn_array::deserialize], there is no length slot to check
+/// against `N` (a solidity tuple has no length prefix of its own), so a
+/// mismatch can only come from the rest of the containing value's layout
+/// disagreeing about where the following field starts, not from this
+/// function itself.
+pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+    impl<'de, T, const N: usize> Visitor<'de> for ArrayVisitor<T, N>
+    where
+        T: Deserialize<'de>,
+    {
+        type Value = [T; N];
+
+        fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            write!(f, "a fixed-size solidity tuple of exactly {} elements", N)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<[T; N], A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut out = alloc::vec::Vec::with_capacity(N);
+            while let Some(e) = seq.next_element()? {
+                out.push(e);
+            }
+            let len = out.len();
+            out.try_into()
+                .map_err(|_| de::Error::invalid_length(len, &self))
+        }
+    }
+
+    deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+}
@@ -28,8 +28,16 @@
 //! }
 //! ```
 
+extern crate alloc;
+
+use alloc::vec::Vec;
+
 use super::ser::DynamicMarker;
-use serde::{ser::SerializeTuple, Serialize, Serializer};
+use serde::{
+    de::{self, DeserializeSeed, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 /// Internal data structure allowing us to serialize the data using
 /// `serialize_bytes`, which unfortunately cannot be specified when calling
@@ -58,3 +66,114 @@ where
     s.serialize_element(&Bytes(v))?; // Write data
     s.end()
 }
+
+/// Reverses [serialize()]: reads the same `(marker, length, data)` tuple back
+/// into a `Vec<u8>`.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_tuple(3, TupleVisitor)
+}
+
+struct TupleVisitor;
+
+impl<'de> Visitor<'de> for TupleVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("a (marker, length, data) tuple representing solidity `bytes`")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<u8>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        seq.next_element::<DynamicMarker>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let len: usize = seq
+            .next_element::<usize>()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        // The data is read by a seed rather than `seq.next_element::<Bytes>()`
+        // because, unlike `serialize`, decoding it needs to know `len` first
+        // - it is not re-encoded anywhere near the data itself, only here.
+        seq.next_element_seed(ExactLen(len))?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))
+    }
+}
+
+/// [DeserializeSeed] that reads exactly `self.0` bytes, packed into
+/// `ceil(self.0/32)` 32-byte slots the same way [Bytes]'s `serialize` packs
+/// them via `serialize_bytes` (no length prefix of its own - `len` has
+/// already been read as the tuple's second element).
+struct ExactLen(usize);
+
+impl<'de> DeserializeSeed<'de> for ExactLen {
+    type Value = Vec<u8>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let r = self.0 % 32;
+        let chunks = self.0 / 32 + if r == 0 { 0 } else { 1 };
+        deserializer.deserialize_tuple(chunks, ChunksVisitor(self.0))
+    }
+}
+
+struct ChunksVisitor(usize);
+
+impl<'de> Visitor<'de> for ChunksVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{} bytes, packed into 32-byte slots", self.0)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<u8>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut data = Vec::new();
+        while let Some(Chunk(chunk)) = seq.next_element()? {
+            data.extend_from_slice(&chunk);
+        }
+        if data[self.0..].iter().any(|b| *b != 0) {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Bytes(&data),
+                &"the trailing slot of a `bytes` value to be zero-padded",
+            ));
+        }
+        data.truncate(self.0);
+        Ok(data)
+    }
+}
+
+/// A single raw 32-byte slot, decoded via `deserialize_bytes` so it is read
+/// as one packed slot instead of 32 individually-encoded `u8` elements.
+struct Chunk([u8; 32]);
+
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ChunkVisitor;
+        impl<'de> Visitor<'de> for ChunkVisitor {
+            type Value = [u8; 32];
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                f.write_str("a 32-byte slot")
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<[u8; 32], E>
+            where
+                E: de::Error,
+            {
+                v.try_into()
+                    .map_err(|_| de::Error::invalid_length(v.len(), &self))
+            }
+        }
+        deserializer.deserialize_bytes(ChunkVisitor).map(Chunk)
+    }
+}
@@ -24,7 +24,16 @@
 //! }
 //! ```
 
-use serde::ser::{Serialize, SerializeSeq, Serializer};
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::{Serialize, SerializeSeq, Serializer},
+    Deserialize, Deserializer,
+};
 
 pub fn serialize<S, T>(v: &[T], serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -37,3 +46,41 @@ where
     }
     s.end()
 }
+
+/// Reverses [serialize()] into a fixed-size `[T; N]`, the shape every
+/// real (non-test) field using `as_dyn_array` in this crate actually has.
+/// Errors (via [de::Error::invalid_length()]) if the decoded solidity array
+/// does not have exactly `N` elements.
+pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+    impl<'de, T, const N: usize> Visitor<'de> for ArrayVisitor<T, N>
+    where
+        T: Deserialize<'de>,
+    {
+        type Value = [T; N];
+
+        fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            write!(f, "a dynamic-length solidity array of exactly {} elements", N)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<[T; N], A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut out = Vec::with_capacity(N);
+            while let Some(e) = seq.next_element()? {
+                out.push(e);
+            }
+            let len = out.len();
+            out.try_into()
+                .map_err(|_| de::Error::invalid_length(len, &self))
+        }
+    }
+
+    deserializer.deserialize_seq(ArrayVisitor(PhantomData))
+}
@@ -0,0 +1,74 @@
+//! Serialize a raw 32-byte big-endian two's-complement value as solidity
+//! `int256`.
+//!
+//! Solidity's ABI encodes `int256` and `uint256` identically: both are a
+//! single 32-byte word, the only difference being how the bits are
+//! interpreted. So, once a value is already in two's-complement form, writing
+//! it is exactly [as_u256][super::as_u256]'s job - this module exists
+//! separately only to document and own the signed side of that contract.
+//!
+//! Like [as_u256][super::as_u256], the value is written directly via
+//! `serialize_bytes` into a single static slot, never through the
+//! `DynamicMarker`/`bytes` path, and is never dynamic.
+//!
+//! Converting a narrower signed integer (e.g. `i128`) up to the full 32 bytes
+//! is the caller's responsibility, and is the same sign-extension this crate's
+//! own `Serializer::write_signed` already does for `i8`/`i16`/`i32`/`i64`
+//! (`i128` is written right-aligned with zero padding instead): fill
+//! every byte above the value's own width with `0xff` if it is negative, or
+//! `0x00` otherwise, e.g.:
+//! ```ignore
+//! let mut bytes = [if v < 0 { 0xff } else { 0x00 }; 32];
+//! bytes[32 - 16..].copy_from_slice(&v.to_be_bytes());
+//! ```
+//!
+//! # Example usage
+//! ```ignore
+//! # // We cannot run this test because abiencode is not public.
+//! # use serde::Serialize;
+//! # use perun::abiencode::as_i256;
+//!
+//! #[derive(Serialize, Debug)]
+//! pub struct Data {
+//!     #[serde(with = "as_i256")]
+//!     pub amount: [u8; 32],
+//! }
+//! ```
+
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
+
+pub fn serialize<S>(v: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(v)
+}
+
+/// Reverses [serialize()]: reads the single 32-byte slot back as-is.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct SlotVisitor;
+
+    impl<'de> Visitor<'de> for SlotVisitor {
+        type Value = [u8; 32];
+
+        fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.write_str("a 32-byte big-endian two's-complement int256 value")
+        }
+
+        fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<[u8; 32], E>
+        where
+            E: de::Error,
+        {
+            v.try_into()
+                .map_err(|_| de::Error::invalid_length(v.len(), &self))
+        }
+    }
+
+    deserializer.deserialize_bytes(SlotVisitor)
+}
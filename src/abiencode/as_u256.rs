@@ -0,0 +1,66 @@
+//! Serialize a raw 32-byte big-endian magnitude as solidity `uint256`.
+//!
+//! Unlike [as_bytes][super::as_bytes], this writes the 32 bytes directly into
+//! a single static slot via `serialize_bytes` instead of going through the
+//! `DynamicMarker`/length-prefixed tuple `as_bytes` uses for solidity
+//! `bytes`: a `uint256` is always exactly one slot and is never dynamic (a
+//! `serialize_bytes` call of exactly 32 bytes is counted as one static slot
+//! by `Pass::HeadSize` in [super::ser] rather than marking the value
+//! dynamic).
+//!
+//! This crate's own [U256][super::types::U256] already implements
+//! `Serialize` directly and does not need this helper. Use this module
+//! instead for a raw `[u8; 32]` magnitude, e.g. one produced by
+//! `ethnum::U256::to_be_bytes()`, to encode real `uint256` values without
+//! truncating to this serializer's native `u128`.
+//!
+//! # Example usage
+//! ```ignore
+//! # // We cannot run this test because abiencode is not public.
+//! # use serde::Serialize;
+//! # use perun::abiencode::as_u256;
+//!
+//! #[derive(Serialize, Debug)]
+//! pub struct Data {
+//!     #[serde(with = "as_u256")]
+//!     pub amount: [u8; 32],
+//! }
+//! ```
+
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
+
+pub fn serialize<S>(v: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(v)
+}
+
+/// Reverses [serialize()]: reads the single 32-byte slot back as-is.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct SlotVisitor;
+
+    impl<'de> Visitor<'de> for SlotVisitor {
+        type Value = [u8; 32];
+
+        fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.write_str("a 32-byte big-endian uint256 magnitude")
+        }
+
+        fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<[u8; 32], E>
+        where
+            E: de::Error,
+        {
+            v.try_into()
+                .map_err(|_| de::Error::invalid_length(v.len(), &self))
+        }
+    }
+
+    deserializer.deserialize_bytes(SlotVisitor)
+}
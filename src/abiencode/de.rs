@@ -0,0 +1,516 @@
+//! Deserialize Rust types (including structs) from Solidity's
+//! `abi.encode(...)` output, the reverse of [super::ser].
+//!
+//! # Scope
+//! Reversing [to_writer][super::ser::to_writer] in full generality would mean
+//! discovering, purely from a `Deserialize` impl, whether a given field is
+//! dynamic (and thus stored as an offset into a Tail) or static (and thus
+//! stored inline) - something the encoder only knows because it is handed an
+//! actual value to inspect first (see [super::ser]'s `compute_size`). Every
+//! type actually (de)serialized in this crate sidesteps that problem: dynamic
+//! fields always go through [as_bytes][super::as_bytes] or
+//! [as_dyn_array][super::as_dyn_array], which fully own their own offset/Tail
+//! handling, while plain struct/tuple fields are always statically sized
+//! (`uintN`, `bool`, single-slot `bytesN`, or nested static structs/tuples)
+//! and thus always read inline. This [Deserializer] supports exactly that
+//! subset, not arbitrary nested dynamic fields on plain (unwrapped) types.
+//!
+//! # Head/Tail model
+//! Mirrors [super::ser]: every dynamic value's Head slot holds a 32-byte
+//! big-endian offset measured from the start of the enclosing tuple (never
+//! from the absolute buffer start), so decoding it means seeking to
+//! `base + offset` and continuing there with a fresh `base` of its own -
+//! [Deserializer::seek_dynamic()] is the single place that happens. Static
+//! fields (`uintN`, `bool`, `bytesN`) are read inline, one slot at a time,
+//! advancing the cursor without touching `base`.
+//!
+//! # Top-level value
+//! [from_slice()] mirrors `to_fnargs_writer` (the `include_outer_struct =
+//! false` variant), not the public `to_writer`: it expects `data` to already
+//! start at the value's own Head. Whether `to_writer` additionally wrote a
+//! leading "outer offset" slot before that depends on whether the top-level
+//! value turned out to be dynamic, which - same problem as above - cannot be
+//! decided without an instance of it. Callers that produced `data` via
+//! `to_writer` and know their value is dynamic must skip that first slot
+//! themselves before calling [from_slice()].
+//!
+//! # Testing
+//! There's no dedicated test module here; round-tripping a [State][
+//! crate::channel::fixed_size_payment::State] through [to_vec][
+//! super::to_vec] and back through [from_slice()]/[State::decode][
+//! crate::channel::fixed_size_payment::State::decode] is covered by
+//! `fixed_size_payment::tests::state_1a2p_decode_roundtrip`, which exercises
+//! the offset/Tail handling for every dynamic field this format actually
+//! uses (`as_bytes`, `as_dyn_array`, `#[serde(transparent)]` wrappers).
+//! `tests::round_trip` extends this with a couple of plain static-field
+//! `serialize_and_compare`/`serialize_and_compare_fnargs` fixtures
+//! (`Address`, `u64`) decoded straight back through [from_slice()] - most of
+//! the suite's other fixtures only derive `Serialize`, not `Deserialize`, so
+//! they aren't round-tripped here.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use super::error::{Error, ErrorKind, Result};
+
+const SLOT_SIZE: usize = 32; // bytes
+
+/// Cursor-based reader over a `&[u8]` buffer containing `abi.encode(...)`
+/// output, implementing [serde::Deserializer].
+///
+/// Does not buffer or copy the input: every decoded `bytes`/`str` is borrowed
+/// directly from the original slice.
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+    /// Absolute offset of the start of the tuple/struct/seq currently being
+    /// read. Dynamic-value offsets read while `base == b` are relative to `b`.
+    base: usize,
+    /// Absolute offset of the next Head slot to read.
+    pos: usize,
+}
+
+/// Deserializes `T` from the start of `data`.
+///
+/// See the [module-level docs][self] for exactly which shapes of `T` are
+/// supported and why the leading "outer offset" slot `to_writer` writes for
+/// dynamic top-level values is not handled here.
+pub fn from_slice<'de, T>(data: &'de [u8]) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer {
+        input: data,
+        base: 0,
+        pos: 0,
+    };
+    T::deserialize(&mut deserializer)
+}
+
+/// Implement this to supply [from_reader()] with ABI-encoded bytes from
+/// somewhere other than an in-memory `&[u8]` ([from_slice()]'s input).
+///
+/// This is not [Writer][super::ser::Writer]'s mirror image: `Writer` only
+/// ever needs to append sequentially, but decoding a dynamic field means
+/// seeking to wherever its offset points (see the module's "Head/Tail model"
+/// above), so a `Reader` has to hand back the entire input up front rather
+/// than being driven one slot at a time.
+pub trait Reader {
+    fn bytes(&self) -> &[u8];
+}
+
+impl Reader for [u8] {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Reader for Vec<u8> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Deserializes `T` from any [Reader], e.g. [IoReader] below.
+///
+/// Just [from_slice()] over `reader.bytes()`; see that function and the
+/// module-level docs for exactly which shapes of `T` are supported.
+pub fn from_reader<'de, T, R>(reader: &'de R) -> Result<T>
+where
+    T: Deserialize<'de>,
+    R: Reader + ?Sized,
+{
+    from_slice(reader.bytes())
+}
+
+/// Reads an entire `std::io::Read` into an owned buffer up front, since
+/// (unlike [IoWriter][super::ser::IoWriter]) there is no way to decode
+/// incrementally: an offset can point anywhere in the input, including past
+/// bytes that haven't been read yet.
+#[cfg(feature = "std")]
+pub struct IoReader(Vec<u8>);
+
+#[cfg(feature = "std")]
+impl IoReader {
+    pub fn new(mut inner: impl std::io::Read) -> Result<Self> {
+        let mut buf = Vec::new();
+        inner.read_to_end(&mut buf).map_err(|e| ErrorKind::Io(e))?;
+        Ok(Self(buf))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Reader for IoReader {
+    fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    fn take_slot(&mut self) -> Result<&'de [u8]> {
+        let end = self
+            .pos
+            .checked_add(SLOT_SIZE)
+            .ok_or(ErrorKind::UnexpectedEndOfInput.into())?;
+        let slot = self
+            .input
+            .get(self.pos..end)
+            .ok_or(ErrorKind::UnexpectedEndOfInput.into())?;
+        self.pos = end;
+        Ok(slot)
+    }
+
+    // Counterpart of `Serializer::write_right_aligned`.
+    fn read_unsigned<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let slot = self.take_slot()?;
+        let (high, low) = slot.split_at(SLOT_SIZE - N);
+        if high.iter().any(|b| *b != 0) {
+            return Err(ErrorKind::IntegerOverflow.into());
+        }
+        let mut out = [0u8; N];
+        out.copy_from_slice(low);
+        Ok(out)
+    }
+
+    // Counterpart of `Serializer::write_signed`.
+    fn read_signed<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let slot = self.take_slot()?;
+        let (high, low) = slot.split_at(SLOT_SIZE - N);
+        let negative = low[0] & 0x80 != 0;
+        let filler = if negative { 0xffu8 } else { 0x00u8 };
+        if high.iter().any(|b| *b != filler) {
+            return Err(ErrorKind::IntegerOverflow.into());
+        }
+        let mut out = [0u8; N];
+        out.copy_from_slice(low);
+        Ok(out)
+    }
+
+    fn read_usize(&mut self) -> Result<usize> {
+        const USIZE_SIZE: usize = core::mem::size_of::<usize>();
+        Ok(usize::from_be_bytes(self.read_unsigned::<USIZE_SIZE>()?))
+    }
+
+    /// Reads an offset slot and returns a fresh [Deserializer] whose `base`
+    /// (and cursor) sit at `self.base + offset` - the decode counterpart of
+    /// `serialize_tuple_element`'s "write an offset now, the value itself
+    /// later, in Tail" branch.
+    fn seek_dynamic(&mut self) -> Result<Deserializer<'de>> {
+        let offset = self.read_usize()?;
+        let start = self
+            .base
+            .checked_add(offset)
+            .ok_or(ErrorKind::InvalidOffset.into())?;
+        if start > self.input.len() {
+            return Err(ErrorKind::InvalidOffset.into());
+        }
+        Ok(Deserializer {
+            input: self.input,
+            base: start,
+            pos: start,
+        })
+    }
+
+    // Counterpart of `serialize_bytes` used directly (i.e. not through
+    // `as_bytes`) for single-slot fixed-size `bytesN` types. Types spanning
+    // more than one slot (like the 65-byte `Signature`) need their own
+    // multi-slot `Deserialize` impl, the same way their `Serialize` impl does
+    // not rely on `compute_size` treating them as a single slot either.
+    fn read_inline_bytes(&mut self) -> Result<&'de [u8]> {
+        self.take_slot()
+    }
+
+    // Counterpart of `serialize_str`'s Tail: a length slot followed by
+    // `ceil(len/32)` data slots with the trailing slot's padding stripped
+    // (and checked to actually be zero).
+    fn read_length_prefixed_bytes(&mut self) -> Result<&'de [u8]> {
+        let len = self.read_usize()?;
+        let r = len % SLOT_SIZE;
+        let padded = len - r + if r == 0 { 0 } else { SLOT_SIZE };
+        let start = self.pos;
+        let end = start
+            .checked_add(padded)
+            .ok_or(ErrorKind::UnexpectedEndOfInput.into())?;
+        let data = self
+            .input
+            .get(start..end)
+            .ok_or(ErrorKind::UnexpectedEndOfInput.into())?;
+        self.pos = end;
+        if data[len..].iter().any(|b| *b != 0) {
+            return Err(ErrorKind::InvalidPadding.into());
+        }
+        Ok(&data[..len])
+    }
+}
+
+/// Reads `remaining` tuple/struct/seq elements in sequence from the same
+/// underlying [Deserializer] (and thus the same `base`) - used for struct
+/// fields, tuple elements and (after seeking to its own Tail, see
+/// [Deserializer::deserialize_seq]) sequence elements alike, mirroring how
+/// `serialize_tuple_element` backs all three on the encoding side.
+struct Elements<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+impl<'a, 'de> SeqAccess<'de> for Elements<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+macro_rules! deserialize_unsigned {
+    ($method:ident, $visit:ident, $ty:ty) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.$visit(<$ty>::from_be_bytes(
+                self.read_unsigned::<{ core::mem::size_of::<$ty>() }>()?,
+            ))
+        }
+    };
+}
+
+macro_rules! deserialize_signed {
+    ($method:ident, $visit:ident, $ty:ty) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.$visit(<$ty>::from_be_bytes(
+                self.read_signed::<{ core::mem::size_of::<$ty>() }>()?,
+            ))
+        }
+    };
+}
+
+impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::NotSelfDescribing.into())
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.read_unsigned::<1>()?[0] {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            other => Err(ErrorKind::InvalidBoolValue(other).into()),
+        }
+    }
+
+    deserialize_signed!(deserialize_i8, visit_i8, i8);
+    deserialize_signed!(deserialize_i16, visit_i16, i16);
+    deserialize_signed!(deserialize_i32, visit_i32, i32);
+    deserialize_signed!(deserialize_i64, visit_i64, i64);
+    // `serialize_i128` writes via `write_right_aligned`, not `write_signed`
+    // like the other signed integers, so this mirrors that (most likely
+    // accidental) asymmetry rather than "fixing" it on only one side of the
+    // round-trip.
+    deserialize_unsigned!(deserialize_i128, visit_i128, i128);
+
+    deserialize_unsigned!(deserialize_u8, visit_u8, u8);
+    deserialize_unsigned!(deserialize_u16, visit_u16, u16);
+    deserialize_unsigned!(deserialize_u32, visit_u32, u32);
+    deserialize_unsigned!(deserialize_u64, visit_u64, u64);
+    deserialize_unsigned!(deserialize_u128, visit_u128, u128);
+
+    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotRepresentable("f32").into())
+    }
+
+    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotRepresentable("f64").into())
+    }
+
+    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotYetSupported("char").into())
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut value = self.seek_dynamic()?;
+        let data = value.read_length_prefixed_bytes()?;
+        let s = core::str::from_utf8(data).map_err(|_| ErrorKind::InvalidUtf8.into())?;
+        visitor.visit_borrowed_str(s)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.read_inline_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotRepresentable("option").into())
+    }
+
+    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotRepresentable("unit").into())
+    }
+
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == super::ser::MARK_DYNAMIC_NAME {
+            visitor.visit_unit()
+        } else {
+            Err(ErrorKind::TypeNotRepresentable("unit struct").into())
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut value = self.seek_dynamic()?;
+        let len = value.read_usize()?;
+        // The length slot is part of the Tail this seq was seeked into, but
+        // (see `serialize_seq`/`serialize_tuple_element`'s `offset_reduction`)
+        // is not counted when elements compute their own offsets, so element
+        // offsets are relative to right after it, not to `value`'s own base.
+        let elements_base = value.pos;
+        let mut elements = Deserializer {
+            input: value.input,
+            base: elements_base,
+            pos: elements_base,
+        };
+        visitor.visit_seq(Elements {
+            de: &mut elements,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(Elements {
+            de: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotRepresentable("map").into())
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // Not self-describing: fields are read positionally, in declaration
+        // order, the same order `SerializeStruct::serialize_field` writes
+        // them in - there is no field name to look up.
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotRepresentable("enum").into())
+    }
+
+    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotYetSupported("identifier").into())
+    }
+
+    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(ErrorKind::TypeNotYetSupported("ignored_any").into())
+    }
+}
@@ -1,28 +1,173 @@
-//! Error type and Return values used by the Serialization.
+//! Error type and Return values used by Serialization and Deserialization.
 
+extern crate alloc;
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 use core::fmt::Display;
 
-use serde::ser;
+use serde::{de, ser};
 
-/// Represents all possible errors that can happen during Serialization.
+/// Represents all possible errors that can happen during Serialization or
+/// Deserialization.
 ///
-/// Note that custom errors using [ser::Error::custom()] are not yet supported.
+/// Note that custom errors using [ser::Error::custom()] are not yet supported
+/// on the serializing side; [de::Error::custom()] is, since [Deserializer][
+/// super::de::Deserializer] needs it to report things like invalid lengths.
 #[derive(Debug)]
-pub enum Error {
+pub enum ErrorKind {
     /// The struct contains a type that is not directly representable in
     /// Solidity types.
     ///
-    /// For example floating point numbers, enums and maps. While we could
-    /// default to some enum representation or automatically convert floats to
-    /// `fixedNxM` we don't do this, as it could lead to loss of accuracy or
-    /// force a specific representation on the Solidity side. Instead use the
-    /// [serde_repr](https://github.com/dtolnay/serde-repr) crate as shown in
-    /// the [Serde Overview](https://serde.rs/enum-number.html) for enums or
-    /// implement a custom serialize method.
+    /// For example floating point numbers. While we could automatically
+    /// convert floats to `fixedNxM` we don't do this, as it could lead to
+    /// loss of accuracy or force a specific representation on the Solidity
+    /// side. Instead implement a custom serialize method.
+    ///
+    /// Note enums are representable (as a `uint8` tag, optionally paired with
+    /// a payload tuple - see [Serializer][super::ser::Serializer]'s
+    /// `serialize_*_variant` methods) and maps are representable (as a sorted
+    /// dynamic array of 2-element key/value tuples - see
+    /// [Serializer][super::ser::Serializer]'s `serialize_map`), so neither
+    /// hits this variant anymore.
     TypeNotRepresentable(&'static str),
     /// Although the type is representable in Solidity (currently only used for
     /// `char`), the Serializer currently does not implement this functionality.
     TypeNotYetSupported(&'static str),
+    /// The input ended before all expected data could be read.
+    UnexpectedEndOfInput,
+    /// A 32-byte slot that is supposed to hold a length or offset does not fit
+    /// into this platform's `usize`, or a signed value's sign-extension/a
+    /// right-aligned value's padding bytes are not what they should be.
+    IntegerOverflow,
+    /// A `bool` slot was neither `0` nor `1`.
+    InvalidBoolValue(u8),
+    /// A dynamic value's offset does not point inside of the buffer it is
+    /// relative to.
+    InvalidOffset,
+    /// The zero-padding of a `bytes`/`str` value's last (partial) slot
+    /// contains non-zero bytes.
+    InvalidPadding,
+    /// A `str` value's bytes are not valid UTF-8.
+    InvalidUtf8,
+    /// `deserialize_any` was called, but this format is not self-describing:
+    /// the concrete Rust type must already be known to decode anything, the
+    /// same way [to_writer][super::ser::to_writer] needs [Serialize] rather
+    /// than inspecting the value at runtime.
+    NotSelfDescribing,
+    /// An error message produced by the type being (de)serialized itself (via
+    /// [de::Error::custom()]), e.g. a derived enum rejecting an unknown
+    /// variant index.
+    Custom(String),
+    /// `value` nests structs/tuples/seqs deeper than the [Serializer][
+    /// super::ser::Serializer]'s configured maximum (see
+    /// [to_writer_with_config][super::ser::to_writer_with_config]), returned
+    /// instead of recursing further and risking a stack overflow.
+    DepthLimitExceeded,
+    /// A [Writer][super::ser::Writer] wrapping a `std::io::Write` (see
+    /// [to_io_writer][super::ser::to_io_writer]) failed to write.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The fixed-size buffer passed to
+    /// [serialize_into][super::ser::serialize_into] is smaller than
+    /// [encoded_size][super::ser::encoded_size] of the value.
+    BufferTooSmall { needed: usize, available: usize },
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ErrorKind::TypeNotRepresentable(type_name) => {
+                f.write_str("type is not representable in abi encoding: ")?;
+                f.write_str(type_name)
+            }
+            ErrorKind::TypeNotYetSupported(type_name) => {
+                f.write_str("type is not yet implemented: ")?;
+                f.write_str(type_name)
+            }
+            ErrorKind::UnexpectedEndOfInput => f.write_str("unexpected end of input"),
+            ErrorKind::IntegerOverflow => {
+                f.write_str("slot does not fit into this platform's usize, or has invalid padding")
+            }
+            ErrorKind::InvalidBoolValue(v) => {
+                write!(f, "expected a 0 or 1 slot for bool, found {}", v)
+            }
+            ErrorKind::InvalidOffset => f.write_str("dynamic value's offset is out of bounds"),
+            ErrorKind::InvalidPadding => {
+                f.write_str("bytes/str value's trailing zero-padding contains non-zero bytes")
+            }
+            ErrorKind::InvalidUtf8 => f.write_str("str value is not valid UTF-8"),
+            ErrorKind::NotSelfDescribing => {
+                f.write_str("abi encoding is not self-describing, deserialize_any is not supported")
+            }
+            ErrorKind::Custom(msg) => f.write_str(msg),
+            ErrorKind::DepthLimitExceeded => {
+                f.write_str("value nests deeper than the serializer's configured depth limit")
+            }
+            #[cfg(feature = "std")]
+            ErrorKind::Io(e) => write!(f, "I/O error: {}", e),
+            ErrorKind::BufferTooSmall { needed, available } => write!(
+                f,
+                "buffer too small: needed {} bytes, only {} available",
+                needed, available
+            ),
+        }
+    }
+}
+
+/// A single step of the field/index path an [Error] occurred at, e.g. the
+/// `.balances` or `[2]` in `.balances[2].allocation`.
+#[derive(Debug, Clone)]
+pub enum PathSegment {
+    /// A named struct field, e.g. `serialize_struct`'s `serialize_field(name, ...)`.
+    Field(&'static str),
+    /// A sequence/tuple element's position, e.g. `serialize_seq`'s
+    /// `serialize_element`.
+    Index(usize),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PathSegment::Field(name) => write!(f, ".{}", name),
+            PathSegment::Index(i) => write!(f, "[{}]", i),
+        }
+    }
+}
+
+/// An [ErrorKind] together with the field/index path it occurred at, so a
+/// failure deep inside a nested struct/tuple/seq can be located, e.g.
+/// `TypeNotRepresentable("map") at .balances[2].allocation` instead of a bare
+/// kind name.
+///
+/// The path is recorded on the way back out of the recursion (see
+/// [serialize_tuple_element][super::ser::Serializer::serialize_tuple_element]),
+/// so it reads outermost-field-first, the same order it appears in the
+/// source struct.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    path: Vec<PathSegment>,
+}
+
+impl Error {
+    /// Prepends `segment` to the recorded path. Used while an error bubbles
+    /// back up through nested struct/tuple/seq fields.
+    pub(super) fn with_segment(mut self, segment: PathSegment) -> Self {
+        self.path.insert(0, segment);
+        self
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            path: Vec::new(),
+        }
+    }
 }
 
 impl ser::Error for Error {
@@ -36,18 +181,25 @@ impl ser::Error for Error {
 #[cfg(feature = "std")]
 impl ser::StdError for Error {}
 
+impl de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: core::fmt::Display,
+    {
+        ErrorKind::Custom(msg.to_string()).into()
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        match self {
-            Error::TypeNotRepresentable(type_name) => {
-                f.write_str("type is not representable in abi encoding: ")?;
-                f.write_str(type_name)
-            }
-            Error::TypeNotYetSupported(type_name) => {
-                f.write_str("type is not yet implemented: ")?;
-                f.write_str(type_name)
+        Display::fmt(&self.kind, f)?;
+        if !self.path.is_empty() {
+            f.write_str(" at ")?;
+            for segment in &self.path {
+                Display::fmt(segment, f)?;
             }
         }
+        Ok(())
     }
 }
 
@@ -3,9 +3,48 @@ use super::{to_writer, types::Hash, Error, Writer};
 use serde::Serialize;
 use sha3::{
     digest::{core_api::CoreWrapper, Output},
-    Digest, Keccak256, Keccak256Core,
+    Digest as Sha3Digest, Keccak256, Keccak256Core,
 };
 
+/// Minimal streaming hasher abstraction, so [DigestWriter] can feed it ABI
+/// slots without pulling in a specific hashing crate's (much larger) trait.
+///
+/// [Keccak256Writer] exists separately and does not use this trait: it is
+/// tied to `sha3`'s own `Digest` because [to_hash()] needs `finalize()`,
+/// which has no generic equivalent here.
+pub trait Digest {
+    fn update(&mut self, data: &[u8]);
+}
+
+/// [Writer] that feeds every slot into a user-supplied [Digest], e.g. to
+/// compute `keccak256(abi.encode(...))` for hashing or signing without ever
+/// materializing the encoded buffer.
+pub struct DigestWriter<D> {
+    digest: D,
+}
+
+impl<D> DigestWriter<D>
+where
+    D: Digest,
+{
+    pub fn new(digest: D) -> Self {
+        Self { digest }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.digest
+    }
+}
+
+impl<D> Writer for DigestWriter<D>
+where
+    D: Digest,
+{
+    fn write(&mut self, slot: &[u8]) {
+        self.digest.update(slot);
+    }
+}
+
 pub struct Keccak256Writer {
     hasher: CoreWrapper<Keccak256Core>,
 }
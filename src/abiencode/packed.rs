@@ -0,0 +1,434 @@
+//! Serialize Rust types like Solidity's `abi.encodePacked(...)`.
+//!
+//! Unlike [super::ser], this is a tight concatenation: no padding to 32-byte
+//! slots, no length prefixes for `bytes`/`str`, and no offset slots for
+//! dynamic types. Needed for the keccak256 preimages Perun uses for channel
+//! IDs and state hashes, which are built from packed encodings rather than
+//! the standard ABI layout [to_writer][super::ser::to_writer] produces.
+//!
+//! Because there are no offsets to resolve, a single pass over the value is
+//! always enough, so unlike [Serializer][super::ser::Serializer] this type
+//! has no notion of [Pass][super::ser::Serializer] at all.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use super::{
+    error::{Error, ErrorKind, Result},
+    ser::{VecWriter, Writer, MARK_DYNAMIC_NAME},
+};
+use serde::{
+    ser::{
+        self, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleStruct, SerializeTupleVariant,
+    },
+    serde_if_integer128, Serialize,
+};
+
+/// Same recursion-depth limit [Serializer][super::ser::Serializer] uses,
+/// bounding how deep nested structs/tuples/seqs may recurse before
+/// [PackedSerializer] gives up instead of overflowing the stack.
+const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Serializes `value` into `writer` using `abi.encodePacked` semantics.
+pub fn to_packed_writer<T, W>(value: &T, writer: &mut W) -> Result<()>
+where
+    T: Serialize,
+    W: Writer,
+{
+    let mut serializer = PackedSerializer {
+        writer,
+        depth: 0,
+        max_depth: DEFAULT_MAX_DEPTH,
+    };
+    value.serialize(&mut serializer)
+}
+
+/// Serializes `value` into a freshly-allocated `Vec<u8>` using
+/// `abi.encodePacked` semantics.
+pub fn to_packed_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    let mut writer = VecWriter(Vec::new());
+    to_packed_writer(value, &mut writer)?;
+    Ok(writer.0)
+}
+
+pub struct PackedSerializer<'a, W>
+where
+    W: Writer,
+{
+    writer: &'a mut W,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<'a, W> PackedSerializer<'a, W>
+where
+    W: Writer,
+{
+    // Recurses into a nested value's own Serialize impl, guarding against
+    // unbounded recursion the same way
+    // [serialize_tuple_element][super::ser::Serializer::serialize_tuple_element]
+    // does for the head/tail Serializer.
+    fn recurse<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        if self.depth >= self.max_depth {
+            return Err(ErrorKind::DepthLimitExceeded.into());
+        }
+        self.depth += 1;
+        let result = value.serialize(&mut *self);
+        self.depth -= 1;
+        result
+    }
+}
+
+impl<'a, 'b, W> ser::Serializer for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.writer.write(&[v as u8]);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    serde_if_integer128! {
+        fn serialize_i128(self, v: i128) -> Result<()> {
+            self.writer.write(&v.to_be_bytes());
+            Ok(())
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.writer.write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    serde_if_integer128! {
+        fn serialize_u128(self, v: u128) -> Result<()> {
+            self.writer.write(&v.to_be_bytes());
+            Ok(())
+        }
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<()> {
+        Err(ErrorKind::TypeNotRepresentable("f32").into())
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<()> {
+        Err(ErrorKind::TypeNotRepresentable("f64").into())
+    }
+
+    fn serialize_char(self, _: char) -> Result<()> {
+        Err(ErrorKind::TypeNotYetSupported("char").into())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.writer.write(v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.writer.write(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(ErrorKind::TypeNotRepresentable("none").into())
+    }
+
+    fn serialize_some<T: ?Sized>(self, _: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        Err(ErrorKind::TypeNotRepresentable("some").into())
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(ErrorKind::TypeNotRepresentable("unit").into())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
+        if name == MARK_DYNAMIC_NAME {
+            // No offsets exist in packed encoding, so there is nothing
+            // "dynamic" to mark: the marker contributes no bytes.
+            Ok(())
+        } else {
+            Err(ErrorKind::TypeNotRepresentable("unit struct").into())
+        }
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.recurse(value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.serialize_u32(variant_index)?;
+        self.recurse(value)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(ErrorKind::TypeNotRepresentable("map").into())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn collect_str<T: ?Sized>(self, _value: &T) -> Result<()>
+    where
+        T: core::fmt::Display,
+    {
+        unimplemented!()
+    }
+}
+
+impl<'a, 'b, W> SerializeSeq for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.recurse(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, 'b, W> SerializeTuple for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.recurse(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, 'b, W> SerializeTupleStruct for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.recurse(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, 'b, W> SerializeTupleVariant for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.recurse(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, 'b, W> SerializeMap for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        unreachable!("Because serialize_map never returns Ok")
+    }
+
+    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        unreachable!("Because serialize_map never returns Ok")
+    }
+
+    fn end(self) -> Result<()> {
+        unreachable!("Because serialize_map never returns Ok")
+    }
+}
+
+impl<'a, 'b, W> SerializeStruct for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.recurse(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, 'b, W> SerializeStructVariant for &'a mut PackedSerializer<'b, W>
+where
+    W: Writer,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.recurse(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
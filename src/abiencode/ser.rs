@@ -58,13 +58,17 @@
 //!   inlined. We may have to implement the passes differently if the compiler
 //!   doesn't inline them due to the match statement.
 
-use super::error::{Error, Result};
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use super::error::{Error, ErrorKind, PathSegment, Result};
 use serde::{
     ser::{
         self, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
         SerializeTupleStruct, SerializeTupleVariant,
     },
-    Serialize,
+    serde_if_integer128, Serialize,
 };
 
 /// Type name used for marking a struct as fake-dynamic (dynamic but
@@ -73,7 +77,7 @@ use serde::{
 /// See [DynamicMarker] for why we need this. The characters have no special
 /// meaning, they have just been chosen in a way that normal Rust types will
 /// never have this name.
-const MARK_DYNAMIC_NAME: &str = ":$&_DYNAMIC";
+pub(super) const MARK_DYNAMIC_NAME: &str = ":$&_DYNAMIC";
 
 // Mark the struct this is serialized in as dynamic, even though all of its
 // fields are not, without causing an additional indirection.
@@ -120,6 +124,33 @@ impl Serialize for DynamicMarker {
     }
 }
 
+// The [de::Deserializer] counterpart of the impl above: recognizes the same
+// marker name and consumes nothing, the same way [Pass::Head]/[Pass::Tail] do
+// not write anything for it on the encoding side.
+impl<'de> serde::Deserialize<'de> for DynamicMarker {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct MarkerVisitor;
+        impl<'de> serde::de::Visitor<'de> for MarkerVisitor {
+            type Value = DynamicMarker;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                f.write_str("a marker for a dynamically-sized value")
+            }
+
+            fn visit_unit<E>(self) -> core::result::Result<DynamicMarker, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(DynamicMarker)
+            }
+        }
+        deserializer.deserialize_unit_struct(MARK_DYNAMIC_NAME, MarkerVisitor)
+    }
+}
+
 /// Flag to prints additional information on non-data slots via stdout.
 ///
 /// Useful when the result differs from the expected value. After returning a
@@ -226,14 +257,45 @@ where
 {
     writer: &'a mut W,
     pass: Pass,
+    depth: usize,
+    max_depth: usize,
+    /// Index of the sequence element currently being serialized, reset by
+    /// [ser::Serializer::serialize_seq] and advanced by
+    /// [SerializeSeq::serialize_element]. Used only to attach a
+    /// [PathSegment::Index] to errors bubbling up from that element; unlike
+    /// `depth`/`max_depth` it never needs to be threaded into a child
+    /// [Serializer], since a sequence's own elements are always serialized
+    /// through this same instance.
+    seq_index: usize,
 }
 
+/// Default recursion-depth limit used by [to_writer()] and [encoded_size()].
+///
+/// Chosen generously for the structures this crate actually encodes while
+/// still bounding the stack usage of [serialize_tuple_element][
+/// Serializer::serialize_tuple_element]'s recursion through nested
+/// structs/tuples/seqs. Use [to_writer_with_config()] to tune this for a
+/// specific target's available stack.
+const DEFAULT_MAX_DEPTH: usize = 32;
+
 pub fn to_writer<T, W>(value: &T, writer: &mut W) -> Result<()>
 where
     T: Serialize,
     W: Writer,
 {
-    to_writer_internal(value, writer, true)
+    to_writer_internal(value, writer, true, 0, DEFAULT_MAX_DEPTH)
+}
+
+/// Like [to_writer()], but with a caller-chosen recursion-depth limit instead
+/// of [DEFAULT_MAX_DEPTH], for targets that need to tune it to their
+/// available stack. Nesting deeper than `max_depth` struct/tuple/seq levels
+/// returns [ErrorKind::DepthLimitExceeded] instead of overflowing the stack.
+pub fn to_writer_with_config<T, W>(value: &T, writer: &mut W, max_depth: usize) -> Result<()>
+where
+    T: Serialize,
+    W: Writer,
+{
+    to_writer_internal(value, writer, true, 0, max_depth)
 }
 
 #[cfg(test)]
@@ -242,19 +304,31 @@ where
     T: Serialize,
     W: Writer,
 {
-    to_writer_internal(value, writer, false)
+    to_writer_internal(value, writer, false, 0, DEFAULT_MAX_DEPTH)
 }
 
-fn to_writer_internal<T, W>(value: &T, writer: &mut W, include_outer_struct: bool) -> Result<()>
+// `depth` lets callers that are themselves nested (e.g. [pre_encode], encoding
+// a map entry that is already `depth` levels deep) keep contributing to the
+// same recursion budget instead of silently resetting it to 0.
+fn to_writer_internal<T, W>(
+    value: &T,
+    writer: &mut W,
+    include_outer_struct: bool,
+    depth: usize,
+    max_depth: usize,
+) -> Result<()>
 where
     T: Serialize,
     W: Writer,
 {
-    let (head_size, is_dynamic, _) = compute_size(&value)?;
+    let (head_size, is_dynamic, _) = compute_size(&value, depth, max_depth)?;
 
     let mut serializer = Serializer {
         writer,
         pass: Pass::Head { offset: head_size },
+        depth,
+        max_depth,
+        seq_index: 0,
     };
 
     if is_dynamic && include_outer_struct {
@@ -269,7 +343,163 @@ where
     Ok(())
 }
 
-fn compute_size<T>(value: &T) -> Result<(usize, bool, bool)>
+/// [Writer] that appends every slot to a `Vec<u8>`.
+///
+/// Backs [to_vec()], the allocating counterpart of [to_writer()] for callers
+/// that would otherwise hand-roll the exact same `Writer` themselves.
+pub(super) struct VecWriter(pub(super) Vec<u8>);
+
+impl Writer for VecWriter {
+    fn write(&mut self, slot: &[u8]) {
+        self.0.extend_from_slice(slot);
+    }
+}
+
+/// Serializes `value` into a freshly-allocated `Vec<u8>`.
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    let mut writer = VecWriter(Vec::new());
+    to_writer(value, &mut writer)?;
+    Ok(writer.0)
+}
+
+/// Like [to_writer()], but first writes a 4-byte function `selector`, matching
+/// Solidity's `abi.encodeWithSelector(selector, value...)`.
+///
+/// The selector is written directly to `writer`, before the [Serializer] for
+/// `value` is even constructed: it is exactly 4 raw bytes, never padded to a
+/// full [SLOT_SIZE] slot, and [to_writer()]'s own head/tail offset math starts
+/// fresh right after it, unaffected by the selector having been written.
+pub fn encode_with_selector<T, W>(selector: [u8; 4], value: &T, writer: &mut W) -> Result<()>
+where
+    T: Serialize,
+    W: Writer,
+{
+    writer.write(&selector);
+    to_writer(value, writer)
+}
+
+/// [Writer] adapting any `std::io::Write`, so it does not have to be
+/// hand-rolled by every caller that already has one (a file, a socket, ...).
+///
+/// [Writer::write()] cannot return a [Result], so a failing inner write is
+/// stored here instead of being surfaced immediately; [to_io_writer()] checks
+/// for it once serialization finishes and turns it into [ErrorKind::Io].
+#[cfg(feature = "std")]
+pub struct IoWriter<W> {
+    inner: W,
+    error: Option<std::io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W> IoWriter<W>
+where
+    W: std::io::Write,
+{
+    pub fn new(inner: W) -> Self {
+        Self { inner, error: None }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W> Writer for IoWriter<W>
+where
+    W: std::io::Write,
+{
+    fn write(&mut self, slot: &[u8]) {
+        if self.error.is_none() {
+            if let Err(e) = self.inner.write_all(slot) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Serializes `value` into any `std::io::Write`, surfacing a failing write as
+/// [ErrorKind::Io] instead of panicking.
+#[cfg(feature = "std")]
+pub fn to_io_writer<T, W>(value: &T, writer: W) -> Result<()>
+where
+    T: Serialize,
+    W: std::io::Write,
+{
+    let mut writer = IoWriter::new(writer);
+    to_writer(value, &mut writer)?;
+    match writer.error {
+        Some(e) => Err(ErrorKind::Io(e).into()),
+        None => Ok(()),
+    }
+}
+
+/// Computes the total number of bytes [to_writer()] would emit for `value`
+/// (head + tail, plus the outer offset slot if `value` itself is dynamic),
+/// without writing anything.
+///
+/// Runs the same size-only passes `to_writer` itself relies on against
+/// [NoWriter], so callers on constrained devices can pre-size a fixed buffer
+/// or validate a message length before committing to serialization.
+pub fn encoded_size<T>(value: &T) -> Result<usize>
+where
+    T: Serialize,
+{
+    let (head_size, is_dynamic, _) = compute_size(&value, 0, DEFAULT_MAX_DEPTH)?;
+    let tail_size = Serializer::<NoWriter>::get_tail_size(&value, 0, DEFAULT_MAX_DEPTH)?;
+    Ok(head_size + tail_size + if is_dynamic { SLOT_SIZE } else { 0 })
+}
+
+/// [Writer] that copies into a caller-provided `&mut [u8]` instead of
+/// allocating, for `no_std`/embedded targets. Backs [serialize_into()].
+///
+/// Every [Writer] impl in this module only ever appends slots in order (see
+/// [VecWriter]) rather than seeking, so this just needs a cursor - there is
+/// no need to pre-zero the buffer or stage writes through
+/// `MaybeUninit<u8>`: the bytes [serialize_into()] hands it are the
+/// caller's own already-initialized `&mut [u8]`, and by construction (see
+/// below) every one of them gets written before [to_writer()] returns.
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer for SliceWriter<'a> {
+    fn write(&mut self, slot: &[u8]) {
+        // `serialize_into()` already checked `buf.len() >= encoded_size(value)`,
+        // so this can never run past the end of `buf`.
+        debug_assert!(self.pos + slot.len() <= self.buf.len());
+        self.buf[self.pos..self.pos + slot.len()].copy_from_slice(slot);
+        self.pos += slot.len();
+    }
+}
+
+/// Like [to_writer()], but writes into a caller-provided `&mut [u8]` instead
+/// of allocating, returning the number of bytes written.
+///
+/// First computes [encoded_size()] for `value` and fails with
+/// [ErrorKind::BufferTooSmall] if `buf` isn't big enough, so the actual
+/// [to_writer()] pass below is guaranteed to fit - `no_std` targets that
+/// can't allocate a `Vec<u8>` can instead reuse one fixed-size stack/static
+/// buffer across calls.
+pub fn serialize_into<T>(buf: &mut [u8], value: &T) -> Result<usize>
+where
+    T: Serialize,
+{
+    let needed = encoded_size(value)?;
+    if buf.len() < needed {
+        return Err(ErrorKind::BufferTooSmall {
+            needed,
+            available: buf.len(),
+        }
+        .into());
+    }
+
+    let mut writer = SliceWriter { buf, pos: 0 };
+    to_writer(value, &mut writer)?;
+    Ok(writer.pos)
+}
+
+fn compute_size<T>(value: &T, depth: usize, max_depth: usize) -> Result<(usize, bool, bool)>
 where
     T: Serialize,
 {
@@ -280,6 +510,9 @@ where
             is_dynamic: false,
             is_fake_dynamic: false,
         },
+        depth,
+        max_depth,
+        seq_index: 0,
     };
     value.serialize(&mut serializer)?;
 
@@ -325,25 +558,31 @@ where
         self.writer.write(bytes.as_slice())
     }
 
-    fn serialize<T>(&mut self, value: &T, pass: Pass) -> Result<()>
+    fn serialize<T>(&mut self, value: &T, pass: Pass, depth: usize) -> Result<()>
     where
         T: Serialize,
     {
         let mut serializer = Serializer {
             writer: self.writer,
             pass,
+            depth,
+            max_depth: self.max_depth,
+            seq_index: 0,
         };
         value.serialize(&mut serializer)?;
         Ok(())
     }
 
-    fn get_tail_size<T>(value: &T) -> Result<usize>
+    fn get_tail_size<T>(value: &T, depth: usize, max_depth: usize) -> Result<usize>
     where
         T: Serialize,
     {
         let mut serializer = Serializer {
             writer: &mut NoWriter,
             pass: Pass::TailSize(0),
+            depth,
+            max_depth,
+            seq_index: 0,
         };
         value.serialize(&mut serializer)?;
         // This can only panic if the serializer changes the pass variable.
@@ -369,6 +608,13 @@ where
     // sequences) that has to be written in Pass::Head (and thus has an effect
     // on the total size of the encoded value), but does not count towards the
     // offset.
+    //
+    // When `name` is given (struct/struct-variant fields), an error bubbling
+    // up from this element is tagged with a [PathSegment::Field], so callers
+    // can locate e.g. `TypeNotRepresentable("map") at .balances` rather than a
+    // bare kind name. Seq elements (which pass `name: None`) are tagged with a
+    // [PathSegment::Index] by [SerializeSeq::serialize_element] instead, since
+    // this function has no notion of element position.
     fn serialize_tuple_element<T: ?Sized>(
         &mut self,
         name: Option<&'static str>,
@@ -378,13 +624,35 @@ where
     where
         T: Serialize,
     {
+        self.serialize_tuple_element_inner(name, value, offset_reduction)
+            .map_err(|e| match name {
+                Some(name) => e.with_segment(PathSegment::Field(name)),
+                None => e,
+            })
+    }
+
+    fn serialize_tuple_element_inner<T: ?Sized>(
+        &mut self,
+        name: Option<&'static str>,
+        value: &T,
+        offset_reduction: usize,
+    ) -> Result<()>
+    where
+        T: Serialize,
+    {
+        if self.depth >= self.max_depth {
+            return Err(ErrorKind::DepthLimitExceeded.into());
+        }
+        let depth = self.depth + 1;
+
         match self.pass {
             Pass::HeadSize {
                 ref mut size,
                 ref mut is_dynamic,
                 ..
             } => {
-                let (element_size, is_dyn, is_fake_dynamic) = compute_size(&value)?;
+                let (element_size, is_dyn, is_fake_dynamic) =
+                    compute_size(&value, depth, self.max_depth)?;
                 // Unfortunately we can't use mutable references in the match
                 // statement because compute_size requires a reference, too.
                 // TODO: Make compute_size not use self or value and ideally
@@ -400,7 +668,8 @@ where
                 Ok(())
             }
             Pass::Head { offset } => {
-                let (field_head_size, is_dyn, is_fake_dynamic) = compute_size(&value)?;
+                let (field_head_size, is_dyn, is_fake_dynamic) =
+                    compute_size(&value, depth, self.max_depth)?;
                 if is_dyn && !is_fake_dynamic {
                     // The length (only used in Serde Sequences = Solidity dynamic
                     // length arrays) is part of the Head pass (as it is written
@@ -420,7 +689,9 @@ where
                     };
 
                     self.pass = Pass::Head {
-                        offset: offset + field_head_size + Self::get_tail_size(&value)?,
+                        offset: offset
+                            + field_head_size
+                            + Self::get_tail_size(&value, depth, self.max_depth)?,
                     };
                     Ok(())
                 } else {
@@ -437,12 +708,13 @@ where
                     // release builds this will write hex 0xFFFFFFFFFFFFFFFF as
                     // the offset, which is unlikely to occur normally (still
                     // possible as a U256 of course).
-                    self.serialize(&value, Pass::Head { offset: usize::MAX })
+                    self.serialize(&value, Pass::Head { offset: usize::MAX }, depth)
                 }
             }
             Pass::TailSize(size) => {
-                let (field_head_size, is_dyn, is_fake_dynamic) = compute_size(&value)?;
-                let field_tail_size = Self::get_tail_size(&value)?;
+                let (field_head_size, is_dyn, is_fake_dynamic) =
+                    compute_size(&value, depth, self.max_depth)?;
+                let field_tail_size = Self::get_tail_size(&value, depth, self.max_depth)?;
                 self.pass = Pass::TailSize(
                     size + if is_dyn && !is_fake_dynamic {
                         field_head_size
@@ -453,7 +725,8 @@ where
                 Ok(())
             }
             Pass::Tail => {
-                let (field_head_size, is_dyn, is_fake_dynamic) = compute_size(&value)?;
+                let (field_head_size, is_dyn, is_fake_dynamic) =
+                    compute_size(&value, depth, self.max_depth)?;
                 if is_dyn && !is_fake_dynamic {
                     // This offset might be counter intuitive (I've thought
                     // about it wrong multiple times). It does NOT have an
@@ -468,8 +741,9 @@ where
                         Pass::Head {
                             offset: field_head_size,
                         },
+                        depth,
                     )?;
-                    self.serialize(&value, Pass::Tail)
+                    self.serialize(&value, Pass::Tail, depth)
                 } else {
                     Ok(())
                 }
@@ -489,7 +763,7 @@ where
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
+    type SerializeMap = MapSerializer<'a, 'b, W>;
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
 
@@ -541,15 +815,21 @@ where
         Ok(())
     }
 
-    fn serialize_i128(self, v: i128) -> Result<()> {
-        trace("serialize_i128", &self.pass);
-        match self.pass {
-            Pass::HeadSize { ref mut size, .. } => *size += SLOT_SIZE,
-            Pass::Head { .. } => self.write_right_aligned(v.to_be_bytes()),
-            Pass::TailSize(_) => {}
-            Pass::Tail => {}
-        };
-        Ok(())
+    // Gated the same way serde's own primitive impls (and e.g. the csv
+    // serializer) gate 128-bit support: every target this crate actually
+    // builds for has i128/u128, but the macro is the idiomatic way to spell
+    // that rather than assuming it ourselves.
+    serde_if_integer128! {
+        fn serialize_i128(self, v: i128) -> Result<()> {
+            trace("serialize_i128", &self.pass);
+            match self.pass {
+                Pass::HeadSize { ref mut size, .. } => *size += SLOT_SIZE,
+                Pass::Head { .. } => self.write_right_aligned(v.to_be_bytes()),
+                Pass::TailSize(_) => {}
+                Pass::Tail => {}
+            };
+            Ok(())
+        }
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
@@ -596,30 +876,32 @@ where
         Ok(())
     }
 
-    fn serialize_u128(self, v: u128) -> Result<()> {
-        trace("serialize_u128", &self.pass);
-        match self.pass {
-            Pass::HeadSize { ref mut size, .. } => *size += SLOT_SIZE,
-            Pass::Head { .. } => self.write_right_aligned(v.to_be_bytes()),
-            Pass::TailSize(_) => {}
-            Pass::Tail => {}
-        };
-        Ok(())
+    serde_if_integer128! {
+        fn serialize_u128(self, v: u128) -> Result<()> {
+            trace("serialize_u128", &self.pass);
+            match self.pass {
+                Pass::HeadSize { ref mut size, .. } => *size += SLOT_SIZE,
+                Pass::Head { .. } => self.write_right_aligned(v.to_be_bytes()),
+                Pass::TailSize(_) => {}
+                Pass::Tail => {}
+            };
+            Ok(())
+        }
     }
 
     fn serialize_f32(self, _: f32) -> Result<()> {
         trace("serialize_f32", &self.pass);
-        Err(Error::TypeNotRepresentable("f32"))
+        Err(ErrorKind::TypeNotRepresentable("f32").into())
     }
 
     fn serialize_f64(self, _: f64) -> Result<()> {
         trace("serialize_f64", &self.pass);
-        Err(Error::TypeNotRepresentable("f64"))
+        Err(ErrorKind::TypeNotRepresentable("f64").into())
     }
 
     fn serialize_char(self, _: char) -> Result<()> {
         trace("serialize_char", &self.pass);
-        Err(Error::TypeNotYetSupported("char"))
+        Err(ErrorKind::TypeNotYetSupported("char").into())
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
@@ -686,7 +968,7 @@ where
 
     fn serialize_none(self) -> Result<()> {
         trace("serialize_none", &self.pass);
-        Err(Error::TypeNotRepresentable("none"))
+        Err(ErrorKind::TypeNotRepresentable("none").into())
     }
 
     fn serialize_some<T: ?Sized>(self, _: &T) -> Result<()>
@@ -694,12 +976,12 @@ where
         T: Serialize,
     {
         trace("serialize_some", &self.pass);
-        Err(Error::TypeNotRepresentable("some"))
+        Err(ErrorKind::TypeNotRepresentable("some").into())
     }
 
     fn serialize_unit(self) -> Result<()> {
         trace("serialize_unit", &self.pass);
-        Err(Error::TypeNotRepresentable("unit"))
+        Err(ErrorKind::TypeNotRepresentable("unit").into())
     }
 
     fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
@@ -719,13 +1001,20 @@ where
             Ok(())
         } else {
             trace("serialize_unit_struct", &self.pass);
-            Err(Error::TypeNotRepresentable("unit struct"))
+            Err(ErrorKind::TypeNotRepresentable("unit struct").into())
         }
     }
 
-    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<()> {
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
         trace("serialize_unit_variant", &self.pass);
-        Err(Error::TypeNotRepresentable("unit variant (enum)"))
+        // Solidity enums are a single uint8 word; reuse serialize_tuple_element
+        // so the tag is written/sized exactly like any other static field.
+        self.serialize_tuple_element(None, &variant_index, 0)
     }
 
     fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<()>
@@ -738,20 +1027,25 @@ where
 
     fn serialize_newtype_variant<T: ?Sized>(
         self,
-        _: &'static str,
-        _: u32,
-        _: &'static str,
-        _: &T,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
     ) -> Result<()>
     where
         T: Serialize,
     {
         trace("serialize_newtype_variant", &self.pass);
-        Err(Error::TypeNotRepresentable("newtype variant (enum)"))
+        // Encoded as a (tag, payload) tuple: the tag slot comes first, then the
+        // payload's own head/tail placement is computed relative to it, the
+        // same way any other field would be.
+        self.serialize_tuple_element(None, &variant_index, 0)?;
+        self.serialize_tuple_element(None, value, 0)
     }
 
     fn serialize_seq(self, size: Option<usize>) -> Result<Self::SerializeSeq> {
         trace("serialize_seq", &self.pass);
+        self.seq_index = 0;
         match self.pass {
             Pass::HeadSize {
                 ref mut size,
@@ -788,17 +1082,24 @@ where
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
         trace("serialize_tuple_variant", &self.pass);
-        Err(Error::TypeNotRepresentable("struct variant"))
+        // The tag is written as if it were element 0 of a (tag, field...)
+        // tuple; each subsequent serialize_field() call continues that tuple.
+        self.serialize_tuple_element(None, &variant_index, 0)?;
+        Ok(self)
     }
 
     fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
         trace("serialize_map", &self.pass);
-        Err(Error::TypeNotRepresentable("map"))
+        Ok(MapSerializer {
+            serializer: self,
+            entries: Vec::new(),
+            pending_key: None,
+        })
     }
 
     fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
@@ -809,12 +1110,15 @@ where
     fn serialize_struct_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
         trace("serialize_struct_variant", &self.pass);
-        Err(Error::TypeNotRepresentable("struct variant"))
+        // Same tag-then-fields tuple layout as serialize_tuple_variant, just
+        // with named fields.
+        self.serialize_tuple_element(None, &variant_index, 0)?;
+        Ok(self)
     }
 
     #[cfg(not(feature = "std"))]
@@ -843,7 +1147,10 @@ where
         // The sequence length (written in Pass::Head) is not part of the offset
         // calculation for sequence elements, see comment inside of
         // serialize_tuple_element.
+        let index = self.seq_index;
+        self.seq_index += 1;
         self.serialize_tuple_element(None, value, SLOT_SIZE)
+            .map_err(|e| e.with_segment(PathSegment::Index(index)))
     }
 
     fn end(self) -> Result<()> {
@@ -904,19 +1211,190 @@ where
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
     where
         T: Serialize,
     {
-        unreachable!("Because serialize_tuple_variant never returns Ok")
+        trace("TupleVariant: serialize_field", &self.pass);
+        self.serialize_tuple_element(None, value, 0)
     }
 
     fn end(self) -> Result<()> {
-        unreachable!("Because serialize_tuple_variant never returns Ok")
+        trace("TupleVariant: end", &self.pass);
+        Ok(())
     }
 }
 
-impl<'a, 'b, W> SerializeMap for &'a mut Serializer<'b, W>
+/// Already-encoded form of a value, produced once by [pre_encode] and later
+/// spliced into a containing tuple/array by [assemble] without re-running the
+/// original value's [Serialize] impl.
+///
+/// [SerializeMap] needs this because map entries must be fully encoded before
+/// they can be sorted into the canonical order [MapSerializer::end] emits;
+/// every other `Serialize*` impl in this module instead writes straight
+/// through to the live [Pass], since it never needs to look at an element's
+/// bytes before deciding where they go.
+struct PreEncoded {
+    is_dynamic: bool,
+    is_fake_dynamic: bool,
+    bytes: Vec<u8>,
+}
+
+/// Encodes `value` on its own (head followed by tail, no outer offset slot),
+/// the same shape [to_fnargs_writer] produces, for later use by [assemble].
+fn pre_encode<T: ?Sized>(value: &T, depth: usize, max_depth: usize) -> Result<PreEncoded>
+where
+    T: Serialize,
+{
+    let (_, is_dynamic, is_fake_dynamic) = compute_size(value, depth, max_depth)?;
+    let mut writer = VecWriter(Vec::new());
+    to_writer_internal(value, &mut writer, false, depth, max_depth)?;
+    Ok(PreEncoded {
+        is_dynamic,
+        is_fake_dynamic,
+        bytes: writer.0,
+    })
+}
+
+/// Right-aligns `v` into a zero-padded [SLOT_SIZE]-byte slot, the same layout
+/// [Serializer::write_right_aligned] writes, but into a plain buffer instead
+/// of a [Writer] since [assemble] builds a [PreEncoded] rather than writing
+/// directly.
+fn right_aligned_slot(v: usize) -> [u8; SLOT_SIZE] {
+    let mut bytes = [0u8; SLOT_SIZE];
+    let v_bytes = v.to_be_bytes();
+    bytes[SLOT_SIZE - v_bytes.len()..].copy_from_slice(&v_bytes);
+    bytes
+}
+
+/// Lays out already pre-encoded `elems` as one Solidity tuple (`length_prefix:
+/// None`) or dynamic array (`length_prefix: Some(elems.len())`): static/
+/// fake-dynamic elements are written inline in Head, true dynamic elements get
+/// an offset in Head and their bytes appended to Tail, mirroring
+/// [Serializer::serialize_tuple_element] and [ser::Serializer::serialize_seq]
+/// for a live value.
+fn assemble(elems: &[PreEncoded], length_prefix: Option<usize>) -> PreEncoded {
+    let offset_reduction = if length_prefix.is_some() {
+        SLOT_SIZE
+    } else {
+        0
+    };
+    let head_size: usize = offset_reduction
+        + elems
+            .iter()
+            .map(|e| {
+                if e.is_dynamic && !e.is_fake_dynamic {
+                    SLOT_SIZE
+                } else {
+                    e.bytes.len()
+                }
+            })
+            .sum::<usize>();
+
+    let mut head = Vec::with_capacity(head_size);
+    if let Some(len) = length_prefix {
+        head.extend_from_slice(&right_aligned_slot(len));
+    }
+    let mut tail = Vec::new();
+    let mut offset = head_size;
+    for e in elems {
+        if e.is_dynamic && !e.is_fake_dynamic {
+            head.extend_from_slice(&right_aligned_slot(offset - offset_reduction));
+            tail.extend_from_slice(&e.bytes);
+            offset += e.bytes.len();
+        } else {
+            head.extend_from_slice(&e.bytes);
+        }
+    }
+
+    let is_dynamic =
+        length_prefix.is_some() || elems.iter().any(|e| e.is_dynamic || e.is_fake_dynamic);
+    let mut bytes = head;
+    bytes.extend_from_slice(&tail);
+    PreEncoded {
+        is_dynamic,
+        is_fake_dynamic: false,
+        bytes,
+    }
+}
+
+impl<'a, W> Serializer<'a, W>
+where
+    W: Writer,
+{
+    /// Writes an already-[pre_encode]d value at the current position, exactly
+    /// as [serialize_tuple_element_inner][Self::serialize_tuple_element_inner]
+    /// would for a live value with the same `is_dynamic`/`is_fake_dynamic`/
+    /// encoded size, just without re-serializing it.
+    fn write_pre_encoded(&mut self, value: &PreEncoded, offset_reduction: usize) -> Result<()> {
+        match self.pass {
+            Pass::HeadSize {
+                ref mut size,
+                ref mut is_dynamic,
+                ..
+            } => {
+                *size += if value.is_dynamic && !value.is_fake_dynamic {
+                    SLOT_SIZE
+                } else {
+                    value.bytes.len()
+                };
+                *is_dynamic |= value.is_dynamic || value.is_fake_dynamic;
+            }
+            Pass::Head { offset } => {
+                if value.is_dynamic && !value.is_fake_dynamic {
+                    self.write_right_aligned((offset - offset_reduction).to_be_bytes());
+                    self.pass = Pass::Head {
+                        offset: offset + value.bytes.len(),
+                    };
+                } else {
+                    for chunk in value.bytes.chunks(SLOT_SIZE) {
+                        self.writer.write(chunk);
+                    }
+                }
+            }
+            Pass::TailSize(size) => {
+                self.pass = Pass::TailSize(
+                    size + if value.is_dynamic && !value.is_fake_dynamic {
+                        value.bytes.len()
+                    } else {
+                        0
+                    },
+                );
+            }
+            Pass::Tail => {
+                if value.is_dynamic && !value.is_fake_dynamic {
+                    for chunk in value.bytes.chunks(SLOT_SIZE) {
+                        self.writer.write(chunk);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// [SerializeMap] implementation for [Serializer].
+///
+/// Unlike every other `Serialize*` impl in this module, this cannot just be
+/// `&mut Serializer`: map keys must be fully encoded before they can be
+/// sorted into the deterministic order `abi.encode` needs for hashing, so
+/// entries are buffered here and only written to the underlying [Serializer]
+/// once [end()][SerializeMap::end] knows the final order.
+///
+/// Map keys must themselves be ABI-representable static or dynamic types,
+/// same as everywhere else in this module; the entries are emitted as a
+/// dynamic array of 2-element `(key, value)` tuples, sorted by the encoded
+/// bytes of the key.
+pub struct MapSerializer<'a, 'b, W>
+where
+    W: Writer,
+{
+    serializer: &'a mut Serializer<'b, W>,
+    entries: Vec<(PreEncoded, PreEncoded)>,
+    pending_key: Option<PreEncoded>,
+}
+
+impl<'a, 'b, W> SerializeMap for MapSerializer<'a, 'b, W>
 where
     W: Writer,
 {
@@ -924,22 +1402,45 @@ where
 
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
     where
         T: Serialize,
     {
-        unreachable!("Because serialize_map never returns Ok")
+        if self.serializer.depth >= self.serializer.max_depth {
+            return Err(ErrorKind::DepthLimitExceeded.into());
+        }
+        let depth = self.serializer.depth + 1;
+        self.pending_key = Some(pre_encode(key, depth, self.serializer.max_depth)?);
+        Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
     where
         T: Serialize,
     {
-        unreachable!("Because serialize_map never returns Ok")
+        if self.serializer.depth >= self.serializer.max_depth {
+            return Err(ErrorKind::DepthLimitExceeded.into());
+        }
+        let depth = self.serializer.depth + 1;
+        let value = pre_encode(value, depth, self.serializer.max_depth)?;
+        let key = self
+            .pending_key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.entries.push((key, value));
+        Ok(())
     }
 
     fn end(self) -> Result<()> {
-        unreachable!("Because serialize_map never returns Ok")
+        let mut entries = self.entries;
+        entries.sort_by(|(a, _), (b, _)| a.bytes.cmp(&b.bytes));
+        let tuples: Vec<PreEncoded> = entries
+            .into_iter()
+            .map(|(key, value)| assemble(&[key, value], None))
+            .collect();
+        let len = tuples.len();
+        let array = assemble(&tuples, Some(len));
+        self.serializer.write_pre_encoded(&array, 0)
     }
 }
 
@@ -973,14 +1474,16 @@ where
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: Serialize,
     {
-        unreachable!("Because serialize_struct_variant never returns Ok")
+        trace("StructVariant: serialize_field", &self.pass);
+        self.serialize_tuple_element(Some(key), value, 0)
     }
 
     fn end(self) -> Result<()> {
-        unreachable!("Because serialize_struct_variant never returns Ok")
+        trace("StructVariant: end", &self.pass);
+        Ok(())
     }
 }
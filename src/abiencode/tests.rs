@@ -3,14 +3,19 @@ mod bytes;
 mod bytes_in;
 mod bytescontainer;
 mod dynstruct_in;
+mod packed;
+mod serialize_into;
 mod simple;
 mod solidity_docs;
 mod static_in;
 mod staticstruct_in;
 mod string;
 
+extern crate alloc;
+
 use super::*;
-use serde::Serialize;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
 use uint::hex::FromHex;
 
 use core::fmt::Debug;
@@ -168,6 +173,23 @@ where
     assert_eq!(next, None, "there are less slots than expected.");
 }
 
+/// Encodes `value` via `to_fnargs_writer` and decodes the result back
+/// through [from_slice()], asserting the outcome equals `value` - exercising
+/// [Deserializer] against the same fixture a [serialize_and_compare_fnargs]
+/// call already covers, instead of hand-writing a second expectation for the
+/// decode direction. `to_fnargs_writer` (rather than `to_writer`) is required
+/// here because `from_slice()` expects `data` to start at the value's own
+/// Head.
+pub fn round_trip<T>(value: &T)
+where
+    T: Serialize + for<'de> Deserialize<'de> + PartialEq + Debug,
+{
+    let mut writer = super::ser::VecWriter(Vec::new());
+    super::ser::to_fnargs_writer(value, &mut writer).unwrap();
+    let decoded: T = from_slice(&writer.0).unwrap();
+    assert_eq!(&decoded, value);
+}
+
 // More or less the same as BytesContainer, the only difference is that it is
 // encoded in a flattened way (the container itself is not visible).
 trait Bytes {
@@ -21,7 +21,8 @@ fn in_function_args() {
 00000000000000000000000095222290dd7278aa3ddd389cc1e1d165cc4bafe5
     ";
 
-    serialize_and_compare_fnargs(&addr, expected)
+    serialize_and_compare_fnargs(&addr, expected);
+    round_trip(&addr);
 }
 
 #[test]
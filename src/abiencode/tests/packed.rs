@@ -0,0 +1,78 @@
+use super::*;
+
+#[test]
+fn u64() {
+    /*
+    ```solidity
+        function u64Packed() public pure returns(bytes memory) {
+            uint64 d = 0x1337000012341111;
+            return abi.encodePacked(d);
+        }
+    ```
+    */
+    let d: u64 = 0x1337000012341111;
+
+    let expected = <[u8; 8]>::from_hex("1337000012341111").unwrap();
+
+    assert_eq!(to_packed_vec(&d).unwrap(), expected);
+}
+
+#[test]
+fn bytes_no_length_prefix() {
+    /*
+    ```solidity
+        function bytesPacked() public pure returns(bytes memory) {
+            bytes memory d = "\xa1\xa2\xa3\xa4";
+            return abi.encodePacked(d);
+        }
+    ```
+    */
+    let d = BytesViaNormalAttr::gen(0xa0);
+
+    let expected = <[u8; 4]>::from_hex("a1a2a3a4").unwrap();
+
+    assert_eq!(to_packed_vec(&d).unwrap(), expected);
+}
+
+#[test]
+fn tuple_concatenates_without_padding_or_offsets() {
+    /*
+    ```solidity
+        function tuplePacked() public pure returns(bytes memory) {
+            uint64 a = 0x1337000012341111;
+            bytes memory b = "\xa1\xa2\xa3\xa4";
+            return abi.encodePacked(a, b);
+        }
+    ```
+    */
+    let d = (0x1337000012341111u64, BytesViaNormalAttr::gen(0xa0));
+
+    // No 32-byte padding of `a`, no length word or offset slot for `b` - just
+    // the 8 raw bytes of `a` followed by the 4 raw bytes of `b`.
+    let expected = <[u8; 12]>::from_hex("1337000012341111a1a2a3a4").unwrap();
+
+    assert_eq!(to_packed_vec(&d).unwrap(), expected);
+}
+
+#[test]
+fn dyn_array_concatenates_elements_without_offsets() {
+    /*
+    ```solidity
+        function dynArrayPacked() public pure returns(bytes memory) {
+            uint64[] memory d = new uint64[](2);
+            d[0] = 0x1111;
+            d[1] = 0x2222;
+            return abi.encodePacked(d);
+        }
+    ```
+    */
+    #[derive(Serialize, Debug)]
+    #[serde(transparent)]
+    struct DynArray(#[serde(with = "as_dyn_array")] [u64; 2]);
+
+    let d = DynArray([0x1111, 0x2222]);
+
+    let expected = <[u8; 16]>::from_hex("00000000000011110000000000002222").unwrap();
+
+    assert_eq!(to_packed_vec(&d).unwrap(), expected);
+}
@@ -0,0 +1,80 @@
+use super::*;
+
+#[test]
+fn matches_to_vec_for_fixed_size_value() {
+    let d: u64 = 0x1337000012341111;
+
+    let mut buf = [0u8; 32];
+    let len = serialize_into(&mut buf, &d).unwrap();
+
+    assert_eq!(len, 32);
+    assert_eq!(&buf[..len], to_vec(&d).unwrap().as_slice());
+}
+
+#[test]
+fn matches_to_vec_for_dynamic_value() {
+    #[derive(Serialize, Debug)]
+    struct WithBytes {
+        a: u64,
+        #[serde(with = "as_bytes")]
+        b: [u8; 5],
+    }
+
+    let d = WithBytes {
+        a: 0x1111,
+        b: [0x11, 0x22, 0x33, 0x44, 0x55],
+    };
+
+    let expected = to_vec(&d).unwrap();
+    let mut buf = vec![0u8; expected.len()];
+    let len = serialize_into(&mut buf, &d).unwrap();
+
+    assert_eq!(len, expected.len());
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn matches_to_vec_for_dyn_in_dyn_value() {
+    // Same shape `static_in::dyn_in_dyn` exercises against `to_writer` - a
+    // `#[serde(transparent)]` wrapper (must not add its own offset slot)
+    // nested inside another `as_dyn_array` field.
+    #[derive(Serialize, Debug)]
+    #[serde(transparent)]
+    struct Inner(#[serde(with = "as_dyn_array")] [u64; 3]);
+
+    #[derive(Serialize, Debug)]
+    struct Outer {
+        #[serde(with = "as_dyn_array")]
+        a: [Inner; 2],
+        #[serde(with = "as_bytes")]
+        b: [u8; 5],
+    }
+
+    let d = Outer {
+        a: [Inner([0xaa, 0xbb, 0xcc]), Inner([0xdd, 0xee, 0xff])],
+        b: [0x11, 0x22, 0x33, 0x44, 0x55],
+    };
+
+    let expected = to_vec(&d).unwrap();
+    let mut buf = vec![0u8; expected.len()];
+    let len = serialize_into(&mut buf, &d).unwrap();
+
+    assert_eq!(len, expected.len());
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn rejects_buffer_smaller_than_encoded_size() {
+    let d: u64 = 0x1337000012341111;
+
+    let mut buf = [0u8; 4];
+    let err = serialize_into(&mut buf, &d).unwrap_err();
+
+    assert!(matches!(
+        err.kind,
+        ErrorKind::BufferTooSmall {
+            needed: 32,
+            available: 4,
+        }
+    ));
+}
@@ -50,7 +50,8 @@ fn u64() {
     0000000000000000000000000000000000000000000000001337000012341111
     ";
 
-    serialize_and_compare(&d, expected)
+    serialize_and_compare(&d, expected);
+    round_trip(&d);
 }
 
 mod bytes_zerolen {
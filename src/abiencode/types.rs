@@ -1,7 +1,7 @@
 use core::fmt::Debug;
 
 use rand::{distributions::Standard, prelude::Distribution};
-use serde::Serialize;
+use serde::{de::Visitor, Deserialize, Serialize};
 use uint::construct_uint;
 
 #[cfg(feature = "secp256k1")]
@@ -53,40 +53,113 @@ macro_rules! bytesN {
     };
 }
 
+// Counterpart of `bytesN!`'s `Serialize` impl for every size that fits in a
+// single ABI slot (i.e. every `bytesN!` user except the 65-byte `Signature`,
+// which spans multiple slots and implements `Deserialize` by hand below).
+// Mirrors [Deserializer::read_inline_bytes][crate::abiencode::de::Deserializer]
+// (one slot, left-aligned, zero-padded on the right).
+macro_rules! bytesN_deserialize {
+    ( $T:ident, $N:literal ) => {
+        impl<'de> Deserialize<'de> for $T {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct SlotVisitor;
+
+                impl<'de> Visitor<'de> for SlotVisitor {
+                    type Value = [u8; $N];
+
+                    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                        write!(f, "a {}-byte left-aligned ABI word", $N)
+                    }
+
+                    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+                    where
+                        E: serde::de::Error,
+                    {
+                        if v[$N..].iter().any(|&b| b != 0) {
+                            return Err(E::custom("non-zero padding in fixed-size bytes"));
+                        }
+                        let mut out = [0u8; $N];
+                        out.copy_from_slice(&v[..$N]);
+                        Ok(out)
+                    }
+                }
+
+                Ok($T(deserializer.deserialize_bytes(SlotVisitor)?))
+            }
+        }
+    };
+}
+
 bytesN!(Bytes1, 1);
+bytesN_deserialize!(Bytes1, 1);
 bytesN!(Bytes2, 2);
+bytesN_deserialize!(Bytes2, 2);
 bytesN!(Bytes3, 3);
+bytesN_deserialize!(Bytes3, 3);
 bytesN!(Bytes4, 4);
+bytesN_deserialize!(Bytes4, 4);
 bytesN!(Bytes5, 5);
+bytesN_deserialize!(Bytes5, 5);
 bytesN!(Bytes6, 6);
+bytesN_deserialize!(Bytes6, 6);
 bytesN!(Bytes7, 7);
+bytesN_deserialize!(Bytes7, 7);
 bytesN!(Bytes8, 8);
+bytesN_deserialize!(Bytes8, 8);
 bytesN!(Bytes9, 9);
+bytesN_deserialize!(Bytes9, 9);
 bytesN!(Bytes10, 10);
+bytesN_deserialize!(Bytes10, 10);
 bytesN!(Bytes11, 11);
+bytesN_deserialize!(Bytes11, 11);
 bytesN!(Bytes12, 12);
+bytesN_deserialize!(Bytes12, 12);
 bytesN!(Bytes13, 13);
+bytesN_deserialize!(Bytes13, 13);
 bytesN!(Bytes14, 14);
+bytesN_deserialize!(Bytes14, 14);
 bytesN!(Bytes15, 15);
+bytesN_deserialize!(Bytes15, 15);
 bytesN!(Bytes16, 16);
+bytesN_deserialize!(Bytes16, 16);
 bytesN!(Bytes17, 17);
+bytesN_deserialize!(Bytes17, 17);
 bytesN!(Bytes18, 18);
+bytesN_deserialize!(Bytes18, 18);
 bytesN!(Bytes19, 19);
+bytesN_deserialize!(Bytes19, 19);
 bytesN!(Bytes20, 20);
+bytesN_deserialize!(Bytes20, 20);
 bytesN!(Bytes21, 21);
+bytesN_deserialize!(Bytes21, 21);
 bytesN!(Bytes22, 22);
+bytesN_deserialize!(Bytes22, 22);
 bytesN!(Bytes23, 23);
+bytesN_deserialize!(Bytes23, 23);
 bytesN!(Bytes24, 24);
+bytesN_deserialize!(Bytes24, 24);
 bytesN!(Bytes25, 25);
+bytesN_deserialize!(Bytes25, 25);
 bytesN!(Bytes26, 26);
+bytesN_deserialize!(Bytes26, 26);
 bytesN!(Bytes27, 27);
+bytesN_deserialize!(Bytes27, 27);
 bytesN!(Bytes28, 28);
+bytesN_deserialize!(Bytes28, 28);
 bytesN!(Bytes29, 29);
+bytesN_deserialize!(Bytes29, 29);
 bytesN!(Bytes30, 30);
+bytesN_deserialize!(Bytes30, 30);
 bytesN!(Bytes31, 31);
+bytesN_deserialize!(Bytes31, 31);
 bytesN!(Bytes32, 32);
+bytesN_deserialize!(Bytes32, 32);
 
 bytesN!(Hash, 32);
+bytesN_deserialize!(Hash, 32);
 
 #[cfg(feature = "secp256k1")]
 impl ThirtyTwoByteHash for Hash {
@@ -96,6 +169,34 @@ impl ThirtyTwoByteHash for Hash {
 }
 
 bytesN!(Signature, 65);
+
+// `Signature` spans 3 ABI slots instead of 1, so it can't use
+// `bytesN_deserialize!`. Deserialize it as `[Bytes32; 3]` (serde's built-in
+// array support drives our `Deserializer::deserialize_tuple`, reading one
+// slot per element via `Bytes32::deserialize`) and glue the 65 meaningful
+// bytes back together, mirroring how [Self::serialize] is produced by
+// `bytesN!`'s `chunks_exact(32)` split in [crate::abiencode::ser].
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let chunks = <[Bytes32; 3]>::deserialize(deserializer)?;
+
+        if chunks[2].0[1..].iter().any(|&b| b != 0) {
+            return Err(serde::de::Error::custom(
+                "non-zero padding in Signature's final ABI word",
+            ));
+        }
+
+        let mut sig = [0u8; 65];
+        sig[..32].copy_from_slice(&chunks[0].0);
+        sig[32..64].copy_from_slice(&chunks[1].0);
+        sig[64] = chunks[2].0[0];
+        Ok(Signature(sig))
+    }
+}
+
 impl Signature {
     pub fn new(rs: &[u8; 64], v: u8) -> Self {
         let mut sig: Signature = Signature([0; 65]);
@@ -103,6 +204,31 @@ impl Signature {
         sig.0[64] = v;
         sig
     }
+
+    /// Fold this signature into the
+    /// [EIP-2098](https://eips.ethereum.org/EIPS/eip-2098) compact 64-byte
+    /// representation by storing `yParity = v - 27` in the top bit of `s`.
+    /// This halves the size of a signature on the wire at the cost of only
+    /// being valid for `v` values of 27/28 (i.e. no EIP-155 chain id).
+    pub fn to_compact(&self) -> [u8; 64] {
+        debug_assert!(self.0[64] == 27 || self.0[64] == 28, "to_compact only supports v values of 27/28, see EIP-2098");
+        debug_assert!(self.0[32] & 0x80 == 0, "s is not canonical, top bit already in use");
+
+        let y_parity = self.0[64] - 27;
+        let mut compact = [0u8; 64];
+        compact.copy_from_slice(&self.0[..64]);
+        compact[32] |= y_parity << 7;
+        compact
+    }
+
+    /// Inverse of [Self::to_compact()]: recover the full 65-byte signature
+    /// from its EIP-2098 compact form.
+    pub fn from_compact(compact: &[u8; 64]) -> Self {
+        let y_parity = (compact[32] & 0x80) >> 7;
+        let mut rs = *compact;
+        rs[32] &= 0x7f;
+        Self::new(&rs, 27 + y_parity)
+    }
 }
 
 // We could use primitive_types:U256 or ethereum_types::U256 here, too. Both
@@ -129,6 +255,32 @@ impl Serialize for U256 {
     }
 }
 
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct SlotVisitor;
+
+        impl<'de> Visitor<'de> for SlotVisitor {
+            type Value = U256;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(f, "a 32-byte big-endian ABI word")
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(U256::from_big_endian(v))
+            }
+        }
+
+        deserializer.deserialize_bytes(SlotVisitor)
+    }
+}
+
 impl Distribution<U256> for Standard {
     fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> U256 {
         let buf: [u8; 32] = rng.gen();
@@ -153,6 +305,40 @@ impl Serialize for Address {
     }
 }
 
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct SlotVisitor;
+
+        impl<'de> Visitor<'de> for SlotVisitor {
+            type Value = [u8; 20];
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(f, "a 20-byte right-aligned ABI word")
+            }
+
+            // For some unknown reason abi encoding has addresses right
+            // aligned (like uints) instead of left aligned like
+            // bytes/bytesN, see [Address::serialize].
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                if v[..32 - 20].iter().any(|&b| b != 0) {
+                    return Err(E::custom("non-zero padding in Address"));
+                }
+                let mut out = [0u8; 20];
+                out.copy_from_slice(&v[32 - 20..]);
+                Ok(out)
+            }
+        }
+
+        Ok(Address(deserializer.deserialize_bytes(SlotVisitor)?))
+    }
+}
+
 #[cfg(feature = "secp256k1")]
 impl From<PublicKey> for Address {
     fn from(pk: PublicKey) -> Self {
@@ -1,36 +1,45 @@
 use super::{
-    channel_update::ChannelUpdate,
+    channel_update::{ChannelError, ChannelUpdate},
+    closing::ClosingChannel,
     fixed_size_payment::{self},
     PartIdx, Peers, SignError,
 };
 use crate::{
     abiencode::{
         self,
-        types::{Address, Hash, Signature},
+        types::{Address, Hash, Signature, U256},
     },
+    client::ChannelFeatures,
     messages::{
-        LedgerChannelUpdate,
-        ParticipantMessage,
-        StartWatchingLedgerChannelReq,
-        WatcherRequestMessage,
-        FunderRequestMessage,
-        RegisterReq,
-        AdjudicatorReq,
-        Transaction,
+        ChannelSync, LedgerChannelUpdate, LedgerChannelUpdateAccepted, ParticipantMessage,
+        Shutdown, StartWatchingLedgerChannelReq, WatcherRequestMessage,
     },
-    sig,
+    sig::{EthSigner, SigningError},
     wire::{BroadcastMessageBus, MessageBus},
     PerunClient,
 };
+use alloc::{string::ToString, vec::Vec};
+use serde::{Deserialize, Serialize};
 
+/// Default, currently only fully wire-compatible shape - see
+/// [ActiveChannel]'s own docs for which of its methods are generalized to any
+/// `<ASSETS, PARTICIPANTS, LOCKED>` and which are restricted to this default.
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
-type Params = fixed_size_payment::Params<PARTICIPANTS>;
+// One reserved sub-allocation slot, so a channel can lock funds into a single
+// sub/virtual channel at a time - see [ActiveChannel::lock_into_subchannel].
+// Not generalized to an arbitrary, changing count yet: that would need the
+// still-unstable `generic_const_exprs` to express "L+1" at the type level.
+const LOCKED: usize = 1;
+type State<const A: usize, const P: usize, const L: usize = 1> =
+    fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>;
+type Params<const P: usize> = fixed_size_payment::Params<P>;
+type SubAlloc<const A: usize> = fixed_size_payment::SubAlloc<A>;
 
 #[derive(Debug)]
 pub enum ProposeUpdateError {
     AbiEncodeError(abiencode::Error),
+    SigningFailed(SigningError),
     InvalidUpdate(InvalidUpdate),
 }
 impl From<abiencode::Error> for ProposeUpdateError {
@@ -38,6 +47,11 @@ impl From<abiencode::Error> for ProposeUpdateError {
         Self::AbiEncodeError(e)
     }
 }
+impl From<SigningError> for ProposeUpdateError {
+    fn from(e: SigningError) -> Self {
+        Self::SigningFailed(e)
+    }
+}
 impl From<InvalidUpdate> for ProposeUpdateError {
     fn from(e: InvalidUpdate) -> Self {
         Self::InvalidUpdate(e)
@@ -47,17 +61,24 @@ impl From<InvalidUpdate> for ProposeUpdateError {
 #[derive(Debug)]
 pub enum HandleUpdateError {
     AbiEncodeError(abiencode::Error),
-    RecoveryFailed(sig::Error),
+    RecoveryFailed(SigningError),
     InvalidSignature(Address),
     InvalidUpdate(InvalidUpdate),
+    /// We already broadcast our own [ActiveChannel::update] proposal for
+    /// this same version, and the [PartIdx] tie-break (see [PendingUpdate])
+    /// means ours wins the race. [ActiveChannel::handle_update] has already
+    /// broadcast a `ChannelUpdateRejected` for the peer's colliding update by
+    /// the time this is returned, so the peer doesn't have to wait for its
+    /// own independent tie-break to figure out it lost.
+    SupersededByLocalProposal,
 }
 impl From<abiencode::Error> for HandleUpdateError {
     fn from(e: abiencode::Error) -> Self {
         Self::AbiEncodeError(e)
     }
 }
-impl From<sig::Error> for HandleUpdateError {
-    fn from(e: sig::Error) -> Self {
+impl From<SigningError> for HandleUpdateError {
+    fn from(e: SigningError) -> Self {
         Self::RecoveryFailed(e)
     }
 }
@@ -67,6 +88,119 @@ impl From<InvalidUpdate> for HandleUpdateError {
     }
 }
 
+/// Recommended response to a [HandleUpdateError], for callers that want to
+/// reply to the sending peer automatically instead of special-casing every
+/// variant themselves. Loosely mirrors rust-lightning's `ErrorAction`.
+///
+/// This only classifies the error; it deliberately does not send anything
+/// itself. Nothing in this crate talks to the network on its own (it never
+/// even talks to the chain on its own, see [SignedChannel::mark_funded][
+/// super::signed::SignedChannel::mark_funded]), so acting on the
+/// recommendation - e.g. calling [ChannelUpdate::reject][
+/// super::channel_update::ChannelUpdate::reject] or
+/// [ActiveChannel::force_close] - is left to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// Not a protocol violation by the peer; nothing more for the caller to
+    /// do. For [HandleUpdateError::SupersededByLocalProposal] specifically,
+    /// the rejection reply has already been sent by the time this action is
+    /// returned.
+    Ignore,
+    /// The peer's message is malformed or violates the channel's rules.
+    /// Reply with a [ParticipantMessage::ChannelUpdateRejected] (see
+    /// [ChannelUpdate::reject][super::channel_update::ChannelUpdate::reject])
+    /// so the peer can propose a corrected update instead of stalling on a
+    /// response that will never arrive.
+    Reject,
+    /// The peer can no longer be trusted to follow the off-chain protocol
+    /// (e.g. it produced a signature that doesn't recover to its own
+    /// [Address]); fall back to an on-chain dispute via
+    /// [ActiveChannel::force_close]/[ActiveChannel::handle_dispute] rather
+    /// than continuing to exchange off-chain messages with it.
+    ForceClose,
+}
+
+impl HandleUpdateError {
+    /// Classifies this error into a recommended [ErrorAction]. See
+    /// [ErrorAction] for why this stops at a recommendation instead of
+    /// replying itself.
+    pub fn action(&self) -> ErrorAction {
+        match self {
+            HandleUpdateError::SupersededByLocalProposal => ErrorAction::Ignore,
+            HandleUpdateError::InvalidSignature(_) => ErrorAction::ForceClose,
+            HandleUpdateError::AbiEncodeError(_)
+            | HandleUpdateError::RecoveryFailed(_)
+            | HandleUpdateError::InvalidUpdate(_) => ErrorAction::Reject,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReestablishError {
+    AbiEncodeError(abiencode::Error),
+    RecoveryFailed(SigningError),
+    InvalidChannelID,
+    InvalidSignature(PartIdx),
+    /// The peer's version is neither equal to, one behind, nor one ahead of
+    /// ours - too far apart to resolve by retransmitting a single update, so
+    /// the caller should fall back to force-closing via the watcher.
+    VersionDiverged,
+    Resend(SignError),
+}
+impl From<abiencode::Error> for ReestablishError {
+    fn from(e: abiencode::Error) -> Self {
+        Self::AbiEncodeError(e)
+    }
+}
+impl From<SigningError> for ReestablishError {
+    fn from(e: SigningError) -> Self {
+        Self::RecoveryFailed(e)
+    }
+}
+impl From<SignError> for ReestablishError {
+    fn from(e: SignError) -> Self {
+        Self::Resend(e)
+    }
+}
+
+/// What [ActiveChannel::reestablish] did to resolve the version difference
+/// between our state and the peer's [ChannelSync].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReestablishOutcome {
+    /// Both sides already agree on the latest state; nothing to do.
+    InSync,
+    /// The peer was one version behind; we retransmitted our last signed
+    /// `ChannelUpdate` so they can catch up.
+    Retransmitted,
+    /// We were one version behind; the peer's retransmitted state (which
+    /// already carries every participant's signature) has been applied.
+    Applied,
+}
+
+/// Error from [ActiveChannel::lock_into_subchannel] or
+/// [ActiveChannel::release_subchannel].
+#[derive(Debug)]
+pub enum LockError {
+    /// Every reserved [SubAlloc] slot (see [LOCKED]) already holds a
+    /// sub-channel; this build doesn't support more than [LOCKED] at once.
+    AllSlotsOccupied,
+    /// No reserved slot is locked into the given sub-channel id.
+    NoSuchSubchannel,
+    /// A contribution exceeds the participant's current balance for that
+    /// asset.
+    InsufficientBalance,
+    /// A peer never advertised the [ChannelFeatures] this operation needs
+    /// (see [ActiveChannel::require_peer_feature]), so it may not understand
+    /// - or may even refuse - the resulting [ChannelUpdate].
+    FeatureNotNegotiated(ChannelFeatures),
+    ProposeUpdate(ProposeUpdateError),
+}
+impl From<ProposeUpdateError> for LockError {
+    fn from(e: ProposeUpdateError) -> Self {
+        Self::ProposeUpdate(e)
+    }
+}
+
 #[derive(Debug)]
 pub enum InvalidUpdate {
     InvalidChannelID,
@@ -74,26 +208,91 @@ pub enum InvalidUpdate {
     CurrentStateIsFinal,
     AssetsMismatch,
     TotalAllocationAmountMismatch,
+    /// A locked (sub/virtual-channel) slot was changed in an unsupported way:
+    /// either its balance changed while staying locked into the same
+    /// sub-channel, or it was swapped directly from one sub-channel id to a
+    /// different one without first closing back to empty. See
+    /// [ActiveChannel::check_locked_set].
+    LockedSetChanged,
+}
+
+/// Our own [ActiveChannel::update]-proposed state for a version that hasn't
+/// been committed (via [ActiveChannel::force_update]) yet. Tracked so that
+/// if both participants propose simultaneously - each producing a different
+/// state at `self.state.version() + 1` - the resulting collision can be
+/// resolved deterministically instead of deadlocking on
+/// [InvalidUpdate::InvalidVersionNumber] once the loser's signatures never
+/// arrive: whichever participant has the lower [PartIdx] always wins (see
+/// [HandleUpdateError::SupersededByLocalProposal]), and the loser's proposal
+/// is rebased onto the winning state and resent one version higher (see
+/// [ActiveChannel::resolve_pending_update]). Adapted from the nonce-based
+/// scheduler in [crate::nonce], which resolves the analogous race between
+/// concurrently-dispatched on-chain funding transactions.
+#[derive(Debug, Clone, Copy)]
+struct PendingUpdate<const ASSETS: usize, const PARTICIPANTS: usize, const LOCKED: usize> {
+    version: u64,
+    hash: Hash,
+    state: State<ASSETS, PARTICIPANTS, LOCKED>,
 }
 
+/// An active (funded) channel.
+///
+/// Generic over the number of assets/participants/reserved locked slots, like
+/// the rest of [fixed_size_payment] - see [super::proposal]'s module docs for
+/// why that generalization stops at the wire boundary rather than reaching
+/// all the way through it. Concretely: the struct itself, its plain
+/// accessors, [Self::check_valid_transition], [Self::send_shutdown] and
+/// everything [ChannelUpdate] does to collect signatures are
+/// generic over `<ASSETS, PARTICIPANTS, LOCKED>`. [Self::update],
+/// [Self::handle_update], [Self::reestablish], [Self::force_update] (and
+/// therefore [ChannelUpdate::apply]), [Self::lock_into_subchannel]/
+/// [Self::release_subchannel] (which go through [Self::update]) and
+/// [Self::force_close]/
+/// [Self::handle_dispute] are restricted to the default shape in a separate
+/// `impl` block below, since they serialize to [LedgerChannelUpdate],
+/// [ChannelSync], [crate::messages::StartWatchingLedgerChannelReq] and
+/// [ClosingChannel], none of which are generalized yet.
 #[derive(Debug)]
-pub struct ActiveChannel<'cl, B: MessageBus> {
+pub struct ActiveChannel<
+    'cl,
+    B: MessageBus,
+    S: EthSigner,
+    const ASSETS: usize = 1,
+    const PARTICIPANTS: usize = 2,
+    const LOCKED: usize = 1,
+> {
     part_idx: PartIdx,
     withdraw_receiver: Address,
-    client: &'cl PerunClient<B>,
-    state: State,
-    params: Params,
+    client: &'cl PerunClient<B, S>,
+    state: State<ASSETS, PARTICIPANTS, LOCKED>,
+    params: Params<PARTICIPANTS>,
     signatures: [Signature; PARTICIPANTS],
     peers: Peers,
+    pending_update: Option<PendingUpdate<ASSETS, PARTICIPANTS, LOCKED>>,
+    /// A [LedgerChannelUpdateAccepted] one version ahead of whatever
+    /// [ChannelUpdate] currently exists for this channel, stashed by
+    /// [ChannelUpdate::participant_accepted] instead of being rejected as
+    /// out-of-order - see [Self::buffer_acceptance]/[Self::replay_buffered_acceptance].
+    /// Dropped for free on [Self::force_close]/[Self::handle_dispute], since
+    /// both consume `self` by value.
+    buffered_acceptance: Option<(PartIdx, LedgerChannelUpdateAccepted)>,
 }
 
-impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
+impl<
+        'cl,
+        B: MessageBus,
+        S: EthSigner,
+        const ASSETS: usize,
+        const PARTICIPANTS: usize,
+        const LOCKED: usize,
+    > ActiveChannel<'cl, B, S, ASSETS, PARTICIPANTS, LOCKED>
+{
     pub(super) fn new(
-        client: &'cl PerunClient<B>,
+        client: &'cl PerunClient<B, S>,
         part_idx: PartIdx,
         withdraw_receiver: Address,
-        init_state: State,
-        params: Params,
+        init_state: State<ASSETS, PARTICIPANTS, LOCKED>,
+        params: Params<PARTICIPANTS>,
         signatures: [Signature; PARTICIPANTS],
         peers: Peers,
     ) -> Self {
@@ -107,6 +306,8 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
             signatures,
             withdraw_receiver,
             peers,
+            pending_update: None,
+            buffered_acceptance: None,
         }
     }
 
@@ -117,7 +318,7 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
         self.state.version()
     }
 
-    pub fn state(&self) -> State {
+    pub fn state(&self) -> State<ASSETS, PARTICIPANTS, LOCKED> {
         self.state
     }
 
@@ -125,7 +326,7 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
         self.part_idx
     }
 
-    pub fn client(&self) -> &PerunClient<B> {
+    pub fn client(&self) -> &PerunClient<B, S> {
         self.client
     }
 
@@ -133,12 +334,38 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
         &self.peers
     }
 
-    pub fn params(&self) -> Params {
+    pub fn params(&self) -> Params<PARTICIPANTS> {
         self.params
     }
 
-    fn check_valid_transition(&self, new_state: State) -> Result<(), InvalidUpdate> {
-        debug_assert_eq!(new_state.outcome.locked.len(), 0, "At the moment we don't support subchannels and thus don't represent locked balances. This assert exists for when we do add it, thus warning us if this 'we don't have locked values' assumption changes. If it does: Go-Perun asserts that the `SubAlloc` (locked values) are equivalent and did not change, see `validTwoPartyUpdate`.");
+    pub fn withdraw_receiver(&self) -> Address {
+        self.withdraw_receiver
+    }
+
+    pub fn signatures(&self) -> [Signature; PARTICIPANTS] {
+        self.signatures
+    }
+
+    /// Broadcast a `ShutdownMsg`, proposing to settle the channel on-chain at
+    /// its current state instead of going through the Watcher's dispute
+    /// process. Only meaningful once both sides have signed a final state
+    /// via [update][Self::update]/[close_normal][Self::close_normal] - this
+    /// does not check `self.state().is_final` itself, the caller is
+    /// responsible for only calling it once that's true.
+    pub fn send_shutdown(&self) {
+        self.client.bus.broadcast_to_participants(
+            self.part_idx,
+            &self.peers,
+            ParticipantMessage::Shutdown(Shutdown {
+                channel: self.channel_id(),
+            }),
+        );
+    }
+
+    fn check_valid_transition(
+        &self,
+        new_state: State<ASSETS, PARTICIPANTS, LOCKED>,
+    ) -> Result<(), InvalidUpdate> {
         new_state.outcome.debug_assert_valid();
 
         if new_state.channel_id() != self.state.channel_id() {
@@ -149,19 +376,199 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
             Err(InvalidUpdate::InvalidVersionNumber)
         } else if new_state.outcome.assets != self.state.outcome.assets {
             Err(InvalidUpdate::AssetsMismatch)
-        } else if new_state.outcome.total_assets() != self.state.outcome.total_assets() {
+        } else if !self.state.valid_app_transition(&new_state) {
             Err(InvalidUpdate::TotalAllocationAmountMismatch)
         } else {
+            Self::check_locked_set(&self.state.outcome.locked, &new_state.outcome.locked)
+        }
+    }
+
+    /// Each reserved locked (sub/virtual-channel) slot may only change by
+    /// opening (an empty slot, `id == Hash::default()`, taking on a fresh
+    /// sub-channel id) or closing (an occupied slot going back to empty); a
+    /// slot that stays locked into the same sub-channel must keep its exact
+    /// balance, and one locked id can never be swapped directly for another
+    /// without going through empty first. Go-perun's `validTwoPartyUpdate`
+    /// requires the whole `Locked` slice to stay unchanged, since it doesn't
+    /// support opening/closing sub-channels through a regular update; this is
+    /// the slot-wise generalization of that same check.
+    fn check_locked_set(
+        old: &[SubAlloc<ASSETS>; LOCKED],
+        new: &[SubAlloc<ASSETS>; LOCKED],
+    ) -> Result<(), InvalidUpdate> {
+        for (old_sub, new_sub) in old.iter().zip(new) {
+            if old_sub.id == new_sub.id {
+                if old_sub.bals != new_sub.bals {
+                    return Err(InvalidUpdate::LockedSetChanged);
+                }
+            } else if old_sub.id != Hash::default() && new_sub.id != Hash::default() {
+                return Err(InvalidUpdate::LockedSetChanged);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns [LockError::FeatureNotNegotiated] unless every peer has
+    /// advertised `feature` (see [crate::client::PerunClient::negotiated_features]).
+    /// A peer that hasn't authenticated at all (and therefore has no
+    /// negotiated set yet) is treated the same as one that negotiated
+    /// nothing - this only runs once a channel is already active, by which
+    /// point every peer should long have completed the handshake.
+    fn require_peer_feature(&self, feature: ChannelFeatures) -> Result<(), LockError> {
+        let all_negotiated = self
+            .peers
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| idx != self.part_idx)
+            .all(|(_, peer)| {
+                self.client
+                    .negotiated_features(peer)
+                    .is_some_and(|negotiated| negotiated.contains(feature))
+            });
+
+        if all_negotiated {
             Ok(())
+        } else {
+            Err(LockError::FeatureNotNegotiated(feature))
+        }
+    }
+
+    /// Stashes `msg` for [Self::replay_buffered_acceptance] to fold into the
+    /// next [ChannelUpdate] created for this channel, instead of
+    /// [ChannelUpdate::participant_accepted] rejecting it as out-of-order.
+    /// Only ever holds a single entry - per
+    /// [ChannelUpdate::participant_accepted]'s one-version-ahead invariant,
+    /// a second, differently-versioned attempt to buffer here means more
+    /// than one version has already raced ahead, which is rejected with
+    /// [ChannelError::OutOfOrder] rather than silently overwriting the
+    /// existing entry.
+    pub(super) fn buffer_acceptance(
+        &mut self,
+        part_idx: PartIdx,
+        msg: LedgerChannelUpdateAccepted,
+    ) -> Result<(), ChannelError> {
+        match &self.buffered_acceptance {
+            Some((_, existing)) if existing.version != msg.version => Err(ChannelError::OutOfOrder),
+            _ => {
+                self.buffered_acceptance = Some((part_idx, msg));
+                Ok(())
+            }
+        }
+    }
+
+    /// Takes the [buffered][Self::buffer_acceptance] acceptance (if any) and
+    /// applies it to `update` if it is for `update`'s version, so an
+    /// acceptance that arrived before `update` existed doesn't have to be
+    /// resent by the peer. Best-effort: if applying it now fails (e.g. a bad
+    /// signature), it is dropped rather than buffered again - `update` still
+    /// independently collects the real signature from the peer.
+    fn replay_buffered_acceptance(
+        &mut self,
+        update: &mut ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED>,
+    ) {
+        let Some((part_idx, msg)) = self.buffered_acceptance.take() else {
+            return;
+        };
+        if msg.version != update.version() {
+            self.buffered_acceptance = Some((part_idx, msg));
+            return;
+        }
+        let _ = update.participant_accepted(self, part_idx, msg);
+    }
+}
+
+/// Restricted to the default single-asset, two-party, one-reserved-slot shape
+/// - see [ActiveChannel]'s own docs for why. Everything here either
+/// serializes to a not-yet-generalized wire message, or (transitively, via
+/// [Self::update]/[Self::force_update]) depends on something that does.
+impl<'cl, B: MessageBus, S: EthSigner> ActiveChannel<'cl, B, S, ASSETS, PARTICIPANTS, LOCKED> {
+    /// The [ChannelSync] handshake to send when resuming this channel over a
+    /// freshly (re-)established connection, so the peer can tell whether it
+    /// missed an update while disconnected. See [Self::reestablish] for the
+    /// receiving side.
+    pub fn sync_msg(&self) -> ChannelSync {
+        ChannelSync {
+            state: self.state,
+            sigs: self.signatures,
+        }
+    }
+
+    /// Broadcast [Self::sync_msg] to the peer, e.g. after reconnecting.
+    pub fn send_sync_msg(&self) {
+        self.client.bus.broadcast_to_participants(
+            self.part_idx,
+            &self.peers,
+            ParticipantMessage::ChannelSync(self.sync_msg()),
+        );
+    }
+
+    /// Reconcile our state with a peer's [ChannelSync], sent after
+    /// reconnecting. Verifies every participant's signature on `sync.state`
+    /// first, then compares its version to ours: equal means both sides
+    /// already agree, one behind means the peer missed our last update (so we
+    /// retransmit it), and one ahead means we missed theirs (so we adopt it,
+    /// since a `ChannelSync` already carries every participant's signature,
+    /// unlike a fresh `ChannelUpdate` which only carries the proposer's).
+    /// Anything else is too far apart to resolve this way - the caller should
+    /// force-close instead, the same as it would for any other unrecoverable
+    /// channel error.
+    pub fn reestablish(
+        &mut self,
+        sync: ChannelSync,
+    ) -> Result<ReestablishOutcome, ReestablishError> {
+        if sync.state.channel_id() != self.channel_id() {
+            return Err(ReestablishError::InvalidChannelID);
+        }
+
+        let hash = abiencode::to_hash(&sync.state)?;
+        for (part_idx, sig) in sync.sigs.iter().enumerate() {
+            let signer = self
+                .client
+                .signer
+                .recover_signer(hash, *sig)
+                .map_err(SigningError::capture)?;
+            if self.params.participants[part_idx] != signer {
+                return Err(ReestablishError::InvalidSignature(part_idx));
+            }
+        }
+
+        let remote_version = sync.state.version();
+        let local_version = self.version();
+
+        if remote_version == local_version {
+            Ok(ReestablishOutcome::InSync)
+        } else if remote_version + 1 == local_version {
+            self.client.bus.broadcast_to_participants(
+                self.part_idx,
+                &self.peers,
+                ParticipantMessage::ChannelUpdate(LedgerChannelUpdate {
+                    state: self.state,
+                    actor_idx: self.part_idx,
+                    sig: self.signatures[self.part_idx],
+                }),
+            );
+            Ok(ReestablishOutcome::Retransmitted)
+        } else if local_version + 1 == remote_version {
+            self.force_update(sync.state, sync.sigs)?;
+            Ok(ReestablishOutcome::Applied)
+        } else {
+            Err(ReestablishError::VersionDiverged)
         }
     }
 
-    pub fn update(&self, new_state: State) -> Result<ChannelUpdate, ProposeUpdateError> {
+    pub fn update(
+        &mut self,
+        new_state: State<ASSETS, PARTICIPANTS, LOCKED>,
+    ) -> Result<ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED>, ProposeUpdateError> {
         self.check_valid_transition(new_state)?;
 
         // Sign immediately, we need the signature to send the proposal.
         let hash = abiencode::to_hash(&new_state)?;
-        let sig = self.client.signer.sign_eth(hash);
+        let sig = self
+            .client
+            .signer
+            .sign_channel_state(self.params, Some(self.state), new_state, hash)
+            .map_err(SigningError::capture)?;
         self.client.bus.broadcast_to_participants(
             self.part_idx,
             &self.peers,
@@ -172,28 +579,64 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
             }),
         );
 
-        Ok(ChannelUpdate::new(self, new_state, self.part_idx, sig))
+        self.pending_update = Some(PendingUpdate {
+            version: new_state.version(),
+            hash,
+            state: new_state,
+        });
+
+        let mut update = ChannelUpdate::new(self, new_state, self.part_idx, sig);
+        self.replay_buffered_acceptance(&mut update);
+        Ok(update)
     }
 
     pub fn handle_update(
-        &self,
+        &mut self,
         msg: LedgerChannelUpdate,
-    ) -> Result<ChannelUpdate, HandleUpdateError> {
+    ) -> Result<ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED>, HandleUpdateError> {
+        if let Some(pending) = self.pending_update {
+            if pending.version == msg.state.version() && self.part_idx < msg.actor_idx {
+                // We win the tie-break (lower `part_idx`): reply with a
+                // rejection for the peer's colliding update ourselves, right
+                // here, instead of leaving it to the caller via
+                // `ErrorAction::Reject` - the peer is expected to run this
+                // same tie-break independently once it receives our own
+                // `update()` broadcast, but there's no reason to make it wait
+                // for that when we already know it lost.
+                self.client.bus.broadcast_to_participants(
+                    self.part_idx,
+                    &self.peers,
+                    ParticipantMessage::ChannelUpdateRejected {
+                        id: self.channel_id(),
+                        version: msg.state.version(),
+                        reason: "superseded by our own concurrent update proposal".to_string(),
+                    },
+                );
+                return Err(HandleUpdateError::SupersededByLocalProposal);
+            }
+        }
+
         self.check_valid_transition(msg.state)?;
 
         let hash = abiencode::to_hash(&msg.state)?;
-        let signer = self.client.signer.recover_signer(hash, msg.sig)?;
+        let signer = self
+            .client
+            .signer
+            .recover_signer(hash, msg.sig)
+            .map_err(SigningError::capture)?;
 
         if self.params.participants[msg.actor_idx] != signer {
             return Err(HandleUpdateError::InvalidSignature(signer));
         }
 
-        Ok(ChannelUpdate::new(self, msg.state, msg.actor_idx, msg.sig))
+        let mut update = ChannelUpdate::new(self, msg.state, msg.actor_idx, msg.sig);
+        self.replay_buffered_acceptance(&mut update);
+        Ok(update)
     }
 
     pub(super) fn force_update(
         &mut self,
-        new_state: State,
+        new_state: State<ASSETS, PARTICIPANTS, LOCKED>,
         signatures: [Signature; PARTICIPANTS],
     ) -> Result<(), SignError> {
         // To prevent modifying self (the channel state+signatures) in case
@@ -221,7 +664,10 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
         self.signatures = signatures;
 
         match self.send_current_state_to_watcher() {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.resolve_pending_update();
+                Ok(())
+            }
             Err(e) => {
                 self.state = old_state;
                 self.signatures = old_sigs;
@@ -230,6 +676,32 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
         }
     }
 
+    /// Called once [Self::force_update] has committed a new state: if our
+    /// own [Self::update] proposal for that same version is still pending
+    /// and isn't what just got committed, a peer's differing proposal won
+    /// the collision (see [PendingUpdate]/[HandleUpdateError::SupersededByLocalProposal])
+    /// - rebase our intended change onto the new state and resend it one
+    /// version higher, rather than silently dropping it.
+    fn resolve_pending_update(&mut self) {
+        let Some(pending) = self.pending_update.take() else {
+            return;
+        };
+        if pending.version != self.state.version() {
+            return;
+        }
+        if abiencode::to_hash(&self.state).ok() == Some(pending.hash) {
+            // Our own proposal is exactly what got committed - no collision.
+            return;
+        }
+
+        let mut rebased = self.state.make_next_state();
+        rebased.outcome = pending.state.outcome;
+        rebased.is_final = pending.state.is_final;
+        // Best-effort: the state committed above stands either way, so
+        // there's nothing to roll back if re-proposing this fails.
+        let _ = self.update(rebased);
+    }
+
     fn make_watch_info(&self) -> Result<StartWatchingLedgerChannelReq, SignError> {
         Ok(StartWatchingLedgerChannelReq {
             params: self.params,
@@ -238,19 +710,6 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
         })
     }
 
-    fn make_adjudicator_req(&self) -> AdjudicatorReq {
-         AdjudicatorReq {
-            params: self.params(),
-            acc:       self.withdraw_receiver,
-            tx:        Transaction {
-                state: self.state(),
-                sigs: self.signatures,
-            },
-            idx:       self.part_idx,
-            secondary: false, // opposite of close initiated.
-        }
-    }
-
     pub fn send_current_state_to_watcher(&self) -> Result<(), SignError> {
         self.client
             .bus
@@ -260,29 +719,193 @@ impl<'cl, B: MessageBus> ActiveChannel<'cl, B> {
 
     // Use `update()` if the state has to change, too
 
-    pub fn close_normal(&self) -> Result<ChannelUpdate, ProposeUpdateError> {
+    pub fn close_normal(
+        &mut self,
+    ) -> Result<ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED>, ProposeUpdateError> {
         let mut new_state = self.state.make_next_state();
         new_state.is_final = true;
         self.update(new_state)
     }
 
-    // At the moment this just drops the channel after sending the message. In
-    // the future it might make sense to have a struct representing a closing
-    // channel, for example to allow resending the last message.
-    pub fn force_close(self) -> Result<Self, (Self, SignError)> {
-        self.client
-            .bus
-            .send_to_funder(FunderRequestMessage::RegisterReq(
-                RegisterReq {
-                    adj_req: self.make_adjudicator_req(),
-                },
-            ));
+    /// Propose locking `contributions` (how much of each asset each of *our*
+    /// participants currently contributes, in this channel's participant
+    /// order) out of this channel's balances and into a new sub/virtual
+    /// channel identified by `sub_channel_id`, filling the first empty
+    /// reserved [SubAlloc] slot (see [LOCKED]). The new sub-channel reuses
+    /// this channel's participant order unchanged (`index_map: None`, see
+    /// [fixed_size_payment::IndexMap]); funding/running it is independent of
+    /// this type from here on - see [Self::release_subchannel] for settling
+    /// it back into this channel once it closes.
+    pub fn lock_into_subchannel(
+        &mut self,
+        sub_channel_id: Hash,
+        contributions: [[U256; ASSETS]; PARTICIPANTS],
+    ) -> Result<ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED>, LockError> {
+        self.require_peer_feature(ChannelFeatures::SUB_CHANNEL_UPDATES)?;
+
+        let mut new_state = self.state.make_next_state();
+
+        let slot = new_state
+            .outcome
+            .locked
+            .iter()
+            .position(|sub| sub.id == Hash::default())
+            .ok_or(LockError::AllSlotsOccupied)?;
+
+        let mut bals = [U256::zero(); ASSETS];
+        for (asset_idx, asset_bals) in new_state.outcome.balances.0.iter_mut().enumerate() {
+            for (part_idx, contribution) in contributions.iter().enumerate() {
+                let amount = contribution[asset_idx];
+                asset_bals.0[part_idx] = asset_bals.0[part_idx]
+                    .checked_sub(amount)
+                    .ok_or(LockError::InsufficientBalance)?;
+                bals[asset_idx] += amount;
+            }
+        }
+
+        new_state.outcome.locked[slot] = SubAlloc::<ASSETS> {
+            id: sub_channel_id,
+            bals,
+            index_map: Default::default(),
+        };
+
+        Ok(self.update(new_state)?)
+    }
+
+    /// Propose releasing the reserved slot locked into `sub_channel_id` (see
+    /// [Self::lock_into_subchannel]), crediting `payouts` (how much of each
+    /// asset each of *our* participants receives back, in this channel's
+    /// participant order) into this channel's balances. The caller is
+    /// responsible for `payouts` reflecting however the sub-channel actually
+    /// settled - this only clears the slot and updates the parent balances.
+    pub fn release_subchannel(
+        &mut self,
+        sub_channel_id: Hash,
+        payouts: [[U256; ASSETS]; PARTICIPANTS],
+    ) -> Result<ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED>, LockError> {
+        self.require_peer_feature(ChannelFeatures::SUB_CHANNEL_UPDATES)?;
+
+        let mut new_state = self.state.make_next_state();
+
+        let slot = new_state
+            .outcome
+            .locked
+            .iter()
+            .position(|sub| sub.id == sub_channel_id)
+            .ok_or(LockError::NoSuchSubchannel)?;
+
+        for (asset_idx, asset_bals) in new_state.outcome.balances.0.iter_mut().enumerate() {
+            for (part_idx, payout) in payouts.iter().enumerate() {
+                asset_bals.0[part_idx] += payout[asset_idx];
+            }
+        }
+
+        new_state.outcome.locked[slot] = SubAlloc::<ASSETS>::default();
 
-        Ok(self)
+        Ok(self.update(new_state)?)
     }
-    // At the moment this just drops the channel. In the future it might make
-    // sense to have a struct representing a closing channel, for example to
-    // allow resending the last message.
 
-    pub fn handle_dispute(self) {}
+    /// Register our current state with the Adjudicator and start tracking its
+    /// on-chain resolution, see [ClosingChannel].
+    pub fn force_close(self) -> ClosingChannel<'cl, B, S> {
+        ClosingChannel::initiate(
+            self.client,
+            self.part_idx,
+            self.withdraw_receiver,
+            self.state,
+            self.params,
+            self.signatures,
+        )
+    }
+
+    /// A peer (or the Watcher on our behalf) registered a dispute for this
+    /// channel at `disputed_version`. If that's older than the state we
+    /// already hold, automatically refute it by registering our own newer,
+    /// already-signed state instead. Either way, returns a [ClosingChannel]
+    /// that tracks the dispute's on-chain resolution from here on - once a
+    /// dispute is registered, this channel can no longer keep applying
+    /// off-chain updates.
+    pub fn handle_dispute(self, disputed_version: u64) -> ClosingChannel<'cl, B, S> {
+        ClosingChannel::observe(
+            self.client,
+            self.part_idx,
+            self.withdraw_receiver,
+            self.state,
+            self.params,
+            self.signatures,
+            disputed_version,
+        )
+    }
+
+    /// Serializes the channel's latest mutually-signed state - its
+    /// [Params], [State] and the participants' [Signature]s over it - into a
+    /// compact snapshot [Self::restore] can rebuild a disputable
+    /// [ActiveChannel] from after a crash. Mirrors rust-lightning's
+    /// `ChannelMonitor` persistence: callers are expected to write this out
+    /// (e.g. overwrite a per-channel file) after every successful
+    /// [Self::update]/[Self::handle_update]/[Self::force_update] and on
+    /// first entering this phase (see [super::signed::SignedChannel::mark_funded]),
+    /// so there is always an up to date, fully-signed state on disk to
+    /// register with the Adjudicator even if the process never comes back
+    /// up.
+    ///
+    /// Deliberately only ever serializes `self.state`/`self.signatures`, not
+    /// [Self::pending_update]: a half-collected `update()` proposal that
+    /// hasn't gathered every participant's signature yet can't be disputed
+    /// with, so it must never be the thing a restored channel comes back
+    /// with.
+    pub fn snapshot(&self) -> Result<Vec<u8>, abiencode::Error> {
+        abiencode::to_vec(&Snapshot {
+            params: self.params,
+            state: self.state,
+            signatures: self.signatures,
+        })
+    }
+
+    /// Rebuilds an [ActiveChannel] from a [Self::snapshot] blob, ready to
+    /// call [Self::force_close]/[Self::handle_dispute] against whatever
+    /// dispute it comes back to.
+    ///
+    /// `part_idx`, `withdraw_receiver` and `peers` are this participant's
+    /// own local channel configuration, the same values that would have
+    /// been passed to [super::proposal]/[super::agreed_upon] originally -
+    /// they describe this device's role, not the disputed state itself, so
+    /// unlike `params`/`state`/`signatures` they aren't part of the
+    /// snapshot and must be supplied by the caller (e.g. from its own
+    /// channel registry) instead.
+    pub fn restore(
+        client: &'cl PerunClient<B, S>,
+        part_idx: PartIdx,
+        withdraw_receiver: Address,
+        peers: Peers,
+        snapshot: &[u8],
+    ) -> Result<Self, abiencode::Error> {
+        let Snapshot {
+            params,
+            state,
+            signatures,
+        } = abiencode::from_slice(snapshot)?;
+
+        Ok(ActiveChannel::new(
+            client,
+            part_idx,
+            withdraw_receiver,
+            state,
+            params,
+            signatures,
+            peers,
+        ))
+    }
+}
+
+/// On-disk payload for [ActiveChannel::snapshot]/[ActiveChannel::restore].
+/// Exists only to give `params`/`state`/`signatures` a single [Serialize]/
+/// [Deserialize] impl to (de)serialize together - its byte layout is not a
+/// `perunwire`/Adjudicator wire format and is not meant to be read by
+/// anything other than [ActiveChannel::restore].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Snapshot {
+    params: Params<PARTICIPANTS>,
+    state: State<ASSETS, PARTICIPANTS, LOCKED>,
+    signatures: [Signature; PARTICIPANTS],
 }
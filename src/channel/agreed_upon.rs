@@ -13,20 +13,23 @@ use crate::{
         FunderRequestMessage, LedgerChannelFundingRequest, LedgerChannelUpdateAccepted,
         ParticipantMessage, WatchInfo, WatcherRequestMessage,
     },
-    sig,
+    sig::{EthSigner, SigningError},
     wire::{BroadcastMessageBus, MessageBus},
     PerunClient,
 };
 
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
+// One reserved sub-allocation slot, see `super::active::LOCKED`.
+const LOCKED: usize = 1;
+type State = fixed_size_payment::State<fixed_size_payment::NoApp, ASSETS, PARTICIPANTS, LOCKED>;
 type Params = fixed_size_payment::Params<PARTICIPANTS>;
 type Balances = fixed_size_payment::Balances<ASSETS, PARTICIPANTS>;
 
 #[derive(Debug)]
 pub enum SignError {
     AbiEncodeError(abiencode::Error),
+    SigningFailed(SigningError),
     AlreadySigned,
 }
 impl From<abiencode::Error> for SignError {
@@ -34,11 +37,16 @@ impl From<abiencode::Error> for SignError {
         Self::AbiEncodeError(e)
     }
 }
+impl From<SigningError> for SignError {
+    fn from(e: SigningError) -> Self {
+        Self::SigningFailed(e)
+    }
+}
 
 #[derive(Debug)]
 pub enum AddSignatureError {
     AbiEncodeError(abiencode::Error),
-    RecoveryFailed(sig::Error),
+    RecoveryFailed(SigningError),
     AlreadySigned,
     InvalidSignature(Address),
     InvalidChannelID,
@@ -49,8 +57,8 @@ impl From<abiencode::Error> for AddSignatureError {
         Self::AbiEncodeError(e)
     }
 }
-impl From<sig::Error> for AddSignatureError {
-    fn from(e: sig::Error) -> Self {
+impl From<SigningError> for AddSignatureError {
+    fn from(e: SigningError) -> Self {
         Self::RecoveryFailed(e)
     }
 }
@@ -59,34 +67,56 @@ impl From<sig::Error> for AddSignatureError {
 pub enum BuildError {
     MissingSignatureResponse(PartIdx),
     AbiEncodeError(abiencode::Error),
+    SigningFailed(SigningError),
 }
 impl From<abiencode::Error> for BuildError {
     fn from(e: abiencode::Error) -> Self {
         Self::AbiEncodeError(e)
     }
 }
+impl From<SigningError> for BuildError {
+    fn from(e: SigningError) -> Self {
+        Self::SigningFailed(e)
+    }
+}
+impl From<super::withdrawal_auth::MakeWithdrawalAuthError> for BuildError {
+    fn from(e: super::withdrawal_auth::MakeWithdrawalAuthError) -> Self {
+        match e {
+            super::withdrawal_auth::MakeWithdrawalAuthError::AbiEncodeError(e) => {
+                Self::AbiEncodeError(e)
+            }
+            super::withdrawal_auth::MakeWithdrawalAuthError::SigningFailed(e) => {
+                Self::SigningFailed(e)
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
-pub struct AgreedUponChannel<'cl, B: MessageBus> {
+pub struct AgreedUponChannel<'cl, B: MessageBus, S: EthSigner> {
     part_idx: PartIdx,
     withdraw_receiver: Address,
-    client: &'cl PerunClient<B>,
+    client: &'cl PerunClient<B, S>,
     funding_agreement: Balances,
     init_state: State,
     params: Params,
     signatures: [Option<Signature>; 2],
     peers: Peers,
+    /// The `perunwire` dialect negotiated with the other participants during
+    /// proposal, see [fixed_size_payment::ProtocolVersion].
+    protocol_version: fixed_size_payment::ProtocolVersion,
 }
 
-impl<'cl, B: MessageBus> AgreedUponChannel<'cl, B> {
+impl<'cl, B: MessageBus, S: EthSigner> AgreedUponChannel<'cl, B, S> {
     pub(super) fn new(
-        client: &'cl PerunClient<B>,
+        client: &'cl PerunClient<B, S>,
         funding_agreement: Balances,
         part_idx: PartIdx,
         withdraw_receiver: Address,
         init_state: State,
         params: Params,
         peers: Peers,
+        protocol_version: fixed_size_payment::ProtocolVersion,
     ) -> Self {
         AgreedUponChannel {
             part_idx,
@@ -97,16 +127,31 @@ impl<'cl, B: MessageBus> AgreedUponChannel<'cl, B> {
             params,
             signatures: [None; PARTICIPANTS],
             peers,
+            protocol_version,
         }
     }
 
+    /// The `perunwire` dialect this channel was proposed with, see
+    /// [fixed_size_payment::ProtocolVersion].
+    pub fn protocol_version(&self) -> fixed_size_payment::ProtocolVersion {
+        self.protocol_version
+    }
+
     pub fn sign(&mut self) -> Result<(), SignError> {
         match self.signatures[self.part_idx] {
             Some(_) => Err(SignError::AlreadySigned),
             None => {
                 // Sign the initial state
                 let hash = abiencode::to_hash(&self.init_state)?;
-                let sig = self.client.signer.sign_eth(hash);
+                let sig = self
+                    .client
+                    .signer
+                    .sign_state(
+                        self.init_state.channel_id(),
+                        self.init_state.version(),
+                        hash,
+                    )
+                    .map_err(SigningError::capture)?;
                 // Add signature to the proposed channel
                 self.signatures[self.part_idx] = Some(sig);
                 // Send to other participants
@@ -138,7 +183,11 @@ impl<'cl, B: MessageBus> AgreedUponChannel<'cl, B> {
         }
 
         let hash = abiencode::to_hash(&self.init_state)?;
-        let signer = self.client.signer.recover_signer(hash, msg.sig)?;
+        let signer = self
+            .client
+            .signer
+            .recover_signer(hash, msg.sig)
+            .map_err(SigningError::capture)?;
 
         // Verify signature is comming from a valid participant.
         //
@@ -169,7 +218,7 @@ impl<'cl, B: MessageBus> AgreedUponChannel<'cl, B> {
         }
     }
 
-    pub fn build(self) -> Result<SignedChannel<'cl, B>, (Self, BuildError)> {
+    pub fn build(self) -> Result<SignedChannel<'cl, B, S>, (Self, BuildError)> {
         // Make sure we have the signature from all participants. They have
         // already been verified in `add_signature()` or we created it ourselves
         // with `sign()`. At the same time, this loop collects the signatures
@@ -182,36 +231,36 @@ impl<'cl, B: MessageBus> AgreedUponChannel<'cl, B> {
             };
         }
 
+        let watch_request = WatchInfo {
+            part_idx: self.part_idx,
+            params: self.params,
+            state: self.init_state,
+            signatures,
+            withdrawal_auths: match make_signed_withdrawal_auths(
+                &self.client.signer,
+                self.init_state.channel_id(),
+                self.params,
+                self.init_state,
+                self.withdraw_receiver,
+                self.part_idx,
+            ) {
+                Ok(v) => v,
+                Err(e) => return Err((self, e.into())),
+            },
+        };
         self.client
             .bus
-            .send_to_watcher(WatcherRequestMessage::WatchRequest(WatchInfo {
-                part_idx: self.part_idx,
-                params: self.params,
-                state: self.init_state,
-                signatures,
-                withdrawal_auths: match make_signed_withdrawal_auths(
-                    &self.client.signer,
-                    self.init_state.channel_id(),
-                    self.params,
-                    self.init_state,
-                    self.withdraw_receiver,
-                    self.part_idx,
-                ) {
-                    Ok(v) => v,
-                    Err(e) => return Err((self, e.into())),
-                },
-            }));
+            .send_to_watcher(WatcherRequestMessage::WatchRequest(watch_request));
 
+        let funding_request = LedgerChannelFundingRequest {
+            part_idx: self.part_idx,
+            funding_agreement: self.funding_agreement,
+            params: self.params,
+            state: self.init_state,
+        };
         self.client
             .bus
-            .send_to_funder(FunderRequestMessage::FundingRequest(
-                LedgerChannelFundingRequest {
-                    part_idx: self.part_idx,
-                    funding_agreement: self.funding_agreement,
-                    params: self.params,
-                    state: self.init_state,
-                },
-            ));
+            .send_to_funder(FunderRequestMessage::FundingRequest(funding_request));
 
         Ok(SignedChannel::new(
             self.client,
@@ -221,14 +270,18 @@ impl<'cl, B: MessageBus> AgreedUponChannel<'cl, B> {
             self.params,
             signatures,
             self.peers,
+            funding_request,
+            watch_request,
         ))
     }
 }
 
-impl<'cl, B: MessageBus> TryFrom<AgreedUponChannel<'cl, B>> for SignedChannel<'cl, B> {
-    type Error = (AgreedUponChannel<'cl, B>, BuildError);
+impl<'cl, B: MessageBus, S: EthSigner> TryFrom<AgreedUponChannel<'cl, B, S>>
+    for SignedChannel<'cl, B, S>
+{
+    type Error = (AgreedUponChannel<'cl, B, S>, BuildError);
 
-    fn try_from(value: AgreedUponChannel<'cl, B>) -> Result<Self, Self::Error> {
+    fn try_from(value: AgreedUponChannel<'cl, B, S>) -> Result<Self, Self::Error> {
         value.build()
     }
 }
@@ -4,19 +4,25 @@ use super::{
 use crate::{
     abiencode::{self, types::Signature},
     messages::{LedgerChannelUpdateAccepted, ParticipantMessage},
+    sig::{EthSigner, SigningError},
     wire::{BroadcastMessageBus, MessageBus},
     Hash,
 };
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 
+// Default, currently only fully wire-compatible shape - see
+// `super::active::ASSETS`/`PARTICIPANTS`/`LOCKED`.
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
+const LOCKED: usize = 1;
+type State<const A: usize, const P: usize, const L: usize = 1> =
+    fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>;
 
 /// Error returned when the proposal was already accepted by a participant.
 #[derive(Debug)]
 pub enum AcceptError {
     AbiEncodeError(abiencode::Error),
+    SigningFailed(SigningError),
     AlreadyAccepted,
     WrongVersion,
     WrongChannelId,
@@ -26,6 +32,11 @@ impl From<abiencode::Error> for AcceptError {
         Self::AbiEncodeError(e)
     }
 }
+impl From<SigningError> for AcceptError {
+    fn from(e: SigningError) -> Self {
+        Self::SigningFailed(e)
+    }
+}
 impl From<InvalidChannel> for AcceptError {
     fn from(e: InvalidChannel) -> Self {
         match e {
@@ -38,6 +49,12 @@ impl From<InvalidChannel> for AcceptError {
 #[derive(Debug)]
 pub enum ApplyError {
     MissingSignature(PartIdx),
+    /// A participant rejected this update (see
+    /// [ChannelUpdate::participant_rejected]) instead of signing it, so it
+    /// can never collect every participant's signature - the caller should
+    /// retire it (e.g. propose a corrected update) instead of continuing to
+    /// wait.
+    Rejected(String),
     SignError(SignError),
     WrongVersion,
     WrongChannelId,
@@ -56,13 +73,63 @@ impl From<InvalidChannel> for ApplyError {
     }
 }
 
+#[derive(Debug)]
 pub enum InvalidChannel {
     WrongVersion,
     WrongChannelId,
 }
 
+/// Returned by [ChannelUpdate::participant_accepted] instead of
+/// [AddSignatureError], so a caller can tell a genuine protocol violation
+/// apart from a message that arrived early and has simply been retained for
+/// replay (see [ActiveChannel::buffer_acceptance][super::active::ActiveChannel::buffer_acceptance]).
 #[derive(Debug)]
-pub struct ChannelUpdate {
+pub enum ChannelError {
+    /// `msg` is one version ahead of the current [ChannelUpdate], but an
+    /// acceptance for a different version is already buffered, so it can't
+    /// be buffered for later replay - see
+    /// [super::active::ActiveChannel::buffer_acceptance]'s single-slot
+    /// invariant.
+    OutOfOrder,
+    /// This [ChannelUpdate] is no longer the channel's current pending one.
+    InvalidChannel(InvalidChannel),
+    /// The message itself was rejected - wrong signature, already-collected
+    /// signature, etc.
+    InvalidSignature(AddSignatureError),
+}
+impl From<InvalidChannel> for ChannelError {
+    fn from(e: InvalidChannel) -> Self {
+        Self::InvalidChannel(e)
+    }
+}
+impl From<AddSignatureError> for ChannelError {
+    fn from(e: AddSignatureError) -> Self {
+        Self::InvalidSignature(e)
+    }
+}
+impl From<abiencode::Error> for ChannelError {
+    fn from(e: abiencode::Error) -> Self {
+        Self::InvalidSignature(e.into())
+    }
+}
+impl From<SigningError> for ChannelError {
+    fn from(e: SigningError) -> Self {
+        Self::InvalidSignature(e.into())
+    }
+}
+
+/// Generic over the number of assets/participants/reserved locked slots, the
+/// same as [ActiveChannel] - see that struct's own docs for why that
+/// generalization stops before [Self::apply]. Everything else here only
+/// collects signatures against a fixed `new_state`/`channel_id`, so it's
+/// generic over `<ASSETS, PARTICIPANTS, LOCKED>` like the rest of
+/// [ActiveChannel]'s non-wire-coupled surface.
+#[derive(Debug)]
+pub struct ChannelUpdate<
+    const ASSETS: usize = 1,
+    const PARTICIPANTS: usize = 2,
+    const LOCKED: usize = 1,
+> {
     // Previously we had a mutable reference here, which gave a good amount of
     // guarantees on the type-system level. Unfortunately this proved quite
     // difficult to work with so we've reduced the amount of compile-time
@@ -84,14 +151,22 @@ pub struct ChannelUpdate {
     // degregading security or introducing things the user/application developer
     // could accidentaly get wrong.
     channel_id: Hash,
-    new_state: State,
+    new_state: State<ASSETS, PARTICIPANTS, LOCKED>,
     signatures: [Option<Signature>; PARTICIPANTS],
+    /// Set by [Self::participant_rejected] once some participant has sent a
+    /// [ParticipantMessage::ChannelUpdateRejected] for this update instead of
+    /// signing it - from then on [Self::signatures]/[Self::apply] report
+    /// [ApplyError::Rejected] instead of waiting on signatures that will never
+    /// arrive.
+    rejected: Option<String>,
 }
 
-impl ChannelUpdate {
+impl<const ASSETS: usize, const PARTICIPANTS: usize, const LOCKED: usize>
+    ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED>
+{
     pub(crate) fn new(
-        channel: &ActiveChannel<impl MessageBus>,
-        new_state: State,
+        channel: &ActiveChannel<impl MessageBus, impl EthSigner, ASSETS, PARTICIPANTS, LOCKED>,
+        new_state: State<ASSETS, PARTICIPANTS, LOCKED>,
         sig_part_idx: PartIdx,
         sig: Signature,
     ) -> Self {
@@ -101,12 +176,24 @@ impl ChannelUpdate {
             channel_id: channel.channel_id(),
             new_state,
             signatures,
+            rejected: None,
         }
     }
 
+    /// The version of [ChannelUpdate::new]'s `new_state`, i.e. the version
+    /// this update is trying to move the channel to.
+    pub(crate) fn version(&self) -> u64 {
+        self.new_state.version()
+    }
+
+    /// The channel this update belongs to.
+    pub(crate) fn channel_id(&self) -> Hash {
+        self.channel_id
+    }
+
     pub fn accept(
         &mut self,
-        channel: &mut ActiveChannel<impl MessageBus>,
+        channel: &mut ActiveChannel<impl MessageBus, impl EthSigner, ASSETS, PARTICIPANTS, LOCKED>,
     ) -> Result<(), AcceptError> {
         self.ensure_valid_channel(channel)?;
 
@@ -114,7 +201,11 @@ impl ChannelUpdate {
             Some(_) => Err(AcceptError::AlreadyAccepted),
             None => {
                 let hash = abiencode::to_hash(&self.new_state)?;
-                let sig = channel.client().signer.sign_eth(hash);
+                let sig = channel
+                    .client()
+                    .signer
+                    .sign_state(self.channel_id, self.new_state.version(), hash)
+                    .map_err(SigningError::capture)?;
 
                 let acc: _ = LedgerChannelUpdateAccepted {
                     channel: self.channel_id,
@@ -134,7 +225,7 @@ impl ChannelUpdate {
 
     pub fn reject(
         self,
-        channel: &mut ActiveChannel<impl MessageBus>,
+        channel: &mut ActiveChannel<impl MessageBus, impl EthSigner, ASSETS, PARTICIPANTS, LOCKED>,
         reason: &str,
     ) -> Result<(), InvalidChannel> {
         self.ensure_valid_channel(channel)?;
@@ -151,30 +242,50 @@ impl ChannelUpdate {
         Ok(())
     }
 
+    /// Records `msg` as `part_idx`'s signature for this update.
+    ///
+    /// A `msg` for exactly one version ahead of this update is not rejected
+    /// outright: it is handed to
+    /// [ActiveChannel::buffer_acceptance][super::active::ActiveChannel::buffer_acceptance]
+    /// instead, which stashes it for the *next* [ChannelUpdate] (once
+    /// [ActiveChannel::update][super::active::ActiveChannel::update]/
+    /// [ActiveChannel::handle_update][super::active::ActiveChannel::handle_update]
+    /// creates one) to pick up automatically - real peers routinely race
+    /// ahead like this; if a different version is already buffered, it is
+    /// rejected with [ChannelError::OutOfOrder]. Anything older, or more than
+    /// one version ahead, is still rejected with
+    /// [AddSignatureError::InvalidVersionNumber].
     pub fn participant_accepted(
         &mut self,
-        channel: &ActiveChannel<impl MessageBus>,
+        channel: &mut ActiveChannel<impl MessageBus, impl EthSigner, ASSETS, PARTICIPANTS, LOCKED>,
         part_idx: PartIdx,
         msg: LedgerChannelUpdateAccepted,
-    ) -> Result<(), AddSignatureError> {
+    ) -> Result<(), ChannelError> {
         self.ensure_valid_channel(channel)?;
 
         if msg.channel != self.channel_id {
-            return Err(AddSignatureError::InvalidChannelID);
+            return Err(AddSignatureError::InvalidChannelID.into());
+        }
+        if msg.version == self.new_state.version() + 1 {
+            return channel.buffer_acceptance(part_idx, msg);
         }
         if msg.version != self.new_state.version() {
-            return Err(AddSignatureError::InvalidVersionNumber);
+            return Err(AddSignatureError::InvalidVersionNumber.into());
         }
 
         let hash = abiencode::to_hash(&self.new_state)?;
-        let signer = channel.client().signer.recover_signer(hash, msg.sig)?;
+        let signer = channel
+            .client()
+            .signer
+            .recover_signer(hash, msg.sig)
+            .map_err(SigningError::capture)?;
 
         if channel.params().participants[part_idx] != signer {
-            return Err(AddSignatureError::InvalidSignature(signer));
+            return Err(AddSignatureError::InvalidSignature(signer).into());
         }
 
         match self.signatures[part_idx] {
-            Some(_) => Err(AddSignatureError::AlreadySigned),
+            Some(_) => Err(AddSignatureError::AlreadySigned.into()),
             None => {
                 self.signatures[part_idx] = Some(msg.sig);
                 Ok(())
@@ -182,7 +293,29 @@ impl ChannelUpdate {
         }
     }
 
+    /// Records that a participant sent
+    /// [ParticipantMessage::ChannelUpdateRejected] for this update instead of
+    /// accepting it, so [Self::apply] can report [ApplyError::Rejected]
+    /// instead of waiting forever on a signature that will never come.
+    ///
+    /// `ParticipantMessage::ChannelUpdateRejected` carries no `part_idx`, so
+    /// unlike [Self::participant_accepted] this can't attribute the rejection
+    /// to a specific participant - only record the reason they sent.
+    pub fn participant_rejected(
+        &mut self,
+        channel: &ActiveChannel<impl MessageBus, impl EthSigner, ASSETS, PARTICIPANTS, LOCKED>,
+        reason: String,
+    ) -> Result<(), InvalidChannel> {
+        self.ensure_valid_channel(channel)?;
+        self.rejected = Some(reason);
+        Ok(())
+    }
+
     fn signatures(&self) -> Result<[Signature; PARTICIPANTS], ApplyError> {
+        if let Some(reason) = &self.rejected {
+            return Err(ApplyError::Rejected(reason.clone()));
+        }
+
         let mut signatures: [Signature; PARTICIPANTS] = [Signature::default(); PARTICIPANTS];
         for (part_idx, s) in self.signatures.iter().enumerate() {
             signatures[part_idx] = s.ok_or(ApplyError::MissingSignature(part_idx))?;
@@ -193,7 +326,7 @@ impl ChannelUpdate {
 
     fn ensure_valid_channel(
         &self,
-        channel: &ActiveChannel<impl MessageBus>,
+        channel: &ActiveChannel<impl MessageBus, impl EthSigner, ASSETS, PARTICIPANTS, LOCKED>,
     ) -> Result<(), InvalidChannel> {
         if self.new_state.version() != channel.version() + 1 {
             Err(InvalidChannel::WrongVersion)
@@ -203,10 +336,15 @@ impl ChannelUpdate {
             Ok(())
         }
     }
+}
 
+/// Restricted to the default single-asset, two-party, one-reserved-slot shape
+/// - see [ActiveChannel]'s own docs for why. [Self::apply] calls
+/// [ActiveChannel::force_update], which isn't generalized yet.
+impl ChannelUpdate<ASSETS, PARTICIPANTS, LOCKED> {
     pub fn apply(
         &mut self,
-        channel: &mut ActiveChannel<impl MessageBus>,
+        channel: &mut ActiveChannel<impl MessageBus, impl EthSigner>,
     ) -> Result<(), ApplyError> {
         self.ensure_valid_channel(channel)?;
 
@@ -214,3 +352,184 @@ impl ChannelUpdate {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abiencode::types::Address,
+        channel::{
+            fixed_size_payment::{
+                Allocation, Balances, NoApp, Params, ParticipantBalances, SubAlloc,
+            },
+            Asset, Peers,
+        },
+        test_support::{signature_for, NullBus, StubSigner},
+        PerunClient,
+    };
+
+    fn addr(byte: u8) -> Address {
+        Address([byte; 20])
+    }
+
+    /// See [StubSigner]'s docs for why this can fabricate any participant's
+    /// signature without needing that participant's own [StubSigner].
+    fn sig_for(part_idx: PartIdx) -> Signature {
+        signature_for(addr(part_idx as u8 + 1))
+    }
+
+    /// Builds an `N`-party, single-asset `ActiveChannel` at version 0 plus the
+    /// `ChannelUpdate` proposing version 1 with only participant 0's signature
+    /// collected - the starting point every test below shares. `ChannelUpdate`
+    /// is generic over `<ASSETS, PARTICIPANTS, LOCKED>` like `ActiveChannel`
+    /// itself (see both types' own docs), so this exercises shapes other
+    /// than the crate-wide default `<1, 2, 1>`.
+    fn channel_and_update<const N: usize>(
+        client: &PerunClient<NullBus, StubSigner>,
+    ) -> (
+        ActiveChannel<'_, NullBus, StubSigner, 1, N, 1>,
+        ChannelUpdate<1, N, 1>,
+    ) {
+        let participants: [Address; N] = core::array::from_fn(|i| addr(i as u8 + 1));
+        let params = Params {
+            chain_id: 1u64.into(),
+            challenge_duration: 1,
+            nonce: 1u64.into(),
+            participants,
+            app: Address::default(),
+            ledger_channel: true,
+            virtual_channel: false,
+        };
+        let balances =
+            Balances::<1, N>([ParticipantBalances(core::array::from_fn(|_| 0u64.into()))]);
+        let init_bals = Allocation::<1, N, 1> {
+            assets: [Asset {
+                chain_id: 1u64.into(),
+                holder: addr(1),
+            }],
+            balances,
+            locked: [SubAlloc::default()],
+        };
+        let init_state = State::<1, N, 1>::new(params, init_bals, NoApp).unwrap();
+        let signatures = core::array::from_fn(|_| Signature::default());
+        let peers: Peers = (0..N).map(|i| alloc::vec![i as u8]).collect();
+
+        let channel = ActiveChannel::new(client, 0, addr(1), init_state, params, signatures, peers);
+        let new_state = init_state.make_next_state();
+        let update = ChannelUpdate::new(&channel, new_state, 0, sig_for(0));
+        (channel, update)
+    }
+
+    #[test]
+    fn signatures_reports_missing_signature_for_a_three_party_update() {
+        let client = PerunClient::new(NullBus, StubSigner(addr(0)), 1);
+        let (_channel, update) = channel_and_update::<3>(&client);
+        assert!(matches!(
+            update.signatures(),
+            Err(ApplyError::MissingSignature(1))
+        ));
+    }
+
+    #[test]
+    fn participant_accepted_rejects_a_second_signature_for_a_four_party_update() {
+        let client = PerunClient::new(NullBus, StubSigner(addr(0)), 1);
+        let (mut channel, mut update) = channel_and_update::<4>(&client);
+        let msg = LedgerChannelUpdateAccepted {
+            channel: update.channel_id,
+            version: update.new_state.version(),
+            sig: sig_for(1),
+        };
+        assert!(update.participant_accepted(&mut channel, 1, msg).is_ok());
+        assert!(matches!(
+            update.participant_accepted(&mut channel, 1, msg),
+            Err(ChannelError::InvalidSignature(
+                AddSignatureError::AlreadySigned
+            ))
+        ));
+    }
+
+    #[test]
+    fn all_signatures_collected_for_a_four_party_update_is_ready_to_apply() {
+        let client = PerunClient::new(NullBus, StubSigner(addr(0)), 1);
+        let (mut channel, mut update) = channel_and_update::<4>(&client);
+        for part_idx in 1..4 {
+            let msg = LedgerChannelUpdateAccepted {
+                channel: update.channel_id,
+                version: update.new_state.version(),
+                sig: sig_for(part_idx),
+            };
+            assert!(update
+                .participant_accepted(&mut channel, part_idx, msg)
+                .is_ok());
+        }
+        assert!(update.signatures().is_ok());
+    }
+
+    #[test]
+    fn an_acceptance_one_version_ahead_is_buffered_instead_of_rejected() {
+        let client = PerunClient::new(NullBus, StubSigner(addr(0)), 1);
+        let (mut channel, mut update) = channel_and_update::<3>(&client);
+        let next_version_msg = LedgerChannelUpdateAccepted {
+            channel: update.channel_id,
+            version: update.new_state.version() + 1,
+            sig: sig_for(1),
+        };
+        assert!(update
+            .participant_accepted(&mut channel, 1, next_version_msg)
+            .is_ok());
+        // Not recorded against the current update - it was buffered instead.
+        assert!(matches!(
+            update.signatures(),
+            Err(ApplyError::MissingSignature(1))
+        ));
+    }
+
+    #[test]
+    fn a_buffered_acceptance_is_replayed_once_the_matching_update_exists() {
+        // `apply`/`update` are restricted to the crate-wide default shape
+        // (see `ActiveChannel`'s own docs), so this uses the default
+        // two-party arity instead of `channel_and_update`'s usual non-default
+        // `N` - unlike the rest of this module's tests.
+        let client = PerunClient::new(NullBus, StubSigner(addr(0)), 1);
+        let (mut channel, mut first_update) = channel_and_update::<2>(&client);
+
+        let current_version_msg = LedgerChannelUpdateAccepted {
+            channel: first_update.channel_id,
+            version: first_update.new_state.version(),
+            sig: sig_for(1),
+        };
+        assert!(first_update
+            .participant_accepted(&mut channel, 1, current_version_msg)
+            .is_ok());
+
+        let buffered = LedgerChannelUpdateAccepted {
+            channel: first_update.channel_id,
+            version: first_update.new_state.version() + 1,
+            sig: sig_for(1),
+        };
+        assert!(first_update
+            .participant_accepted(&mut channel, 1, buffered)
+            .is_ok());
+
+        first_update.apply(&mut channel).unwrap();
+
+        let rebased = channel.state().make_next_state();
+        let second_update = channel.update(rebased).unwrap();
+        // The acceptance buffered against `first_update`'s successor version
+        // was replayed automatically into `second_update`.
+        assert!(second_update.signatures().is_ok());
+    }
+
+    #[test]
+    fn a_rejected_three_party_update_reports_rejected_instead_of_missing_signature() {
+        let client = PerunClient::new(NullBus, StubSigner(addr(0)), 1);
+        let (channel, mut update) = channel_and_update::<3>(&client);
+        assert!(update
+            .participant_rejected(&channel, "not happy with this update".to_string())
+            .is_ok());
+        assert!(matches!(
+            update.signatures(),
+            Err(ApplyError::Rejected(reason)) if reason == "not happy with this update"
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+//! [ClosingChannel], a resumable replacement for the old fire-and-forget
+//! `force_close`/`handle_dispute`: it owns the final signed state so it can
+//! re-emit its `RegisterReq`/`AdjudicatorReq` on a resend timeout, and tracks
+//! the dispute's on-chain progress via [ClosingChannel::confirm_completion]
+//! instead of dropping the channel the moment one message goes out. This
+//! mirrors the Serai Ethereum integration's move to an explicit
+//! `Eventuality`/`confirm_completion` step instead of fire-and-forget
+//! broadcasts.
+
+use super::{fixed_size_payment, PartIdx};
+use crate::{
+    abiencode::types::{Address, Hash, Signature},
+    messages::{AdjudicatorReq, FunderRequestMessage, RegisterReq, Transaction},
+    sig::EthSigner,
+    wire::MessageBus,
+    PerunClient,
+};
+
+const ASSETS: usize = 1;
+const PARTICIPANTS: usize = 2;
+// One reserved sub-allocation slot, see `super::active::LOCKED`.
+const LOCKED: usize = 1;
+type State = fixed_size_payment::State<fixed_size_payment::NoApp, ASSETS, PARTICIPANTS, LOCKED>;
+type Params = fixed_size_payment::Params<PARTICIPANTS>;
+
+/// Where a [ClosingChannel] is in the on-chain dispute/settlement lifecycle,
+/// advanced by [ClosingChannel::confirm_completion].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClosingPhase {
+    /// A `RegisterReq` was sent to the Funder; the Adjudicator hasn't
+    /// confirmed the registration on-chain yet, so it may need resending (see
+    /// [ClosingChannel::resend]).
+    Registering,
+    /// The Adjudicator has registered our state on-chain; waiting out the
+    /// challenge duration (or a newer competing dispute) before it can be
+    /// concluded.
+    Registered,
+    /// The Adjudicator has concluded the dispute; the outcome is ready to be
+    /// withdrawn.
+    Concluded,
+    /// Funds have been withdrawn - this channel is fully settled.
+    Withdrawn,
+}
+
+/// Notifies a [ClosingChannel] that its on-chain dispute has moved forward,
+/// see [ClosingChannel::confirm_completion].
+#[derive(Debug, Clone, Copy)]
+pub enum AdjudicatorEvent {
+    Registered,
+    Concluded,
+    Withdrawn,
+}
+
+/// [ClosingChannel::confirm_completion] was given an event that doesn't
+/// follow from the channel's current [ClosingPhase].
+#[derive(Debug)]
+pub struct UnexpectedAdjudicatorEvent {
+    pub phase: ClosingPhase,
+    pub event: AdjudicatorEvent,
+}
+
+/// A channel that has entered the on-chain dispute/settlement path via
+/// [ActiveChannel::force_close](super::ActiveChannel::force_close) or
+/// [ActiveChannel::handle_dispute](super::ActiveChannel::handle_dispute). See
+/// the module documentation.
+#[derive(Debug)]
+pub struct ClosingChannel<'cl, B: MessageBus, S: EthSigner> {
+    client: &'cl PerunClient<B, S>,
+    part_idx: PartIdx,
+    withdraw_receiver: Address,
+    state: State,
+    params: Params,
+    signatures: [Signature; PARTICIPANTS],
+    phase: ClosingPhase,
+}
+
+impl<'cl, B: MessageBus, S: EthSigner> ClosingChannel<'cl, B, S> {
+    /// We're the one initiating the close: register our current state and
+    /// start tracking it.
+    pub(super) fn initiate(
+        client: &'cl PerunClient<B, S>,
+        part_idx: PartIdx,
+        withdraw_receiver: Address,
+        state: State,
+        params: Params,
+        signatures: [Signature; PARTICIPANTS],
+    ) -> Self {
+        let channel = Self {
+            client,
+            part_idx,
+            withdraw_receiver,
+            state,
+            params,
+            signatures,
+            phase: ClosingPhase::Registering,
+        };
+        channel.send_register_req(false);
+        channel
+    }
+
+    /// A dispute initiated by a peer was observed (see
+    /// [ActiveChannel::handle_dispute](super::ActiveChannel::handle_dispute)).
+    /// `disputed_version` is the version the Watcher reported as registered.
+    /// If it's older than `state`, we hold a newer, already-signed state the
+    /// peer apparently doesn't (or a stale registration is being replayed) -
+    /// refute by registering ours instead of accepting theirs.
+    pub(super) fn observe(
+        client: &'cl PerunClient<B, S>,
+        part_idx: PartIdx,
+        withdraw_receiver: Address,
+        state: State,
+        params: Params,
+        signatures: [Signature; PARTICIPANTS],
+        disputed_version: u64,
+    ) -> Self {
+        let needs_refutation = disputed_version < state.version();
+        let channel = Self {
+            client,
+            part_idx,
+            withdraw_receiver,
+            state,
+            params,
+            signatures,
+            // Already matches (or is ahead of) what we hold: nothing to
+            // refute, just start tracking the dispute the peer already
+            // registered. Otherwise we're about to refute it below, so stay
+            // in `Registering` until that registration is confirmed.
+            phase: if needs_refutation {
+                ClosingPhase::Registering
+            } else {
+                ClosingPhase::Registered
+            },
+        };
+        if needs_refutation {
+            channel.send_register_req(true);
+        }
+        channel
+    }
+
+    pub fn channel_id(&self) -> Hash {
+        self.state.channel_id()
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn phase(&self) -> ClosingPhase {
+        self.phase
+    }
+
+    fn send_register_req(&self, secondary: bool) {
+        self.client
+            .bus
+            .send_to_funder(FunderRequestMessage::RegisterReq(RegisterReq {
+                adj_req: AdjudicatorReq {
+                    params: self.params,
+                    acc: self.withdraw_receiver,
+                    tx: Transaction {
+                        state: self.state,
+                        sigs: self.signatures,
+                    },
+                    idx: self.part_idx,
+                    secondary,
+                },
+            }));
+    }
+
+    /// Re-emit a `RegisterReq` for the held state, e.g. after a resend
+    /// timeout while still waiting for [AdjudicatorEvent::Registered]. It is
+    /// always sent with `secondary: false`, also after [Self::observe] sent
+    /// its refutation with `secondary: true`.
+    pub fn resend(&self) {
+        self.send_register_req(false);
+    }
+
+    /// Advance this channel's [ClosingPhase] as the Adjudicator/Watcher
+    /// confirm each step of the dispute, in order: `Registered` ->
+    /// `Concluded` -> `Withdrawn`. Re-delivery of the event already reflected
+    /// in the current phase is accepted (idempotent); anything out of order
+    /// is rejected.
+    pub fn confirm_completion(
+        &mut self,
+        event: AdjudicatorEvent,
+    ) -> Result<(), UnexpectedAdjudicatorEvent> {
+        use AdjudicatorEvent::*;
+        use ClosingPhase::*;
+
+        self.phase = match (self.phase, event) {
+            (Registering, Registered) => Registered,
+            (Registered, Registered) => Registered,
+            (Registered, Concluded) => Concluded,
+            (Concluded, Concluded) => Concluded,
+            (Concluded, Withdrawn) => Withdrawn,
+            (Withdrawn, Withdrawn) => Withdrawn,
+            (phase, event) => return Err(UnexpectedAdjudicatorEvent { phase, event }),
+        };
+        Ok(())
+    }
+}
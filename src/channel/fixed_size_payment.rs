@@ -3,23 +3,75 @@
 //!
 //! These types can be useful when the number of Participants and Assets are
 //! known at compile time or we don't have heap allocation.
+//!
+//! The structs below, their ABI `serde` layout and their `perunwire`
+//! conversions are hand-maintained and must stay byte-for-byte aligned with
+//! the Solidity `Channel` struct and with the protobuf messages compiled by
+//! `build.rs`. Generating all of this from one declarative schema (so the
+//! Solidity layout, the ABI encoding and the protobuf mapping can't drift
+//! apart) would need its own schema format plus a non-trivial build-time
+//! generator; that's a larger undertaking than fits alongside the rest of
+//! this crate's build tooling, so for now the three representations
+//! continue to be kept in sync by hand, the way the rest of this module
+//! already does for `Params`/`State`/`Allocation`.
 
-use super::Asset;
+use super::{Asset, PartID};
 use crate::{
     abiencode::{
         self, as_bytes, as_dyn_array,
         types::{Address, Hash, U256},
     },
+    json::{DecU256, HexAddress, HexHash},
     messages::ConversionError,
     perunwire,
 };
-use alloc::vec;
-use serde::Serialize;
+use alloc::{vec, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+/// Identifies which dialect of the `perunwire` encoding a
+/// [Params]/[State]/[Allocation] conversion should speak - e.g. whether
+/// [Allocation]'s assets strip leading zero bytes from `chain_id` (see
+/// [Allocation::try_from_wire]) or sub-allocations are present. Not part of
+/// any ABI-encoded type (those must stay byte-compatible with the on-chain
+/// contract), only of the `perunwire` conversions exchanged during channel
+/// proposal, see [LedgerChannelProposal](crate::messages::LedgerChannelProposal::protocol_version).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProtocolVersion(pub u32);
+
+impl ProtocolVersion {
+    /// The only dialect this build can produce or fully understand; see
+    /// [ConversionError::UnsupportedProtocolVersion] for anything else.
+    pub const CURRENT: ProtocolVersion = ProtocolVersion(0);
+}
+
+/// Which signature scheme a channel's participants settle disputes with:
+/// everyone posting an independent ECDSA signature (today's only fully
+/// wired-up option), or a single [crate::sig::musig]-aggregated Schnorr
+/// signature in its place. Not part of any ABI-encoded type - like
+/// [ProtocolVersion], it's negotiated during channel proposal rather than
+/// baked into [Params], so it can't change the hash every participant signs
+/// or the layout the on-chain contract expects, neither of which has a
+/// variant for an aggregated signature yet.
+///
+/// Only [SigningMode::IndependentEcdsa] is wired through proposal/update/
+/// dispute today; [SigningMode::AggregatedSchnorr] exists so a channel can
+/// already record which mode it asked for ahead of that wiring landing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SigningMode {
+    #[default]
+    IndependentEcdsa,
+    AggregatedSchnorr,
+}
 
 /// Parameters for this channel, exchanged during channel proposal and sent
 /// on-chain during a dispute.
-#[derive(Serialize, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct Params<const P: usize> {
+    /// Chain id of the adjudicator deployment this channel is bound to, see
+    /// [crate::PerunClient::chain_id]. Part of the hash every participant
+    /// signs, so a state cannot be replayed against an
+    /// identically-parameterized channel on another chain.
+    pub chain_id: U256,
     pub challenge_duration: u64,
     pub nonce: U256,
     #[serde(with = "as_dyn_array")]
@@ -30,7 +82,11 @@ pub struct Params<const P: usize> {
 }
 
 impl<const P: usize> Params<P> {
-    fn channel_id(&self) -> Result<Hash, abiencode::Error> {
+    /// Independently re-derive the `channel_id` a [State] created from these
+    /// `Params` must carry, see [State::channel_id]. `pub` so callers outside
+    /// this module (e.g. [crate::sig::validating]) can check a state against
+    /// the params it's supposed to belong to instead of trusting the caller.
+    pub fn channel_id(&self) -> Result<Hash, abiencode::Error> {
         abiencode::to_hash(self)
     }
 }
@@ -45,6 +101,7 @@ impl<const P: usize> TryFrom<perunwire::Params> for Params<P> {
         }
 
         Ok(Self {
+            chain_id: U256::from_big_endian(&value.chain_id),
             challenge_duration: value.challenge_duration,
             nonce: U256::from_big_endian(&value.nonce),
             participants,
@@ -55,6 +112,24 @@ impl<const P: usize> TryFrom<perunwire::Params> for Params<P> {
     }
 }
 
+impl<const P: usize> Params<P> {
+    /// Like `TryFrom<perunwire::Params>`, but rejects anything other than
+    /// [ProtocolVersion::CURRENT] up front instead of silently applying this
+    /// build's (possibly wrong) dialect to it. There is currently only one
+    /// dialect, so this can't yet branch on `protocol_version`, but callers
+    /// negotiate it during channel proposal, see
+    /// [LedgerChannelProposal::protocol_version](crate::messages::LedgerChannelProposal::protocol_version).
+    pub fn try_from_wire(
+        value: perunwire::Params,
+        protocol_version: ProtocolVersion,
+    ) -> Result<Self, ConversionError> {
+        if protocol_version != ProtocolVersion::CURRENT {
+            return Err(ConversionError::UnsupportedProtocolVersion);
+        }
+        value.try_into()
+    }
+}
+
 impl<const P: usize> From<Params<P>> for perunwire::Params {
     fn from(value: Params<P>) -> Self {
         Self {
@@ -63,6 +138,11 @@ impl<const P: usize> From<Params<P>> for perunwire::Params {
                 .expect("should be impossible to get an encoding-error for a Params object")
                 .0
                 .to_vec(),
+            chain_id: {
+                let mut buf = vec![0u8; 32];
+                value.chain_id.to_big_endian(&mut buf);
+                buf
+            },
             challenge_duration: value.challenge_duration,
             nonce: {
                 let mut buf = vec![0u8; 32];
@@ -77,30 +157,214 @@ impl<const P: usize> From<Params<P>> for perunwire::Params {
     }
 }
 
+impl<const P: usize> Params<P> {
+    /// Reconstructs a [Params] from the Solidity `abi.encode` bytes produced
+    /// by this type's [Serialize] impl (e.g. read back from a
+    /// dispute/progression event, or a `channelID` lookup), the reverse of
+    /// [Params::channel_id]'s [abiencode::to_hash]. Errors with
+    /// [ConversionError::ParticipantSizeMissmatch] if the recovered
+    /// `participants` array does not have exactly `P` elements, or if `data`
+    /// is otherwise malformed.
+    pub fn decode(data: &[u8]) -> Result<Self, ConversionError> {
+        abiencode::from_slice(data).map_err(|_| ConversionError::ParticipantSizeMissmatch)
+    }
+}
+
+/// Human-readable JSON mirror of [Params], for debugging/logging/tooling;
+/// see the module-level docs on [crate::json] for why [Hash]/[Address] get
+/// hex strings while other fields keep their natural JSON form. Does not
+/// carry `app`, same as the protobuf conversion above (not a state channel,
+/// so it is always empty).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParamsDto {
+    pub chain_id: DecU256,
+    pub challenge_duration: u64,
+    pub nonce: DecU256,
+    pub participants: Vec<HexAddress>,
+    pub ledger_channel: bool,
+    pub virtual_channel: bool,
+}
+
+impl<const P: usize> From<Params<P>> for ParamsDto {
+    fn from(value: Params<P>) -> Self {
+        Self {
+            chain_id: value.chain_id.into(),
+            challenge_duration: value.challenge_duration,
+            nonce: value.nonce.into(),
+            participants: value.participants.iter().map(|&a| a.into()).collect(),
+            ledger_channel: value.ledger_channel,
+            virtual_channel: value.virtual_channel,
+        }
+    }
+}
+
+impl<const P: usize> TryFrom<ParamsDto> for Params<P> {
+    type Error = ConversionError;
+
+    fn try_from(value: ParamsDto) -> Result<Self, Self::Error> {
+        if value.participants.len() != P {
+            return Err(ConversionError::ParticipantSizeMissmatch);
+        }
+
+        let mut participants = [Address::default(); P];
+        for (a, dto) in participants.iter_mut().zip(value.participants) {
+            *a = dto.into();
+        }
+
+        Ok(Self {
+            chain_id: value.chain_id.into(),
+            challenge_duration: value.challenge_duration,
+            nonce: value.nonce.into(),
+            participants,
+            app: Address([0; 20]),
+            ledger_channel: value.ledger_channel,
+            virtual_channel: value.virtual_channel,
+        })
+    }
+}
+
+/// Per-state application data for an app/state channel, plus the identity of
+/// the on-chain app contract it is valid for (see [Params::app]).
+///
+/// [State] is generic over this so app/state channels can reuse the exact
+/// same ABI encoding/hashing/decoding machinery as plain ledger channels
+/// (see [NoApp], the `App` every ledger channel in this crate currently
+/// uses). `Serialize`/`Copy` are required directly on `App` (rather than via
+/// a `#[serde(with = "as_bytes")]` field attribute, which cannot depend on a
+/// generic parameter) so each concrete `App` is responsible for encoding
+/// itself as the dynamic `appData` bytes the same way [NoApp] does below.
+pub trait AppData: Serialize + Copy {
+    /// Address of the on-chain app contract this data is valid for. Written
+    /// into [Params::app] and the wire `app` field.
+    fn address(&self) -> Address;
+    /// The opaque application data itself, as written into the wire `data`
+    /// field (the ABI-encoded `appData` is produced by this type's own
+    /// [Serialize] impl instead, see the trait docs above).
+    fn bytes(&self) -> Vec<u8>;
+
+    /// Whether `old_outcome`/`new_outcome` (a [State::outcome] before/after
+    /// one [ActiveChannel::update][crate::channel::active::ActiveChannel::update])
+    /// is a valid transition under this app's own rules, checked via
+    /// [State::valid_app_transition] in addition to - not instead of - the
+    /// `App`-independent invariants
+    /// [ActiveChannel::check_valid_transition][crate::channel::active::ActiveChannel::check_valid_transition]
+    /// already enforces (matching channel id, strictly incrementing
+    /// version, unchanged locked set). Generic over `A`/`P`/`L` itself
+    /// rather than on the trait, so a single concrete `App` can still
+    /// validate channels of any shape.
+    fn valid_transition<const A: usize, const P: usize, const L: usize>(
+        &self,
+        old_outcome: &Allocation<A, P, L>,
+        new_outcome: &Allocation<A, P, L>,
+    ) -> bool;
+}
+
+/// [AppData] reproducing the behavior every channel in this crate used
+/// before [AppData] existed: no app contract (the zero address) and no
+/// app-specific data.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NoApp;
+
+impl AppData for NoApp {
+    fn address(&self) -> Address {
+        Address([0; 20])
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// A plain payment channel has no app logic to justify minting or
+    /// burning funds, so the only transition this app allows is one that
+    /// conserves the total allocated amount per asset - the behavior this
+    /// crate already enforced before [AppData] existed.
+    fn valid_transition<const A: usize, const P: usize, const L: usize>(
+        &self,
+        old_outcome: &Allocation<A, P, L>,
+        new_outcome: &Allocation<A, P, L>,
+    ) -> bool {
+        old_outcome.total_assets() == new_outcome.total_assets()
+    }
+}
+
+// Implemented manually (instead of deriving, then attaching
+// `#[serde(with = "as_bytes")]`, which only works on fields, not whole
+// types) so that encoding a [State<NoApp, _, _>] produces byte-for-byte the
+// same `appData` this crate emitted back when it was a hard-coded `[u8; 0]`
+// field.
+impl Serialize for NoApp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        as_bytes::serialize(&[], serializer)
+    }
+}
 
+impl<'de> Deserialize<'de> for NoApp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data: Vec<u8> = as_bytes::deserialize(deserializer)?;
+        if !data.is_empty() {
+            return Err(serde::de::Error::invalid_length(data.len(), &"0"));
+        }
+        Ok(NoApp)
+    }
+}
 
 /// Stores the complete state of a channel.
-#[derive(Serialize, Debug, Copy, Clone)]
-pub struct State<const A: usize, const P: usize> {
+///
+/// `L` is the number of sub-allocations currently locked into
+/// other (sub/virtual) channels, same as [Allocation]'s own `L` - defaults to
+/// `0` (no sub-channels), like the rest of this crate still assumes for now.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct State<App: AppData, const A: usize, const P: usize, const L: usize = 0> {
     id: Hash,
     version: u64,
-    pub outcome: Allocation<A, P>,
-    #[serde(with = "as_bytes")]
-    app_data: [u8; 0],
+    pub outcome: Allocation<A, P, L>,
+    app_data: App,
     pub is_final: bool,
 }
 
-impl<const A: usize, const P: usize> State<A, P> {
+impl<App: AppData, const A: usize, const P: usize, const L: usize> State<App, A, P, L> {
     pub fn version(&self) -> u64 {
         self.version
     }
     pub fn channel_id(&self) -> Hash {
         self.id
     }
+
+    /// Forwards to [Allocation::transfer] on this state's [Self::outcome] -
+    /// e.g. to adjust a state returned by [Self::make_next_state] before
+    /// proposing it, instead of indexing `outcome.balances.0[asset].0[idx]`
+    /// directly.
+    pub fn transfer(
+        &mut self,
+        asset: usize,
+        from: PartID,
+        to: PartID,
+        amount: U256,
+    ) -> Result<(), InsufficientBalance> {
+        self.outcome.transfer(asset, from, to, amount)
+    }
+
+    /// Whether transitioning from this state to `new` is valid under
+    /// [AppData::valid_transition] for this channel's `App` - e.g. [NoApp]
+    /// requires `new`'s total allocated amount per asset to exactly match
+    /// this state's.
+    pub fn valid_app_transition(&self, new: &Self) -> bool {
+        self.app_data.valid_transition(&self.outcome, &new.outcome)
+    }
 }
 
-impl<const A: usize, const P: usize> State<A, P> {
-    pub fn new(params: Params<P>, init_bals: Allocation<A, P>) -> Result<Self, abiencode::Error> {
+impl<App: AppData, const A: usize, const P: usize, const L: usize> State<App, A, P, L> {
+    pub fn new(
+        params: Params<P>,
+        init_bals: Allocation<A, P, L>,
+        app_data: App,
+    ) -> Result<Self, abiencode::Error> {
         init_bals.debug_assert_valid();
         // Length equivalence to the other balances is checked in
         // debug_assert_valid (and the following is also impossible to represent
@@ -112,12 +376,17 @@ impl<const A: usize, const P: usize> State<A, P> {
             init_bals.balances.0[0].0.len(),
             "number of participants in parameters and initial balances don't match"
         );
+        debug_assert_eq!(
+            params.app,
+            app_data.address(),
+            "Params::app must match the app contract address of the initial app data"
+        );
 
         Ok(State {
             id: params.channel_id()?,
             version: 0,
             outcome: init_bals,
-            app_data: [],
+            app_data,
             is_final: false,
         })
     }
@@ -140,7 +409,13 @@ impl<const A: usize, const P: usize> State<A, P> {
     }
 }
 
-impl<const A: usize, const P: usize> TryFrom<perunwire::State> for State<A, P> {
+// Only implemented for `NoApp`: the wire protocol doesn't carry an app
+// contract address/data of its own yet (see `perunwire::State::app`/`data`
+// below, which `NoApp` always encodes as the zero address/empty bytes), so
+// there is nothing to reconstruct a different `App` from.
+impl<const A: usize, const P: usize, const L: usize> TryFrom<perunwire::State>
+    for State<NoApp, A, P, L>
+{
     type Error = ConversionError;
 
     fn try_from(value: perunwire::State) -> Result<Self, Self::Error> {
@@ -160,29 +435,99 @@ impl<const A: usize, const P: usize> TryFrom<perunwire::State> for State<A, P> {
                 .allocation
                 .ok_or(ConversionError::ExptectedSome)?
                 .try_into()?,
-            app_data: [],
+            app_data: NoApp,
             is_final: value.is_final,
         })
     }
 }
 
-impl<const A: usize, const P: usize> From<State<A, P>> for perunwire::State {
-    fn from(value: State<A, P>) -> Self {
+impl<const A: usize, const P: usize, const L: usize> State<NoApp, A, P, L> {
+    /// Like `TryFrom<perunwire::State>`, but rejects anything other than
+    /// [ProtocolVersion::CURRENT] up front; see [Params::try_from_wire].
+    pub fn try_from_wire(
+        value: perunwire::State,
+        protocol_version: ProtocolVersion,
+    ) -> Result<Self, ConversionError> {
+        if protocol_version != ProtocolVersion::CURRENT {
+            return Err(ConversionError::UnsupportedProtocolVersion);
+        }
+        value.try_into()
+    }
+}
+
+impl<App: AppData, const A: usize, const P: usize, const L: usize> From<State<App, A, P, L>>
+    for perunwire::State
+{
+    fn from(value: State<App, A, P, L>) -> Self {
         Self {
             id: value.id.0.to_vec(),
             version: value.version,
             allocation: Some(value.outcome.into()),
-            app: vec![], // Only different if it is a state channel, which we don't support, yet
-            data: vec![],
+            app: value.app_data.address().0.to_vec(),
+            data: value.app_data.bytes(),
+            is_final: value.is_final,
+        }
+    }
+}
+
+impl<App: AppData, const A: usize, const P: usize, const L: usize> State<App, A, P, L> {
+    /// Reconstructs a [State] from the Solidity `abi.encode` bytes produced
+    /// by this type's [Serialize] impl (e.g. read back from a
+    /// dispute/progression event), the reverse of `abiencode::to_writer`
+    /// applied to a [State]. Malformed input - including a nested
+    /// [Allocation] whose asset/participant counts don't match `A`/`P` - is
+    /// reported as [ConversionError::ByteLengthMissmatch], the same variant
+    /// this type's `TryFrom<perunwire::State>` uses for its own malformed
+    /// fields.
+    pub fn decode(data: &[u8]) -> Result<Self, ConversionError>
+    where
+        App: for<'de> Deserialize<'de>,
+    {
+        abiencode::from_slice(data).map_err(|_| ConversionError::ByteLengthMissmatch)
+    }
+}
+
+/// Human-readable JSON mirror of [State]; see [ParamsDto]. Like the
+/// protobuf conversion above, only defined for [NoApp] - there is no app
+/// data to mirror yet. Like [AllocationDto], its `outcome` is only defined
+/// for `L == 0` - these conversions only exist for that case.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StateDto {
+    pub id: HexHash,
+    pub version: u64,
+    pub outcome: AllocationDto,
+    pub is_final: bool,
+}
+
+impl<const A: usize, const P: usize> From<State<NoApp, A, P>> for StateDto {
+    fn from(value: State<NoApp, A, P>) -> Self {
+        Self {
+            id: value.id.into(),
+            version: value.version,
+            outcome: value.outcome.into(),
             is_final: value.is_final,
         }
     }
 }
 
+impl<const A: usize, const P: usize> TryFrom<StateDto> for State<NoApp, A, P> {
+    type Error = ConversionError;
+
+    fn try_from(value: StateDto) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.id.into(),
+            version: value.version,
+            outcome: value.outcome.try_into()?,
+            app_data: NoApp,
+            is_final: value.is_final,
+        })
+    }
+}
+
 /// Separate type for storing just the allocated balance, not the assets.
 ///
 /// This type is used in the channel proposals to specify the funding agreement.
-#[derive(Serialize, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 #[serde(transparent)]
 pub struct Balances<const A: usize, const P: usize>(
     #[serde(with = "as_dyn_array")] pub [ParticipantBalances<P>; A],
@@ -194,6 +539,31 @@ impl<const A: usize, const P: usize> Default for Balances<A, P> {
     }
 }
 
+/// Returned by [Balances::transfer]/[Allocation::transfer]/[State::transfer]:
+/// `from` doesn't have enough of the asset left to cover the transferred
+/// amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InsufficientBalance;
+
+impl<const A: usize, const P: usize> Balances<A, P> {
+    /// Moves `amount` of `asset`'s balance from participant `from` to
+    /// participant `to`, so applications don't have to index
+    /// `balances.0[asset].0[from]` directly. Errors with
+    /// [InsufficientBalance] instead of underflowing `from`'s balance.
+    pub fn transfer(
+        &mut self,
+        asset: usize,
+        from: PartID,
+        to: PartID,
+        amount: U256,
+    ) -> Result<(), InsufficientBalance> {
+        let bals = &mut self.0[asset].0;
+        bals[from] = bals[from].checked_sub(amount).ok_or(InsufficientBalance)?;
+        bals[to] += amount;
+        Ok(())
+    }
+}
+
 impl<const A: usize, const P: usize> TryFrom<perunwire::Balances> for Balances<A, P> {
     type Error = ConversionError;
 
@@ -219,18 +589,161 @@ impl<const A: usize, const P: usize> From<Balances<A, P>> for perunwire::Balance
     }
 }
 
-/// Stores which participant has how much of each asset.
-#[derive(Serialize, Debug, Copy, Clone)]
-pub struct Allocation<const A: usize, const P: usize> {
+/// Human-readable JSON mirror of [Balances]; see [ParamsDto].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct BalancesDto(pub Vec<Vec<DecU256>>);
+
+impl<const A: usize, const P: usize> From<Balances<A, P>> for BalancesDto {
+    fn from(value: Balances<A, P>) -> Self {
+        Self(
+            value
+                .0
+                .iter()
+                .map(|bals| bals.0.iter().map(|&amount| amount.into()).collect())
+                .collect(),
+        )
+    }
+}
+
+impl<const A: usize, const P: usize> TryFrom<BalancesDto> for Balances<A, P> {
+    type Error = ConversionError;
+
+    fn try_from(value: BalancesDto) -> Result<Self, Self::Error> {
+        if value.0.len() != A {
+            return Err(ConversionError::AssetSizeMissmatch);
+        }
+
+        let mut balances = Self::default();
+        for (a, dto) in balances.0.iter_mut().zip(value.0) {
+            if dto.len() != P {
+                return Err(ConversionError::ParticipantSizeMissmatch);
+            }
+            for (amount, dto_amount) in a.0.iter_mut().zip(dto) {
+                *amount = dto_amount.into();
+            }
+        }
+
+        Ok(balances)
+    }
+}
+
+/// One sub-allocation within an [Allocation] (go-perun's
+/// `Allocation.Locked`): funds currently locked into another (sub/virtual)
+/// channel, identified by that channel's id.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct SubAlloc<const A: usize> {
+    pub id: Hash,
+    #[serde(with = "as_dyn_array")]
+    pub bals: [U256; A],
+    pub index_map: IndexMap,
+}
+
+impl<const A: usize> Default for SubAlloc<A> {
+    fn default() -> Self {
+        Self {
+            id: Hash::default(),
+            bals: [U256::default(); A],
+            index_map: IndexMap::default(),
+        }
+    }
+}
+
+impl<const A: usize> TryFrom<perunwire::SubAlloc> for SubAlloc<A> {
+    type Error = ConversionError;
+
+    fn try_from(value: perunwire::SubAlloc) -> Result<Self, Self::Error> {
+        if value.bals.len() != A {
+            return Err(ConversionError::AssetSizeMissmatch);
+        }
+
+        let mut bals = [U256::default(); A];
+        for (a, b) in bals.iter_mut().zip(value.bals) {
+            *a = U256::from_big_endian(&b);
+        }
+
+        Ok(Self {
+            id: Hash(
+                value
+                    .id
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            bals,
+            index_map: IndexMap(if value.index_map.is_empty() {
+                None
+            } else {
+                Some(value.index_map.iter().map(|&i| i as u16).collect())
+            }),
+        })
+    }
+}
+
+impl<const A: usize> From<SubAlloc<A>> for perunwire::SubAlloc {
+    fn from(value: SubAlloc<A>) -> Self {
+        Self {
+            id: value.id.0.to_vec(),
+            bals: value
+                .bals
+                .map(|amount| {
+                    let mut buf = vec![0u8; 32];
+                    amount.to_big_endian(&mut buf);
+                    buf
+                })
+                .to_vec(),
+            index_map: value
+                .index_map
+                .0
+                .unwrap_or_default()
+                .iter()
+                .map(|&i| i as u32)
+                .collect(),
+        }
+    }
+}
+
+/// Remaps a locked-into (sub/virtual) channel's participant indices onto
+/// this [Allocation]'s own participant indices (go-perun's
+/// `Allocation.Locked[].IndexMap`). `None` means no remapping is needed - the
+/// locked-into channel's participants are this channel's participants, in
+/// the same order - and is encoded on the wire as an empty array, the same
+/// convention go-perun itself uses.
+#[derive(Debug, Clone, Default)]
+pub struct IndexMap(pub Option<Vec<u16>>);
+
+impl Serialize for IndexMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.as_deref().unwrap_or(&[]).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for IndexMap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = Vec::<u16>::deserialize(deserializer)?;
+        Ok(IndexMap(if data.is_empty() { None } else { Some(data) }))
+    }
+}
+
+/// Stores which participant has how much of each asset, plus any funds
+/// currently locked into other (sub/virtual) channels.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct Allocation<const A: usize, const P: usize, const L: usize = 0> {
     #[serde(with = "as_dyn_array")]
     pub assets: [Asset; A],
     pub balances: Balances<A, P>,
     #[serde(with = "as_dyn_array")]
-    pub(crate) locked: [(); 0], // Only needed for encoding
+    pub locked: [SubAlloc<A>; L],
 }
 
-impl<const A: usize, const P: usize> Allocation<A, P> {
-    /// Returns the sum amount in this allocation for all assets.
+impl<const A: usize, const P: usize, const L: usize> Allocation<A, P, L> {
+    /// Returns the sum amount in this allocation for all assets, including
+    /// funds locked into sub/virtual channels.
     pub fn total_assets(&self) -> [U256; A] {
         let mut totals = [0.into(); A];
         for (total, bals) in totals.iter_mut().zip(self.balances.0) {
@@ -238,9 +751,25 @@ impl<const A: usize, const P: usize> Allocation<A, P> {
                 *total += amt;
             }
         }
+        for sub_alloc in self.locked {
+            for (total, amt) in totals.iter_mut().zip(sub_alloc.bals) {
+                *total += amt;
+            }
+        }
         totals
     }
 
+    /// Forwards to [Balances::transfer] on this allocation's `balances`.
+    pub fn transfer(
+        &mut self,
+        asset: usize,
+        from: PartID,
+        to: PartID,
+        amount: U256,
+    ) -> Result<(), InsufficientBalance> {
+        self.balances.transfer(asset, from, to, amount)
+    }
+
     pub(crate) fn debug_assert_valid(&self) {
         // Go-perun checks if the new state is valid (see `Allocation.Valid` in
         // go-perun). This includes checking the asset slice lengths (which are
@@ -284,11 +813,15 @@ impl<const A: usize, const P: usize> Allocation<A, P> {
             // is unlikely to change here any time soon (we're using uint) and
             // currently impossible to represent.
         }
-        debug_assert!(self.locked.is_empty(), "Not a go-perun requirement, but the asserts above don't include anything about the content of locked, while go-perun does");
+        // Go-perun additionally validates that each `Locked[].Bals` has
+        // exactly as many entries as there are assets, which is enforced
+        // here at the type level instead ([SubAlloc::bals] is `[U256; A]`).
     }
 }
 
-impl<const A: usize, const P: usize> TryFrom<perunwire::Allocation> for Allocation<A, P> {
+impl<const A: usize, const P: usize, const L: usize> TryFrom<perunwire::Allocation>
+    for Allocation<A, P, L>
+{
     type Error = ConversionError;
 
     fn try_from(value: perunwire::Allocation) -> Result<Self, Self::Error> {
@@ -334,48 +867,177 @@ impl<const A: usize, const P: usize> TryFrom<perunwire::Allocation> for Allocati
             *a = Asset { chain_id, holder }
         }
 
+        if value.locked.len() != L {
+            return Err(ConversionError::SubAllocSizeMissmatch);
+        }
+        let mut locked = [SubAlloc::default(); L];
+        for (a, b) in locked.iter_mut().zip(value.locked) {
+            *a = b.try_into()?;
+        }
+
         Ok(Self {
             assets,
             balances: value
                 .balances
                 .ok_or(ConversionError::ExptectedSome)?
                 .try_into()?,
-            locked: [],
+            locked,
         })
     }
 }
 
-impl<const A: usize, const P: usize> From<Allocation<A, P>> for perunwire::Allocation {
-    fn from(value: Allocation<A, P>) -> Self {
+impl<const A: usize, const P: usize, const L: usize> Allocation<A, P, L> {
+    /// Like `TryFrom<perunwire::Allocation>`, but rejects anything other
+    /// than [ProtocolVersion::CURRENT] up front; see [Params::try_from_wire].
+    pub fn try_from_wire(
+        value: perunwire::Allocation,
+        protocol_version: ProtocolVersion,
+    ) -> Result<Self, ConversionError> {
+        if protocol_version != ProtocolVersion::CURRENT {
+            return Err(ConversionError::UnsupportedProtocolVersion);
+        }
+        value.try_into()
+    }
+
+    /// Like `Into<perunwire::Allocation>`, but for a `protocol_version`
+    /// other than [ProtocolVersion::CURRENT] this build doesn't know how to
+    /// produce - e.g. an older dialect that doesn't strip leading zero
+    /// bytes from `chain_id` (see [Asset]'s minimal-byte encoding) or
+    /// doesn't support sub-allocations at all.
+    pub fn into_wire(
+        self,
+        protocol_version: ProtocolVersion,
+    ) -> Result<perunwire::Allocation, ConversionError> {
+        if protocol_version != ProtocolVersion::CURRENT {
+            return Err(ConversionError::UnsupportedProtocolVersion);
+        }
+        Ok(self.into())
+    }
+}
+
+impl<const A: usize, const P: usize, const L: usize> From<Allocation<A, P, L>>
+    for perunwire::Allocation
+{
+    fn from(value: Allocation<A, P, L>) -> Self {
         perunwire::Allocation {
             assets: value
                 .assets
                 .map(|a| {
                     let mut b = vec![];
 
-                    // go-perun uses less bytes, as it strips away some leading
-                    // zeroes, which this implementation does not (for
-                    // simplicity). However this should still be understandable
-                    // by go-perun.
-                    b.extend_from_slice(&32u16.to_le_bytes());
+                    // go-perun currently uses `encoding/binary` in go and
+                    // manually adds the length of each field, stripping
+                    // leading zero bytes from `chain_id` first (so
+                    // `chain_id == 0` is written as a zero-length field). We
+                    // have to match that exactly for wire-compatibility with
+                    // heterogeneous go-perun deployments.
                     let mut buf = [0u8; 32];
                     a.chain_id.to_big_endian(&mut buf);
-                    b.extend_from_slice(&buf);
+                    let chain_id = match buf.iter().position(|&byte| byte != 0) {
+                        Some(first_nonzero) => &buf[first_nonzero..],
+                        None => &buf[32..],
+                    };
+                    b.extend_from_slice(&(chain_id.len() as u16).to_le_bytes());
+                    b.extend_from_slice(chain_id);
 
-                    // go-perun currently uses `encoding/binary` in go and
-                    // manually adds the length of each field.
-                    b.extend_from_slice(&20u16.to_le_bytes()); // Length of asset holder (address)
-                    b.extend_from_slice(&a.holder.0);
+                    // Addresses are never stripped, only entirely zero vs.
+                    // not: the zero address is written with a zero-length
+                    // field, same as go-perun.
+                    let holder: &[u8] = if a.holder == Address::default() {
+                        &[]
+                    } else {
+                        &a.holder.0
+                    };
+                    b.extend_from_slice(&(holder.len() as u16).to_le_bytes());
+                    b.extend_from_slice(holder);
 
                     b
                 })
                 .to_vec(),
             balances: Some(value.balances.into()),
-            locked: vec![],
+            locked: value.locked.map(|s| s.into()).to_vec(),
+        }
+    }
+}
+
+impl<const A: usize, const P: usize, const L: usize> Allocation<A, P, L> {
+    /// Reconstructs an [Allocation] from the Solidity `abi.encode` bytes
+    /// produced by this type's [Serialize] impl, the reverse of
+    /// `abiencode::to_writer` applied to an [Allocation]. Errors with
+    /// [ConversionError::AssetSizeMissmatch] if the recovered `assets` array
+    /// does not have exactly `A` elements (this also covers a participant
+    /// count mismatch in the nested [Balances], since both are needed to
+    /// reconstruct a valid [Allocation]), or if `data` is otherwise
+    /// malformed.
+    pub fn decode(data: &[u8]) -> Result<Self, ConversionError> {
+        abiencode::from_slice(data).map_err(|_| ConversionError::AssetSizeMissmatch)
+    }
+}
+
+/// Human-readable JSON mirror of [Asset].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssetDto {
+    pub chain_id: DecU256,
+    pub holder: HexAddress,
+}
+
+impl From<Asset> for AssetDto {
+    fn from(value: Asset) -> Self {
+        Self {
+            chain_id: value.chain_id.into(),
+            holder: value.holder.into(),
+        }
+    }
+}
+
+impl From<AssetDto> for Asset {
+    fn from(value: AssetDto) -> Self {
+        Self {
+            chain_id: value.chain_id.into(),
+            holder: value.holder.into(),
+        }
+    }
+}
+
+/// Human-readable JSON mirror of [Allocation]; see [ParamsDto]. Does not
+/// carry `locked` - only defined for `L == 0`, the same as every allocation
+/// used throughout the rest of this crate so far.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AllocationDto {
+    pub assets: Vec<AssetDto>,
+    pub balances: BalancesDto,
+}
+
+impl<const A: usize, const P: usize> From<Allocation<A, P>> for AllocationDto {
+    fn from(value: Allocation<A, P>) -> Self {
+        Self {
+            assets: value.assets.iter().map(|&a| a.into()).collect(),
+            balances: value.balances.into(),
         }
     }
 }
 
+impl<const A: usize, const P: usize> TryFrom<AllocationDto> for Allocation<A, P> {
+    type Error = ConversionError;
+
+    fn try_from(value: AllocationDto) -> Result<Self, Self::Error> {
+        if value.assets.len() != A {
+            return Err(ConversionError::AssetSizeMissmatch);
+        }
+
+        let mut assets = [Asset::default(); A];
+        for (a, dto) in assets.iter_mut().zip(value.assets) {
+            *a = dto.into();
+        }
+
+        Ok(Self {
+            assets,
+            balances: value.balances.try_into()?,
+            locked: [],
+        })
+    }
+}
+
 impl<const A: usize, const P: usize> Allocation<A, P> {
     pub fn new(assets: [Asset; A], balances: Balances<A, P>) -> Self {
         Self {
@@ -392,7 +1054,7 @@ impl<const A: usize, const P: usize> Allocation<A, P> {
 /// serialization method if the item type of the outer array does not have its
 /// own type. It should be possible to do it by wrapping each item into a new
 /// type before calling `serialize_element`.
-#[derive(Serialize, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 #[serde(transparent)]
 pub struct ParticipantBalances<const P: usize>(#[serde(with = "as_dyn_array")] pub [U256; P]);
 
@@ -463,7 +1125,7 @@ mod tests {
     ```
     */
 
-    fn build_test_state() -> State<1, 2> {
+    fn build_test_state() -> State<NoApp, 1, 2> {
         // Random address from etherscan, do not use!
         let addr = "5B38Da6a701c568545dCfcB03FcB875f56beddC4";
         let addr = Address(<[u8; 20]>::from_hex(addr).unwrap());
@@ -479,7 +1141,7 @@ mod tests {
                 balances: Balances([ParticipantBalances([0x5555.into(), 0x6666.into()])]),
                 locked: [],
             },
-            app_data: [],
+            app_data: NoApp,
             is_final: true,
         }
     }
@@ -544,6 +1206,100 @@ mod tests {
         assert_eq!(hash, expected);
     }
 
+    #[test]
+    fn state_1a2p_decode_roundtrip() {
+        let state = build_test_state();
+        let encoded = abiencode::to_vec(&state).unwrap();
+
+        let decoded = State::<NoApp, 1, 2>::decode(&encoded).unwrap();
+
+        // `State` doesn't implement `PartialEq`, so compare field-by-field
+        // (including re-encoding `outcome`, which does not expose its
+        // `assets`/`balances` any other way here) instead.
+        assert_eq!(decoded.channel_id(), state.channel_id());
+        assert_eq!(decoded.version(), state.version());
+        assert_eq!(decoded.is_final, state.is_final);
+        assert_eq!(
+            abiencode::to_vec(&decoded.outcome).unwrap(),
+            abiencode::to_vec(&state.outcome).unwrap()
+        );
+
+        // Round-tripping through decode+encode must reproduce the exact same
+        // bytes.
+        assert_eq!(abiencode::to_vec(&decoded).unwrap(), encoded);
+    }
+
+    #[test]
+    fn params_decode_rejects_wrong_participant_count() {
+        let addr = Address::default();
+        let params = Params::<3> {
+            chain_id: 1.into(),
+            challenge_duration: 60,
+            nonce: 0.into(),
+            participants: [addr; 3],
+            app: Address([0u8; 20]),
+            ledger_channel: true,
+            virtual_channel: false,
+        };
+        let encoded = abiencode::to_vec(&params).unwrap();
+
+        assert!(matches!(
+            Params::<2>::decode(&encoded),
+            Err(ConversionError::ParticipantSizeMissmatch)
+        ));
+    }
+
+    #[test]
+    fn asset_wire_roundtrip_strips_leading_zero_chain_id_bytes() {
+        // Chain ids spanning every interesting byte-length: zero itself (0
+        // bytes on the wire), a single byte, a value that only fills half the
+        // word, and a full 32-byte value with the top bit set.
+        let chain_ids: [U256; 4] = [
+            0.into(),
+            0xab.into(),
+            0x1122_3344_5566_7788u64.into(),
+            U256::from_big_endian(&[0xff; 32]),
+        ];
+
+        for &chain_id in &chain_ids {
+            for holder in [Address::default(), Address([0x42; 20])] {
+                let alloc = Allocation::<1, 2, 0> {
+                    assets: [Asset { chain_id, holder }],
+                    balances: Balances([ParticipantBalances([0x1.into(), 0x2.into()])]),
+                    locked: [],
+                };
+
+                let wire: perunwire::Allocation = alloc.into();
+                let decoded: Allocation<1, 2, 0> = wire.try_into().unwrap();
+
+                assert_eq!(decoded.assets[0].chain_id, chain_id);
+                assert_eq!(decoded.assets[0].holder, holder);
+            }
+        }
+    }
+
+    #[test]
+    fn allocation_decode_rejects_wrong_locked_count() {
+        let alloc = Allocation::<1, 2, 1> {
+            assets: [Asset {
+                chain_id: 0x3333.into(),
+                holder: Address::default(),
+            }],
+            balances: Balances([ParticipantBalances([0x5555.into(), 0x6666.into()])]),
+            locked: [SubAlloc {
+                id: Hash::default(),
+                bals: [0x1111.into()],
+                index_map: IndexMap::default(),
+            }],
+        };
+        let encoded = abiencode::to_vec(&alloc).unwrap();
+
+        assert!(matches!(
+            Allocation::<1, 2, 0>::decode(&encoded),
+            Err(ConversionError::AssetSizeMissmatch)
+        ));
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn state_1a2p_sign() {
@@ -567,7 +1323,7 @@ mod tests {
 
         // Do not use that on any real device, this is just for testing.
         let mut rng = StdRng::seed_from_u64(0);
-        let signer = Signer::new(&mut rng);
+        let signer = Signer::random(&mut rng);
 
         let sig = signer.sign_eth(hash);
 
@@ -0,0 +1,175 @@
+//! Trust-minimized funding verification.
+//!
+//! [super::AgreedUponChannel::build] hands the channel off to the Funder
+//! service, and [super::SignedChannel::mark_funded] now refuses to move on to
+//! [super::ActiveChannel] until a matching
+//! [crate::messages::FunderReplyMessage::Funded] has actually been observed
+//! (see [super::SignedChannel::on_funder_response]) - but that reply is still
+//! trusted blindly once it arrives. [verify_deposits] closes that remaining
+//! gap: following the approach the Serai Ethereum integration uses for its
+//! `InInstructions`, a deposit event on the AssetHolder/adjudicator contract
+//! isn't trusted on its own - it only counts once a same-block ERC20
+//! `Transfer` into that contract confirms the same sender actually moved the
+//! same amount.
+
+use super::{fixed_size_payment::Balances, PartIdx};
+use crate::abiencode::types::{Address, U256};
+
+const ASSETS: usize = 1;
+
+/// A `Deposited` log the Funder read off the AssetHolder contract for one
+/// participant.
+#[derive(Debug, Clone, Copy)]
+pub struct DepositEvent {
+    pub part_idx: PartIdx,
+    pub depositor: Address,
+    pub amount: U256,
+}
+
+/// An ERC20 `Transfer` log read from the same block as a [DepositEvent],
+/// used to confirm the deposit actually moved tokens into the contract
+/// instead of only emitting the event.
+#[derive(Debug, Clone, Copy)]
+pub struct Erc20Transfer {
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+}
+
+/// Per-participant result of [verify_deposits].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FundingStatus {
+    /// No [DepositEvent] for this participant, or none backed by a matching
+    /// [Erc20Transfer].
+    NotFunded,
+    /// A confirmed deposit exists, but for less than the agreed amount.
+    PartiallyFunded,
+    /// A confirmed deposit covers at least the agreed amount.
+    FullyFunded,
+}
+
+/// Cross-check `deposits` (all read for the same channel) against
+/// `transfers` (all read from the same block as `deposits`) and
+/// `funding_agreement`, returning a [FundingStatus] per participant. Callers
+/// use this to decide whether to progress to [super::ActiveChannel], dispute,
+/// or abort instead of trusting [crate::messages::FunderReplyMessage::Funded]
+/// on its own.
+pub fn verify_deposits<const P: usize>(
+    funding_agreement: &Balances<ASSETS, P>,
+    asset_holder: Address,
+    deposits: &[DepositEvent],
+    transfers: &[Erc20Transfer],
+) -> [FundingStatus; P] {
+    let agreed = &funding_agreement.0[0];
+    let mut status = [FundingStatus::NotFunded; P];
+
+    for (part_idx, status) in status.iter_mut().enumerate() {
+        // `checked_add` mirrors the overflow check `check_valid_proposal`
+        // already applies to agreed balances (see
+        // [crate::client::InvalidProposal::BalanceOverflow]); several
+        // confirmed deposits summing past a 256-bit word can only mean the
+        // participant is over-funded, so that's treated as the max amount
+        // rather than propagating an error here.
+        let confirmed_amount = deposits
+            .iter()
+            .filter(|d| d.part_idx == part_idx)
+            .filter(|d| {
+                transfers
+                    .iter()
+                    .any(|t| t.from == d.depositor && t.to == asset_holder && t.amount == d.amount)
+            })
+            .map(|d| d.amount)
+            .fold(U256::zero(), |acc, amount| {
+                acc.checked_add(amount).unwrap_or(U256::max_value())
+            });
+
+        *status = if confirmed_amount >= agreed.0[part_idx] {
+            // Also covers a participant who agreed to deposit nothing.
+            FundingStatus::FullyFunded
+        } else if confirmed_amount == U256::zero() {
+            FundingStatus::NotFunded
+        } else {
+            FundingStatus::PartiallyFunded
+        };
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::fixed_size_payment::ParticipantBalances;
+
+    const HOLDER: Address = Address([0x42; 20]);
+    const ALICE: Address = Address([0xa1; 20]);
+    const BOB: Address = Address([0xb0; 20]);
+
+    fn agreement(alice: u64, bob: u64) -> Balances<ASSETS, 2> {
+        Balances([ParticipantBalances([alice.into(), bob.into()])])
+    }
+
+    #[test]
+    fn deposit_without_matching_transfer_is_not_funded() {
+        let deposits = [DepositEvent {
+            part_idx: 0,
+            depositor: ALICE,
+            amount: 100.into(),
+        }];
+        let status = verify_deposits(&agreement(100, 100), HOLDER, &deposits, &[]);
+        assert_eq!(status, [FundingStatus::NotFunded, FundingStatus::NotFunded]);
+    }
+
+    #[test]
+    fn confirmed_partial_deposit_is_partially_funded() {
+        let deposits = [DepositEvent {
+            part_idx: 0,
+            depositor: ALICE,
+            amount: 40.into(),
+        }];
+        let transfers = [Erc20Transfer {
+            from: ALICE,
+            to: HOLDER,
+            amount: 40.into(),
+        }];
+        let status = verify_deposits(&agreement(100, 100), HOLDER, &deposits, &transfers);
+        assert_eq!(status[0], FundingStatus::PartiallyFunded);
+        assert_eq!(status[1], FundingStatus::NotFunded);
+    }
+
+    #[test]
+    fn confirmed_full_deposit_from_both_participants_is_fully_funded() {
+        let deposits = [
+            DepositEvent {
+                part_idx: 0,
+                depositor: ALICE,
+                amount: 100.into(),
+            },
+            DepositEvent {
+                part_idx: 1,
+                depositor: BOB,
+                amount: 100.into(),
+            },
+        ];
+        let transfers = [
+            Erc20Transfer {
+                from: ALICE,
+                to: HOLDER,
+                amount: 100.into(),
+            },
+            Erc20Transfer {
+                from: BOB,
+                to: HOLDER,
+                amount: 100.into(),
+            },
+        ];
+        let status = verify_deposits(&agreement(100, 100), HOLDER, &deposits, &transfers);
+        assert_eq!(status, [FundingStatus::FullyFunded; 2]);
+    }
+
+    #[test]
+    fn participant_owing_nothing_is_fully_funded_without_a_deposit() {
+        let status = verify_deposits(&agreement(100, 0), HOLDER, &[], &[]);
+        assert_eq!(status[1], FundingStatus::FullyFunded);
+    }
+}
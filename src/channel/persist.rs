@@ -0,0 +1,173 @@
+//! A `serde`-serializable checkpoint of an [ActiveChannel], so an
+//! application can recover it after a restart or crash instead of losing
+//! all context the way `examples/lowlevel_basic_channel.rs` notes the
+//! watcher-acknowledgement counter does ("currently not stored in the
+//! channel object") and [ActiveChannel::handle_dispute]/
+//! [ActiveChannel::force_close] do by consuming the channel they're called
+//! on.
+//!
+//! [PersistedChannel::capture] turns a live [ActiveChannel] into a
+//! [PersistedChannel] an application checkpoints to disk after every state
+//! transition (the same moments [ActiveChannel::snapshot]'s own docs already
+//! call for); [PerunClient::restore][crate::PerunClient::restore] turns it
+//! back into a live [ActiveChannel], re-[register_channel][crate::PerunClient::register_channel]ing
+//! its [ChannelId][super::ChannelId] the same way [ProposedChannel::build][super::ProposedChannel::build]
+//! does for a freshly negotiated one.
+//!
+//! Only [ActiveChannel] - the funded phase the notes above are actually
+//! about - is covered. A crash during proposing/signing has nothing on
+//! chain yet to register a dispute against, and the peers lose their own
+//! in-memory state at the same time, so restarting that negotiation from
+//! scratch is simpler than resuming it; add [ProposedChannel][super::ProposedChannel]/
+//! [AgreedUponChannel][super::AgreedUponChannel]/[SignedChannel][super::SignedChannel]
+//! snapshots here if a caller needs to survive a crash during those phases
+//! too.
+//!
+//! [ChannelStatePersister] is the other half: a trait for *where*
+//! [PersistedChannel]s are kept (e.g. a file, a database row), so an
+//! application doesn't have to hand-roll that bookkeeping itself.
+//! [InMemoryChannelStatePersister] is a non-durable implementation for
+//! tests and examples.
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use super::{active::ActiveChannel, ChannelId, PartIdx, Peers};
+use crate::{abiencode, sig::EthSigner, wire::MessageBus, Address};
+
+/// `serde`-serializable checkpoint of an [ActiveChannel] - see the module
+/// docs. Build one with [PersistedChannel::capture] and hand it to
+/// [PerunClient::restore][crate::PerunClient::restore] after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedChannel {
+    /// This participant's own local channel configuration - see
+    /// [ActiveChannel::restore]'s docs for why these describe this device's
+    /// role rather than the disputed state itself.
+    pub part_idx: PartIdx,
+    pub withdraw_receiver: Address,
+    pub peers: Peers,
+    /// [ActiveChannel::snapshot]'s `params`/`state`/`signatures` blob.
+    pub snapshot: Vec<u8>,
+    /// The highest version a [StartWatchingLedgerChannelReq][crate::messages::StartWatchingLedgerChannelReq]
+    /// has been acknowledged for, or `None` if nothing has been
+    /// acknowledged yet. This crate never tracks this itself - same as it
+    /// never talks to the network on its own (see
+    /// [ErrorAction][super::active::ErrorAction]'s docs) - so the caller
+    /// supplies whatever it last observed. After
+    /// [PerunClient::restore][crate::PerunClient::restore], compare this
+    /// against the restored [ActiveChannel::version] to tell whether to
+    /// call [ActiveChannel::send_current_state_to_watcher] again before
+    /// doing anything else.
+    pub last_watcher_acked_version: Option<u64>,
+}
+
+impl PersistedChannel {
+    /// Snapshots `channel`'s current state together with
+    /// `last_watcher_acked_version` as observed by the caller (see that
+    /// field's docs) - call this after every state transition an
+    /// application wants to be able to recover from.
+    pub fn capture<B: MessageBus, S: EthSigner>(
+        channel: &ActiveChannel<'_, B, S>,
+        last_watcher_acked_version: Option<u64>,
+    ) -> Result<Self, abiencode::Error> {
+        Ok(Self {
+            part_idx: channel.part_idx(),
+            withdraw_receiver: channel.withdraw_receiver(),
+            peers: channel.peers().clone(),
+            snapshot: channel.snapshot()?,
+            last_watcher_acked_version,
+        })
+    }
+}
+
+/// Where an application keeps its [PersistedChannel] checkpoints, so a
+/// crashed device can reload its outstanding channels on boot and re-arm the
+/// Watcher with the most recent signed state instead of losing track of it -
+/// see the module docs. Modeled on rust-lightning's `Persist` trait.
+///
+/// Like [PersistedChannel] itself, this crate never calls a
+/// `ChannelStatePersister` on its own - it never talks to storage any more
+/// than it talks to the network on its own (see
+/// [ErrorAction][super::active::ErrorAction]'s docs for why) - so an
+/// application calls [Self::persist_new_channel]/[Self::update_persisted_channel]
+/// at the same moments it already calls [PersistedChannel::capture] (right
+/// after [AgreedUponChannel::build][super::AgreedUponChannel::build] sends
+/// the first [WatcherRequestMessage::WatchRequest][crate::messages::WatcherRequestMessage::WatchRequest]/
+/// [FunderRequestMessage::FundingRequest][crate::messages::FunderRequestMessage::FundingRequest],
+/// and after every [ActiveChannel::force_update][super::active::ActiveChannel::force_update]
+/// that re-arms the Watcher with a new version), and [Self::load_channels]
+/// once at startup to [PerunClient::restore][crate::PerunClient::restore]
+/// each outstanding channel before deciding whether to call
+/// [ActiveChannel::send_current_state_to_watcher] again (see
+/// [PersistedChannel::last_watcher_acked_version]).
+pub trait ChannelStatePersister {
+    type Error;
+
+    /// Store `channel` for the first time.
+    fn persist_new_channel(
+        &mut self,
+        id: ChannelId,
+        channel: PersistedChannel,
+    ) -> Result<(), Self::Error>;
+
+    /// Overwrite the checkpoint for `id`, previously stored with
+    /// [Self::persist_new_channel], with `channel`'s now-latest signed
+    /// state.
+    fn update_persisted_channel(
+        &mut self,
+        id: ChannelId,
+        channel: PersistedChannel,
+    ) -> Result<(), Self::Error>;
+
+    /// Every channel persisted so far, to reload after a restart - see the
+    /// trait docs.
+    fn load_channels(&self) -> Vec<(ChannelId, PersistedChannel)>;
+}
+
+/// [ChannelStatePersister] backed by an in-memory `Vec` - nothing here
+/// survives an actual restart, so this is only useful for tests and
+/// examples exercising the restart/reload flow without real storage.
+#[derive(Debug, Default)]
+pub struct InMemoryChannelStatePersister {
+    channels: Vec<(ChannelId, PersistedChannel)>,
+}
+
+impl InMemoryChannelStatePersister {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ChannelStatePersister for InMemoryChannelStatePersister {
+    /// Storing to a `Vec` never fails.
+    type Error = core::convert::Infallible;
+
+    fn persist_new_channel(
+        &mut self,
+        id: ChannelId,
+        channel: PersistedChannel,
+    ) -> Result<(), Self::Error> {
+        self.channels.push((id, channel));
+        Ok(())
+    }
+
+    fn update_persisted_channel(
+        &mut self,
+        id: ChannelId,
+        channel: PersistedChannel,
+    ) -> Result<(), Self::Error> {
+        match self
+            .channels
+            .iter_mut()
+            .find(|(existing, _)| *existing == id)
+        {
+            Some((_, slot)) => *slot = channel,
+            None => self.channels.push((id, channel)),
+        }
+        Ok(())
+    }
+
+    fn load_channels(&self) -> Vec<(ChannelId, PersistedChannel)> {
+        self.channels.clone()
+    }
+}
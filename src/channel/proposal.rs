@@ -1,40 +1,72 @@
 //! Low-level API for the Proposal phase.
 //!
-//! Currently, this can only handle channels with one asset and two
-//! participants. In the future we'll likely generalize it to work with
-//! arbitrary channel sizes and potentially even arbitrary ways to represent the
-//! data in rust (e.g. using `Vec<T>` vs no-heap `Vec<T>` vs `fixed-size<A,P>`).
+//! [ProposedChannel] is generic over the number of assets/participants, like
+//! the rest of [fixed_size_payment]. `build()` - and therefore the
+//! `TryFrom` conversion to an [AgreedUponChannel] - is still only
+//! implemented for the single-asset, two-party shape, since
+//! [AgreedUponChannel] and everything downstream of it (signature
+//! collection, withdrawal authorizations, ...) has not been generalized
+//! yet. Until that happens, proposing/accepting a channel with a different
+//! arity is possible, but it can't be built.
 
 use super::{
     agreed_upon::AgreedUponChannel,
     fixed_size_payment::{self},
-    NonceShare, PartIdx,
+    ChannelId, NonceShare, PartIdx,
 };
 use crate::{
     abiencode::{
         self,
         types::{Address, U256},
     },
+    client::RegisterChannelError,
     messages::{LedgerChannelProposal, LedgerChannelProposalAcc, ParticipantMessage},
+    sig::EthSigner,
     wire::{BroadcastMessageBus, MessageBus},
-    PerunClient,
+    Hash, PerunClient,
 };
 use alloc::string::ToString;
 use sha3::{Digest, Sha3_256};
 
+/// Default, currently only fully-supported shape - see the module docs.
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
-type Params = fixed_size_payment::Params<PARTICIPANTS>;
+// One reserved sub-allocation slot, see `super::active::LOCKED`. The proposal
+// itself (`LedgerChannelProposal::init_bals`) still carries an L=0
+// allocation - nothing is locked into a sub-channel yet at this point - so
+// `build()` adds the empty reserved slot when it builds the initial `State`.
+const LOCKED: usize = 1;
+type State<const A: usize, const P: usize, const L: usize = 0> =
+    fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>;
+type Params<const P: usize> = fixed_size_payment::Params<P>;
 
-/// Error returned when the proposal was already accepted by a participant.
+/// Error returned by [ProposedChannel::accept].
 #[derive(Debug)]
-pub struct AlreadyAcceptedError;
+pub enum ProposalAcceptError {
+    /// The proposal was already accepted by this participant.
+    AlreadyAccepted,
+    /// `address` isn't one the configured [EthSigner] can sign as (see
+    /// [EthSigner::is_external]), so accepting with it would produce a
+    /// channel this participant could never actually sign the initial
+    /// state for.
+    UnknownAddress(Address),
+    /// [ProposedChannel::part_idx] doesn't address a slot in
+    /// [ProposedChannel::responses] - either `0` (the proposer, who has
+    /// already "accepted" implicitly by proposing) or an index beyond the
+    /// number of participants this channel was proposed with.
+    InvalidPartIndex,
+}
 
 #[derive(Debug)]
 pub enum HandleAcceptError {
     InvalidProposalID,
+    /// A response for this `part_idx` was already recorded and conflicts
+    /// with `msg` (a different participant, or a different nonce share).
+    /// Receiving the exact same [LedgerChannelProposalAcc] again is *not*
+    /// an error, see [ProposedChannel::participant_accepted].
     AlreadyAccepted,
+    /// See [ProposalAcceptError::InvalidPartIndex].
+    InvalidPartIndex,
 }
 
 /// Error returned when the transition from ProposedChannel -> AgreedUponChannel failed.
@@ -42,54 +74,88 @@ pub enum HandleAcceptError {
 pub enum ProposalBuildError {
     AbiEncodeError(abiencode::Error),
     MissingAccResponse(PartIdx),
+    /// The proposal's [fixed_size_payment::ProtocolVersion] is not one this
+    /// build knows how to speak, see
+    /// [ConversionError::UnsupportedProtocolVersion](crate::messages::ConversionError::UnsupportedProtocolVersion).
+    UnsupportedProtocolVersion,
+    /// This channel's [ChannelId] is already registered to another live
+    /// channel, see [PerunClient::register_channel].
+    ChannelIdAlreadyRegistered(ChannelId),
+    /// [PerunClient::register_channel]'s registry is full, see
+    /// [RegisterChannelError::TooManyRegistered].
+    TooManyRegisteredChannels,
+    /// The proposal's [LedgerChannelProposal::app] is not the zero address
+    /// (i.e. it asked for an app/state channel), but this build only knows
+    /// how to construct a [fixed_size_payment::NoApp] initial state - see
+    /// the module docs.
+    AppChannelsNotSupported,
 }
 impl From<abiencode::Error> for ProposalBuildError {
     fn from(e: abiencode::Error) -> Self {
         Self::AbiEncodeError(e)
     }
 }
+impl From<RegisterChannelError> for ProposalBuildError {
+    fn from(e: RegisterChannelError) -> Self {
+        match e {
+            RegisterChannelError::AlreadyRegistered(id) => Self::ChannelIdAlreadyRegistered(id),
+            RegisterChannelError::TooManyRegistered => Self::TooManyRegisteredChannels,
+        }
+    }
+}
 
 /// Represents a channel that was proposed, but not accepted by all
 /// participants.
 ///
 /// Use `build()` or `try_into()` to get an [AgreedUponChannel], to sign the
-/// initial state and exchange those signatures.
+/// initial state and exchange those signatures. Only implemented for the
+/// default `<ASSETS, PARTICIPANTS>` shape for now, see the module docs.
 #[derive(Debug)]
-pub struct ProposedChannel<'cl, B: MessageBus> {
+pub struct ProposedChannel<
+    'cl,
+    B: MessageBus,
+    S: EthSigner,
+    const ASSETS: usize = 1,
+    const PARTICIPANTS: usize = 2,
+> {
     /// Who are we in this channel (0 is the channel proposer).
     part_idx: PartIdx,
     /// Who should receive funds when withdrawing
     withdraw_receiver: Address,
     /// Reference to the PerunClient, used for communication.
-    client: &'cl PerunClient<B>,
+    client: &'cl PerunClient<B, S>,
     /// Needed for creating the initial state, Params and for the application to
     /// decide if those are valid Parameters.
-    proposal: LedgerChannelProposal,
-    /// Holds all accept messages received so far.
+    proposal: LedgerChannelProposal<ASSETS, PARTICIPANTS>,
+    /// Holds all accept messages received so far, indexed by `part_idx`.
     ///
-    /// The data of Participant 0 is already stored in the proposal. We store
-    /// this as an array regardless, to make future transitions to >2 Party
-    /// channels easier.
-    responses: [Option<LedgerChannelProposalAcc>; 1],
+    /// The data of Participant 0 is already stored in the proposal, so slot
+    /// `0` always stays `None` - we still size this array `PARTICIPANTS`
+    /// rather than `PARTICIPANTS - 1` to keep indexing by `part_idx` direct,
+    /// since the generic arithmetic required to size it precisely isn't
+    /// available on stable Rust.
+    responses: [Option<LedgerChannelProposalAcc>; PARTICIPANTS],
 }
 
-impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
+impl<'cl, B: MessageBus, S: EthSigner, const ASSETS: usize, const PARTICIPANTS: usize>
+    ProposedChannel<'cl, B, S, ASSETS, PARTICIPANTS>
+{
     /// Create a new ProposedChannel.
     ///
     /// The caller ([PerunClient]) is responsible for sending the proposal
     /// message to all participants.
     pub(crate) fn new(
-        client: &'cl PerunClient<B>,
+        client: &'cl PerunClient<B, S>,
         part_idx: PartIdx,
         withdraw_receiver: Address,
-        proposal: LedgerChannelProposal,
+        proposal: LedgerChannelProposal<ASSETS, PARTICIPANTS>,
     ) -> Self {
         ProposedChannel {
             part_idx,
             withdraw_receiver,
             client,
             proposal,
-            responses: [None],
+            responses: [None; PARTICIPANTS],
         }
     }
 
@@ -101,21 +167,31 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
         &mut self,
         nonce_share: NonceShare,
         address: Address,
-    ) -> Result<(), AlreadyAcceptedError> {
+    ) -> Result<(), ProposalAcceptError> {
         // In go-perun this "can we sign it" is checked in `completeCPP` by
-        // trying to unlock the corresponding wallet.
-        // assert_eq!(address, self.client.signer.address(), "We have to be able to sign things with this address and the current implementation is only able to have a single singer address. It is still part of the accept function signature because this will probably change in the future and this change would be backwards incompatible.");
+        // trying to unlock the corresponding wallet. We do the equivalent by
+        // asking the configured signer whether it could produce a signature
+        // for `address` at all, instead of assuming there is only ever one
+        // signer address (see [EthSigner::is_external]).
+        if !self.client.signer.is_external(address) {
+            return Err(ProposalAcceptError::UnknownAddress(address));
+        }
+
+        let index = (1..PARTICIPANTS)
+            .contains(&self.part_idx)
+            .then_some(self.part_idx)
+            .ok_or(ProposalAcceptError::InvalidPartIndex)?;
 
-        // if self.part_idx == 0 || self.responses[self.part_idx - 1].is_some() {
-            // return Err(AlreadyAcceptedError);
-        // }
+        if self.responses[index].is_some() {
+            return Err(ProposalAcceptError::AlreadyAccepted);
+        }
 
         let acc: _ = LedgerChannelProposalAcc {
             proposal_id: self.proposal.proposal_id,
             nonce_share,
             participant: address,
         };
-        self.responses[self.part_idx - 1] = Some(acc);
+        self.responses[index] = Some(acc);
         self.client.bus.broadcast_to_participants(
             self.part_idx,
             &self.proposal.peers,
@@ -138,6 +214,7 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
                 reason: reason.to_string(),
             },
         );
+        self.client.forget_in_flight(self.proposal.proposal_id);
     }
 
     /// Call this when receiving an Accept response form a participant.
@@ -145,6 +222,12 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
     /// Adds the response to the list of responses, needed to progress to the
     /// next Phase: Creating and signing the initial state.
     ///
+    /// Receiving the exact same [LedgerChannelProposalAcc] again for a
+    /// `part_idx` that already has a response is a no-op, so that a
+    /// retransmitted/reordered message doesn't fail where the original
+    /// delivery already succeeded. A *different* response for an
+    /// already-answered `part_idx` is rejected.
+    ///
     /// When receiving a reject message, the [ProposedChannel] object can be
     /// dropped.
     pub fn participant_accepted(
@@ -156,8 +239,13 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
             return Err(HandleAcceptError::InvalidProposalID);
         }
 
-        let index = part_idx - 1;
+        let index = (1..PARTICIPANTS)
+            .contains(&part_idx)
+            .then_some(part_idx)
+            .ok_or(HandleAcceptError::InvalidPartIndex)?;
+
         match self.responses[index] {
+            Some(existing) if existing == msg => Ok(()),
             Some(_) => Err(HandleAcceptError::AlreadyAccepted),
             None => {
                 self.responses[index] = Some(msg);
@@ -166,22 +254,45 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
         }
     }
 
+    /// The id of the proposal being negotiated, i.e. what
+    /// [ParticipantMessage::ProposalAccepted]/[ParticipantMessage::ProposalRejected]
+    /// carry for this channel. Used to route inbound messages to the right
+    /// [ProposedChannel] - see [super::propose_and_agree_async].
+    pub(crate) fn proposal_id(&self) -> Hash {
+        self.proposal.proposal_id
+    }
+
+    /// The participants' [crate::wire::Identity]s, in the same order
+    /// [Self::participant_accepted] expects `part_idx` to address. Lets a
+    /// caller resolve an inbound message's sender to a `part_idx`, the same
+    /// role [ActiveChannel::peers][super::active::ActiveChannel::peers]
+    /// plays once the channel is active - see [super::propose_and_agree_async].
+    pub fn peers(&self) -> &super::Peers {
+        &self.proposal.peers
+    }
+}
+
+// `build()` constructs an [AgreedUponChannel], which - unlike
+// [ProposedChannel] itself - hasn't been generalized to arbitrary
+// asset/participant counts yet (see the module docs), so this is
+// deliberately restricted to the default `<1, 2>` shape instead of living
+// in the generic `impl` block above.
+impl<'cl, B: MessageBus, S: EthSigner> ProposedChannel<'cl, B, S, 1, 2> {
     /// Progress to the next phase: Signing the initial state.
     ///
-    /// This does **not** enforce channel_id uniqueness. Though exactly the same
-    /// channel_id is unlikely due to using different nonces. It is up to the
-    /// caller to handle this if he handles multiple channels and uses the
-    /// channel_id for forwarding messages to the correct channel (and having
-    /// multiple channels with the same channel_id will be problematic
-    /// on-chain). Checking this is not the task of this class, which is only
-    /// concerned about a single channel. Go-perun does this check in
-    /// `completeCPP`.
+    /// Registers the finalized [ChannelId] with [PerunClient::register_channel],
+    /// returning [ProposalBuildError::ChannelIdAlreadyRegistered] if another
+    /// still-registered channel already resolves to the same id - exactly
+    /// the same channel_id is unlikely in practice (it requires colliding on
+    /// the combined nonce), but a reused nonce or an adversarial peer could
+    /// still produce one, and on-chain this would be actively problematic.
+    /// Go-perun does the equivalent check in `completeCPP`.
     ///
     /// In the case of an error we still want the caller to be able to recover
     /// from it, so we have to give self back. If we wouldn't do that the caller
     /// would be forced to (implicitly) throw away the entire channel, so we
     /// could just as well have paniced in case of an error.
-    pub fn build(self) -> Result<AgreedUponChannel<'cl, B>, (Self, ProposalBuildError)> {
+    pub fn build(self) -> Result<AgreedUponChannel<'cl, B, S>, (Self, ProposalBuildError)> {
         let mut participants = [Address::default(); PARTICIPANTS];
         participants[0] = self.proposal.participant;
 
@@ -190,8 +301,9 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
         let mut hasher = Sha3_256::new();
         hasher.update(self.proposal.nonce_share.0);
 
-        // Go through all responses and make sure none is missing. Additionally
-        // collect information needed later.
+        // Go through all responses (slot 0 is the proposer, already handled
+        // above, so this starts at 1) and make sure none is missing.
+        // Additionally collect information needed later.
         //
         // Call combining it into a single loop premature optimization if you
         // want, but I didn't like that two loops either required to call
@@ -202,24 +314,32 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
         // argue why it is save to do so. (I didn't want to introduce another
         // intermediate representation array, which I don't know if the compiler
         // would optimize away).
-        for (index, res) in self.responses.iter().enumerate() {
+        for index in 1..PARTICIPANTS {
             // Unwrap all responses, returning an error if one is missing
-            let res = match res {
+            let res = match &self.responses[index] {
                 Some(v) => v,
-                None => return Err((self, ProposalBuildError::MissingAccResponse(index + 1))),
+                None => return Err((self, ProposalBuildError::MissingAccResponse(index))),
             };
 
             // Store in new participants list that doesn't use options and
             // combine the nonces
-            participants[index + 1] = res.participant;
+            participants[index] = res.participant;
             hasher.update(res.nonce_share.0);
         }
 
+        if self.proposal.protocol_version != fixed_size_payment::ProtocolVersion::CURRENT {
+            return Err((self, ProposalBuildError::UnsupportedProtocolVersion));
+        }
+        if self.proposal.app != Address([0u8; 20]) {
+            return Err((self, ProposalBuildError::AppChannelsNotSupported));
+        }
+
         // Finalize the nonce.
         let nonce = U256::from_big_endian(hasher.finalize().as_slice());
 
         // Create the initial state
-        let params: Params = Params {
+        let params: Params<PARTICIPANTS> = Params {
+            chain_id: self.client.chain_id.into(),
             challenge_duration: self.proposal.challenge_duration,
             nonce,
             participants,
@@ -227,11 +347,30 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
             ledger_channel: true,
             virtual_channel: false,
         };
-        let init_state = match State::new(params, self.proposal.init_bals) {
+        // The proposal's allocation doesn't carry a locked sub-channel yet
+        // (see the `LOCKED` comment above) - add the empty reserved slot.
+        let init_bals = fixed_size_payment::Allocation {
+            assets: self.proposal.init_bals.assets,
+            balances: self.proposal.init_bals.balances,
+            locked: [fixed_size_payment::SubAlloc::default(); LOCKED],
+        };
+        let init_state = match State::<ASSETS, PARTICIPANTS, LOCKED>::new(
+            params,
+            init_bals,
+            fixed_size_payment::NoApp,
+        ) {
             Ok(v) => v,
             Err(e) => return Err((self, e.into())),
         };
 
+        if let Err(e) = self
+            .client
+            .register_channel(ChannelId::from(init_state.channel_id()))
+        {
+            return Err((self, e.into()));
+        }
+
+        self.client.forget_in_flight(self.proposal.proposal_id);
         Ok(AgreedUponChannel::new(
             self.client,
             self.proposal.funding_agreement,
@@ -240,14 +379,261 @@ impl<'cl, B: MessageBus> ProposedChannel<'cl, B> {
             init_state,
             params,
             self.proposal.peers,
+            self.proposal.protocol_version,
         ))
     }
 }
 
-impl<'cl, B: MessageBus> TryFrom<ProposedChannel<'cl, B>> for AgreedUponChannel<'cl, B> {
-    type Error = (ProposedChannel<'cl, B>, ProposalBuildError);
+impl<'cl, B: MessageBus, S: EthSigner> TryFrom<ProposedChannel<'cl, B, S, 1, 2>>
+    for AgreedUponChannel<'cl, B, S>
+{
+    type Error = (ProposedChannel<'cl, B, S, 1, 2>, ProposalBuildError);
 
-    fn try_from(value: ProposedChannel<'cl, B>) -> Result<Self, Self::Error> {
+    fn try_from(value: ProposedChannel<'cl, B, S, 1, 2>) -> Result<Self, Self::Error> {
         value.build()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        channel::{
+            fixed_size_payment::{Allocation, Balances, ParticipantBalances, ProtocolVersion},
+            Asset,
+        },
+        test_support::{NullBus, StubSigner},
+        Hash,
+    };
+
+    const PROPOSER: Address = Address([0x01; 20]);
+    const ACCEPTOR: Address = Address([0x02; 20]);
+
+    fn proposed_channel(
+        client: &PerunClient<NullBus, StubSigner>,
+        part_idx: PartIdx,
+    ) -> ProposedChannel<'_, NullBus, StubSigner> {
+        let balances =
+            Balances::<ASSETS, PARTICIPANTS>([ParticipantBalances([0u64.into(), 0u64.into()])]);
+        let proposal = LedgerChannelProposal {
+            proposal_id: Hash([0x42; 32]),
+            challenge_duration: 1,
+            nonce_share: Hash([0x01; 32]),
+            init_bals: Allocation::<ASSETS, PARTICIPANTS>::new(
+                [Asset {
+                    chain_id: 1u64.into(),
+                    holder: PROPOSER,
+                }],
+                balances,
+            ),
+            funding_agreement: balances,
+            participant: PROPOSER,
+            peers: alloc::vec![alloc::vec![0], alloc::vec![1]],
+            protocol_version: ProtocolVersion::CURRENT,
+            app: Address([0u8; 20]),
+            init_data: alloc::vec![],
+        };
+        ProposedChannel::new(client, part_idx, ACCEPTOR, proposal)
+    }
+
+    #[test]
+    fn accept_rejects_the_proposer_calling_its_own_part_idx() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 0);
+        assert!(matches!(
+            channel.accept(Hash([0x02; 32]), ACCEPTOR),
+            Err(ProposalAcceptError::InvalidPartIndex)
+        ));
+    }
+
+    #[test]
+    fn accept_rejects_an_out_of_range_part_idx() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 5);
+        assert!(matches!(
+            channel.accept(Hash([0x02; 32]), ACCEPTOR),
+            Err(ProposalAcceptError::InvalidPartIndex)
+        ));
+    }
+
+    #[test]
+    fn accept_rejects_an_address_the_signer_cannot_sign_for() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 1);
+        assert!(matches!(
+            channel.accept(Hash([0x02; 32]), PROPOSER),
+            Err(ProposalAcceptError::UnknownAddress(PROPOSER))
+        ));
+    }
+
+    #[test]
+    fn accepting_twice_is_rejected() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 1);
+        assert!(channel.accept(Hash([0x02; 32]), ACCEPTOR).is_ok());
+        assert!(matches!(
+            channel.accept(Hash([0x02; 32]), ACCEPTOR),
+            Err(ProposalAcceptError::AlreadyAccepted)
+        ));
+    }
+
+    #[test]
+    fn participant_accepted_rejects_unknown_proposal_id() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 0);
+        let msg = LedgerChannelProposalAcc {
+            proposal_id: Hash([0xff; 32]),
+            nonce_share: Hash([0x02; 32]),
+            participant: ACCEPTOR,
+        };
+        assert!(matches!(
+            channel.participant_accepted(1, msg),
+            Err(HandleAcceptError::InvalidProposalID)
+        ));
+    }
+
+    #[test]
+    fn participant_accepted_rejects_an_out_of_range_part_idx() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 0);
+        let msg = LedgerChannelProposalAcc {
+            proposal_id: Hash([0x42; 32]),
+            nonce_share: Hash([0x02; 32]),
+            participant: ACCEPTOR,
+        };
+        assert!(matches!(
+            channel.participant_accepted(0, msg),
+            Err(HandleAcceptError::InvalidPartIndex)
+        ));
+        assert!(matches!(
+            channel.participant_accepted(7, msg),
+            Err(HandleAcceptError::InvalidPartIndex)
+        ));
+    }
+
+    #[test]
+    fn participant_accepted_twice_with_the_identical_message_is_a_no_op() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 0);
+        let msg = LedgerChannelProposalAcc {
+            proposal_id: Hash([0x42; 32]),
+            nonce_share: Hash([0x02; 32]),
+            participant: ACCEPTOR,
+        };
+        assert!(channel.participant_accepted(1, msg).is_ok());
+        assert!(channel.participant_accepted(1, msg).is_ok());
+    }
+
+    #[test]
+    fn participant_accepted_twice_with_a_conflicting_participant_is_rejected() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 0);
+        let first = LedgerChannelProposalAcc {
+            proposal_id: Hash([0x42; 32]),
+            nonce_share: Hash([0x02; 32]),
+            participant: ACCEPTOR,
+        };
+        let conflicting = LedgerChannelProposalAcc {
+            participant: PROPOSER,
+            ..first
+        };
+        assert!(channel.participant_accepted(1, first).is_ok());
+        assert!(matches!(
+            channel.participant_accepted(1, conflicting),
+            Err(HandleAcceptError::AlreadyAccepted)
+        ));
+    }
+
+    #[test]
+    fn build_registers_the_channel_id_and_rejects_a_duplicate() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+
+        let mut first = proposed_channel(&client, 1);
+        assert!(first.accept(Hash([0x02; 32]), ACCEPTOR).is_ok());
+        assert!(first.build().is_ok());
+
+        // Same proposal content on the same client -> same combined nonce ->
+        // same ChannelId, so this must be rejected instead of silently
+        // coexisting with the first channel.
+        let mut second = proposed_channel(&client, 1);
+        assert!(second.accept(Hash([0x02; 32]), ACCEPTOR).is_ok());
+        match second.build() {
+            Ok(_) => panic!("expected a ChannelIdAlreadyRegistered error"),
+            Err((_, e)) => assert!(matches!(
+                e,
+                ProposalBuildError::ChannelIdAlreadyRegistered(_)
+            )),
+        }
+    }
+
+    #[test]
+    fn our_own_accept_is_visible_to_a_later_participant_accepted() {
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let mut channel = proposed_channel(&client, 1);
+        assert!(channel.accept(Hash([0x02; 32]), ACCEPTOR).is_ok());
+
+        let conflicting = LedgerChannelProposalAcc {
+            proposal_id: Hash([0x42; 32]),
+            nonce_share: Hash([0x99; 32]),
+            participant: PROPOSER,
+        };
+        assert!(matches!(
+            channel.participant_accepted(1, conflicting),
+            Err(HandleAcceptError::AlreadyAccepted)
+        ));
+    }
+
+    /// `ProposedChannel` itself is generic over `<ASSETS, PARTICIPANTS>` -
+    /// exercise a shape other than the default `<1, 2>` to make sure the
+    /// proposal/accept bookkeeping holds up for it, even though `build()`
+    /// isn't available at this arity yet (see the module docs).
+    #[test]
+    fn accept_and_participant_accepted_work_for_a_non_default_arity() {
+        const PARTICIPANTS: usize = 3;
+        const ASSETS: usize = 2;
+
+        let client = PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1);
+        let balances = Balances::<ASSETS, PARTICIPANTS>([
+            ParticipantBalances([0u64.into(), 0u64.into(), 0u64.into()]),
+            ParticipantBalances([0u64.into(), 0u64.into(), 0u64.into()]),
+        ]);
+        let proposal = LedgerChannelProposal::<ASSETS, PARTICIPANTS> {
+            proposal_id: Hash([0x42; 32]),
+            challenge_duration: 1,
+            nonce_share: Hash([0x01; 32]),
+            init_bals: Allocation::<ASSETS, PARTICIPANTS>::new(
+                [
+                    Asset {
+                        chain_id: 1u64.into(),
+                        holder: PROPOSER,
+                    },
+                    Asset {
+                        chain_id: 2u64.into(),
+                        holder: PROPOSER,
+                    },
+                ],
+                balances,
+            ),
+            funding_agreement: balances,
+            participant: PROPOSER,
+            peers: alloc::vec![alloc::vec![0], alloc::vec![1], alloc::vec![2]],
+            protocol_version: ProtocolVersion::CURRENT,
+            app: Address([0u8; 20]),
+            init_data: alloc::vec![],
+        };
+        let mut channel: ProposedChannel<'_, NullBus, StubSigner, ASSETS, PARTICIPANTS> =
+            ProposedChannel::new(&client, 1, ACCEPTOR, proposal);
+
+        assert!(channel.accept(Hash([0x02; 32]), ACCEPTOR).is_ok());
+        let msg = LedgerChannelProposalAcc {
+            proposal_id: Hash([0x42; 32]),
+            nonce_share: Hash([0x03; 32]),
+            participant: ACCEPTOR,
+        };
+        assert!(channel.participant_accepted(2, msg).is_ok());
+        assert!(matches!(
+            channel.participant_accepted(3, msg),
+            Err(HandleAcceptError::InvalidPartIndex)
+        ));
+    }
+}
@@ -1,44 +1,163 @@
 use super::{active::ActiveChannel, fixed_size_payment, PartIdx, Peers};
 use crate::{
     abiencode::types::{Hash, Signature},
+    messages::{
+        FunderReplyMessage, FunderRequestMessage, LedgerChannelFundingRequest, WatchInfo,
+        WatcherReplyMessage, WatcherRequestMessage,
+    },
+    sig::EthSigner,
     wire::MessageBus,
     Address, PerunClient,
 };
 
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
+// One reserved sub-allocation slot, see `super::active::LOCKED`.
+const LOCKED: usize = 1;
+type State = fixed_size_payment::State<fixed_size_payment::NoApp, ASSETS, PARTICIPANTS, LOCKED>;
 type Params = fixed_size_payment::Params<PARTICIPANTS>;
 
+/// Whether the Funder/Watcher request [super::AgreedUponChannel::build] sent
+/// for this channel has been acknowledged yet - the same "eventuality"
+/// tracking [super::closing::ClosingChannel] already does for its on-chain
+/// dispute requests, applied here to the funding/watching requests sent
+/// before a channel becomes a [SignedChannel].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventualityStatus {
+    /// Sent, no matching reply observed yet - see
+    /// [SignedChannel::resend_funding]/[SignedChannel::resend_watch].
+    Pending,
+    /// A reply naming this channel (and, for the Watcher, version 0) arrived.
+    Resolved,
+}
+
+/// A [FunderReplyMessage]/[WatcherReplyMessage] didn't match any request this
+/// [SignedChannel] actually sent - wrong channel id, or (for the Watcher) a
+/// version other than the `0` [super::AgreedUponChannel::build] always asks
+/// to be watched from.
 #[derive(Debug)]
-pub struct SignedChannel<'cl, B: MessageBus>(ActiveChannel<'cl, B>);
+pub struct UnexpectedReply;
+
+#[derive(Debug)]
+pub struct SignedChannel<'cl, B: MessageBus, S: EthSigner> {
+    channel: ActiveChannel<'cl, B, S>,
+    funding_request: LedgerChannelFundingRequest,
+    funding_status: EventualityStatus,
+    watch_request: WatchInfo,
+    watch_status: EventualityStatus,
+}
 
-impl<'cl, B: MessageBus> SignedChannel<'cl, B> {
+impl<'cl, B: MessageBus, S: EthSigner> SignedChannel<'cl, B, S> {
     pub(super) fn new(
-        client: &'cl PerunClient<B>,
+        client: &'cl PerunClient<B, S>,
         part_idx: PartIdx,
         withdraw_receiver: Address,
         init_state: State,
         params: Params,
         signatures: [Signature; PARTICIPANTS],
         peers: Peers,
+        funding_request: LedgerChannelFundingRequest,
+        watch_request: WatchInfo,
     ) -> Self {
-        SignedChannel(ActiveChannel::new(
-            client,
-            part_idx,
-            withdraw_receiver,
-            init_state,
-            params,
-            signatures,
-            peers,
-        ))
+        SignedChannel {
+            channel: ActiveChannel::new(
+                client,
+                part_idx,
+                withdraw_receiver,
+                init_state,
+                params,
+                signatures,
+                peers,
+            ),
+            funding_request,
+            funding_status: EventualityStatus::Pending,
+            watch_request,
+            watch_status: EventualityStatus::Pending,
+        }
+    }
+
+    /// Whether the [FunderRequestMessage::FundingRequest] this channel was
+    /// built with has been acknowledged yet.
+    pub fn pending_funding(&self) -> EventualityStatus {
+        self.funding_status
+    }
+
+    /// Whether the [WatcherRequestMessage::WatchRequest] this channel was
+    /// built with has been acknowledged yet.
+    pub fn pending_watch(&self) -> EventualityStatus {
+        self.watch_status
+    }
+
+    /// Feed in a [FunderReplyMessage], marking the funding request
+    /// [EventualityStatus::Resolved] if it matches. Replaying an already
+    /// observed match (or one for a channel we never asked about) is rejected
+    /// with [UnexpectedReply] rather than silently accepted.
+    pub fn on_funder_response(&mut self, msg: FunderReplyMessage) -> Result<(), UnexpectedReply> {
+        match msg {
+            FunderReplyMessage::Funded { id } if id == self.channel_id() => {
+                self.funding_status = EventualityStatus::Resolved;
+                Ok(())
+            }
+            _ => Err(UnexpectedReply),
+        }
+    }
+
+    /// Feed in a [WatcherReplyMessage], marking the watch request
+    /// [EventualityStatus::Resolved] if it matches. Only
+    /// [WatcherReplyMessage::Ack] for version `0` (the version
+    /// [super::AgreedUponChannel::build] always asks to be watched from)
+    /// resolves it; anything else is rejected with [UnexpectedReply].
+    pub fn on_watcher_response(&mut self, msg: WatcherReplyMessage) -> Result<(), UnexpectedReply> {
+        match msg {
+            WatcherReplyMessage::Ack { id, version: 0 } if id == self.channel_id() => {
+                self.watch_status = EventualityStatus::Resolved;
+                Ok(())
+            }
+            _ => Err(UnexpectedReply),
+        }
+    }
+
+    /// Re-emit the original [FunderRequestMessage::FundingRequest], e.g.
+    /// after a resend timeout while [Self::pending_funding] is still
+    /// [EventualityStatus::Pending].
+    pub fn resend_funding(&self) {
+        self.channel
+            .client()
+            .bus
+            .send_to_funder(FunderRequestMessage::FundingRequest(self.funding_request));
+    }
+
+    /// Re-emit the original [WatcherRequestMessage::WatchRequest], e.g. after
+    /// a resend timeout while [Self::pending_watch] is still
+    /// [EventualityStatus::Pending].
+    pub fn resend_watch(&self) {
+        self.channel
+            .client()
+            .bus
+            .send_to_watcher(WatcherRequestMessage::WatchRequest(self.watch_request));
     }
 
-    pub fn mark_funded(self) -> ActiveChannel<'cl, B> {
-        self.0
+    /// Transition into an [ActiveChannel] once the channel is funded.
+    ///
+    /// Refuses to proceed while [Self::pending_funding] is still
+    /// [EventualityStatus::Pending] - i.e. until a [FunderReplyMessage::Funded]
+    /// naming this channel has actually been observed via
+    /// [Self::on_funder_response], instead of trusting the caller's bare word
+    /// that funding succeeded. This crate never talks to the chain directly,
+    /// so that's still the only guarantee this gives; callers who want to
+    /// cross-check it against the AssetHolder's deposit/`Transfer` events
+    /// themselves should use [super::funding::verify_deposits] first and only
+    /// call this once every participant is
+    /// [super::funding::FundingStatus::FullyFunded].
+    pub fn mark_funded(self) -> Result<ActiveChannel<'cl, B, S>, Self> {
+        if self.funding_status == EventualityStatus::Resolved {
+            Ok(self.channel)
+        } else {
+            Err(self)
+        }
     }
 
     pub fn channel_id(&self) -> Hash {
-        self.0.channel_id()
+        self.channel.channel_id()
     }
 }
@@ -3,16 +3,14 @@ use serde::Serialize;
 use crate::{
     abiencode::{self, types::U256},
     messages::SignedWithdrawalAuth,
-    sig::Signer,
+    sig::{EthSigner, SigningError},
     Address, Hash,
 };
 
-use super::{fixed_size_payment, PartIdx};
-
-const ASSETS: usize = 1;
-const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
-type Params = fixed_size_payment::Params<PARTICIPANTS>;
+use super::{
+    fixed_size_payment::{self, NoApp},
+    PartIdx,
+};
 
 #[derive(Serialize, Debug, Copy, Clone)]
 struct WithdrawalAuth {
@@ -22,26 +20,50 @@ struct WithdrawalAuth {
     pub amount: U256,
 }
 
-pub fn make_signed_withdrawal_auths(
-    signer: &Signer,
+#[derive(Debug)]
+pub enum MakeWithdrawalAuthError {
+    AbiEncodeError(abiencode::Error),
+    SigningFailed(SigningError),
+}
+impl From<abiencode::Error> for MakeWithdrawalAuthError {
+    fn from(e: abiencode::Error) -> Self {
+        Self::AbiEncodeError(e)
+    }
+}
+impl From<SigningError> for MakeWithdrawalAuthError {
+    fn from(e: SigningError) -> Self {
+        Self::SigningFailed(e)
+    }
+}
+
+/// Generic over `ASSETS`/`PARTICIPANTS`/`LOCKED` so it works for any channel
+/// shape this crate is instantiated with (not just this module's own
+/// 1-asset/2-participant default), inferred from `params`/`state` at the
+/// call site. Only ever produces `part_idx`'s own withdrawal auths - one
+/// participant's signer can't authorize anyone else's withdrawal - so the
+/// result is `ASSETS`-sized (one auth per asset), not `ASSETS * PARTICIPANTS`.
+pub fn make_signed_withdrawal_auths<
+    const ASSETS: usize,
+    const PARTICIPANTS: usize,
+    const LOCKED: usize,
+>(
+    signer: &impl EthSigner,
     channel_id: Hash,
-    params: Params,
-    state: State,
+    params: fixed_size_payment::Params<PARTICIPANTS>,
+    state: fixed_size_payment::State<NoApp, ASSETS, PARTICIPANTS, LOCKED>,
     withdraw_receiver: Address,
     part_idx: PartIdx,
-) -> Result<[SignedWithdrawalAuth; ASSETS], abiencode::Error> {
+) -> Result<[SignedWithdrawalAuth; ASSETS], MakeWithdrawalAuthError> {
     let mut withdrawal_auths = [SignedWithdrawalAuth::default(); ASSETS];
 
-    // Just a defensive measure in case the State type is changed without
-    // removing or updating ASSETS.
-    debug_assert_eq!(withdrawal_auths.len(), state.outcome.balances.0.len());
     for (auth, bals) in withdrawal_auths.iter_mut().zip(state.outcome.balances.0) {
-        let sig = signer.sign_eth(abiencode::to_hash(&WithdrawalAuth {
+        let hash = abiencode::to_hash(&WithdrawalAuth {
             channel_id,
             participant: params.participants[part_idx],
             receiver: withdraw_receiver,
             amount: bals.0[part_idx],
-        })?);
+        })?;
+        let sig = signer.sign_eth(hash).map_err(SigningError::capture)?;
         *auth = SignedWithdrawalAuth {
             sig,
             receiver: withdraw_receiver,
@@ -1,14 +1,223 @@
-use crate::channel::ProposedChannel;
+use crate::abiencode;
+use crate::abiencode::types::{Hash, Signature, U256};
+use crate::channel::active::ActiveChannel;
+use crate::channel::{ChannelId, PersistedChannel, ProposedChannel};
 use crate::messages::{LedgerChannelProposal, ParticipantMessage};
-use crate::sig::Signer;
+use crate::sig::{EthSigner, SigningError};
 use crate::wire::{BroadcastMessageBus, Identity, MessageBus};
 use crate::Address;
+use core::cell::RefCell;
 use core::fmt::Debug;
+use sha3::{Digest, Keccak256};
+
+/// A set of optional capabilities a peer advertises during the handshake
+/// (see [PerunClient::handle_auth_challenge]/[PerunClient::handle_auth_response]),
+/// loosely modeled on rust-lightning's `InitFeatures`/`NodeFeatures` bitfield.
+/// [PerunClient::handle_auth_response] intersects both sides' sets once the
+/// handshake completes and remembers the result (see
+/// [PerunClient::negotiated_features]), so later operations - e.g.
+/// [ActiveChannel::lock_into_subchannel][crate::channel::active::ActiveChannel::lock_into_subchannel] -
+/// can refuse to proceed if a peer never advertised support for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChannelFeatures(u32);
+
+impl ChannelFeatures {
+    /// Locking/releasing funds into a sub-channel, see
+    /// [ActiveChannel::lock_into_subchannel][crate::channel::active::ActiveChannel::lock_into_subchannel]/
+    /// [ActiveChannel::release_subchannel][crate::channel::active::ActiveChannel::release_subchannel].
+    /// Bit 0 is even, so this is "must understand" - see
+    /// [Self::missing_required].
+    pub const SUB_CHANNEL_UPDATES: Self = Self(1 << 0);
+    /// More than two [Params::participants][crate::channel::fixed_size_payment::Params].
+    /// Not actually usable yet (see the [crate::channel::proposal] module
+    /// docs), but advertised so a peer that only supports two participants
+    /// can be told apart from one that might not, once that support lands.
+    /// Bit 1 is odd, so this is "optional" - a peer that doesn't echo it
+    /// back just doesn't get it intersected in, see [Self::missing_required].
+    pub const MULTI_PARTY: Self = Self(1 << 1);
+    /// The Watcher this client reports to (see
+    /// [ActiveChannel::send_current_state_to_watcher][crate::channel::active::ActiveChannel::send_current_state_to_watcher])
+    /// supports dispute-watching on locked sub-channels, not just the
+    /// top-level ledger channel. Bit 2 is even, so this is "must understand" -
+    /// see [Self::missing_required].
+    pub const DISPUTE_WATCHING_SUBCHANNELS: Self = Self(1 << 2);
+
+    /// Bits at even indices (0, 2, 4, ...), i.e. the "must understand" bits
+    /// in the even/odd convention [Self::missing_required] enforces,
+    /// borrowed from rust-lightning's feature bitfields: setting one of
+    /// these tells the peer a feature isn't safe to silently ignore, as
+    /// opposed to an odd-indexed bit the peer is free to drop from the
+    /// negotiated [Self::intersection] without aborting the handshake.
+    const REQUIRED_MASK: u32 = 0x5555_5555;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn contains(self, flag: Self) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+
+    /// The bits both `self` and `other` advertised - what
+    /// [PerunClient::handle_auth_response] stores per peer, since a feature
+    /// either side didn't advertise can't safely be used regardless of which
+    /// side would end up exercising it.
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// The even-indexed ("must understand", see [Self::REQUIRED_MASK]) bits
+    /// `self` set that `peer` did not echo back. Non-[Self::empty] means the
+    /// handshake must abort - `peer` doesn't support something `self` is not
+    /// willing to proceed without - rather than silently falling back, the
+    /// way an unset odd bit (optional) would.
+    pub const fn missing_required(self, peer: Self) -> Self {
+        Self(self.0 & !peer.0 & Self::REQUIRED_MASK)
+    }
+}
 
 #[derive(Debug)]
 pub enum InvalidProposal {
     NoChallengeDurationSet,
     PeerParticipantCountMismatch,
+    /// [PerunClient::propose_channel] was called with a [LedgerChannelProposal::proposal_id]
+    /// that isn't already in-flight, but the in-flight table is full. Build
+    /// the channel from the existing handle (or let it finish/drop) before
+    /// proposing a new one.
+    TooManyInFlightProposals,
+    /// The peer this proposal is addressed to has not completed
+    /// [PerunClient::send_handshake_msg]/[PerunClient::handle_auth_response]
+    /// yet, so we have no proof it controls the [Address] it claims.
+    PeerNotAuthenticated(Identity),
+    /// Summing one asset's balances across all participants overflows the
+    /// 256-bit ABI word used to encode it on-chain. A proposal like this
+    /// would only fail much later, during funding serialization, so it is
+    /// rejected up front instead.
+    BalanceOverflow,
+    /// [PerunClient::handle_proposal] received a [LedgerChannelProposal]
+    /// that collides with one of our own still in-flight
+    /// [PerunClient::propose_channel] calls to the same peers (both sides
+    /// proposed a channel to each other at nearly the same time), and the
+    /// two proposals disagree on `challenge_duration` or `init_bals`. There
+    /// is no single channel they could both be describing, so this aborts
+    /// instead of silently preferring one side's values - see
+    /// [PerunClient::handle_proposal]'s docs for the collision handling
+    /// this is part of.
+    SimultaneousProposalMismatch,
+    /// Both proposals in a simultaneous-open collision (see
+    /// [InvalidProposal::SimultaneousProposalMismatch]) carry byte-identical
+    /// [LedgerChannelProposal::proposal_id]s, so comparing them can't
+    /// deterministically elect an initiator. Both are dropped (our own is
+    /// forgotten from [PerunClient::in_flight_proposals]) rather than risk
+    /// an ambiguous pick.
+    AmbiguousProposalCollision,
+    /// Our own [PerunClient::propose_channel] call won a simultaneous-open
+    /// collision (see [InvalidProposal::SimultaneousProposalMismatch]): its
+    /// `proposal_id` sorted higher than the peer's, so we keep the
+    /// initiator role and the peer's colliding proposal is discarded. The
+    /// [ProposedChannel] already returned from that `propose_channel` call
+    /// is still the one to use - there is nothing new to build from this
+    /// one.
+    WonProposalCollision,
+}
+
+/// How many peers [PerunClient::handle_auth_response] can remember as
+/// authenticated at once, see [PerunClient::authenticated_peers].
+const MAX_AUTHENTICATED_PEERS: usize = 4;
+
+/// How many of our own outstanding [ParticipantMessage::AuthChallenge]s
+/// [PerunClient::send_handshake_msg] can track at once, see
+/// [PerunClient::outstanding_challenges].
+const MAX_OUTSTANDING_HANDSHAKES: usize = 4;
+
+/// How many [ChannelId]s [PerunClient::register_channel] can remember at
+/// once, see [PerunClient::registered_channels].
+const MAX_REGISTERED_CHANNELS: usize = 8;
+
+/// Error returned by [PerunClient::register_channel].
+#[derive(Debug)]
+pub enum RegisterChannelError {
+    /// Another still-registered channel already resolves to this
+    /// [ChannelId]. Two honestly-generated channels colliding here is
+    /// astronomically unlikely (it requires the same hash over different
+    /// [Params](crate::channel::fixed_size_payment::Params)); this exists to
+    /// catch a reused nonce or an adversarial peer rather than coincidence.
+    AlreadyRegistered(ChannelId),
+    /// [PerunClient::registered_channels] is full. Call
+    /// [PerunClient::forget_channel] for channels that are no longer live
+    /// before registering new ones.
+    TooManyRegistered,
+}
+
+/// Error returned by [PerunClient::restore].
+#[derive(Debug)]
+pub enum RestoreError {
+    /// The [PersistedChannel::snapshot](crate::channel::PersistedChannel::snapshot)
+    /// blob failed to decode - e.g. it was written by an incompatible
+    /// version of this crate, or corrupted on disk.
+    InvalidSnapshot(abiencode::Error),
+    /// [PerunClient::register_channel] rejected the restored channel, e.g.
+    /// because a channel with the same id is already registered.
+    Register(RegisterChannelError),
+}
+impl From<abiencode::Error> for RestoreError {
+    fn from(e: abiencode::Error) -> Self {
+        Self::InvalidSnapshot(e)
+    }
+}
+impl From<RegisterChannelError> for RestoreError {
+    fn from(e: RegisterChannelError) -> Self {
+        Self::Register(e)
+    }
+}
+
+/// Error returned by the handshake methods ([PerunClient::send_handshake_msg],
+/// [PerunClient::handle_auth_challenge], [PerunClient::handle_auth_response]).
+#[derive(Debug)]
+pub enum HandshakeError {
+    /// [PerunClient::handle_auth_response] was called for a peer/nonce pair
+    /// we never sent an [ParticipantMessage::AuthChallenge] for (or already
+    /// consumed the response for), so the [ParticipantMessage::AuthResponse]
+    /// cannot be trusted.
+    NoChallengeSent,
+    /// Recovering the signer from the [ParticipantMessage::AuthResponse]'s
+    /// signature failed.
+    RecoveryFailed(SigningError),
+    /// The recovered signer does not match the [Address] we expected this
+    /// peer to control.
+    AddressMismatch(Address),
+    /// [PerunClient::send_handshake_msg] was called while
+    /// [PerunClient::outstanding_challenges] is already full. Wait for an
+    /// outstanding challenge to be answered (or time out) before issuing
+    /// another one.
+    TooManyOutstanding,
+    /// [PerunClient::handle_auth_response] verified the peer but
+    /// [PerunClient::authenticated_peers] is already full of other peers.
+    TooManyAuthenticated,
+    /// The peer's [ParticipantMessage::AuthResponse] didn't echo back one or
+    /// more of our own "must understand" [ChannelFeatures] (see
+    /// [ChannelFeatures::missing_required]). The handshake aborts instead of
+    /// negotiating down, since we advertised those bits specifically to mean
+    /// we aren't willing to proceed without them.
+    RequiredFeatureNotSupported(ChannelFeatures),
+}
+impl From<SigningError> for HandshakeError {
+    fn from(e: SigningError) -> Self {
+        Self::RecoveryFailed(e)
+    }
+}
+
+/// How many proposals [PerunClient::propose_channel]/[PerunClient::handle_proposal]
+/// can track at once for idempotent retry, see [PerunClient::in_flight_proposals].
+const MAX_IN_FLIGHT_PROPOSALS: usize = 4;
+
+/// Enough of a [LedgerChannelProposal] to reconstruct the [ProposedChannel]
+/// handle returned for it, keyed by [LedgerChannelProposal::proposal_id].
+#[derive(Debug)]
+struct InFlightProposal {
+    part_idx: usize,
+    withdraw_receiver: Address,
+    proposal: LedgerChannelProposal,
 }
 
 /// The main Perun object used to create new channels and configure
@@ -20,21 +229,283 @@ pub enum InvalidProposal {
 ///
 /// Note: An application will usually have only one MessageBux type, thus using
 /// dynamic dispatch here doesn't make much sense.
+///
+/// `S` is generic over [EthSigner] instead of this crate's concrete `Signer`
+/// so an application can plug in a hardware wallet or an out-of-process
+/// remote signer instead.
 #[derive(Debug)]
-pub struct PerunClient<B: MessageBus> {
+pub struct PerunClient<B: MessageBus, S: EthSigner> {
     pub(crate) bus: B,
-    pub(crate) signer: Signer,
+    pub(crate) signer: S,
+    /// Chain id of the EVM network the adjudicator contracts used by this
+    /// client are deployed on. Used for [EIP-155](https://eips.ethereum.org/EIPS/eip-155)
+    /// transaction-style signatures, and copied into every [Params] this
+    /// client proposes, so it is also covered by the Keccak256 digest that
+    /// gets signed: a state signed for this chain cannot be replayed against
+    /// an identically-parameterized channel on another chain, since doing so
+    /// would require forging a signature over a different hash. The same
+    /// device can still be reconfigured for a different network without
+    /// recompiling.
+    ///
+    /// [Params]: crate::channel::fixed_size_payment::Params
+    /// [State]: crate::channel::fixed_size_payment::State
+    pub(crate) chain_id: u64,
+    /// In-flight [LedgerChannelProposal::proposal_id]s [propose_channel][Self::propose_channel]/
+    /// [handle_proposal][Self::handle_proposal] have already acted on, so a
+    /// caller that retries one (e.g. after a transient [MessageBus] send
+    /// failure) gets the existing handle back instead of a duplicate
+    /// broadcast/[ProposedChannel]. Bounded to [MAX_IN_FLIGHT_PROPOSALS]
+    /// slots; once full, further new proposals are rejected with
+    /// [InvalidProposal::TooManyInFlightProposals] until one finishes
+    /// ([ProposedChannel::build]) or is dropped.
+    ///
+    /// Interior mutability: both methods only need `&self`, matching the
+    /// rest of this type.
+    in_flight_proposals: RefCell<[Option<InFlightProposal>; MAX_IN_FLIGHT_PROPOSALS]>,
+    /// Nonces [send_handshake_msg][Self::send_handshake_msg] has sent an
+    /// [ParticipantMessage::AuthChallenge] for and not yet received a
+    /// matching (verified) [ParticipantMessage::AuthResponse] to, keyed by
+    /// the recipient's [Identity]. Bounded to [MAX_OUTSTANDING_HANDSHAKES]
+    /// slots; see [HandshakeError::TooManyOutstanding].
+    outstanding_challenges: RefCell<[Option<(Identity, Hash)>; MAX_OUTSTANDING_HANDSHAKES]>,
+    /// Peers [handle_auth_response][Self::handle_auth_response] has verified
+    /// control the [Address] they claim, together with the [ChannelFeatures]
+    /// negotiated with them (see [negotiated_features][Self::negotiated_features]).
+    /// [propose_channel][Self::propose_channel]/[handle_proposal][Self::handle_proposal]
+    /// refuse to deal with a peer that isn't in this table, see
+    /// [InvalidProposal::PeerNotAuthenticated]. Bounded to
+    /// [MAX_AUTHENTICATED_PEERS] slots, same as [in_flight_proposals][Self::in_flight_proposals];
+    /// see [HandshakeError::TooManyAuthenticated].
+    authenticated_peers: RefCell<[Option<(Identity, ChannelFeatures)>; MAX_AUTHENTICATED_PEERS]>,
+    /// [ChannelId]s of channels this client currently considers live, so a
+    /// second channel resolving to the same id can be rejected instead of
+    /// silently coexisting with the first one. [ProposedChannel::build][crate::channel::ProposedChannel::build]
+    /// registers a channel here automatically once its initial state is
+    /// finalized. Bounded to [MAX_REGISTERED_CHANNELS] slots, see
+    /// [RegisterChannelError::TooManyRegistered].
+    registered_channels: RefCell<[Option<ChannelId>; MAX_REGISTERED_CHANNELS]>,
 }
 
-impl<B: MessageBus> PerunClient<B> {
-    /// Creates a new [PerunClient] with the given [MessageBus].
-    pub fn new(bus: B, signer: Signer) -> Self {
-        PerunClient { bus, signer }
+impl<B: MessageBus, S: EthSigner> PerunClient<B, S> {
+    /// Creates a new [PerunClient] with the given [MessageBus], signer and
+    /// chain id.
+    pub fn new(bus: B, signer: S, chain_id: u64) -> Self {
+        PerunClient {
+            bus,
+            signer,
+            chain_id,
+            in_flight_proposals: RefCell::new([None, None, None, None]),
+            outstanding_challenges: RefCell::new([None, None, None, None]),
+            authenticated_peers: RefCell::new([None, None, None, None]),
+            registered_channels: RefCell::new([None; MAX_REGISTERED_CHANNELS]),
+        }
+    }
+
+    /// Registers `id` as belonging to a channel this client now considers
+    /// live. Returns [RegisterChannelError::AlreadyRegistered] if another
+    /// still-registered channel already resolves to the same id, instead of
+    /// letting both coexist silently. [ProposedChannel::build][crate::channel::ProposedChannel::build]
+    /// calls this automatically; call it directly if a channel is
+    /// constructed some other way.
+    pub fn register_channel(&self, id: ChannelId) -> Result<(), RegisterChannelError> {
+        let mut slots = self.registered_channels.borrow_mut();
+        if slots.iter().flatten().any(|&existing| existing == id) {
+            return Err(RegisterChannelError::AlreadyRegistered(id));
+        }
+        let slot = slots
+            .iter_mut()
+            .find(|s| s.is_none())
+            .ok_or(RegisterChannelError::TooManyRegistered)?;
+        *slot = Some(id);
+        Ok(())
+    }
+
+    /// Forgets `id`, freeing its slot in [registered_channels][Self::registered_channels]
+    /// so it can be registered again - e.g. once the channel it named has
+    /// settled or been disputed off this client's bookkeeping entirely.
+    pub fn forget_channel(&self, id: ChannelId) {
+        if let Some(slot) = self
+            .registered_channels
+            .borrow_mut()
+            .iter_mut()
+            .find(|s| matches!(s, Some(existing) if *existing == id))
+        {
+            *slot = None;
+        }
+    }
+
+    /// Whether [register_channel][Self::register_channel] currently
+    /// considers `id` live.
+    pub fn is_channel_registered(&self, id: ChannelId) -> bool {
+        self.registered_channels
+            .borrow()
+            .iter()
+            .flatten()
+            .any(|&existing| existing == id)
+    }
+
+    /// Rebuilds an [ActiveChannel] from `persisted` (see
+    /// [PersistedChannel::capture][crate::channel::PersistedChannel::capture])
+    /// and [register_channel][Self::register_channel]s its id, the same way
+    /// [ProposedChannel::build][crate::channel::ProposedChannel::build] does
+    /// for a freshly negotiated channel. Call this once after a restart, for
+    /// every channel a crashed process had checkpointed, before doing
+    /// anything else with it.
+    pub fn restore(
+        &self,
+        persisted: &PersistedChannel,
+    ) -> Result<ActiveChannel<B, S>, RestoreError> {
+        let channel = ActiveChannel::restore(
+            self,
+            persisted.part_idx,
+            persisted.withdraw_receiver,
+            persisted.peers.clone(),
+            &persisted.snapshot,
+        )?;
+        self.register_channel(ChannelId::from(channel.channel_id()))?;
+        Ok(channel)
+    }
+
+    /// Start an authenticated handshake with `recipient`: sends a fresh
+    /// `nonce` as an [ParticipantMessage::AuthChallenge], which `recipient`
+    /// is expected to answer via [ParticipantMessage::AuthResponse] (handled
+    /// by passing it to [handle_auth_response][Self::handle_auth_response]).
+    ///
+    /// `nonce` should be a fresh random value for every call (same
+    /// responsibility as e.g. [crate::messages::LedgerChannelProposal::proposal_id]
+    /// - generated and owned by the application, not by this crate). Reusing
+    /// a nonce lets a recorded [ParticipantMessage::AuthResponse] be replayed.
+    ///
+    /// Run this in both directions (each side challenges the other) so both
+    /// participants end up authenticated, see [authenticated_peers][Self::authenticated_peers].
+    pub fn send_handshake_msg(
+        &self,
+        sender: &Identity,
+        recipient: &Identity,
+        nonce: Hash,
+    ) -> Result<(), HandshakeError> {
+        let mut slots = self.outstanding_challenges.borrow_mut();
+        let slot = slots
+            .iter_mut()
+            .find(|s| s.is_none())
+            .ok_or(HandshakeError::TooManyOutstanding)?;
+        *slot = Some((recipient.clone(), nonce));
+        drop(slots);
+
+        self.bus
+            .send_to_participant(sender, recipient, ParticipantMessage::AuthChallenge(nonce));
+        Ok(())
+    }
+
+    /// Handle an incoming [ParticipantMessage::AuthChallenge] from `sender`:
+    /// signs `nonce` (bound to both identities) and replies with a
+    /// [ParticipantMessage::AuthResponse] advertising `local_features`.
+    pub fn handle_auth_challenge(
+        &self,
+        own_identity: &Identity,
+        sender: &Identity,
+        nonce: Hash,
+        local_features: ChannelFeatures,
+    ) -> Result<(), SigningError> {
+        let hash = handshake_hash(nonce, own_identity, sender);
+        let sig = self.signer.sign_eth(hash).map_err(SigningError::capture)?;
+        self.bus.send_to_participant(
+            own_identity,
+            sender,
+            ParticipantMessage::AuthResponse {
+                nonce,
+                sig,
+                features: local_features,
+            },
+        );
+        Ok(())
+    }
+
+    /// Handle an incoming [ParticipantMessage::AuthResponse] from `sender`:
+    /// checks it answers a challenge we actually sent, recovers the signer,
+    /// and - if it matches `expected` - remembers `sender` as authenticated
+    /// together with the [ChannelFeatures] it advertised, intersected with
+    /// `local_features` (see [authenticated_peers][Self::authenticated_peers]/
+    /// [negotiated_features][Self::negotiated_features]).
+    pub fn handle_auth_response(
+        &self,
+        own_identity: &Identity,
+        sender: &Identity,
+        nonce: Hash,
+        sig: Signature,
+        expected: Address,
+        local_features: ChannelFeatures,
+        remote_features: ChannelFeatures,
+    ) -> Result<(), HandshakeError> {
+        let mut slots = self.outstanding_challenges.borrow_mut();
+        let slot = slots
+            .iter_mut()
+            .find(|s| matches!(s, Some((id, n)) if id == sender && *n == nonce))
+            .ok_or(HandshakeError::NoChallengeSent)?;
+        *slot = None;
+        drop(slots);
+
+        let hash = handshake_hash(nonce, sender, own_identity);
+        let recovered = self
+            .signer
+            .recover_signer(hash, sig)
+            .map_err(SigningError::capture)?;
+        if recovered != expected {
+            return Err(HandshakeError::AddressMismatch(recovered));
+        }
+
+        let missing = local_features.missing_required(remote_features);
+        if missing != ChannelFeatures::empty() {
+            return Err(HandshakeError::RequiredFeatureNotSupported(missing));
+        }
+
+        let negotiated = local_features.intersection(remote_features);
+        let mut peers = self.authenticated_peers.borrow_mut();
+        if let Some(existing) = peers.iter_mut().flatten().find(|(p, _)| p == sender) {
+            existing.1 = negotiated;
+            return Ok(());
+        }
+        let slot = peers
+            .iter_mut()
+            .find(|s| s.is_none())
+            .ok_or(HandshakeError::TooManyAuthenticated)?;
+        *slot = Some((sender.clone(), negotiated));
+        Ok(())
+    }
+
+    /// Whether [handle_auth_response][Self::handle_auth_response] has
+    /// already verified that `peer` controls the [Address] it claims.
+    fn is_authenticated(&self, peer: &Identity) -> bool {
+        self.authenticated_peers
+            .borrow()
+            .iter()
+            .flatten()
+            .any(|(p, _)| p == peer)
+    }
+
+    /// The [ChannelFeatures] negotiated with `peer` by
+    /// [handle_auth_response][Self::handle_auth_response], or `None` if
+    /// `peer` isn't authenticated yet.
+    pub fn negotiated_features(&self, peer: &Identity) -> Option<ChannelFeatures> {
+        self.authenticated_peers
+            .borrow()
+            .iter()
+            .flatten()
+            .find(|(p, _)| p == peer)
+            .map(|(_, features)| *features)
+    }
+
+    /// Send a `PingMsg` keepalive probe to `recipient`, to be answered with
+    /// [send_pong][Self::send_pong].
+    pub fn send_ping(&self, sender: &Identity, recipient: &Identity) {
+        self.bus
+            .send_to_participant(sender, recipient, ParticipantMessage::Ping);
     }
 
-    pub fn send_handshake_msg(&self, sender: &Identity, recipient: &Identity) {
+    /// Reply to a [send_ping][Self::send_ping] probe.
+    pub fn send_pong(&self, sender: &Identity, recipient: &Identity) {
         self.bus
-            .send_to_participant(sender, recipient, ParticipantMessage::Auth);
+            .send_to_participant(sender, recipient, ParticipantMessage::Pong);
     }
 
     fn check_valid_proposal(prop: &LedgerChannelProposal) -> Result<(), InvalidProposal> {
@@ -50,17 +521,92 @@ impl<B: MessageBus> PerunClient<B> {
         } else if prop.peers.len() != prop.init_bals.balances.0[0].0.len() {
             Err(InvalidProposal::PeerParticipantCountMismatch)
         } else {
+            // Every asset column must sum without wrapping the 256-bit ABI
+            // word it is later encoded into (see `abiencode`), otherwise a
+            // malicious/buggy peer's proposal would only fail much later
+            // during funding serialization. U256 is unsigned, so there is no
+            // separate "negative entry" to reject.
+            for bals in prop.init_bals.balances.0 {
+                bals.0
+                    .iter()
+                    .try_fold(U256::zero(), |sum, amt| sum.checked_add(*amt))
+                    .ok_or(InvalidProposal::BalanceOverflow)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns the stored [InFlightProposal] with the given
+    /// [LedgerChannelProposal::proposal_id], if any, as the parameters needed
+    /// to reconstruct the [ProposedChannel] handle that was returned for it.
+    fn find_in_flight(&self, id: Hash) -> Option<(usize, Address, LedgerChannelProposal)> {
+        self.in_flight_proposals
+            .borrow()
+            .iter()
+            .flatten()
+            .find(|p| p.proposal.proposal_id == id)
+            .map(|p| (p.part_idx, p.withdraw_receiver, p.proposal.clone()))
+    }
+
+    /// Frees the in-flight slot (if any) held for `id`. Called once a
+    /// [ProposedChannel] built from it has moved past the proposal phase
+    /// ([ProposedChannel::build]/[ProposedChannel::reject]), since retrying
+    /// `propose_channel`/`handle_proposal` no longer makes sense for it.
+    pub(crate) fn forget_in_flight(&self, id: Hash) {
+        if let Some(slot) = self
+            .in_flight_proposals
+            .borrow_mut()
+            .iter_mut()
+            .find(|s| matches!(s, Some(p) if p.proposal.proposal_id == id))
+        {
+            *slot = None;
+        }
+    }
+
+    /// Returns [InvalidProposal::PeerNotAuthenticated] unless `peer` has
+    /// completed the handshake (see [handle_auth_response][Self::handle_auth_response]).
+    fn require_authenticated(&self, peer: &Identity) -> Result<(), InvalidProposal> {
+        if self.is_authenticated(peer) {
             Ok(())
+        } else {
+            Err(InvalidProposal::PeerNotAuthenticated(peer.clone()))
         }
     }
 
+    /// Records a freshly-handled proposal in the in-flight table so a retry
+    /// carrying the same [LedgerChannelProposal::proposal_id] can be answered
+    /// without repeating its side effects.
+    fn remember_in_flight(
+        &self,
+        part_idx: usize,
+        withdraw_receiver: Address,
+        proposal: LedgerChannelProposal,
+    ) -> Result<(), InvalidProposal> {
+        let mut slots = self.in_flight_proposals.borrow_mut();
+        let slot = slots
+            .iter_mut()
+            .find(|s| s.is_none())
+            .ok_or(InvalidProposal::TooManyInFlightProposals)?;
+        *slot = Some(InFlightProposal {
+            part_idx,
+            withdraw_receiver,
+            proposal,
+        });
+        Ok(())
+    }
+
     /// Propose a new channel with the given parameters/proposal and send a
     /// message to all participants.
+    ///
+    /// Calling this again with a [LedgerChannelProposal::proposal_id] that is
+    /// already in-flight (e.g. because the caller couldn't tell whether a
+    /// previous call's [MessageBus] send actually went out) returns the
+    /// existing handle instead of broadcasting a duplicate proposal.
     pub fn propose_channel(
         &self,
         prop: LedgerChannelProposal,
         withdraw_receiver: Address,
-    ) -> Result<ProposedChannel<B>, InvalidProposal> {
+    ) -> Result<ProposedChannel<B, S>, InvalidProposal> {
         // For sub-channels and virtual-channels, go-perun checks if the parent
         // exists (is known) and locks the parent's context for the duration of
         // the handshake (including funding) or returns an Error if it does not.
@@ -75,6 +621,18 @@ impl<B: MessageBus> PerunClient<B> {
         //   - Client.cleanupChannelOpening
 
         Self::check_valid_proposal(&prop)?;
+        if let Some(peer) = prop.peers.get(1) {
+            self.require_authenticated(peer)?;
+        }
+
+        if let Some((part_idx, withdraw_receiver, prop)) = self.find_in_flight(prop.proposal_id) {
+            return Ok(ProposedChannel::new(
+                self,
+                part_idx,
+                withdraw_receiver,
+                prop,
+            ));
+        }
 
         // ProposedChannel::new cannot fail (panic or return an Error).
         // Therefore it does not make a difference weather we first create the
@@ -91,26 +649,243 @@ impl<B: MessageBus> PerunClient<B> {
         // back from the ProposedChannel.
         let msg = ParticipantMessage::ChannelProposal(prop.clone());
         self.bus.broadcast_to_participants(0, &prop.peers, msg);
+        self.remember_in_flight(0, withdraw_receiver, prop.clone())?;
         Ok(ProposedChannel::new(self, 0, withdraw_receiver, prop))
     }
 
     /// Call this when receiving a proposal message, then call `accept()` or
     /// `reject()` to send the response.
+    ///
+    /// A retransmitted proposal carrying an already-seen
+    /// [LedgerChannelProposal::proposal_id] is answered with the previously
+    /// returned handle instead of creating a second [ProposedChannel] for it.
+    ///
+    /// If `prop` instead collides with one of our own still in-flight
+    /// [propose_channel][Self::propose_channel] calls to the same peers -
+    /// both sides proposed a channel to each other at nearly the same time -
+    /// this resolves the collision (inspired by multistream-select's
+    /// sim-open extension) instead of handing back a second, conflicting
+    /// [ProposedChannel]: the larger [LedgerChannelProposal::proposal_id]
+    /// (compared byte-for-byte) keeps the initiator role. If we lose, our
+    /// own proposal is dropped and this returns a [ProposedChannel] for
+    /// `prop` instead, ready to [ProposedChannel::accept]. If we win,
+    /// `prop` is discarded and this returns
+    /// [InvalidProposal::WonProposalCollision] - our original
+    /// [ProposedChannel] is still the one to use. See
+    /// [InvalidProposal::SimultaneousProposalMismatch]/
+    /// [InvalidProposal::AmbiguousProposalCollision] for the two ways this
+    /// can fail outright instead of picking a winner.
     pub fn handle_proposal(
         &self,
         prop: LedgerChannelProposal,
         withdraw_receiver: Address,
-    ) -> Result<ProposedChannel<B>, InvalidProposal> {
+    ) -> Result<ProposedChannel<B, S>, InvalidProposal> {
         // For sub-channels and virtual-channels, go-perun additionaly checks if
         // the parent channel exists and locks its context until the channel is
         // funded. See propose_channel for details.
 
         // Self::check_valid_proposal(&prop)?;
+        if let Some(peer) = prop.peers.first() {
+            self.require_authenticated(peer)?;
+        }
+
+        if let Some((part_idx, withdraw_receiver, prop)) = self.find_in_flight(prop.proposal_id) {
+            return Ok(ProposedChannel::new(
+                self,
+                part_idx,
+                withdraw_receiver,
+                prop,
+            ));
+        }
+
+        if let Some(ours) = self.find_colliding_own_proposal(&prop) {
+            return self.resolve_proposal_collision(ours, prop, withdraw_receiver);
+        }
 
         // Hard-coding the participant index means only 2-participant channels
         // are possible (which is also the case in go-perun and more channels
         // currently require changing some constants in go-perun, so this isn't
         // a big deal for now).
+        self.remember_in_flight(1, withdraw_receiver, prop.clone())?;
         Ok(ProposedChannel::new(self, 1, withdraw_receiver, prop))
     }
+
+    /// Returns our own still in-flight [propose_channel][Self::propose_channel]
+    /// proposal addressed to the same peers as `incoming`, if any - the
+    /// signal that `incoming` is a simultaneous-open collision rather than a
+    /// fresh proposal from someone else. Compares peers as a set rather than
+    /// in order, since each side numbers itself as participant 0 in its own
+    /// [LedgerChannelProposal::peers], so the two proposals list the same
+    /// identities in reversed order.
+    fn find_colliding_own_proposal(
+        &self,
+        incoming: &LedgerChannelProposal,
+    ) -> Option<LedgerChannelProposal> {
+        self.in_flight_proposals
+            .borrow()
+            .iter()
+            .flatten()
+            .find(|p| {
+                p.part_idx == 0
+                    && p.proposal.proposal_id != incoming.proposal_id
+                    && p.proposal.peers.len() == incoming.peers.len()
+                    && p.proposal
+                        .peers
+                        .iter()
+                        .all(|id| incoming.peers.contains(id))
+            })
+            .map(|p| p.proposal.clone())
+    }
+
+    /// Elects a single initiator for a simultaneous-open collision between
+    /// `ours` (our own still in-flight [propose_channel][Self::propose_channel]
+    /// call) and `incoming` (the peer's [LedgerChannelProposal] that just
+    /// arrived for the same peers), see [handle_proposal][Self::handle_proposal]'s
+    /// docs for the overall resolution this is part of.
+    fn resolve_proposal_collision(
+        &self,
+        ours: LedgerChannelProposal,
+        incoming: LedgerChannelProposal,
+        withdraw_receiver: Address,
+    ) -> Result<ProposedChannel<B, S>, InvalidProposal> {
+        if ours.proposal_id.0 == incoming.proposal_id.0 {
+            self.forget_in_flight(ours.proposal_id);
+            return Err(InvalidProposal::AmbiguousProposalCollision);
+        }
+
+        // Both sides must derive identical `Params` from whichever proposal
+        // wins, and agree on what's actually being funded/governed, so the
+        // two can only collide (rather than be rejected outright) if they
+        // agree on everything besides the proposal-specific `proposal_id`/
+        // `nonce_share`/`participant`/`peers` fields - in particular,
+        // `funding_agreement` (each side's on-chain deposit split) and
+        // `app`/`init_data` (which on-chain app contract governs the channel
+        // and its initial data) must match too, or one side's choice would
+        // silently be overridden by whichever `proposal_id` happens to win
+        // below. Compared via `abiencode::to_hash` (same idiom as
+        // `ActiveChannel::resolve_pending_update`'s state comparison) rather
+        // than deriving `PartialEq`, since neither `Allocation` nor `Asset`
+        // implement it.
+        let ours_terms = abiencode::to_hash(&(
+            ours.challenge_duration,
+            &ours.init_bals,
+            &ours.funding_agreement,
+            ours.app,
+            &ours.init_data,
+        ))
+        .ok();
+        let incoming_terms = abiencode::to_hash(&(
+            incoming.challenge_duration,
+            &incoming.init_bals,
+            &incoming.funding_agreement,
+            incoming.app,
+            &incoming.init_data,
+        ))
+        .ok();
+        if ours_terms != incoming_terms {
+            return Err(InvalidProposal::SimultaneousProposalMismatch);
+        }
+
+        if incoming.proposal_id.0 > ours.proposal_id.0 {
+            self.forget_in_flight(ours.proposal_id);
+            self.remember_in_flight(1, withdraw_receiver, incoming.clone())?;
+            Ok(ProposedChannel::new(self, 1, withdraw_receiver, incoming))
+        } else {
+            Err(InvalidProposal::WonProposalCollision)
+        }
+    }
+}
+
+/// Hashes `nonce` together with both participants' [Identity]s, so a signed
+/// challenge response is bound to this specific pair and direction and can't
+/// be replayed against the other participant or reflected back at its
+/// sender. Keccak256 to match the rest of [crate::sig]'s Ethereum-style
+/// hashing.
+fn handshake_hash(nonce: Hash, responder: &Identity, initiator: &Identity) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update(nonce.0);
+    hasher.update(responder);
+    hasher.update(initiator);
+    Hash(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::fixed_size_payment::{
+        Allocation, Balances, ParticipantBalances, ProtocolVersion,
+    };
+    use crate::channel::Asset;
+    use crate::test_support::{NullBus, StubSigner};
+
+    const PROPOSER: Address = Address([0x01; 20]);
+    const ACCEPTOR: Address = Address([0x02; 20]);
+
+    fn client() -> PerunClient<NullBus, StubSigner> {
+        PerunClient::new(NullBus, StubSigner(ACCEPTOR), 1)
+    }
+
+    /// Builds a proposal that collides with another one built from the same
+    /// `amount` - only `proposal_id`/`funding_agreement` are meant to be
+    /// varied by callers, matching the fields
+    /// [resolve_proposal_collision][PerunClient::resolve_proposal_collision]
+    /// does/doesn't require to match across colliding proposals.
+    fn proposal(proposal_id: Hash, amount: u64) -> LedgerChannelProposal {
+        let balances = Balances::<1, 2>([ParticipantBalances([amount.into(), amount.into()])]);
+        LedgerChannelProposal {
+            proposal_id,
+            challenge_duration: 1,
+            nonce_share: Hash([0x01; 32]),
+            init_bals: Allocation::<1, 2>::new(
+                [Asset {
+                    chain_id: 1u64.into(),
+                    holder: PROPOSER,
+                }],
+                balances,
+            ),
+            funding_agreement: balances,
+            participant: PROPOSER,
+            peers: alloc::vec![alloc::vec![0], alloc::vec![1]],
+            protocol_version: ProtocolVersion::CURRENT,
+            app: Address([0u8; 20]),
+            init_data: alloc::vec![],
+        }
+    }
+
+    #[test]
+    fn differing_funding_agreement_is_rejected() {
+        let client = client();
+        let ours = proposal(Hash([0x01; 32]), 10);
+        let mut incoming = proposal(Hash([0x02; 32]), 10);
+        incoming.funding_agreement = Balances([ParticipantBalances([5u64.into(), 15u64.into()])]);
+
+        assert!(matches!(
+            client.resolve_proposal_collision(ours, incoming, ACCEPTOR),
+            Err(InvalidProposal::SimultaneousProposalMismatch)
+        ));
+    }
+
+    #[test]
+    fn matching_terms_collide_and_the_higher_proposal_id_wins() {
+        let client = client();
+        let ours = proposal(Hash([0x01; 32]), 10);
+        let incoming = proposal(Hash([0x02; 32]), 10);
+
+        let won = client
+            .resolve_proposal_collision(ours, incoming.clone(), ACCEPTOR)
+            .unwrap();
+        assert_eq!(won.proposal_id(), incoming.proposal_id);
+    }
+
+    #[test]
+    fn identical_proposal_ids_are_ambiguous() {
+        let client = client();
+        let ours = proposal(Hash([0x01; 32]), 10);
+        let incoming = proposal(Hash([0x01; 32]), 20);
+
+        assert!(matches!(
+            client.resolve_proposal_collision(ours, incoming, ACCEPTOR),
+            Err(InvalidProposal::AmbiguousProposalCollision)
+        ));
+    }
 }
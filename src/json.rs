@@ -0,0 +1,356 @@
+//! Hex/decimal string serde helpers shared by the JSON DTOs in
+//! [crate::messages] and [crate::channel::fixed_size_payment]. Those DTOs
+//! exist so the same internal structs used for protobuf/ABI on the wire can
+//! also round-trip through a human-readable JSON mirror, for debugging,
+//! logging, and a future REST/monitoring endpoint.
+//!
+//! `hex_wrapper!` already takes the wrapped type and its byte length as
+//! parameters, so it isn't limited to [Hash]/[Address]/[Signature] - a fixed
+//! DTO field for any other `BytesN`-shaped type (see
+//! [crate::abiencode::types]) gets the same `0x`-prefixed hex mirror by
+//! adding one more `hex_wrapper!` invocation below, without writing a new
+//! `Serialize`/`Deserialize` pair by hand. [HexU256] is this module's
+//! equivalent for the numeric [U256] family, alongside the existing
+//! [DecU256] - same value, minimal-hex instead of decimal digits.
+
+// When using no_std, enable the alloc crate
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::{format, string::String, vec::Vec};
+
+use core::marker::PhantomData;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::abiencode::types::{Address, Hash, Signature, U256};
+
+fn encode_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(2 + bytes.len() * 2);
+    s.push_str("0x");
+    for b in bytes {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
+fn decode_hex<const N: usize>(s: &str) -> Result<[u8; N], String> {
+    let digits = s
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("hex string {:?} is missing its 0x prefix", s))?;
+    if digits.len() != N * 2 {
+        return Err(format!(
+            "expected {} hex digits, got {}",
+            N * 2,
+            digits.len()
+        ));
+    }
+    let mut out = [0u8; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16)
+            .map_err(|e| format!("invalid hex digit: {}", e))?;
+    }
+    Ok(out)
+}
+
+/// Same decoding as [decode_hex], but for a string whose length isn't known
+/// up front - [Encoding::decode] implementations use this and leave the
+/// length check to [HexStr]'s caller, who knows which concrete [FixedBytes]
+/// type it's decoding into.
+fn decode_hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("hex string {:?} is missing its 0x prefix", s))?;
+    if digits.len() % 2 != 0 {
+        return Err(format!("hex string {:?} has an odd number of digits", s));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| format!("invalid hex digit: {e}"))
+        })
+        .collect()
+}
+
+macro_rules! hex_wrapper {
+    ($(#[$doc:meta])* $Wrapper:ident, $Inner:ident, $N:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $Wrapper(pub $Inner);
+
+        impl Serialize for $Wrapper {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&encode_hex(&self.0 .0))
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $Wrapper {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                decode_hex::<$N>(&s)
+                    .map($Inner)
+                    .map(Self)
+                    .map_err(D::Error::custom)
+            }
+        }
+
+        impl From<$Inner> for $Wrapper {
+            fn from(value: $Inner) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$Wrapper> for $Inner {
+            fn from(value: $Wrapper) -> Self {
+                value.0
+            }
+        }
+    };
+}
+
+hex_wrapper!(
+    /// `0x`-prefixed lowercase hex JSON mirror of [Hash], with strict
+    /// length validation on parse.
+    HexHash, Hash, 32
+);
+hex_wrapper!(
+    /// `0x`-prefixed lowercase hex JSON mirror of [Address], with strict
+    /// length validation on parse.
+    HexAddress, Address, 20
+);
+hex_wrapper!(
+    /// `0x`-prefixed lowercase hex JSON mirror of [Signature], with strict
+    /// length validation on parse.
+    HexSignature, Signature, 65
+);
+
+/// Decimal-string JSON mirror of [U256]: unlike [Hash]/[Address]/[Signature]
+/// this is a number rather than an opaque byte string, but it still can't be
+/// a plain JSON number, since those can't losslessly hold the full 256-bit
+/// range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecU256(pub U256);
+
+impl Serialize for DecU256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for DecU256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        U256::from_dec_str(&s)
+            .map(Self)
+            .map_err(|_| D::Error::custom(format!("{:?} is not a valid decimal u256", s)))
+    }
+}
+
+impl From<U256> for DecU256 {
+    fn from(value: U256) -> Self {
+        Self(value)
+    }
+}
+
+impl From<DecU256> for U256 {
+    fn from(value: DecU256) -> Self {
+        value.0
+    }
+}
+
+/// `0x`-prefixed minimal-hex JSON mirror of [U256], e.g. the `QUANTITY`
+/// encoding `eth_*` JSON-RPC methods use for numeric fields: no leading zero
+/// digits (`"0x0"` for zero, never `"0x00"`). Parsing is permissive and also
+/// accepts a bare decimal string, so a [HexU256] field reads either an
+/// `eth_*`-style response or a [DecU256]-style one without the caller having
+/// to know up front which it's holding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexU256(pub U256);
+
+impl Serialize for HexU256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.is_zero() {
+            return serializer.serialize_str("0x0");
+        }
+        let mut bytes = [0u8; 32];
+        self.0.to_big_endian(&mut bytes);
+        let digits = encode_hex(&bytes);
+        // encode_hex always emits the full 64 digits; strip the "0x" prefix
+        // it added back off before trimming leading zero digits, then add it
+        // back - `self.0.is_zero()` above already ruled out "all digits are
+        // zero", so at least one non-zero digit survives the trim.
+        let trimmed = digits[2..].trim_start_matches('0');
+        serializer.serialize_str(&format!("0x{}", trimmed))
+    }
+}
+
+impl<'de> Deserialize<'de> for HexU256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match s.strip_prefix("0x") {
+            Some(digits) if !digits.is_empty() => U256::from_str_radix(digits, 16)
+                .map(Self)
+                .map_err(|_| D::Error::custom(format!("{:?} is not a valid hex quantity", s))),
+            Some(_) => Err(D::Error::custom("hex quantity is missing its digits")),
+            // Permissive fallback: also accept a bare decimal string, so this
+            // type can read a [DecU256]-encoded value without the caller
+            // having to know in advance which style produced it.
+            None => U256::from_dec_str(&s)
+                .map(Self)
+                .map_err(|_| D::Error::custom(format!("{:?} is not a valid u256", s))),
+        }
+    }
+}
+
+impl From<U256> for HexU256 {
+    fn from(value: U256) -> Self {
+        Self(value)
+    }
+}
+
+impl From<HexU256> for U256 {
+    fn from(value: HexU256) -> Self {
+        value.0
+    }
+}
+
+/// Raw fixed-size byte view shared by [Hash]/[Address]/[Signature] (and, by
+/// the same one-line `impl_fixed_bytes!` invocation, any `BytesN` type from
+/// [crate::abiencode::types] that later needs a [HexStr] mirror). [HexStr]
+/// is generic over this instead of being a dedicated wrapper struct per
+/// concrete type the way [HexHash]/[HexAddress]/[HexSignature] are - so a
+/// new [Encoding] doesn't need its own `HexFooUpper`/`HexFooBase64`/...
+/// struct for every byte-backed type it's used with.
+pub trait FixedBytes: Copy {
+    fn as_slice(&self) -> &[u8];
+    fn from_slice(bytes: &[u8]) -> Option<Self>
+    where
+        Self: Sized;
+}
+
+macro_rules! impl_fixed_bytes {
+    ($T:ident, $N:literal) => {
+        impl FixedBytes for $T {
+            fn as_slice(&self) -> &[u8] {
+                &self.0
+            }
+
+            fn from_slice(bytes: &[u8]) -> Option<Self> {
+                <[u8; $N]>::try_from(bytes).ok().map($T)
+            }
+        }
+    };
+}
+
+impl_fixed_bytes!(Hash, 32);
+impl_fixed_bytes!(Address, 20);
+impl_fixed_bytes!(Signature, 65);
+
+/// A string encoding [HexStr] can be parameterized over. `encode`/`decode`
+/// round-trip raw bytes through whatever textual form this encoding uses -
+/// [LowerHex]/[UpperHex] today, with room for e.g. a base64 encoding later
+/// without changing [HexStr] itself.
+pub trait Encoding {
+    fn encode(bytes: &[u8]) -> String;
+    fn decode(s: &str) -> Result<Vec<u8>, String>;
+}
+
+/// Default [Encoding]: the same `0x`-prefixed lowercase hex
+/// [HexHash]/[HexAddress]/[HexSignature] already use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LowerHex;
+
+impl Encoding for LowerHex {
+    fn encode(bytes: &[u8]) -> String {
+        encode_hex(bytes)
+    }
+
+    fn decode(s: &str) -> Result<Vec<u8>, String> {
+        decode_hex_bytes(s)
+    }
+}
+
+/// Like [LowerHex], but encodes with uppercase hex digits. Decoding accepts
+/// either case, same as [LowerHex]'s, since `u8::from_str_radix` already
+/// does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpperHex;
+
+impl Encoding for UpperHex {
+    fn encode(bytes: &[u8]) -> String {
+        let mut s = String::with_capacity(2 + bytes.len() * 2);
+        s.push_str("0x");
+        for b in bytes {
+            s.push_str(&format!("{:02X}", b));
+        }
+        s
+    }
+
+    fn decode(s: &str) -> Result<Vec<u8>, String> {
+        decode_hex_bytes(s)
+    }
+}
+
+/// Generic string JSON mirror of any [FixedBytes] type (so, unlike
+/// [HexHash]/[HexAddress]/[HexSignature], one type serves the whole
+/// `BytesN`/[Hash]/[Address]/[Signature] family), parameterized by the
+/// [Encoding] to use - [LowerHex] by default. Decoding validates the decoded
+/// byte count against the wrapped type's own fixed length via
+/// [FixedBytes::from_slice].
+///
+/// This crate's own ABI [Serializer][crate::abiencode::Serializer] doesn't
+/// distinguish a human-readable mode from a binary one the way
+/// `serde_json`/`bincode` do (see [crate::json]'s module docs) - reusing the
+/// same `ChannelUpdate`/DTO struct for both forms by branching on
+/// `Serializer::is_human_readable` isn't something this crate can rely on
+/// today. [HexStr] therefore plays the same role [HexHash]/[HexAddress]/
+/// [HexSignature]/[HexU256] already do: a field type for the *separate*
+/// JSON-mirror DTO, not a drop-in replacement for the ABI-encoded field
+/// itself.
+#[derive(Clone, Copy)]
+pub struct HexStr<T, Enc = LowerHex>(pub T, PhantomData<Enc>);
+
+impl<T, Enc> HexStr<T, Enc> {
+    pub fn new(value: T) -> Self {
+        Self(value, PhantomData)
+    }
+}
+
+impl<T: core::fmt::Debug, Enc> core::fmt::Debug for HexStr<T, Enc> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("HexStr").field(&self.0).finish()
+    }
+}
+
+impl<T: PartialEq, Enc> PartialEq for HexStr<T, Enc> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq, Enc> Eq for HexStr<T, Enc> {}
+
+impl<T: FixedBytes, Enc: Encoding> Serialize for HexStr<T, Enc> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&Enc::encode(self.0.as_slice()))
+    }
+}
+
+impl<'de, T: FixedBytes, Enc: Encoding> Deserialize<'de> for HexStr<T, Enc> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = Enc::decode(&s).map_err(D::Error::custom)?;
+        T::from_slice(&bytes)
+            .map(Self::new)
+            .ok_or_else(|| D::Error::custom(format!("{:?} is the wrong length", s)))
+    }
+}
+
+impl<T, Enc> From<T> for HexStr<T, Enc> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
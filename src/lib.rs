@@ -1,32 +1,52 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-mod abiencode {
+// TODO: This probably shouldn't be public, but `fuzz/` needs it to drive the
+// serializer directly instead of just through whatever happens to embed it.
+pub mod abiencode {
+    mod de;
     mod error;
     mod hashing;
+    mod packed;
     mod ser;
 
     pub mod as_bytes;
     pub mod as_dyn_array;
+    pub mod as_i256;
+    pub mod as_u256;
     pub mod types;
 
-    pub use error::{Error, Result};
-    pub use hashing::to_hash;
-    pub use ser::{to_writer, Serializer, Writer};
+    pub use de::{from_reader, from_slice, Deserializer, Reader};
+    #[cfg(feature = "std")]
+    pub use de::IoReader;
+    pub use error::{Error, ErrorKind, PathSegment, Result};
+    pub use hashing::{to_hash, Digest, DigestWriter};
+    pub use packed::{to_packed_vec, to_packed_writer, PackedSerializer};
+    pub use ser::{
+        encode_with_selector, encoded_size, serialize_into, to_vec, to_writer,
+        to_writer_with_config, MapSerializer, Serializer, Writer,
+    };
+    #[cfg(feature = "std")]
+    pub use ser::{to_io_writer, IoWriter};
 
     #[cfg(test)]
     pub mod tests;
 }
 pub mod sig;
+pub mod nonce;
 
 pub mod channel;
 mod client;
+pub mod json;
+#[cfg(test)]
+mod test_support;
 pub mod wire;
 
 pub use abiencode::types::{Address, Hash};
-pub use client::PerunClient;
+pub use client::{ChannelFeatures, PerunClient};
 
-// TODO: This probably shouldn't be public, but the example currently needs it,
-// since the encoding layer doesn't do decoding, yet.
+// TODO: This probably shouldn't be public, but the example currently needs it
+// to talk to the raw `perunwire`-framed transport directly instead of through
+// a `MessageBus`/`BytesBus` impl of its own.
 pub mod perunwire {
     // The message types are currently defined in two separate .proto files with
     // different package names. This makes sense (as of now), since they are
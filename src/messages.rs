@@ -1,23 +1,53 @@
 mod funding_request;
 mod proposal;
+mod shutdown;
+mod sync;
 mod update;
+mod virtual_channel;
 mod watch_request;
 
-pub use funding_request::{LedgerChannelFundingRequest, RegisterReq, WithdrawReq, AdjudicatorReq, Transaction};
+pub use funding_request::{
+    AdjudicatorReq, AdjudicatorReqDto, AdjudicatorReqG, LedgerChannelFundingRequest,
+    LedgerChannelFundingRequestDto, LedgerChannelFundingRequestG, RegisterReq, Transaction,
+    TransactionDto, TransactionG, WithdrawReq,
+};
 pub use proposal::{LedgerChannelProposal, LedgerChannelProposalAcc};
-pub use update::{LedgerChannelUpdate, LedgerChannelUpdateAccepted};
+pub use shutdown::Shutdown;
+pub use sync::ChannelSync;
+pub use update::{
+    LedgerChannelUpdate, LedgerChannelUpdateAccepted, LedgerChannelUpdateAcceptedDto,
+    LedgerChannelUpdateDto, LedgerChannelUpdateG,
+};
+pub use virtual_channel::{
+    VirtualChannelFundingProposal, VirtualChannelProposal, VirtualChannelProposalAcc,
+    VirtualChannelSettlementProposal,
+};
 pub use watch_request::{SignedWithdrawalAuth, WatchInfo, StartWatchingLedgerChannelReq};
 
-use crate::abiencode::types::Hash;
+use crate::abiencode::types::{Hash, Signature};
 use alloc::string::String;
 
 #[derive(Debug)]
 pub enum ConversionError {
     ParticipantSizeMissmatch,
     AssetSizeMissmatch,
+    /// The number of sub-allocations (locked funds) in a wire/DTO
+    /// [Allocation](crate::channel::fixed_size_payment::Allocation) does not
+    /// match the `L` this device expects for that channel.
+    SubAllocSizeMissmatch,
     ByteLengthMissmatch,
     ExptectedSome,
     StateChannelsNotSupported,
+    /// An [AdjudicatorReqG](crate::messages::AdjudicatorReqG) (or anything
+    /// built from it) carried a [Params::chain_id](crate::channel::fixed_size_payment::Params::chain_id)
+    /// that doesn't match the chain this device is configured for, see
+    /// [PerunClient::chain_id](crate::PerunClient::chain_id).
+    ChainIdMismatch,
+    /// A `perunwire` conversion was asked to speak a
+    /// [ProtocolVersion](crate::channel::fixed_size_payment::ProtocolVersion)
+    /// other than [ProtocolVersion::CURRENT](crate::channel::fixed_size_payment::ProtocolVersion::CURRENT),
+    /// which this build doesn't know how to produce or fully understand.
+    UnsupportedProtocolVersion,
 }
 
 /// Messages sent to the Watcher service.
@@ -41,8 +71,10 @@ pub enum WatcherReplyMessage {
     DisputeAck { id: Hash },
     /// Used by the Watcher to notify the device of the existence of an on-chain
     /// dispute. This way the device knows that it does not/should not continue
-    /// updating the channel.
-    DisputeNotification { id: Hash },
+    /// updating the channel. `version` is the version of the state that was
+    /// registered, so [crate::channel::ActiveChannel::handle_dispute] can tell
+    /// whether it needs to refute with a newer, already-signed state.
+    DisputeNotification { id: Hash, version: u64 },
 }
 
 /// Messages sent to the Funder service.
@@ -62,7 +94,27 @@ pub enum FunderReplyMessage {
 /// Messages sent between participants of a channel.
 #[derive(Debug, Clone)]
 pub enum ParticipantMessage {
-    Auth,
+    /// Start of the mutual handshake: a fresh nonce the recipient must sign
+    /// over (together with both participants' [crate::wire::Identity]s) and
+    /// return in [ParticipantMessage::AuthResponse], to prove it controls the
+    /// [crate::Address] it claims before any [ParticipantMessage::ChannelProposal]
+    /// is trusted. See [crate::PerunClient::send_handshake_msg].
+    AuthChallenge(Hash),
+    /// Reply to [ParticipantMessage::AuthChallenge], signing over the nonce
+    /// and both participants' identities, and advertising the sender's
+    /// [crate::client::ChannelFeatures]. See
+    /// [crate::PerunClient::handle_auth_challenge].
+    AuthResponse {
+        nonce: Hash,
+        sig: Signature,
+        features: crate::client::ChannelFeatures,
+    },
+    /// Keepalive probe, answered with [ParticipantMessage::Pong]. See
+    /// [crate::PerunClient::send_ping].
+    Ping,
+    /// Reply to [ParticipantMessage::Ping]. See
+    /// [crate::PerunClient::send_pong].
+    Pong,
     ChannelProposal(LedgerChannelProposal),
     ProposalAccepted(LedgerChannelProposalAcc),
     ProposalRejected {
@@ -76,4 +128,17 @@ pub enum ParticipantMessage {
         version: u64,
         reason: String,
     },
+    ChannelSync(ChannelSync),
+    /// Mutual agreement to settle the channel on-chain at its current
+    /// (already-final) state, bypassing the Watcher's dispute process.
+    Shutdown(Shutdown),
+    // The intermediary role (locking a matching balance in each adjacent
+    // ledger channel, running the virtual channel off-chain, and unwinding
+    // the locks again on settlement/dispute) is not implemented yet - these
+    // variants only let a participant/intermediary decode and reject such a
+    // message cleanly instead of erroring out on an unknown wire message.
+    VirtualChannelProposal(VirtualChannelProposal),
+    VirtualChannelProposalAccepted(VirtualChannelProposalAcc),
+    VirtualChannelFundingProposal(VirtualChannelFundingProposal),
+    VirtualChannelSettlementProposal(VirtualChannelSettlementProposal),
 }
@@ -1,31 +1,47 @@
 use super::ConversionError;
 use crate::{
     channel::{fixed_size_payment, PartIdx},
-    abiencode::types::{Address, Signature},
+    abiencode::types::{Address, Signature, U256},
+    json::{HexAddress, HexSignature},
     perunwire,
 };
+use serde::{Deserialize, Serialize};
 
 // When using no_std, enable the alloc crate
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 #[cfg(not(feature = "std"))]
-use alloc::string::String; // Use String from alloc crate
+use alloc::{string::String, vec::Vec}; // Use String/Vec from alloc crate
 
 #[cfg(feature = "std")]
-use std::string::String; // Use String from std library
+use std::{string::String, vec::Vec}; // Use String/Vec from std library
 
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
-type Params = fixed_size_payment::Params<PARTICIPANTS>;
-type Balances = fixed_size_payment::Balances<ASSETS, PARTICIPANTS>;
+// One reserved sub-allocation slot, see `channel::active::LOCKED`.
+const LOCKED: usize = 1;
 
+/// The 1-asset/2-participant instantiation used throughout the rest of this
+/// crate; see [LedgerChannelFundingRequest] itself for the generic version.
+pub type LedgerChannelFundingRequest = LedgerChannelFundingRequestG<ASSETS, PARTICIPANTS, LOCKED>;
+/// The 1-asset/2-participant instantiation used throughout the rest of this
+/// crate; see [Transaction] itself for the generic version.
+pub type Transaction = TransactionG<ASSETS, PARTICIPANTS, LOCKED>;
+/// The 1-asset/2-participant instantiation used throughout the rest of this
+/// crate; see [AdjudicatorReq] itself for the generic version.
+pub type AdjudicatorReq = AdjudicatorReqG<ASSETS, PARTICIPANTS, LOCKED>;
+
+/// Generic over the channel's asset/participant/locked-sub-allocation count
+/// (`A`/`P`/`L`) so the same conversion works for any
+/// `fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>`; see
+/// [LedgerChannelFundingRequest] for the 1-asset/2-participant instantiation
+/// used throughout the rest of this crate.
 #[derive(Debug, Clone, Copy)]
-pub struct LedgerChannelFundingRequest {
+pub struct LedgerChannelFundingRequestG<const A: usize, const P: usize, const L: usize = 0> {
     pub part_idx: PartIdx,
-    pub funding_agreement: Balances,
-    pub params: Params,
-    pub state: State,
+    pub funding_agreement: fixed_size_payment::Balances<A, P>,
+    pub params: fixed_size_payment::Params<P>,
+    pub state: fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -38,22 +54,30 @@ pub struct WithdrawReq {
     pub adj_req: AdjudicatorReq,
 }
 
+/// Generic over the channel's asset/participant/locked-sub-allocation count
+/// (`A`/`P`/`L`); see [Transaction] for the 1-asset/2-participant
+/// instantiation used throughout the rest of this crate.
 #[derive(Debug, Clone, Copy)]
-pub struct Transaction {
-    pub state: State,
-    pub sigs: [Signature; PARTICIPANTS],
+pub struct TransactionG<const A: usize, const P: usize, const L: usize = 0> {
+    pub state: fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>,
+    pub sigs: [Signature; P],
 }
 
+/// Generic over the channel's asset/participant/locked-sub-allocation count
+/// (`A`/`P`/`L`); see [AdjudicatorReq] for the 1-asset/2-participant
+/// instantiation used throughout the rest of this crate.
 #[derive(Debug, Clone, Copy)]
-pub struct AdjudicatorReq {
-	pub params:    Params,
+pub struct AdjudicatorReqG<const A: usize, const P: usize, const L: usize = 0> {
+	pub params:    fixed_size_payment::Params<P>,
 	pub acc:       Address,
-	pub tx:        Transaction,
+	pub tx:        TransactionG<A, P, L>,
 	pub idx:       PartIdx,
 	pub secondary: bool,
 }
 
-impl TryFrom<perunwire::FundReq> for LedgerChannelFundingRequest {
+impl<const A: usize, const P: usize, const L: usize> TryFrom<perunwire::FundReq>
+    for LedgerChannelFundingRequestG<A, P, L>
+{
     type Error = ConversionError;
 
     fn try_from(value: perunwire::FundReq) -> Result<Self, Self::Error> {
@@ -75,8 +99,10 @@ impl TryFrom<perunwire::FundReq> for LedgerChannelFundingRequest {
     }
 }
 
-impl From<LedgerChannelFundingRequest> for perunwire::FundReq {
-    fn from(value: LedgerChannelFundingRequest) -> Self {
+impl<const A: usize, const P: usize, const L: usize> From<LedgerChannelFundingRequestG<A, P, L>>
+    for perunwire::FundReq
+{
+    fn from(value: LedgerChannelFundingRequestG<A, P, L>) -> Self {
         Self {
             session_id: String::from(""),
             agreement: Some(value.funding_agreement.into()),
@@ -131,16 +157,16 @@ impl From<WithdrawReq> for perunwire::WithdrawReq {
     }
 }
 
-impl TryFrom<perunwire::Transaction> for Transaction {
+impl<const A: usize, const P: usize, const L: usize> TryFrom<perunwire::Transaction> for TransactionG<A, P, L> {
     type Error = ConversionError;
 
     fn try_from(value: perunwire::Transaction) -> Result<Self, Self::Error> {
         let signed_state = value.state.ok_or(ConversionError::ExptectedSome)?;
 
-        if value.sigs.len() != PARTICIPANTS {
+        if value.sigs.len() != P {
             return Err(ConversionError::ParticipantSizeMissmatch);
         }
-        let mut sigs = [Signature::default(); PARTICIPANTS];
+        let mut sigs = [Signature::default(); P];
 
         for (a, b) in sigs.iter_mut().zip(value.sigs) {
             *a = Signature(b.try_into().or(Err(ConversionError::ByteLengthMissmatch))?);
@@ -154,8 +180,8 @@ impl TryFrom<perunwire::Transaction> for Transaction {
     }
 }
 
-impl From<Transaction> for perunwire::Transaction {
-    fn from(value: Transaction) -> Self {
+impl<const A: usize, const P: usize, const L: usize> From<TransactionG<A, P, L>> for perunwire::Transaction {
+    fn from(value: TransactionG<A, P, L>) -> Self {
         Self {
             state: Some(value.state.into()),
             sigs: value.sigs.map(|sig| sig.0.to_vec()).to_vec(),
@@ -163,8 +189,45 @@ impl From<Transaction> for perunwire::Transaction {
     }
 }
 
+/// Human-readable JSON mirror of [TransactionG]; see
+/// [fixed_size_payment::ParamsDto] for why [HexSignature] gets a hex string.
+/// Like [fixed_size_payment::StateDto], only defined for `L == 0`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionDto {
+    pub state: fixed_size_payment::StateDto,
+    pub sigs: Vec<HexSignature>,
+}
+
+impl<const A: usize, const P: usize> From<TransactionG<A, P>> for TransactionDto {
+    fn from(value: TransactionG<A, P>) -> Self {
+        Self {
+            state: value.state.into(),
+            sigs: value.sigs.iter().map(|&sig| sig.into()).collect(),
+        }
+    }
+}
+
+impl<const A: usize, const P: usize> TryFrom<TransactionDto> for TransactionG<A, P> {
+    type Error = ConversionError;
+
+    fn try_from(value: TransactionDto) -> Result<Self, Self::Error> {
+        if value.sigs.len() != P {
+            return Err(ConversionError::ParticipantSizeMissmatch);
+        }
+
+        let mut sigs = [Signature::default(); P];
+        for (a, dto) in sigs.iter_mut().zip(value.sigs) {
+            *a = dto.into();
+        }
+
+        Ok(Self {
+            state: value.state.try_into()?,
+            sigs,
+        })
+    }
+}
 
-impl TryFrom<perunwire::AdjudicatorReq> for AdjudicatorReq {
+impl<const A: usize, const P: usize, const L: usize> TryFrom<perunwire::AdjudicatorReq> for AdjudicatorReqG<A, P, L> {
     type Error = ConversionError;
     fn try_from(value: perunwire::AdjudicatorReq) -> Result<Self, Self::Error> {
         Ok(Self {
@@ -188,8 +251,26 @@ impl TryFrom<perunwire::AdjudicatorReq> for AdjudicatorReq {
     }
 }
 
-impl From<AdjudicatorReq> for perunwire::AdjudicatorReq {
-    fn from(value: AdjudicatorReq) -> Self {
+impl<const A: usize, const P: usize, const L: usize> AdjudicatorReqG<A, P, L> {
+    /// Like `TryFrom<perunwire::AdjudicatorReq>`, but additionally rejects a
+    /// request whose [Params::chain_id](fixed_size_payment::Params) doesn't
+    /// match `expected_chain_id` (this device's own
+    /// [PerunClient::chain_id](crate::PerunClient::chain_id)), so a request
+    /// meant for a different chain can't be mistaken for one on ours.
+    pub fn try_from_wire(
+        value: perunwire::AdjudicatorReq,
+        expected_chain_id: U256,
+    ) -> Result<Self, ConversionError> {
+        let req = Self::try_from(value)?;
+        if req.params.chain_id != expected_chain_id {
+            return Err(ConversionError::ChainIdMismatch);
+        }
+        Ok(req)
+    }
+}
+
+impl<const A: usize, const P: usize, const L: usize> From<AdjudicatorReqG<A, P, L>> for perunwire::AdjudicatorReq {
+    fn from(value: AdjudicatorReqG<A, P, L>) -> Self {
         Self {
             params: Some(value.params.into()),
             acc: value.acc.0.to_vec(),
@@ -199,3 +280,78 @@ impl From<AdjudicatorReq> for perunwire::AdjudicatorReq {
         }
     }
 }
+
+/// Human-readable JSON mirror of [AdjudicatorReqG]; see
+/// [fixed_size_payment::ParamsDto] for why [HexAddress] gets a hex string.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdjudicatorReqDto {
+    pub params: fixed_size_payment::ParamsDto,
+    pub acc: HexAddress,
+    pub tx: TransactionDto,
+    pub idx: PartIdx,
+    pub secondary: bool,
+}
+
+impl<const A: usize, const P: usize> From<AdjudicatorReqG<A, P>> for AdjudicatorReqDto {
+    fn from(value: AdjudicatorReqG<A, P>) -> Self {
+        Self {
+            params: value.params.into(),
+            acc: value.acc.into(),
+            tx: value.tx.into(),
+            idx: value.idx,
+            secondary: value.secondary,
+        }
+    }
+}
+
+impl<const A: usize, const P: usize> TryFrom<AdjudicatorReqDto> for AdjudicatorReqG<A, P> {
+    type Error = ConversionError;
+
+    fn try_from(value: AdjudicatorReqDto) -> Result<Self, Self::Error> {
+        Ok(Self {
+            params: value.params.try_into()?,
+            acc: value.acc.into(),
+            tx: value.tx.try_into()?,
+            idx: value.idx,
+            secondary: value.secondary,
+        })
+    }
+}
+
+/// Human-readable JSON mirror of [LedgerChannelFundingRequestG]; see
+/// [fixed_size_payment::ParamsDto].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LedgerChannelFundingRequestDto {
+    pub part_idx: PartIdx,
+    pub funding_agreement: fixed_size_payment::BalancesDto,
+    pub params: fixed_size_payment::ParamsDto,
+    pub state: fixed_size_payment::StateDto,
+}
+
+impl<const A: usize, const P: usize> From<LedgerChannelFundingRequestG<A, P>>
+    for LedgerChannelFundingRequestDto
+{
+    fn from(value: LedgerChannelFundingRequestG<A, P>) -> Self {
+        Self {
+            part_idx: value.part_idx,
+            funding_agreement: value.funding_agreement.into(),
+            params: value.params.into(),
+            state: value.state.into(),
+        }
+    }
+}
+
+impl<const A: usize, const P: usize> TryFrom<LedgerChannelFundingRequestDto>
+    for LedgerChannelFundingRequestG<A, P>
+{
+    type Error = ConversionError;
+
+    fn try_from(value: LedgerChannelFundingRequestDto) -> Result<Self, Self::Error> {
+        Ok(Self {
+            part_idx: value.part_idx,
+            funding_agreement: value.funding_agreement.try_into()?,
+            params: value.params.try_into()?,
+            state: value.state.try_into()?,
+        })
+    }
+}
@@ -3,42 +3,57 @@ use crate::{
     channel::{fixed_size_payment, NonceShare, Peers},
     messages::ConversionError,
     perunwire,
+    wire::DecodeError,
 };
-use alloc::vec;
+use alloc::vec::Vec;
 
-const ASSETS: usize = 1;
-const PARTICIPANTS: usize = 2;
-type Allocation = fixed_size_payment::Allocation<ASSETS, PARTICIPANTS>;
-type Balances = fixed_size_payment::Balances<ASSETS, PARTICIPANTS>;
-
-/// Channel configuration (also exchanged over the network)
+/// Channel configuration (also exchanged over the network).
+///
+/// Generic over the number of assets/participants like
+/// [fixed_size_payment], defaulting to the single-asset, two-party shape
+/// most of the crate still hardcodes - see
+/// [ProposedChannel](crate::channel::ProposedChannel) for the part of the
+/// API that actually makes use of other arities.
 #[derive(Debug, Clone)]
-pub struct LedgerChannelProposal {
+pub struct LedgerChannelProposal<const ASSETS: usize = 1, const PARTICIPANTS: usize = 2> {
     pub proposal_id: Hash,
     pub challenge_duration: u64,
     pub nonce_share: NonceShare,
-    pub init_bals: Allocation,
-    pub funding_agreement: Balances,
+    pub init_bals: fixed_size_payment::Allocation<ASSETS, PARTICIPANTS>,
+    pub funding_agreement: fixed_size_payment::Balances<ASSETS, PARTICIPANTS>,
     pub participant: Address,
     pub peers: Peers,
+    /// The `perunwire` dialect the proposer wants to speak for this
+    /// channel, see [fixed_size_payment::ProtocolVersion]. The channel's
+    /// other participants accept the proposal as-is or reject it - there is
+    /// currently no counter-negotiation.
+    pub protocol_version: fixed_size_payment::ProtocolVersion,
+    /// The on-chain app contract this channel's states are valid for, see
+    /// [fixed_size_payment::Params::app]/[fixed_size_payment::AppData::address].
+    /// The zero address (matching [fixed_size_payment::NoApp::address])
+    /// proposes a plain payment channel - see
+    /// [ProposedChannel::build](crate::channel::ProposedChannel::build) for
+    /// the only shape that can currently be built from an accepted proposal.
+    pub app: Address,
+    /// Opaque initial application data for [Self::app], see
+    /// [fixed_size_payment::AppData::bytes]. Empty for a plain payment
+    /// channel (matching [fixed_size_payment::NoApp::bytes]).
+    pub init_data: Vec<u8>,
 }
 
-impl TryFrom<perunwire::LedgerChannelProposalMsg> for LedgerChannelProposal {
-    type Error = ConversionError;
+impl<const ASSETS: usize, const PARTICIPANTS: usize> TryFrom<perunwire::LedgerChannelProposalMsg>
+    for LedgerChannelProposal<ASSETS, PARTICIPANTS>
+{
+    type Error = DecodeError;
 
     fn try_from(value: perunwire::LedgerChannelProposalMsg) -> Result<Self, Self::Error> {
-        let base = match value.base_channel_proposal {
-            Some(v) => v,
-            None => return Err(ConversionError::ExptectedSome),
-        };
-        let init_bals = match base.init_bals {
-            Some(v) => v,
-            None => return Err(ConversionError::ExptectedSome),
-        };
-        let funding_agreement = match base.funding_agreement {
-            Some(v) => v,
-            None => return Err(ConversionError::ExptectedSome),
-        };
+        let base = value
+            .base_channel_proposal
+            .ok_or(ConversionError::ExptectedSome)?;
+        let init_bals = base.init_bals.ok_or(ConversionError::ExptectedSome)?;
+        let funding_agreement = base
+            .funding_agreement
+            .ok_or(ConversionError::ExptectedSome)?;
 
         Ok(LedgerChannelProposal {
             proposal_id: Hash(
@@ -61,21 +76,31 @@ impl TryFrom<perunwire::LedgerChannelProposalMsg> for LedgerChannelProposal {
                     .or(Err(ConversionError::ByteLengthMissmatch))?,
             ),
             peers: value.peers,
+            protocol_version: fixed_size_payment::ProtocolVersion(base.protocol_version),
+            app: Address(
+                base.app
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            init_data: base.init_data,
         })
     }
 }
 
-impl From<LedgerChannelProposal> for perunwire::LedgerChannelProposalMsg {
-    fn from(value: LedgerChannelProposal) -> Self {
+impl<const ASSETS: usize, const PARTICIPANTS: usize>
+    From<LedgerChannelProposal<ASSETS, PARTICIPANTS>> for perunwire::LedgerChannelProposalMsg
+{
+    fn from(value: LedgerChannelProposal<ASSETS, PARTICIPANTS>) -> Self {
         Self {
             base_channel_proposal: Some(perunwire::BaseChannelProposal {
                 proposal_id: value.proposal_id.0.to_vec(),
                 challenge_duration: value.challenge_duration,
                 nonce_share: value.nonce_share.0.to_vec(),
-                app: vec![],
-                init_data: vec![],
+                app: value.app.0.to_vec(),
+                init_data: value.init_data,
                 init_bals: Some(value.init_bals.into()),
                 funding_agreement: Some(value.funding_agreement.into()),
+                protocol_version: value.protocol_version.0,
             }),
             participant: value.participant.0.to_vec(),
             peers: value.peers,
@@ -84,7 +109,7 @@ impl From<LedgerChannelProposal> for perunwire::LedgerChannelProposalMsg {
 }
 
 /// Message sent when a participant accepts the proposed channel.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LedgerChannelProposalAcc {
     pub proposal_id: Hash,
     pub nonce_share: NonceShare,
@@ -92,7 +117,7 @@ pub struct LedgerChannelProposalAcc {
 }
 
 impl TryFrom<perunwire::LedgerChannelProposalAccMsg> for LedgerChannelProposalAcc {
-    type Error = ConversionError;
+    type Error = DecodeError;
 
     fn try_from(value: perunwire::LedgerChannelProposalAccMsg) -> Result<Self, Self::Error> {
         let base = value
@@ -0,0 +1,37 @@
+use super::ConversionError;
+use crate::{abiencode::types::Hash, perunwire};
+
+/// A peer's agreement to settle the channel on-chain at its current state
+/// outside of the Watcher's dispute process, sent once both sides have
+/// exchanged signatures on a final (`is_final`) state via a normal
+/// [ChannelUpdate][super::LedgerChannelUpdate]. Analogous to Lightning's
+/// mutual-close `shutdown`/`closing_signed` exchange, minus the fee
+/// negotiation (this channel only supports settling at the already-agreed
+/// balances).
+#[derive(Debug, Clone, Copy)]
+pub struct Shutdown {
+    pub channel: Hash,
+}
+
+impl TryFrom<perunwire::ShutdownMsg> for Shutdown {
+    type Error = ConversionError;
+
+    fn try_from(value: perunwire::ShutdownMsg) -> Result<Self, Self::Error> {
+        Ok(Self {
+            channel: Hash(
+                value
+                    .channel_id
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+        })
+    }
+}
+
+impl From<Shutdown> for perunwire::ShutdownMsg {
+    fn from(value: Shutdown) -> Self {
+        Self {
+            channel_id: value.channel.0.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use super::ConversionError;
+use crate::{
+    abiencode::types::Signature,
+    channel::fixed_size_payment,
+    perunwire,
+};
+
+const ASSETS: usize = 1;
+const PARTICIPANTS: usize = 2;
+// One reserved sub-allocation slot, see `channel::active::LOCKED`.
+const LOCKED: usize = 1;
+type State = fixed_size_payment::State<fixed_size_payment::NoApp, ASSETS, PARTICIPANTS, LOCKED>;
+
+/// A peer's view of a channel after reconnecting: its highest known state and
+/// every participant's signature on it. Sent so both sides can tell whether
+/// they missed an update while disconnected and resolve it, the way
+/// [crate::channel::ActiveChannel::reestablish] does - analogous to
+/// go-perun's channel-sync handshake (itself modelled on Lightning's
+/// channel-reestablish).
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelSync {
+    pub state: State,
+    pub sigs: [Signature; PARTICIPANTS],
+}
+
+impl TryFrom<perunwire::ChannelSyncMsg> for ChannelSync {
+    type Error = ConversionError;
+
+    fn try_from(value: perunwire::ChannelSyncMsg) -> Result<Self, Self::Error> {
+        let tx = value.current_tx.ok_or(ConversionError::ExptectedSome)?;
+        let state: State = tx
+            .state
+            .ok_or(ConversionError::ExptectedSome)?
+            .try_into()?;
+
+        if tx.sigs.len() != PARTICIPANTS {
+            return Err(ConversionError::ParticipantSizeMissmatch);
+        }
+        let mut sigs = [Signature::default(); PARTICIPANTS];
+        for (i, sig) in tx.sigs.into_iter().enumerate() {
+            sigs[i] = Signature(sig.try_into().or(Err(ConversionError::ByteLengthMissmatch))?);
+        }
+
+        Ok(Self { state, sigs })
+    }
+}
+
+impl From<ChannelSync> for perunwire::ChannelSyncMsg {
+    fn from(value: ChannelSync) -> Self {
+        Self {
+            current_tx: Some(perunwire::Transaction {
+                state: Some(value.state.into()),
+                sigs: value.sigs.iter().map(|sig| sig.0.to_vec()).collect(),
+            }),
+        }
+    }
+}
@@ -2,22 +2,37 @@ use super::ConversionError;
 use crate::{
     abiencode::types::{Hash, Signature},
     channel::{fixed_size_payment, PartIdx},
+    json::{HexHash, HexSignature},
     perunwire,
+    wire::DecodeError,
 };
+use serde::{Deserialize, Serialize};
 
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
+// One reserved sub-allocation slot, see `channel::active::LOCKED`.
+const LOCKED: usize = 1;
 
+/// The 1-asset/2-participant instantiation used throughout the rest of this
+/// crate; see [LedgerChannelUpdateG] for the generic version.
+pub type LedgerChannelUpdate = LedgerChannelUpdateG<ASSETS, PARTICIPANTS, LOCKED>;
+
+/// Generic over the channel's asset/participant/locked-sub-allocation count
+/// (`A`/`P`/`L`) so the same conversion works for any
+/// `fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>`; see
+/// [LedgerChannelUpdate] for the 1-asset/2-participant instantiation used
+/// throughout the rest of this crate.
 #[derive(Debug, Clone, Copy)]
-pub struct LedgerChannelUpdate {
-    pub state: State,
+pub struct LedgerChannelUpdateG<const A: usize, const P: usize, const L: usize = 0> {
+    pub state: fixed_size_payment::State<fixed_size_payment::NoApp, A, P, L>,
     pub actor_idx: PartIdx,
     pub sig: Signature,
 }
 
-impl TryFrom<perunwire::ChannelUpdateMsg> for LedgerChannelUpdate {
-    type Error = ConversionError;
+impl<const A: usize, const P: usize, const L: usize> TryFrom<perunwire::ChannelUpdateMsg>
+    for LedgerChannelUpdateG<A, P, L>
+{
+    type Error = DecodeError;
 
     fn try_from(value: perunwire::ChannelUpdateMsg) -> Result<Self, Self::Error> {
         let update = value.channel_update.ok_or(ConversionError::ExptectedSome)?;
@@ -38,8 +53,10 @@ impl TryFrom<perunwire::ChannelUpdateMsg> for LedgerChannelUpdate {
     }
 }
 
-impl From<LedgerChannelUpdate> for perunwire::ChannelUpdateMsg {
-    fn from(value: LedgerChannelUpdate) -> Self {
+impl<const A: usize, const P: usize, const L: usize> From<LedgerChannelUpdateG<A, P, L>>
+    for perunwire::ChannelUpdateMsg
+{
+    fn from(value: LedgerChannelUpdateG<A, P, L>) -> Self {
         Self {
             channel_update: Some(perunwire::ChannelUpdate {
                 state: Some(value.state.into()),
@@ -50,6 +67,40 @@ impl From<LedgerChannelUpdate> for perunwire::ChannelUpdateMsg {
     }
 }
 
+/// Human-readable JSON mirror of [LedgerChannelUpdateG]; see
+/// [fixed_size_payment::ParamsDto] for why [HexSignature] gets a hex string.
+/// Like [fixed_size_payment::StateDto], only defined for `L == 0`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LedgerChannelUpdateDto {
+    pub state: fixed_size_payment::StateDto,
+    pub actor_idx: PartIdx,
+    pub sig: HexSignature,
+}
+
+impl<const A: usize, const P: usize> From<LedgerChannelUpdateG<A, P>> for LedgerChannelUpdateDto {
+    fn from(value: LedgerChannelUpdateG<A, P>) -> Self {
+        Self {
+            state: value.state.into(),
+            actor_idx: value.actor_idx,
+            sig: value.sig.into(),
+        }
+    }
+}
+
+impl<const A: usize, const P: usize> TryFrom<LedgerChannelUpdateDto>
+    for LedgerChannelUpdateG<A, P>
+{
+    type Error = ConversionError;
+
+    fn try_from(value: LedgerChannelUpdateDto) -> Result<Self, Self::Error> {
+        Ok(Self {
+            state: value.state.try_into()?,
+            actor_idx: value.actor_idx,
+            sig: value.sig.into(),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct LedgerChannelUpdateAccepted {
     pub channel: Hash,
@@ -58,7 +109,7 @@ pub struct LedgerChannelUpdateAccepted {
 }
 
 impl TryFrom<perunwire::ChannelUpdateAccMsg> for LedgerChannelUpdateAccepted {
-    type Error = ConversionError;
+    type Error = DecodeError;
 
     fn try_from(value: perunwire::ChannelUpdateAccMsg) -> Result<Self, Self::Error> {
         Ok(LedgerChannelUpdateAccepted {
@@ -88,3 +139,32 @@ impl From<LedgerChannelUpdateAccepted> for perunwire::ChannelUpdateAccMsg {
         }
     }
 }
+
+/// Human-readable JSON mirror of [LedgerChannelUpdateAccepted]; see
+/// [fixed_size_payment::ParamsDto].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LedgerChannelUpdateAcceptedDto {
+    pub channel: HexHash,
+    pub version: u64,
+    pub sig: HexSignature,
+}
+
+impl From<LedgerChannelUpdateAccepted> for LedgerChannelUpdateAcceptedDto {
+    fn from(value: LedgerChannelUpdateAccepted) -> Self {
+        Self {
+            channel: value.channel.into(),
+            version: value.version,
+            sig: value.sig.into(),
+        }
+    }
+}
+
+impl From<LedgerChannelUpdateAcceptedDto> for LedgerChannelUpdateAccepted {
+    fn from(value: LedgerChannelUpdateAcceptedDto) -> Self {
+        Self {
+            channel: value.channel.into(),
+            version: value.version,
+            sig: value.sig.into(),
+        }
+    }
+}
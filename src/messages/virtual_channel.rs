@@ -0,0 +1,242 @@
+use crate::{
+    abiencode::types::{Address, Bytes32, Hash},
+    channel::{fixed_size_payment, NonceShare, Peers},
+    messages::ConversionError,
+    perunwire,
+};
+use alloc::vec;
+
+const ASSETS: usize = 1;
+const PARTICIPANTS: usize = 2;
+type Allocation = fixed_size_payment::Allocation<ASSETS, PARTICIPANTS>;
+type Balances = fixed_size_payment::Balances<ASSETS, PARTICIPANTS>;
+type State = fixed_size_payment::State<fixed_size_payment::NoApp, ASSETS, PARTICIPANTS>;
+
+/// Proposes opening a channel with a peer we have no direct ledger channel
+/// with, routed through an intermediary that already has a ledger channel
+/// with each of us. `parents` names that ledger channel on each end, so the
+/// intermediary knows which of its channels to lock funds in once funding is
+/// agreed - see [VirtualChannelFundingProposal].
+#[derive(Debug, Clone)]
+pub struct VirtualChannelProposal {
+    pub proposal_id: Hash,
+    pub challenge_duration: u64,
+    pub nonce_share: NonceShare,
+    pub init_bals: Allocation,
+    pub funding_agreement: Balances,
+    pub participant: Address,
+    pub peers: Peers,
+    pub parents: [Hash; PARTICIPANTS],
+}
+
+impl TryFrom<perunwire::VirtualChannelProposalMsg> for VirtualChannelProposal {
+    type Error = ConversionError;
+
+    fn try_from(value: perunwire::VirtualChannelProposalMsg) -> Result<Self, Self::Error> {
+        let base = value
+            .base_channel_proposal
+            .ok_or(ConversionError::ExptectedSome)?;
+        let init_bals = base.init_bals.ok_or(ConversionError::ExptectedSome)?;
+        let funding_agreement = base
+            .funding_agreement
+            .ok_or(ConversionError::ExptectedSome)?;
+
+        if value.parents.len() != PARTICIPANTS {
+            return Err(ConversionError::ParticipantSizeMissmatch);
+        }
+        let mut parents = [Hash::default(); PARTICIPANTS];
+        for (i, parent) in value.parents.into_iter().enumerate() {
+            parents[i] = Hash(parent.try_into().or(Err(ConversionError::ByteLengthMissmatch))?);
+        }
+
+        Ok(Self {
+            proposal_id: Hash(
+                base.proposal_id
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            challenge_duration: base.challenge_duration,
+            nonce_share: Bytes32(
+                base.nonce_share
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            init_bals: init_bals.try_into()?,
+            funding_agreement: funding_agreement.try_into()?,
+            participant: Address(
+                value
+                    .participant
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            peers: value.peers,
+            parents,
+        })
+    }
+}
+
+impl From<VirtualChannelProposal> for perunwire::VirtualChannelProposalMsg {
+    fn from(value: VirtualChannelProposal) -> Self {
+        Self {
+            base_channel_proposal: Some(perunwire::BaseChannelProposal {
+                proposal_id: value.proposal_id.0.to_vec(),
+                challenge_duration: value.challenge_duration,
+                nonce_share: value.nonce_share.0.to_vec(),
+                app: vec![],
+                init_data: vec![],
+                init_bals: Some(value.init_bals.into()),
+                funding_agreement: Some(value.funding_agreement.into()),
+            }),
+            participant: value.participant.0.to_vec(),
+            peers: value.peers,
+            parents: value.parents.iter().map(|p| p.0.to_vec()).collect(),
+        }
+    }
+}
+
+/// Message sent when a participant accepts a [VirtualChannelProposal].
+#[derive(Debug, Clone, Copy)]
+pub struct VirtualChannelProposalAcc {
+    pub proposal_id: Hash,
+    pub nonce_share: NonceShare,
+    pub participant: Address,
+}
+
+impl TryFrom<perunwire::VirtualChannelProposalAccMsg> for VirtualChannelProposalAcc {
+    type Error = ConversionError;
+
+    fn try_from(value: perunwire::VirtualChannelProposalAccMsg) -> Result<Self, Self::Error> {
+        let base = value
+            .base_channel_proposal_acc
+            .ok_or(ConversionError::ExptectedSome)?;
+
+        Ok(Self {
+            proposal_id: Hash(
+                base.proposal_id
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            nonce_share: Bytes32(
+                base.nonce_share
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            participant: Address(
+                value
+                    .participant
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+        })
+    }
+}
+
+impl From<VirtualChannelProposalAcc> for perunwire::VirtualChannelProposalAccMsg {
+    fn from(value: VirtualChannelProposalAcc) -> Self {
+        Self {
+            base_channel_proposal_acc: Some(perunwire::BaseChannelProposalAcc {
+                proposal_id: value.proposal_id.0.to_vec(),
+                nonce_share: value.nonce_share.0.to_vec(),
+            }),
+            participant: value.participant.0.to_vec(),
+        }
+    }
+}
+
+/// Sent by the two virtual-channel end-participants to the intermediary,
+/// asking it to lock `state.outcome`'s balance for the virtual channel as a
+/// sub-allocation of `parent` (one of the intermediary's existing ledger
+/// channels). The intermediary responds by countersigning a normal
+/// `ChannelUpdate` on `parent` that adds the lock, reusing the existing
+/// update handshake rather than a bespoke one.
+#[derive(Debug, Clone, Copy)]
+pub struct VirtualChannelFundingProposal {
+    pub channel: Hash,
+    pub parent: Hash,
+    pub state: State,
+}
+
+impl TryFrom<perunwire::VirtualChannelFundingProposalMsg> for VirtualChannelFundingProposal {
+    type Error = ConversionError;
+
+    fn try_from(
+        value: perunwire::VirtualChannelFundingProposalMsg,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            channel: Hash(
+                value
+                    .channel_id
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            parent: Hash(
+                value
+                    .parent
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            state: value
+                .state
+                .ok_or(ConversionError::ExptectedSome)?
+                .try_into()?,
+        })
+    }
+}
+
+impl From<VirtualChannelFundingProposal> for perunwire::VirtualChannelFundingProposalMsg {
+    fn from(value: VirtualChannelFundingProposal) -> Self {
+        Self {
+            channel_id: value.channel.0.to_vec(),
+            parent: value.parent.0.to_vec(),
+            state: Some(value.state.into()),
+        }
+    }
+}
+
+/// The counterpart to [VirtualChannelFundingProposal], sent once the virtual
+/// channel is closed: asks the intermediary to remove the sub-allocation
+/// lock from `parent` and pay the virtual channel's final balances out into
+/// it directly.
+#[derive(Debug, Clone, Copy)]
+pub struct VirtualChannelSettlementProposal {
+    pub channel: Hash,
+    pub parent: Hash,
+    pub state: State,
+}
+
+impl TryFrom<perunwire::VirtualChannelSettlementProposalMsg> for VirtualChannelSettlementProposal {
+    type Error = ConversionError;
+
+    fn try_from(
+        value: perunwire::VirtualChannelSettlementProposalMsg,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            channel: Hash(
+                value
+                    .channel_id
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            parent: Hash(
+                value
+                    .parent
+                    .try_into()
+                    .or(Err(ConversionError::ByteLengthMissmatch))?,
+            ),
+            state: value
+                .state
+                .ok_or(ConversionError::ExptectedSome)?
+                .try_into()?,
+        })
+    }
+}
+
+impl From<VirtualChannelSettlementProposal> for perunwire::VirtualChannelSettlementProposalMsg {
+    fn from(value: VirtualChannelSettlementProposal) -> Self {
+        Self {
+            channel_id: value.channel.0.to_vec(),
+            parent: value.parent.0.to_vec(),
+            state: Some(value.state.into()),
+        }
+    }
+}
@@ -16,7 +16,9 @@ use std::string::String; // Use String from std library
 
 const ASSETS: usize = 1;
 const PARTICIPANTS: usize = 2;
-type State = fixed_size_payment::State<ASSETS, PARTICIPANTS>;
+// One reserved sub-allocation slot, see `channel::active::LOCKED`.
+const LOCKED: usize = 1;
+type State = fixed_size_payment::State<fixed_size_payment::NoApp, ASSETS, PARTICIPANTS, LOCKED>;
 type Params = fixed_size_payment::Params<PARTICIPANTS>;
 
 #[derive(Debug, Clone, Copy)]
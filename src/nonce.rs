@@ -0,0 +1,127 @@
+//! Nonce reservation for concurrent on-chain funding transactions.
+//!
+//! [crate::channel::agreed_upon::AgreedUponChannel] hands funding off to an
+//! external Funder service via [crate::messages::FunderRequestMessage], and
+//! firing several channels concurrently from the same signing address means
+//! their funding transactions would race for the same Ethereum account
+//! nonce. [NonceReservation] hands out nonces up front - recast from the
+//! reserve-and-dispatch model OpenEthereum uses for its own transaction
+//! queue - so a caller can sign several funding transactions in parallel
+//! before any of them confirm, without colliding. Dispatch order need not
+//! match reservation order: nonce `N+1` can be reserved, signed, and
+//! released again before nonce `N` is even dispatched.
+
+use crate::abiencode::types::Address;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests;
+
+/// Lifecycle of a single nonce handed out by [NonceReservation::reserve].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceStatus {
+    /// Handed out, not yet broadcast.
+    Reserved,
+    /// Broadcast on-chain via [NonceReservation::dispatch].
+    Dispatched,
+    /// Returned to the free-list via [NonceReservation::release] after a
+    /// signing/broadcast failure, so a later reservation reuses it instead
+    /// of leaving a permanent gap that stalls every higher nonce.
+    Released,
+}
+
+/// [NonceReservation::dispatch]/[NonceReservation::release] was called for a
+/// nonce that either was never reserved or isn't [NonceStatus::Reserved]
+/// anymore.
+#[derive(Debug)]
+pub struct UnknownNonce(pub u64);
+
+#[derive(Debug, Default)]
+struct AddressNonces {
+    /// Smallest nonce never yet handed out for this address.
+    next_free: u64,
+    status: BTreeMap<u64, NonceStatus>,
+}
+
+/// Per-address nonce reservation manager, see the module documentation.
+#[derive(Debug, Default)]
+pub struct NonceReservation {
+    // Linear lookup, same tradeoff as [crate::sig::remote::MultiSigner]: the
+    // number of funding addresses a single client juggles is small enough
+    // that a `Vec` beats pulling in a `Address: Ord` impl just for this.
+    addresses: Vec<(Address, AddressNonces)>,
+}
+
+impl NonceReservation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entry(&mut self, address: Address) -> &mut AddressNonces {
+        if let Some(index) = self.addresses.iter().position(|(a, _)| *a == address) {
+            &mut self.addresses[index].1
+        } else {
+            self.addresses.push((address, AddressNonces::default()));
+            &mut self.addresses.last_mut().expect("just pushed").1
+        }
+    }
+
+    /// Hand out the lowest free nonce for `address` - the smallest
+    /// [NonceStatus::Released] one if any, otherwise the next-free counter -
+    /// and mark it [NonceStatus::Reserved].
+    pub fn reserve(&mut self, address: Address) -> u64 {
+        let entry = self.entry(address);
+        let released = entry
+            .status
+            .iter()
+            .find(|(_, status)| **status == NonceStatus::Released)
+            .map(|(nonce, _)| *nonce);
+        let nonce = match released {
+            Some(nonce) => nonce,
+            None => {
+                let nonce = entry.next_free;
+                entry.next_free += 1;
+                nonce
+            }
+        };
+        entry.status.insert(nonce, NonceStatus::Reserved);
+        nonce
+    }
+
+    /// Mark `nonce` as broadcast, once the funding transaction using it was
+    /// actually sent.
+    pub fn dispatch(&mut self, address: Address, nonce: u64) -> Result<(), UnknownNonce> {
+        self.transition(address, nonce, NonceStatus::Dispatched)
+    }
+
+    /// Return `nonce` to `address`'s free-list after a signing/broadcast
+    /// failure, so a later [NonceReservation::reserve] reuses it rather than
+    /// leaving a permanent gap that stalls every higher nonce.
+    pub fn release(&mut self, address: Address, nonce: u64) -> Result<(), UnknownNonce> {
+        self.transition(address, nonce, NonceStatus::Released)
+    }
+
+    /// Move `nonce` to `status`, failing if it isn't currently
+    /// [NonceStatus::Reserved] (the only state [dispatch]/[release] are
+    /// valid from).
+    ///
+    /// [dispatch]: NonceReservation::dispatch
+    /// [release]: NonceReservation::release
+    fn transition(
+        &mut self,
+        address: Address,
+        nonce: u64,
+        status: NonceStatus,
+    ) -> Result<(), UnknownNonce> {
+        let entry = self.entry(address);
+        match entry.status.get_mut(&nonce) {
+            Some(current @ NonceStatus::Reserved) => {
+                *current = status;
+                Ok(())
+            }
+            _ => Err(UnknownNonce(nonce)),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use super::*;
+
+const ADDR: Address = Address([1; 20]);
+const OTHER: Address = Address([2; 20]);
+
+#[test]
+fn reserve_hands_out_increasing_nonces() {
+    let mut r = NonceReservation::new();
+    assert_eq!(r.reserve(ADDR), 0);
+    assert_eq!(r.reserve(ADDR), 1);
+    assert_eq!(r.reserve(ADDR), 2);
+}
+
+#[test]
+fn addresses_have_independent_nonce_spaces() {
+    let mut r = NonceReservation::new();
+    assert_eq!(r.reserve(ADDR), 0);
+    assert_eq!(r.reserve(OTHER), 0);
+    assert_eq!(r.reserve(ADDR), 1);
+}
+
+#[test]
+fn released_nonce_is_reused_before_handing_out_a_new_one() {
+    let mut r = NonceReservation::new();
+    let a = r.reserve(ADDR);
+    let b = r.reserve(ADDR);
+    r.release(ADDR, a).unwrap();
+    assert_eq!(r.reserve(ADDR), a);
+    // `b` is still outstanding, so the next free nonce is the one after it.
+    assert_eq!(r.reserve(ADDR), b + 1);
+}
+
+#[test]
+fn prospective_reservation_does_not_require_dispatch_in_order() {
+    let mut r = NonceReservation::new();
+    let n = r.reserve(ADDR);
+    let n_plus_one = r.reserve(ADDR);
+    r.dispatch(ADDR, n_plus_one).unwrap();
+    r.dispatch(ADDR, n).unwrap();
+}
+
+#[test]
+fn dispatch_of_unreserved_nonce_fails() {
+    let mut r = NonceReservation::new();
+    assert!(r.dispatch(ADDR, 0).is_err());
+}
+
+#[test]
+fn dispatch_after_release_fails() {
+    let mut r = NonceReservation::new();
+    let n = r.reserve(ADDR);
+    r.release(ADDR, n).unwrap();
+    assert!(r.dispatch(ADDR, n).is_err());
+}
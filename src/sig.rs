@@ -6,23 +6,55 @@
 //! feature flags are present, [secp256k1] is used because [k256] is marked as
 //! the default in cargo.toml.
 
-use crate::abiencode::types::Hash;
+use crate::abiencode::types::{Address, Hash, Signature};
+use crate::channel::fixed_size_payment;
+use alloc::string::String;
 use sha3::{Digest, Keccak256};
 
+// Same default single-asset, two-party shape [crate::channel::active],
+// [crate::channel::agreed_upon] and [crate::channel::withdrawal_auth] hardcode
+// for now, see those modules' own `ASSETS`/`PARTICIPANTS` aliases.
+const ASSETS: usize = 1;
+const PARTICIPANTS: usize = 2;
+// One reserved sub-allocation slot, see `channel::active::LOCKED`.
+const LOCKED: usize = 1;
+type ChannelState =
+    fixed_size_payment::State<fixed_size_payment::NoApp, ASSETS, PARTICIPANTS, LOCKED>;
+type ChannelParams = fixed_size_payment::Params<PARTICIPANTS>;
+
 #[cfg(test)]
 #[cfg(feature = "std")]
 mod tests;
 
 // Import the requested implementation(s), as well as the dummy fallback to make
 // sure it always compiles, too, even if the feature flags are set.
+#[cfg(any(feature = "secp256k1", feature = "k256"))]
+mod bip32;
 #[doc(hidden)]
 mod dummy;
+#[cfg(feature = "ed25519")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ed25519")))]
+pub mod ed25519;
+pub mod enforcing;
 #[cfg(feature = "k256")]
 #[cfg_attr(docsrs, doc(cfg(feature = "k256")))]
 pub mod k256;
+#[cfg(any(feature = "secp256k1", feature = "k256"))]
+mod modn;
+#[cfg(feature = "secp256k1")]
+#[cfg_attr(docsrs, doc(cfg(feature = "secp256k1")))]
+pub mod musig;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod queue;
+pub mod remote;
+#[cfg(feature = "secp256k1")]
+#[cfg_attr(docsrs, doc(cfg(feature = "secp256k1")))]
+pub mod schnorr;
 #[cfg(feature = "secp256k1")]
 #[cfg_attr(docsrs, doc(cfg(feature = "secp256k1")))]
 pub mod secp256k1;
+pub mod validating;
 
 // Complain if no signing implementation is set, while hiding all the errors
 // resulting from that by using the dummy implementation.
@@ -31,17 +63,168 @@ compile_error!(
     "Signature dependency needed, use one of the following feature flags: 'secp256k1', 'k256'"
 );
 #[cfg(not(any(feature = "secp256k1", feature = "k256")))]
+pub(crate) use self::dummy::recover_eth_signer;
+#[cfg(not(any(feature = "secp256k1", feature = "k256")))]
 pub use self::dummy::{Error, Signer};
 
 // Only use k256 (part of default) if the secp256k1 feature flag is not set. The
 // application may enable both feature flags, this logic chooses secp256k1 in
 // this case (thus ignoring k256 which is enabled by default).
 #[cfg(all(not(feature = "secp256k1"), feature = "k256"))]
+pub(crate) use self::k256::recover_eth_signer;
+#[cfg(all(not(feature = "secp256k1"), feature = "k256"))]
 pub use self::k256::{Error, Signer};
 #[cfg(feature = "secp256k1")]
+pub(crate) use self::secp256k1::recover_eth_signer;
+#[cfg(feature = "secp256k1")]
 #[doc(hidden)]
 pub use self::secp256k1::{Error, Signer};
 
+/// Abstraction over "can sign/verify Ethereum-style signatures for a single
+/// address", so [crate::PerunClient] can be used with an in-memory [Signer],
+/// a hardware wallet, or an out-of-process remote signer, instead of being
+/// locked to whichever concrete implementation this crate was compiled with.
+/// Mirrors the key-interface traits (`NodeSigner`/`SignerProvider`) that
+/// rust-lightning exposes for the same reason.
+///
+/// Implementations are free to fail for their own reasons (a hardware wallet
+/// the user declined to unlock, a remote signer that's unreachable), which is
+/// why [EthSigner::Error] is an associated type instead of this crate's own
+/// [Error] - the channel types built on top of [EthSigner] only need to
+/// report that signing failed and why, so they capture it as a
+/// [SigningError] instead of having to become generic over every possible
+/// signer's error type themselves.
+pub trait EthSigner {
+    /// Failure reason for [EthSigner::sign_eth]/[EthSigner::recover_signer].
+    type Error: core::fmt::Debug;
+
+    /// The Ethereum address this signer signs for and recovers against.
+    fn address(&self) -> Address;
+    /// Sign `msg`, which already has the `\x19Ethereum Signed Message`
+    /// prefix (see [hash_to_eth_signed_msg_hash]) applied if needed.
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, Self::Error>;
+    /// Recover the address that produced `eth_sig` over `msg`.
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, Self::Error>;
+
+    /// Whether this signer is able to act as `address`, i.e. whether
+    /// [EthSigner::sign_eth] would actually sign on `address`'s behalf.
+    /// Defaults to comparing against [EthSigner::address]; account-provider
+    /// style signers that hold more than one address (see
+    /// [remote::MultiSigner]) override this to check their whole registry.
+    /// Named after the `AccountProvider::is_external` query OpenEthereum
+    /// used to let callers check an address without assuming the signer
+    /// only ever controls one.
+    fn is_external(&self, address: Address) -> bool {
+        address == self.address()
+    }
+
+    /// Like [EthSigner::sign_eth], but also tells the signer which
+    /// `(channel_id, version)` the state behind `msg` belongs to. The
+    /// default implementation ignores both and forwards to
+    /// [EthSigner::sign_eth]; [enforcing::EnforcingSigner] overrides it to
+    /// catch a protocol bug that asks it to sign two different states at the
+    /// same version.
+    fn sign_state(
+        &self,
+        channel_id: Hash,
+        version: u64,
+        msg: Hash,
+    ) -> Result<Signature, Self::Error> {
+        let _ = (channel_id, version);
+        self.sign_eth(msg)
+    }
+
+    /// Like [EthSigner::sign_state], but hands the signer the full `params`,
+    /// the previous state (`None` for a channel's very first state) and the
+    /// state it's being asked to sign, instead of just the opaque `msg` hash.
+    /// The default implementation ignores all of that extra context and
+    /// forwards to [EthSigner::sign_state]; [validating::ValidatingSigner]
+    /// overrides it to independently re-run the same checks
+    /// [crate::channel::ActiveChannel::check_valid_transition] already
+    /// performs before rejecting a signature, so an out-of-process signer
+    /// (an HSM, a remote VLS-style policy signer) doesn't have to trust the
+    /// caller to have run them.
+    fn sign_channel_state(
+        &self,
+        params: ChannelParams,
+        old_state: Option<ChannelState>,
+        new_state: ChannelState,
+        msg: Hash,
+    ) -> Result<Signature, Self::Error> {
+        let _ = (params, old_state);
+        self.sign_state(new_state.channel_id(), new_state.version(), msg)
+    }
+}
+
+/// Abstracts a signature scheme's own representation - its [Signature] type,
+/// that type's ABI-encoded wire length, and how an address is derived from a
+/// verifying key - independently of [EthSigner]'s sign/recover shape, which
+/// is specific to `ecrecover`-compatible schemes like the Ethereum ECDSA path
+/// [k256]/[secp256k1] implement.
+///
+/// [EthSigner] - and everything built on it ([crate::channel::ChannelUpdate],
+/// [crate::channel::active::ActiveChannel::update], the wire DTOs that carry
+/// a state signature) - deliberately does *not* become generic over this
+/// trait. [crate::channel::fixed_size_payment::Params::participants] is an
+/// `[Address; P]`, and the on-chain adjudicator this crate talks to verifies
+/// a dispute with Solidity's `ecrecover` precompile against exactly that
+/// 20-byte address and a 65-byte `r‖s‖v` signature - a non-recoverable
+/// scheme (see [ed25519]) can't satisfy that without a contract-side change
+/// first, the same kind of hard on-chain-compatibility constraint that keeps
+/// [crate::wire::encoding::ProtoBufEncodingLayer] from growing new
+/// `perunwire` fields without an upstream `.proto` change (see its
+/// `ParticipantMessage::AuthResponse` conversion). A [SignatureScheme] is
+/// therefore a standalone building block for schemes used outside that
+/// pipeline - [schnorr]'s key-aggregation signatures today, [ed25519] behind
+/// its own feature flag - not a generic parameter threaded through it.
+pub trait SignatureScheme {
+    /// The scheme's own signature representation, e.g. [Signature] for the
+    /// Ethereum ECDSA path, or `ed25519::Signature`.
+    type Signature: Clone + core::fmt::Debug;
+    /// The verifying key a signature is checked against. Schemes without
+    /// recovery (unlike [EthSigner::recover_signer]) need the caller to keep
+    /// this around to verify a signature at all - see [ed25519]'s module
+    /// docs.
+    type VerifyingKey: Clone + core::fmt::Debug;
+
+    /// Length in bytes of [SignatureScheme::Signature]'s ABI-encoded form.
+    const SIGNATURE_LEN: usize;
+
+    /// Derives the address associated with `key`, the same role
+    /// [EthSigner::address] plays for the Ethereum ECDSA path.
+    fn derive_address(key: &Self::VerifyingKey) -> Address;
+}
+
+impl EthSigner for Signer {
+    type Error = Error;
+
+    fn address(&self) -> Address {
+        self.address()
+    }
+
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, Self::Error> {
+        Ok(self.sign_eth(msg))
+    }
+
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, Self::Error> {
+        self.recover_signer(msg, eth_sig)
+    }
+}
+
+/// Captures an [EthSigner::Error] via its [core::fmt::Debug] output. Lets the
+/// channel types that thread a signing failure through this crate's own
+/// (concrete) error enums do so without becoming generic over every possible
+/// [EthSigner] implementation's error type - they only need to report that
+/// signing failed and why, not let callers match on it structurally.
+#[derive(Debug)]
+pub struct SigningError(String);
+
+impl SigningError {
+    pub(crate) fn capture(e: impl core::fmt::Debug) -> Self {
+        SigningError(alloc::format!("{e:?}"))
+    }
+}
+
 /// Helper function for the Signers.
 ///
 /// Add the `\x19Ethereum Signed Message\n<length>` prefix to hash. This is the
@@ -0,0 +1,125 @@
+//! Minimal BIP-39 seed derivation and BIP-32/BIP-44 key derivation down the
+//! Ethereum path `m/44'/60'/0'/0/index`. Shared by the [secp256k1][super::secp256k1]
+//! and [k256][super::k256] backends so both expose an identical
+//! `from_mnemonic` constructor without duplicating the derivation math.
+//!
+//! This is intentionally limited to what the two signer backends need (secp256k1
+//! private key derivation only) rather than a general purpose HD-wallet crate.
+
+use hmac::{Hmac, Mac, NewMac};
+use pbkdf2::pbkdf2;
+use sha2::Sha512;
+
+use super::modn::{self, SECP256K1_N};
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::string::String;
+
+/// BIP-39: mnemonic phrase + passphrase -> 64-byte seed, via
+/// PBKDF2-HMAC-SHA512 with 2048 iterations and salt `"mnemonic" || passphrase`.
+/// This does not validate the mnemonic's checksum/wordlist, it merely
+/// reproduces the seed-stretching step, mirroring what wallets do once a
+/// valid phrase has been entered.
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> [u8; 64] {
+    let mut salt = String::with_capacity(8 + passphrase.len());
+    salt.push_str("mnemonic");
+    salt.push_str(passphrase);
+
+    let mut seed = [0u8; 64];
+    pbkdf2::<Hmac<Sha512>>(phrase.as_bytes(), salt.as_bytes(), 2048, &mut seed);
+    seed
+}
+
+struct ExtendedKey {
+    key: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+fn master_key_from_seed(seed: &[u8; 64]) -> ExtendedKey {
+    let mut mac =
+        Hmac::<Sha512>::new_from_slice(b"Bitcoin seed").expect("HMAC accepts any key length");
+    mac.update(seed);
+    let i = mac.finalize().into_bytes();
+
+    let mut key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    key.copy_from_slice(&i[..32]);
+    chain_code.copy_from_slice(&i[32..]);
+    ExtendedKey { key, chain_code }
+}
+
+/// Hardened and normal BIP-32 `CKDpriv` child key derivation. `index` must
+/// already include the `0x80000000` hardened offset when a hardened child is
+/// wanted.
+fn derive_child(parent: &ExtendedKey, index: u32) -> ExtendedKey {
+    let mut mac =
+        Hmac::<Sha512>::new_from_slice(&parent.chain_code).expect("HMAC accepts any key length");
+    if index & 0x8000_0000 != 0 {
+        // Hardened: data = 0x00 || ser256(parent key) || ser32(index)
+        mac.update(&[0]);
+        mac.update(&parent.key);
+    } else {
+        // Normal: data = serP(point(parent key)) || ser32(index)
+        let pk = secp256k1_public_key(&parent.key);
+        mac.update(&pk);
+    }
+    mac.update(&index.to_be_bytes());
+    let i = mac.finalize().into_bytes();
+
+    let mut il = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    il.copy_from_slice(&i[..32]);
+    chain_code.copy_from_slice(&i[32..]);
+
+    ExtendedKey {
+        key: modn::add_mod(&il, &parent.key, &SECP256K1_N),
+        chain_code,
+    }
+}
+
+fn secp256k1_public_key(secret: &[u8; 32]) -> [u8; 33] {
+    // Only needed to derive non-hardened children, which this module's
+    // `m/44'/60'/0'/0/index` path does not use below the 4th level, but is
+    // kept general in case callers derive further.
+    #[cfg(feature = "secp256k1")]
+    {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let sk = secp256k1::SecretKey::from_slice(secret).expect("derived key is always in range");
+        secp256k1::PublicKey::from_secret_key(&secp, &sk).serialize()
+    }
+    #[cfg(all(not(feature = "secp256k1"), feature = "k256"))]
+    {
+        use k256::elliptic_curve::sec1::ToEncodedPoint;
+        let sk =
+            k256::ecdsa::SigningKey::from_bytes(secret).expect("derived key is always in range");
+        sk.verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .try_into()
+            .expect("compressed point is always 33 bytes")
+    }
+    #[cfg(not(any(feature = "secp256k1", feature = "k256")))]
+    {
+        let _ = secret;
+        unimplemented!()
+    }
+}
+
+/// Derives the secret key at `m/44'/60'/0'/0/account_index` (the standard
+/// Ethereum BIP-44 path) from a BIP-39 seed.
+pub fn derive_secret_key(seed: &[u8; 64], account_index: u32) -> [u8; 32] {
+    const HARDENED: u32 = 0x8000_0000;
+
+    let master = master_key_from_seed(seed);
+    let purpose = derive_child(&master, 44 + HARDENED);
+    let coin_type = derive_child(&purpose, 60 + HARDENED);
+    let account = derive_child(&coin_type, HARDENED); // account' = 0'
+    let change = derive_child(&account, 0); // change = 0 (external chain)
+    let addr_index = derive_child(&change, account_index);
+
+    addr_index.key
+}
@@ -13,7 +13,15 @@ pub struct Error {}
 pub struct Signer {}
 
 impl Signer {
-    pub fn new<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
+    pub fn random<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
+        unimplemented!()
+    }
+
+    pub fn from_secret_bytes(sk: &[u8; 32]) -> Result<Self, Error> {
+        unimplemented!()
+    }
+
+    pub fn from_mnemonic(phrase: &str, account_index: u32) -> Result<Self, Error> {
         unimplemented!()
     }
 
@@ -29,3 +37,9 @@ impl Signer {
         unimplemented!()
     }
 }
+
+/// See the real implementations' `recover_eth_signer` (e.g.
+/// [crate::sig::k256::recover_eth_signer]).
+pub(crate) fn recover_eth_signer(_hash: Hash, _eth_sig: Signature) -> Result<Address, Error> {
+    unimplemented!()
+}
@@ -0,0 +1,91 @@
+//! Non-recoverable ed25519 signatures, for off-chain-only consumers that
+//! don't need the on-chain `ecrecover` compatibility [EthSigner][super::EthSigner]'s
+//! Ethereum ECDSA path provides - see [super]'s [SignatureScheme][super::SignatureScheme]
+//! docs for why this can't simply replace that path for channel state
+//! signing.
+//!
+//! Unlike [super::recover_eth_signer], an ed25519 [Signature] cannot be
+//! verified without already knowing the signer's [VerifyingKey] - there is
+//! no recovery - so a caller checking one needs to keep the signer's
+//! [VerifyingKey] around itself (e.g. alongside the address it would
+//! otherwise look up via
+//! [Params::participants][crate::channel::fixed_size_payment::Params::participants])
+//! instead of recovering it from the signature the way [super::k256]/
+//! [super::secp256k1] do.
+
+use ed25519_dalek::{Signer as DalekSigner, SigningKey, VerifyingKey};
+
+use super::SignatureScheme;
+use crate::abiencode::types::{Address, Hash};
+use sha3::{Digest, Keccak256};
+
+pub use ed25519_dalek::SignatureError as Error;
+
+/// Raw 64-byte `(R, s)` ed25519 signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature(pub [u8; 64]);
+
+/// Marker type implementing [SignatureScheme] for ed25519, so code that is
+/// generic over a [SignatureScheme] (rather than a concrete [Signer]) can
+/// name this scheme.
+#[derive(Debug)]
+pub struct Scheme;
+
+impl SignatureScheme for Scheme {
+    type Signature = Signature;
+    type VerifyingKey = VerifyingKey;
+    const SIGNATURE_LEN: usize = 64;
+
+    // ed25519 has no `ecrecover`-style address derivation of its own; this
+    // crate's [Address] is specifically "Keccak256 of an uncompressed
+    // secp256k1 public key", so deriving one here is only ever a local
+    // bookkeeping convenience (e.g. to key a lookup table by something
+    // `Address`-shaped) - it carries none of the on-chain meaning
+    // [super::k256]'s `From<VerifyingKey> for Address` does.
+    fn derive_address(key: &VerifyingKey) -> Address {
+        let hash: [u8; 32] = Keccak256::digest(key.as_bytes()).into();
+        let mut addr = Address([0; 20]);
+        addr.0.copy_from_slice(&hash[32 - 20..]);
+        addr
+    }
+}
+
+#[derive(Debug)]
+pub struct Signer {
+    key: SigningKey,
+}
+
+impl Signer {
+    /// Generate a fresh, random keypair using the given RNG.
+    pub fn random<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        Self {
+            key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    /// Load a keypair from an existing 32-byte seed.
+    pub fn from_secret_bytes(sk: &[u8; 32]) -> Self {
+        Self {
+            key: SigningKey::from_bytes(sk),
+        }
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    pub fn sign(&self, msg: Hash) -> Signature {
+        Signature(self.key.sign(&msg.0).to_bytes())
+    }
+}
+
+/// Checks that `sig` was produced by the holder of `key` over `msg`. Doesn't
+/// need a [Signer] instance - unlike signing, verification is pure public-key
+/// math - mirroring [super::k256::recover_eth_signer]'s role for the
+/// recoverable Ethereum ECDSA path.
+pub fn verify(key: &VerifyingKey, msg: Hash, sig: &Signature) -> Result<(), Error> {
+    let sig = ed25519_dalek::Signature::from_bytes(&sig.0);
+    key.verify_strict(&msg.0, &sig)
+}
@@ -0,0 +1,196 @@
+//! [EnforcingSigner], a test/fuzzing-only [EthSigner] wrapper porting the
+//! `EnforcingSigner`/`TestChannelSigner` idea from rust-lightning to this
+//! crate: it records every `(channel_id, version, state_hash)` it's asked to
+//! sign via [EthSigner::sign_state] and rejects a call that would violate
+//! this crate's own signing invariants - signing two different states at
+//! the same version, or a version lower than one already signed for a
+//! channel - instead of silently producing a signature for what should have
+//! been caught in [crate::channel::AgreedUponChannel]/
+//! [crate::channel::ChannelUpdate]/[crate::channel::ActiveChannel] first.
+//! Wrap any [EthSigner] in one of these in a test or [fuzz_target] harness
+//! to shake out ordering bugs in `participant_accepted`/`build`/`update`
+//! instead of only finding them against a real chain.
+//!
+//! A state's `channel_id` is itself derived from the [Params](crate::channel::fixed_size_payment::Params)
+//! it was created with, so mismatches between a state and the params it
+//! belongs to are already caught there (e.g.
+//! [crate::channel::AddSignatureError::InvalidChannelID]); this wrapper only
+//! needs to track signing order per `channel_id`.
+//!
+//! [fuzz_target]: https://docs.rs/libfuzzer-sys/latest/libfuzzer_sys/macro.fuzz_target.html
+
+use super::EthSigner;
+use crate::abiencode::types::{Address, Hash, Signature};
+use core::cell::RefCell;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// One `(channel_id, version, state_hash)` tuple [EnforcingSigner] has
+/// already signed.
+#[derive(Debug, Clone, Copy)]
+struct Signed {
+    channel_id: Hash,
+    version: u64,
+    state_hash: Hash,
+}
+
+/// [EnforcingSigner::sign_state] refused to sign, see the variants.
+#[derive(Debug)]
+pub enum EnforcingError<E> {
+    /// The wrapped signer itself failed.
+    Inner(E),
+    /// Asked to sign `version` for `channel_id` again, but with a different
+    /// state hash than the one already signed at that version - a fork in
+    /// the channel's history no honest protocol run should ever produce.
+    DifferentStateAtSameVersion { channel_id: Hash, version: u64 },
+    /// Asked to sign a `version` lower than one already signed for
+    /// `channel_id` - a channel's version should only ever move forward.
+    VersionWentBackwards {
+        channel_id: Hash,
+        version: u64,
+        highest_signed: u64,
+    },
+}
+
+/// Test/fuzzing-only [EthSigner] wrapper, see the module documentation.
+#[derive(Debug)]
+pub struct EnforcingSigner<S: EthSigner> {
+    inner: S,
+    // `sign_eth`/`sign_state` take `&self` (see [EthSigner]), so the signing
+    // history needs interior mutability.
+    signed: RefCell<Vec<Signed>>,
+}
+
+impl<S: EthSigner> EnforcingSigner<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            signed: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl<S: EthSigner> EthSigner for EnforcingSigner<S> {
+    type Error = EnforcingError<S::Error>;
+
+    fn address(&self) -> Address {
+        self.inner.address()
+    }
+
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, Self::Error> {
+        self.inner.sign_eth(msg).map_err(EnforcingError::Inner)
+    }
+
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, Self::Error> {
+        self.inner
+            .recover_signer(msg, eth_sig)
+            .map_err(EnforcingError::Inner)
+    }
+
+    fn sign_state(
+        &self,
+        channel_id: Hash,
+        version: u64,
+        msg: Hash,
+    ) -> Result<Signature, Self::Error> {
+        {
+            let signed = self.signed.borrow();
+
+            if signed
+                .iter()
+                .any(|s| s.channel_id == channel_id && s.version == version && s.state_hash != msg)
+            {
+                return Err(EnforcingError::DifferentStateAtSameVersion {
+                    channel_id,
+                    version,
+                });
+            }
+            if let Some(highest) = signed
+                .iter()
+                .filter(|s| s.channel_id == channel_id)
+                .map(|s| s.version)
+                .max()
+            {
+                if version < highest {
+                    return Err(EnforcingError::VersionWentBackwards {
+                        channel_id,
+                        version,
+                        highest_signed: highest,
+                    });
+                }
+            }
+        }
+
+        let sig = self.inner.sign_eth(msg).map_err(EnforcingError::Inner)?;
+
+        let mut signed = self.signed.borrow_mut();
+        if !signed
+            .iter()
+            .any(|s| s.channel_id == channel_id && s.version == version)
+        {
+            signed.push(Signed {
+                channel_id,
+                version,
+                state_hash: msg,
+            });
+        }
+        Ok(sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubSigner;
+
+    const CHANNEL: Hash = Hash([0x11; 32]);
+    const OTHER_CHANNEL: Hash = Hash([0x22; 32]);
+    const STATE_A: Hash = Hash([0xaa; 32]);
+    const STATE_B: Hash = Hash([0xbb; 32]);
+
+    fn signer() -> EnforcingSigner<StubSigner> {
+        EnforcingSigner::new(StubSigner(Address([0; 20])))
+    }
+
+    #[test]
+    fn signs_increasing_versions_of_the_same_channel() {
+        let s = signer();
+        assert!(s.sign_state(CHANNEL, 0, STATE_A).is_ok());
+        assert!(s.sign_state(CHANNEL, 1, STATE_B).is_ok());
+    }
+
+    #[test]
+    fn resigning_the_same_version_with_the_same_state_is_allowed() {
+        let s = signer();
+        assert!(s.sign_state(CHANNEL, 0, STATE_A).is_ok());
+        assert!(s.sign_state(CHANNEL, 0, STATE_A).is_ok());
+    }
+
+    #[test]
+    fn signing_a_different_state_at_an_already_signed_version_is_rejected() {
+        let s = signer();
+        assert!(s.sign_state(CHANNEL, 0, STATE_A).is_ok());
+        assert!(matches!(
+            s.sign_state(CHANNEL, 0, STATE_B),
+            Err(EnforcingError::DifferentStateAtSameVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn signing_an_earlier_version_than_already_signed_is_rejected() {
+        let s = signer();
+        assert!(s.sign_state(CHANNEL, 1, STATE_A).is_ok());
+        assert!(matches!(
+            s.sign_state(CHANNEL, 0, STATE_B),
+            Err(EnforcingError::VersionWentBackwards { .. })
+        ));
+    }
+
+    #[test]
+    fn different_channels_have_independent_histories() {
+        let s = signer();
+        assert!(s.sign_state(CHANNEL, 1, STATE_A).is_ok());
+        assert!(s.sign_state(OTHER_CHANNEL, 0, STATE_B).is_ok());
+    }
+}
@@ -11,7 +11,7 @@ use k256::{
 };
 use sha3::{Digest, Keccak256};
 
-use super::hash_to_eth_signed_msg_hash;
+use super::{bip32, hash_to_eth_signed_msg_hash};
 
 pub use k256::ecdsa::Error;
 
@@ -44,22 +44,31 @@ impl From<VerifyingKey> for Address {
 }
 
 impl Signer {
-    pub fn new<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
+    /// Generate a fresh, random keypair using the given RNG.
+    pub fn random<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
+        let key = SigningKey::random(rng);
+        let addr = key.verifying_key().into();
 
-            let private_key_bytes: [u8; 32] = [
-                0x24, 0x4F, 0xFC, 0x73, 0xC4, 0x48, 0xB5, 0x6D,
-                0xDB, 0xA6, 0xA7, 0xBF, 0xA8, 0xD5, 0x8E, 0xD3,
-                0x60, 0x12, 0x61, 0x1D, 0xA8, 0x3D, 0x4C, 0xB8,
-                0x30, 0x25, 0xEA, 0x12, 0xAC, 0xCF, 0x49, 0xFE,
-            ];
+        Self { key, addr }
+    }
 
-            let key = SigningKey::from_bytes(&private_key_bytes)
-                .expect("Invalid private key");
+    /// Load a keypair from an existing 32-byte secret key, e.g. one kept in
+    /// a device's configuration.
+    pub fn from_secret_bytes(sk: &[u8; 32]) -> Result<Self, Error> {
+        let key = SigningKey::from_bytes(sk)?;
+        let addr = key.verifying_key().into();
 
-            let addr = key.verifying_key().into();
+        Ok(Self { key, addr })
+    }
 
-            Self { key, addr }
-        }
+    /// Derive a keypair from a BIP-39 mnemonic phrase, following the
+    /// Ethereum BIP-44 derivation path `m/44'/60'/0'/0/account_index`. This
+    /// does not validate the mnemonic's wordlist/checksum.
+    pub fn from_mnemonic(phrase: &str, account_index: u32) -> Result<Self, Error> {
+        let seed = bip32::mnemonic_to_seed(phrase, "");
+        let sk = bip32::derive_secret_key(&seed, account_index);
+        Self::from_secret_bytes(&sk)
+    }
 
     pub fn address(&self) -> Address {
         self.addr
@@ -86,17 +95,25 @@ impl Signer {
     }
 
     pub fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, Error> {
-        // "\x19Ethereum Signed Message:\n32" format
-        let hash = hash_to_eth_signed_msg_hash(msg);
+        recover_eth_signer(msg, eth_sig)
+    }
+}
 
-        // Undo adding the 27, to go back to the format expected below
-        let mut sig_bytes: [u8; 65] = eth_sig.0;
-        sig_bytes[64] -= 27;
+/// Recover the address that produced `eth_sig` over `msg`. Doesn't need a
+/// [Signer] instance - unlike signing, recovery is pure public-key math - so
+/// [super::remote::RemoteSigner] can use this to implement
+/// [crate::sig::EthSigner::recover_signer] without holding a local keypair.
+pub(crate) fn recover_eth_signer(msg: Hash, eth_sig: Signature) -> Result<Address, Error> {
+    // "\x19Ethereum Signed Message:\n32" format
+    let hash = hash_to_eth_signed_msg_hash(msg);
 
-        let sig = recoverable::Signature::from_bytes(&sig_bytes)
-            .expect("Can't fail because size is known at compile time");
+    // Undo adding the 27, to go back to the format expected below
+    let mut sig_bytes: [u8; 65] = eth_sig.0;
+    sig_bytes[64] -= 27;
 
-        let verifying_key = sig.recover_verifying_key_from_digest_bytes(&hash.0.into())?;
-        Ok(verifying_key.into())
-    }
+    let sig = recoverable::Signature::from_bytes(&sig_bytes)
+        .expect("Can't fail because size is known at compile time");
+
+    let verifying_key = sig.recover_verifying_key_from_digest_bytes(&hash.0.into())?;
+    Ok(verifying_key.into())
 }
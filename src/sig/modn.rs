@@ -0,0 +1,84 @@
+//! Minimal fixed-width 256-bit modular arithmetic, shared by the BIP-32 child
+//! key derivation ([super::bip32]) and the Schnorr signer ([super::schnorr]),
+//! both of which need to add/multiply scalars modulo the secp256k1 group
+//! order `n`. Not a general purpose bignum library, just enough to avoid
+//! duplicating this math in both places.
+
+/// secp256k1 group order `n`.
+pub const SECP256K1_N: [u8; 32] = [
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xBA, 0xAE, 0xDC, 0xE6, 0xAF, 0x48, 0xA0, 0x3B, 0xBF, 0xD2, 0x5E, 0x8C, 0xD0, 0x36, 0x41, 0x41,
+];
+
+fn ge(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.iter()
+        .zip(b.iter())
+        .find(|(x, y)| x != y)
+        .map_or(true, |(x, y)| x >= y)
+}
+
+fn sub(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let mut borrow = 0i16;
+    let mut diff = [0u8; 32];
+    for i in (0..32).rev() {
+        let d = a[i] as i16 - b[i] as i16 - borrow;
+        if d < 0 {
+            diff[i] = (d + 256) as u8;
+            borrow = 1;
+        } else {
+            diff[i] = d as u8;
+            borrow = 0;
+        }
+    }
+    diff
+}
+
+/// `(a + b) mod n`
+pub fn add_mod(a: &[u8; 32], b: &[u8; 32], n: &[u8; 32]) -> [u8; 32] {
+    let mut carry = 0u16;
+    let mut sum = [0u8; 32];
+    for i in (0..32).rev() {
+        let s = a[i] as u16 + b[i] as u16 + carry;
+        sum[i] = s as u8;
+        carry = s >> 8;
+    }
+    if carry != 0 || ge(&sum, n) {
+        sub(&sum, n)
+    } else {
+        sum
+    }
+}
+
+/// `(n - a) mod n`, i.e. the additive inverse of `a`.
+pub fn neg_mod(a: &[u8; 32], n: &[u8; 32]) -> [u8; 32] {
+    if a == &[0u8; 32] {
+        [0u8; 32]
+    } else {
+        sub(n, a)
+    }
+}
+
+/// `(a * b) mod n`, computed via binary double-and-add since we don't have a
+/// wide-multiply primitive available for arbitrary moduli in `no_std`.
+pub fn mul_mod(a: &[u8; 32], b: &[u8; 32], n: &[u8; 32]) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    for byte in a.iter() {
+        for bit in (0..8).rev() {
+            result = add_mod(&result, &result, n);
+            if byte & (1 << bit) != 0 {
+                result = add_mod(&result, b, n);
+            }
+        }
+    }
+    result
+}
+
+/// Reduce `a` into `[0, n)`. Only ever has to subtract once since the inputs
+/// this is used on (Keccak256 digests) are always `< 2^256 < 2n`.
+pub fn reduce(a: &[u8; 32], n: &[u8; 32]) -> [u8; 32] {
+    if ge(a, n) {
+        sub(a, n)
+    } else {
+        *a
+    }
+}
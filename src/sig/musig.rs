@@ -0,0 +1,440 @@
+//! Two-round MuSig-style Schnorr signature aggregation, built on top of
+//! [super::schnorr]'s single-key on-chain-verifiable Schnorr signature so a
+//! force-close/dispute settlement can eventually be posted as one aggregated
+//! signature against the sum of every participant's public key instead of
+//! `PARTICIPANTS` independent ECDSA signatures - the same trade-off Serai's
+//! Ethereum integration makes, verifying a single aggregated Schnorr
+//! signature against one on-chain key via a dedicated verifier contract.
+//!
+//! Round 1 (commit): every participant picks a nonce `r_i`, `R_i = r_i G`,
+//! and broadcasts a [NonceCommitment] `H(R_i)` - not `R_i` itself. Round 2
+//! (reveal): once every commitment has been collected, participants reveal
+//! their [NonceReveal] `R_i`; [check_reveals] rejects any reveal that
+//! doesn't hash back to the commitment collected for the same participant.
+//! Committing before revealing is what stops a late participant from
+//! choosing its own nonce *after* seeing everyone else's, which would let
+//! it bias/cancel the aggregate nonce `R` and forge a signature - callers
+//! must run [check_reveals] (and reject the whole round on failure) before
+//! calling [aggregate_nonce]/[Participant::partial_sign] with the result,
+//! since nothing below re-checks it.
+//!
+//! Key aggregation uses the standard MuSig coefficients
+//! `a_i = H(L || P_i) mod n`, `L = H(P_1 || .. || P_n)`, i.e.
+//! `P = Σ a_i P_i`, rather than a plain sum: without them, a participant
+//! could register a crafted public key (chosen after seeing everyone else's)
+//! that cancels the other keys out of the sum and lets it sign alone for
+//! the whole group - the "rogue key attack" these coefficients exist to
+//! prevent.
+//!
+//! [verify_partial] must be used to check every partial signature before it
+//! reaches [aggregate_signature] - a missing or invalid partial must abort
+//! the aggregation instead of being silently dropped or summed anyway,
+//! either of which would produce a signature that doesn't verify (best
+//! case) or, if the caller then tried to patch it up some other way, risks
+//! leaking scalar relationships that help forge one (worst case).
+//!
+//! This module only implements the signing math, reusing
+//! [super::schnorr::challenge]/[super::schnorr::verify] unmodified - from
+//! the verifier's perspective the aggregated `P`/`R` are just points, so the
+//! existing single-signer verification (and its on-chain `ecrecover`
+//! reconstruction) applies to an aggregated signature without any changes.
+//!
+//! Not yet wired up: negotiating this signing mode during channel proposal
+//! and carrying the resulting single signature through
+//! [crate::channel::fixed_size_payment::State]/the dispute path instead of
+//! `PARTICIPANTS` ECDSA ones is a separate, larger change (touching the
+//! wire-format-locked proposal/update/dispute plumbing - see
+//! [crate::channel::active]'s module docs for why that surface is
+//! deliberately narrow) and is left for when something actually drives it;
+//! [crate::channel::fixed_size_payment::SigningMode] only records which mode
+//! a channel asked for so far.
+
+use alloc::vec::Vec;
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey, Signing, Verification};
+use sha3::{Digest, Keccak256};
+
+use super::modn::{self, SECP256K1_N};
+use super::schnorr;
+use crate::abiencode::types::{Address, Hash};
+
+/// Round 1: a commitment to a nonce point, sent before any [NonceReveal] -
+/// see the module docs for why the ordering matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceCommitment(pub Hash);
+
+/// Round 2: the nonce point a [NonceCommitment] committed to.
+#[derive(Debug, Clone, Copy)]
+pub struct NonceReveal(pub PublicKey);
+
+/// One participant's contribution to the aggregated signature, handed to
+/// whoever collects every participant's ([aggregate_signature]).
+#[derive(Debug, Clone, Copy)]
+pub struct PartialSignature(pub [u8; 32]);
+
+#[derive(Debug)]
+pub enum AggregationError {
+    /// A [NonceReveal] doesn't hash back to the [NonceCommitment] collected
+    /// for the same participant.
+    RevealDoesNotMatchCommitment(usize),
+    /// A [PartialSignature] doesn't satisfy `s_i*G == R_i + e*a_i*P_i` for
+    /// the participant it was attributed to - either it's forged/corrupted,
+    /// or computed against a different `R`/`P`/message than the rest of the
+    /// group agreed on. Also used when the participant's index is out of
+    /// bounds for `keys`, which would otherwise panic indexing into it.
+    InvalidPartial(usize),
+    /// Input lists (commitments/reveals/keys) handed to an aggregation step
+    /// didn't all agree on the number of participants.
+    ParticipantCountMismatch,
+    /// No participants to aggregate.
+    Empty,
+}
+
+/// One participant's MuSig signing state across both rounds. Dropped (or
+/// re-created via [Participant::new]) after [Participant::partial_sign] -
+/// reusing a nonce across two signatures leaks the secret key, the same
+/// invariant [schnorr::Signer::sign] upholds by drawing a fresh nonce every
+/// call.
+pub struct Participant {
+    sk: SecretKey,
+    nonce: Option<SecretKey>,
+}
+
+impl Participant {
+    pub fn new(sk: SecretKey) -> Self {
+        Participant { sk, nonce: None }
+    }
+
+    pub fn public_key<C: Signing>(&self, secp: &Secp256k1<C>) -> PublicKey {
+        PublicKey::from_secret_key(secp, &self.sk)
+    }
+
+    /// Round 1: pick a fresh nonce `r_i`, returning its commitment `H(R_i)`.
+    /// Must be called (and the resulting [NonceCommitment] exchanged with
+    /// every other participant) before [Self::reveal_nonce].
+    pub fn commit_nonce<R: rand::Rng + rand::CryptoRng, C: Signing>(
+        &mut self,
+        secp: &Secp256k1<C>,
+        rng: &mut R,
+    ) -> NonceCommitment {
+        let r = SecretKey::new(rng);
+        let pub_r = PublicKey::from_secret_key(secp, &r);
+        self.nonce = Some(r);
+        NonceCommitment(hash_point(&pub_r))
+    }
+
+    /// Round 2: reveal the nonce [Self::commit_nonce] picked.
+    ///
+    /// # Panics
+    /// If [Self::commit_nonce] hasn't run yet.
+    pub fn reveal_nonce<C: Signing>(&self, secp: &Secp256k1<C>) -> NonceReveal {
+        let r = self
+            .nonce
+            .expect("commit_nonce must run before reveal_nonce");
+        NonceReveal(PublicKey::from_secret_key(secp, &r))
+    }
+
+    /// Produce this participant's partial signature `s_i = r_i + e*a_i*x_i`
+    /// once every [NonceReveal] has been collected and checked (see
+    /// [check_reveals]) and the aggregate nonce is known. `keys` must list
+    /// every participant's public key in the same order used everywhere
+    /// else (signature ordering/aggregation both depend on it matching),
+    /// with `our_index` this participant's position in it.
+    ///
+    /// Consumes the nonce [Self::commit_nonce] picked, so a second call
+    /// without an intervening [Self::commit_nonce] panics rather than
+    /// silently reusing it.
+    ///
+    /// # Panics
+    /// If [Self::commit_nonce] hasn't run yet (or its nonce was already
+    /// consumed by an earlier call to this method).
+    pub fn partial_sign<C: Verification>(
+        &mut self,
+        secp: &Secp256k1<C>,
+        keys: &[PublicKey],
+        our_index: usize,
+        agg_nonce: PublicKey,
+        msg: Hash,
+    ) -> Result<PartialSignature, AggregationError> {
+        if our_index >= keys.len() {
+            return Err(AggregationError::InvalidPartial(our_index));
+        }
+
+        let r = self
+            .nonce
+            .take()
+            .expect("commit_nonce must run before partial_sign");
+
+        let agg_key = aggregate_key(secp, keys)?;
+        let (px, _) = schnorr::split_compressed(&agg_key);
+        let addr_r = Address::from(agg_nonce);
+        let e = schnorr::challenge(addr_r, &px, msg);
+        let a_i = coefficient(keys, our_index);
+
+        let e_ai = modn::mul_mod(&e, &a_i, &SECP256K1_N);
+        let e_ai_xi = modn::mul_mod(&e_ai, &self.sk.secret_bytes(), &SECP256K1_N);
+        Ok(PartialSignature(modn::add_mod(
+            &r.secret_bytes(),
+            &e_ai_xi,
+            &SECP256K1_N,
+        )))
+    }
+}
+
+/// Checks each [NonceReveal] against the [NonceCommitment] collected for the
+/// same participant before either is trusted for [aggregate_nonce] - see
+/// the module docs for why this has to happen before any nonce is used.
+pub fn check_reveals(
+    commitments: &[NonceCommitment],
+    reveals: &[NonceReveal],
+) -> Result<(), AggregationError> {
+    if commitments.len() != reveals.len() {
+        return Err(AggregationError::ParticipantCountMismatch);
+    }
+    for (i, (c, r)) in commitments.iter().zip(reveals).enumerate() {
+        if hash_point(&r.0) != c.0 {
+            return Err(AggregationError::RevealDoesNotMatchCommitment(i));
+        }
+    }
+    Ok(())
+}
+
+/// `R = Σ R_i`, the aggregate nonce every participant signs against.
+pub fn aggregate_nonce(reveals: &[NonceReveal]) -> Result<PublicKey, AggregationError> {
+    combine(reveals.iter().map(|r| r.0))
+}
+
+/// `P = Σ a_i P_i`, the aggregate public key the resulting signature
+/// verifies against (via unmodified [schnorr::verify]).
+pub fn aggregate_key<C: Verification>(
+    secp: &Secp256k1<C>,
+    keys: &[PublicKey],
+) -> Result<PublicKey, AggregationError> {
+    if keys.is_empty() {
+        return Err(AggregationError::Empty);
+    }
+    let tweaked: Vec<PublicKey> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, k)| {
+            let a_i = coefficient(keys, i);
+            let scalar = Scalar::from_be_bytes(a_i).map_err(|_| AggregationError::Empty)?;
+            k.mul_tweak(secp, &scalar)
+                .map_err(|_| AggregationError::Empty)
+        })
+        .collect::<Result<_, _>>()?;
+    combine(tweaked)
+}
+
+/// Checks `s_i*G == R_i + e*a_i*P_i` for one partial signature before it's
+/// trusted - see the module docs for why every partial has to pass this
+/// before [aggregate_signature] sums it in.
+pub fn verify_partial<C: Verification>(
+    secp: &Secp256k1<C>,
+    keys: &[PublicKey],
+    index: usize,
+    reveal: NonceReveal,
+    agg_nonce: PublicKey,
+    msg: Hash,
+    partial: PartialSignature,
+) -> Result<(), AggregationError> {
+    if index >= keys.len() {
+        return Err(AggregationError::InvalidPartial(index));
+    }
+
+    let agg_key = aggregate_key(secp, keys)?;
+    let (px, _) = schnorr::split_compressed(&agg_key);
+    let addr_r = Address::from(agg_nonce);
+    let e = schnorr::challenge(addr_r, &px, msg);
+    let a_i = coefficient(keys, index);
+    let e_ai = modn::mul_mod(&e, &a_i, &SECP256K1_N);
+
+    let invalid = || AggregationError::InvalidPartial(index);
+
+    let lhs = SecretKey::from_slice(&partial.0)
+        .map(|s| PublicKey::from_secret_key(secp, &s))
+        .map_err(|_| invalid())?;
+
+    let e_ai_scalar = Scalar::from_be_bytes(e_ai).map_err(|_| invalid())?;
+    let rhs_tweak = keys[index]
+        .mul_tweak(secp, &e_ai_scalar)
+        .map_err(|_| invalid())?;
+    let rhs = reveal.0.combine(&rhs_tweak).map_err(|_| invalid())?;
+
+    if lhs == rhs {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Sums every [PartialSignature] into the final `(e, s)` signature,
+/// verifiable against [aggregate_key]'s result the same way a
+/// single-signer [schnorr::Signature] is. Callers must have already run
+/// [verify_partial] on each entry of `partials` - this only sums, it
+/// doesn't re-check.
+pub fn aggregate_signature<C: Verification>(
+    secp: &Secp256k1<C>,
+    keys: &[PublicKey],
+    agg_nonce: PublicKey,
+    msg: Hash,
+    partials: &[PartialSignature],
+) -> Result<schnorr::Signature, AggregationError> {
+    if partials.is_empty() || partials.len() != keys.len() {
+        return Err(AggregationError::ParticipantCountMismatch);
+    }
+
+    let agg_key = aggregate_key(secp, keys)?;
+    let (px, _) = schnorr::split_compressed(&agg_key);
+    let addr_r = Address::from(agg_nonce);
+    let e = schnorr::challenge(addr_r, &px, msg);
+
+    let mut s = [0u8; 32];
+    for p in partials {
+        s = modn::add_mod(&s, &p.0, &SECP256K1_N);
+    }
+
+    Ok(schnorr::Signature { e: Hash(e), s })
+}
+
+fn hash_point(pk: &PublicKey) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update(pk.serialize());
+    Hash(hasher.finalize().into())
+}
+
+/// `L = H(P_1 || .. || P_n)`, the binding hash the `a_i` coefficients are
+/// derived from.
+fn key_hash(keys: &[PublicKey]) -> Hash {
+    let mut hasher = Keccak256::new();
+    for k in keys {
+        hasher.update(k.serialize());
+    }
+    Hash(hasher.finalize().into())
+}
+
+/// `a_i = H(L || P_i) mod n`. `index` must be in bounds for `keys` - callers
+/// passing a caller-supplied (rather than internally-generated) index must
+/// check this themselves and map out-of-bounds to a typed error instead of
+/// letting this panic; see [verify_partial] and [Participant::partial_sign].
+fn coefficient(keys: &[PublicKey], index: usize) -> [u8; 32] {
+    let l = key_hash(keys);
+    let mut hasher = Keccak256::new();
+    hasher.update(l.0);
+    hasher.update(keys[index].serialize());
+    let digest: [u8; 32] = hasher.finalize().into();
+    modn::reduce(&digest, &SECP256K1_N)
+}
+
+fn combine(points: impl IntoIterator<Item = PublicKey>) -> Result<PublicKey, AggregationError> {
+    let points: Vec<PublicKey> = points.into_iter().collect();
+    if points.is_empty() {
+        return Err(AggregationError::Empty);
+    }
+    let refs: Vec<&PublicKey> = points.iter().collect();
+    PublicKey::combine_keys(&refs).map_err(|_| AggregationError::Empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Runs both MuSig rounds for `n` freshly generated participants and
+    /// returns their keys alongside each participant's partial signature
+    /// over `msg`, already checked via [verify_partial].
+    fn sign_round<C: Signing + Verification>(
+        secp: &Secp256k1<C>,
+        rng: &mut StdRng,
+        n: usize,
+        msg: Hash,
+    ) -> (Vec<PublicKey>, PublicKey, Vec<PartialSignature>) {
+        let mut participants: Vec<Participant> = (0..n)
+            .map(|_| Participant::new(SecretKey::new(rng)))
+            .collect();
+        let keys: Vec<PublicKey> = participants.iter().map(|p| p.public_key(secp)).collect();
+
+        let commitments: Vec<NonceCommitment> = participants
+            .iter_mut()
+            .map(|p| p.commit_nonce(secp, rng))
+            .collect();
+        let reveals: Vec<NonceReveal> = participants.iter().map(|p| p.reveal_nonce(secp)).collect();
+        check_reveals(&commitments, &reveals).unwrap();
+
+        let agg_nonce = aggregate_nonce(&reveals).unwrap();
+        let partials: Vec<PartialSignature> = participants
+            .iter_mut()
+            .enumerate()
+            .map(|(i, p)| {
+                let partial = p
+                    .partial_sign(secp, &keys, i, agg_nonce, msg)
+                    .expect("partial_sign");
+                verify_partial(secp, &keys, i, reveals[i], agg_nonce, msg, partial)
+                    .expect("partial should verify");
+                partial
+            })
+            .collect();
+
+        (keys, agg_nonce, partials)
+    }
+
+    #[test]
+    fn three_participant_round_trip_verifies() {
+        let secp = Secp256k1::new();
+        let mut rng = StdRng::seed_from_u64(0);
+        let msg = Hash([0x42; 32]);
+
+        let (keys, agg_nonce, partials) = sign_round(&secp, &mut rng, 3, msg);
+        let sig = aggregate_signature(&secp, &keys, agg_nonce, msg, &partials).unwrap();
+
+        let agg_key = aggregate_key(&secp, &keys).unwrap();
+        let (px, parity) = schnorr::split_compressed(&agg_key);
+        assert!(schnorr::verify(&px, parity, msg, sig).unwrap());
+    }
+
+    #[test]
+    fn verify_partial_rejects_out_of_bounds_index() {
+        let secp = Secp256k1::new();
+        let mut rng = StdRng::seed_from_u64(1);
+        let msg = Hash([0x11; 32]);
+
+        let (keys, agg_nonce, partials) = sign_round(&secp, &mut rng, 2, msg);
+        let bogus_reveal = reveal_for_test(&secp, &mut rng);
+
+        let result = verify_partial(
+            &secp,
+            &keys,
+            keys.len(),
+            bogus_reveal,
+            agg_nonce,
+            msg,
+            partials[0],
+        );
+        assert!(matches!(
+            result,
+            Err(AggregationError::InvalidPartial(index)) if index == keys.len()
+        ));
+    }
+
+    /// Any [NonceReveal] works for [verify_partial_rejects_out_of_bounds_index]
+    /// - the bounds check runs before `reveal` is ever used.
+    fn reveal_for_test<C: Signing>(secp: &Secp256k1<C>, rng: &mut StdRng) -> NonceReveal {
+        NonceReveal(PublicKey::from_secret_key(secp, &SecretKey::new(rng)))
+    }
+
+    #[test]
+    fn partial_sign_rejects_out_of_bounds_index() {
+        let secp = Secp256k1::new();
+        let mut rng = StdRng::seed_from_u64(2);
+        let msg = Hash([0x22; 32]);
+
+        let mut p1 = Participant::new(SecretKey::new(&mut rng));
+        let keys = vec![p1.public_key(&secp)];
+        let _ = p1.commit_nonce(&secp, &mut rng);
+        let agg_nonce = keys[0];
+
+        assert!(matches!(
+            p1.partial_sign(&secp, &keys, 1, agg_nonce, msg),
+            Err(AggregationError::InvalidPartial(1))
+        ));
+    }
+}
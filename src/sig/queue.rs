@@ -0,0 +1,272 @@
+//! Decouples "a signature is needed" from "a signature is produced" for
+//! callers whose private key can't answer [EthSigner::sign_eth] inline - a
+//! UI waiting on operator approval, a hardware wallet bridge, a remote
+//! signer that only replies out of band. [SigningQueue] is itself an
+//! [EthSigner]: drop it into [crate::PerunClient] like any other one, and
+//! every call the channel makes to sign an initial state, an update, or a
+//! withdrawal auth becomes a [SignRequest] instead of computing a signature
+//! immediately.
+//!
+//! [SigningQueue::sign_eth] blocks the calling thread until the request it
+//! enqueues is resolved via [SigningQueue::confirm]/[SigningQueue::reject],
+//! which - same as [super::remote::RemoteSigner] forwarding to a
+//! [super::remote::RemoteSignerTransport] - has to happen from another
+//! thread; this is therefore `std`-only; a non-blocking, `.await`-based
+//! counterpart belongs next to [crate::channel::update_and_apply_async]
+//! once something needs one for the embedded target, which has no threads
+//! to resolve a request from concurrently.
+//!
+//! [SigningQueue::auto_confirming] keeps today's behavior for callers that
+//! don't need any of this: it resolves every request itself with a wrapped
+//! backend [EthSigner] instead of ever blocking, while still reporting the
+//! full [QueueEvent::NewRequest]/[QueueEvent::RequestConfirmed] lifecycle to
+//! listeners, so swapping it in doesn't silently go dark on their event
+//! stream.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{
+    mpsc::{sync_channel, Receiver, SyncSender},
+    Mutex,
+};
+
+use super::EthSigner;
+use crate::abiencode::types::{Address, Hash, Signature};
+
+/// How many past-due [QueueEvent]s [SigningQueue::subscribe] buffers for a
+/// listener before [SigningQueue::sign_eth] starts blocking on that listener
+/// instead of continuing - bounded instead of unbounded like
+/// [crate::wire::AsyncMessageBus]'s queues, since a listener that stops
+/// draining a UI-facing event stream should eventually push back on the
+/// signer waiting on it rather than let memory grow without limit.
+const LISTENER_CAPACITY: usize = 64;
+
+/// Identifies a [SignRequest], stable across [SigningQueue::confirm]/
+/// [SigningQueue::reject] and the [QueueEvent]s a listener sees for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RequestId(u64);
+
+/// What a [SignRequest] is for, beyond the raw digest - lets a listener
+/// surface something more useful than an opaque hash to whoever approves it
+/// (e.g. the channel and version an operator is confirming on a hardware
+/// wallet's screen). Mirrors the extra context [EthSigner::sign_state]
+/// already receives over the plain [EthSigner::sign_eth] call.
+#[derive(Debug, Clone, Copy)]
+pub enum SignPurpose {
+    /// A plain [EthSigner::sign_eth] call with no further context.
+    Opaque,
+    /// Signing a channel state at `(channel_id, version)` - see
+    /// [EthSigner::sign_state].
+    ChannelState { channel_id: Hash, version: u64 },
+}
+
+/// A signature [SigningQueue] is waiting on, carried by
+/// [QueueEvent::NewRequest].
+#[derive(Debug, Clone, Copy)]
+pub struct SignRequest {
+    pub id: RequestId,
+    pub address: Address,
+    pub msg: Hash,
+    pub purpose: SignPurpose,
+}
+
+/// Emitted by [SigningQueue] as a [SignRequest] moves through its lifecycle.
+/// Subscribe with [SigningQueue::subscribe].
+#[derive(Debug, Clone, Copy)]
+pub enum QueueEvent {
+    NewRequest(SignRequest),
+    RequestConfirmed(RequestId),
+    RequestRejected(RequestId),
+}
+
+/// Failure reason for [SigningQueue]'s [EthSigner] impl.
+#[derive(Debug)]
+pub enum QueueSignError<E> {
+    /// The request was resolved with [SigningQueue::reject] instead of
+    /// [SigningQueue::confirm].
+    Rejected,
+    /// Every [SigningQueue] handle was dropped before the request could be
+    /// resolved, so nothing can ever confirm or reject it.
+    QueueDropped,
+    /// [SigningQueue::auto_confirming]'s wrapped backend itself failed.
+    Backend(E),
+    Recovery(super::Error),
+}
+
+/// [SigningQueue::confirm]/[SigningQueue::reject] was called for an id that
+/// doesn't exist - never enqueued, already resolved, or belonging to a
+/// different [SigningQueue].
+#[derive(Debug)]
+pub struct UnknownRequest(pub RequestId);
+
+struct Inner<S> {
+    address: Address,
+    /// `Some` for [SigningQueue::auto_confirming]: every request is signed
+    /// with this backend immediately instead of ever waiting on
+    /// [SigningQueue::confirm]. `None` for [SigningQueue::new], where every
+    /// request is resolved out-of-band.
+    auto_confirm: Option<S>,
+    next_id: AtomicU64,
+    pending: Mutex<BTreeMap<RequestId, SyncSender<Result<Signature, ()>>>>,
+    listeners: Mutex<Vec<SyncSender<QueueEvent>>>,
+}
+
+/// [EthSigner] that turns every signing call into a [SignRequest] instead of
+/// producing a signature inline - see the module docs.
+///
+/// Cloning a [SigningQueue] shares the same pending-request table and
+/// listener set (it's a thin [Arc] handle), so the side that drives
+/// [crate::PerunClient] and the side that resolves requests (a UI thread,
+/// an approval workflow) each hold their own handle onto the same queue.
+#[derive(Clone)]
+pub struct SigningQueue<S: EthSigner> {
+    inner: Arc<Inner<S>>,
+}
+
+impl<S: EthSigner> SigningQueue<S> {
+    /// A queue with no auto-confirming backend: every [SignRequest] blocks
+    /// the calling [EthSigner::sign_eth] until something calls
+    /// [SigningQueue::confirm]/[SigningQueue::reject] for it.
+    pub fn new(address: Address) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                address,
+                auto_confirm: None,
+                next_id: AtomicU64::new(0),
+                pending: Mutex::new(BTreeMap::new()),
+                listeners: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// A queue that resolves every [SignRequest] itself with `backend`
+    /// instead of ever blocking on [SigningQueue::confirm] - the default
+    /// this module's docs promise, so that swapping an in-process [Signer][
+    /// super::Signer] for a [SigningQueue] doesn't change behavior for a
+    /// caller that has no out-of-band approval step yet. Still reports the
+    /// full [QueueEvent::NewRequest]/[QueueEvent::RequestConfirmed]
+    /// lifecycle to listeners, so adding one later is just a matter of
+    /// switching to [SigningQueue::new].
+    pub fn auto_confirming(backend: S) -> Self {
+        let address = backend.address();
+        Self {
+            inner: Arc::new(Inner {
+                address,
+                auto_confirm: Some(backend),
+                next_id: AtomicU64::new(0),
+                pending: Mutex::new(BTreeMap::new()),
+                listeners: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Subscribe to every [QueueEvent] this queue emits from now on - the
+    /// listener API a front-end drains to surface pending requests and let
+    /// an operator approve them.
+    pub fn subscribe(&self) -> Receiver<QueueEvent> {
+        let (tx, rx) = sync_channel(LISTENER_CAPACITY);
+        self.inner.listeners.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Resolve `id` with `signature`, waking the [EthSigner::sign_eth] call
+    /// that's waiting on it.
+    pub fn confirm(&self, id: RequestId, signature: Signature) -> Result<(), UnknownRequest> {
+        self.resolve(id, Ok(signature))
+    }
+
+    /// Resolve `id` as rejected, so the waiting [EthSigner::sign_eth] call
+    /// fails with [QueueSignError::Rejected] instead of blocking forever.
+    pub fn reject(&self, id: RequestId) -> Result<(), UnknownRequest> {
+        self.resolve(id, Err(()))
+    }
+
+    fn resolve(&self, id: RequestId, result: Result<Signature, ()>) -> Result<(), UnknownRequest> {
+        let tx = self
+            .inner
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(UnknownRequest(id))?;
+        let event = match &result {
+            Ok(_) => QueueEvent::RequestConfirmed(id),
+            Err(()) => QueueEvent::RequestRejected(id),
+        };
+        // The waiting `sign_eth` call may already have given up on some
+        // other error path - that's not this call's problem to report.
+        let _ = tx.send(result);
+        self.broadcast(event);
+        Ok(())
+    }
+
+    fn broadcast(&self, event: QueueEvent) {
+        let mut listeners = self.inner.listeners.lock().unwrap();
+        listeners.retain(|tx| tx.send(event).is_ok());
+    }
+
+    fn next_id(&self) -> RequestId {
+        RequestId(self.inner.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn sign(&self, msg: Hash, purpose: SignPurpose) -> Result<Signature, QueueSignError<S::Error>> {
+        let id = self.next_id();
+
+        if let Some(backend) = &self.inner.auto_confirm {
+            let signature = backend.sign_eth(msg).map_err(QueueSignError::Backend)?;
+            self.broadcast(QueueEvent::NewRequest(SignRequest {
+                id,
+                address: self.inner.address,
+                msg,
+                purpose,
+            }));
+            self.broadcast(QueueEvent::RequestConfirmed(id));
+            return Ok(signature);
+        }
+
+        let (tx, rx) = sync_channel(1);
+        self.inner.pending.lock().unwrap().insert(id, tx);
+        self.broadcast(QueueEvent::NewRequest(SignRequest {
+            id,
+            address: self.inner.address,
+            msg,
+            purpose,
+        }));
+
+        rx.recv()
+            .map_err(|_| QueueSignError::QueueDropped)?
+            .map_err(|()| QueueSignError::Rejected)
+    }
+}
+
+impl<S: EthSigner> EthSigner for SigningQueue<S> {
+    type Error = QueueSignError<S::Error>;
+
+    fn address(&self) -> Address {
+        self.inner.address
+    }
+
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, Self::Error> {
+        self.sign(msg, SignPurpose::Opaque)
+    }
+
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, Self::Error> {
+        super::recover_eth_signer(msg, eth_sig).map_err(QueueSignError::Recovery)
+    }
+
+    fn sign_state(
+        &self,
+        channel_id: Hash,
+        version: u64,
+        msg: Hash,
+    ) -> Result<Signature, Self::Error> {
+        self.sign(
+            msg,
+            SignPurpose::ChannelState {
+                channel_id,
+                version,
+            },
+        )
+    }
+}
@@ -0,0 +1,214 @@
+//! [EthSigner] backends whose private key doesn't live in this process: a
+//! [RemoteSigner] that forwards signing requests to an external device, and
+//! a [MultiSigner] that lets [crate::PerunClient] be configured with more
+//! than one account (local and/or remote) at once.
+
+use super::{recover_eth_signer, EthSigner, SigningError};
+use crate::abiencode::types::{Address, Hash, Signature};
+
+extern crate alloc;
+use alloc::boxed::Box;
+
+/// Round-trips a signing request to wherever `address`'s private key
+/// actually lives - an HSM, a secure enclave on the embedded target, or a
+/// hardware wallet reached over some other transport - and back.
+///
+/// Implementations only need to produce the raw 65-byte recoverable
+/// signature over `digest`; [RemoteSigner] already applies the
+/// `\x19Ethereum Signed Message` prefix before calling this (`digest` is the
+/// same value [EthSigner::sign_eth] would hash with
+/// [super::hash_to_eth_signed_msg_hash]), so the remote side never has to
+/// know about Ethereum's message format, only sign a 32-byte digest.
+pub trait RemoteSignerTransport {
+    type Error: core::fmt::Debug;
+
+    /// Ask the remote device to sign `digest` as `address`.
+    fn sign_digest(&self, address: Address, digest: Hash) -> Result<Signature, Self::Error>;
+}
+
+/// Failure reason for [RemoteSigner]'s [EthSigner] impl: either the
+/// [RemoteSignerTransport] itself failed, or the (purely local) signature
+/// recovery did.
+#[derive(Debug)]
+pub enum RemoteSignerError<E> {
+    Transport(E),
+    Recovery(super::Error),
+}
+
+/// An [EthSigner] that forwards every [EthSigner::sign_eth] call to a
+/// [RemoteSignerTransport] instead of holding a private key itself.
+/// Recovery doesn't need the remote device at all - it's pure public-key
+/// math - so [EthSigner::recover_signer] is handled locally via
+/// [recover_eth_signer].
+#[derive(Debug)]
+pub struct RemoteSigner<T: RemoteSignerTransport> {
+    address: Address,
+    transport: T,
+}
+
+impl<T: RemoteSignerTransport> RemoteSigner<T> {
+    /// `address` must be the address controlled by the key `transport`
+    /// signs with; this isn't verified here (the caller is expected to know
+    /// which address their hardware device was provisioned with).
+    pub fn new(address: Address, transport: T) -> Self {
+        Self { address, transport }
+    }
+}
+
+impl<T: RemoteSignerTransport> EthSigner for RemoteSigner<T> {
+    type Error = RemoteSignerError<T::Error>;
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, Self::Error> {
+        let digest = super::hash_to_eth_signed_msg_hash(msg);
+        self.transport
+            .sign_digest(self.address, digest)
+            .map_err(RemoteSignerError::Transport)
+    }
+
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, Self::Error> {
+        recover_eth_signer(msg, eth_sig).map_err(RemoteSignerError::Recovery)
+    }
+}
+
+/// Object-safe counterpart to [EthSigner], used by [MultiSigner] to hold
+/// heterogeneous signer backends (e.g. one address backed by a local
+/// [super::Signer], another by a [RemoteSigner]) behind a single `dyn`
+/// entry. Erases the backend's own error type into [SigningError], the same
+/// way this crate's channel types already do for a single [EthSigner] (see
+/// [SigningError]).
+pub trait DynEthSigner {
+    fn address(&self) -> Address;
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, SigningError>;
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, SigningError>;
+}
+
+impl<S: EthSigner> DynEthSigner for S {
+    fn address(&self) -> Address {
+        EthSigner::address(self)
+    }
+
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, SigningError> {
+        EthSigner::sign_eth(self, msg).map_err(SigningError::capture)
+    }
+
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, SigningError> {
+        EthSigner::recover_signer(self, msg, eth_sig).map_err(SigningError::capture)
+    }
+}
+
+/// How many accounts a single [MultiSigner] can hold at once, mirroring the
+/// fixed capacity [crate::PerunClient] already uses for its own bounded
+/// tables.
+const MAX_ACCOUNTS: usize = 4;
+
+/// Error returned by [MultiSigner::register].
+#[derive(Debug)]
+pub enum RegisterError {
+    /// [MultiSigner] is already holding [MAX_ACCOUNTS] accounts.
+    TooManyAccounts,
+    /// `address` is already registered.
+    AlreadyRegistered(Address),
+}
+
+/// [MultiSigner::select] was called for an [Address] that isn't registered.
+#[derive(Debug)]
+pub struct UnknownAddress(pub Address);
+
+/// Lets a [crate::PerunClient] be configured with more than one signing
+/// account - some local, some reached through a [RemoteSigner] - instead of
+/// being locked to a single address for its whole lifetime. Exactly one
+/// registered account is "active" at a time ([MultiSigner::select]); that's
+/// the one [EthSigner::address]/[EthSigner::sign_eth] use. Mirrors the
+/// `AccountProvider` idea OpenEthereum uses to juggle local and external
+/// (hardware) accounts behind one interface.
+pub struct MultiSigner {
+    accounts: [Option<Box<dyn DynEthSigner>>; MAX_ACCOUNTS],
+    active: usize,
+}
+
+impl core::fmt::Debug for MultiSigner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let addresses: alloc::vec::Vec<_> = self
+            .accounts
+            .iter()
+            .map(|s| s.as_ref().map(|s| s.address()))
+            .collect();
+        f.debug_struct("MultiSigner")
+            .field("accounts", &addresses)
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+impl MultiSigner {
+    /// Create a new [MultiSigner], registering `first` as its initial
+    /// active account.
+    pub fn new(first: impl DynEthSigner + 'static) -> Self {
+        const EMPTY: Option<Box<dyn DynEthSigner>> = None;
+        let mut accounts = [EMPTY; MAX_ACCOUNTS];
+        accounts[0] = Some(Box::new(first) as Box<dyn DynEthSigner>);
+        Self {
+            accounts,
+            active: 0,
+        }
+    }
+
+    /// Register another account. Does not change which account is active,
+    /// see [MultiSigner::select].
+    pub fn register(&mut self, signer: impl DynEthSigner + 'static) -> Result<(), RegisterError> {
+        let address = signer.address();
+        if self.is_external(address) {
+            return Err(RegisterError::AlreadyRegistered(address));
+        }
+        let slot = self
+            .accounts
+            .iter_mut()
+            .find(|s| s.is_none())
+            .ok_or(RegisterError::TooManyAccounts)?;
+        *slot = Some(Box::new(signer));
+        Ok(())
+    }
+
+    /// Make `address` the active account, see [MultiSigner].
+    pub fn select(&mut self, address: Address) -> Result<(), UnknownAddress> {
+        let index = self
+            .accounts
+            .iter()
+            .position(|s| matches!(s, Some(s) if s.address() == address))
+            .ok_or(UnknownAddress(address))?;
+        self.active = index;
+        Ok(())
+    }
+}
+
+impl EthSigner for MultiSigner {
+    type Error = SigningError;
+
+    fn address(&self) -> Address {
+        self.accounts[self.active]
+            .as_ref()
+            .expect("active always points at a registered account")
+            .address()
+    }
+
+    fn sign_eth(&self, msg: Hash) -> Result<Signature, Self::Error> {
+        self.accounts[self.active]
+            .as_ref()
+            .expect("active always points at a registered account")
+            .sign_eth(msg)
+    }
+
+    fn recover_signer(&self, msg: Hash, eth_sig: Signature) -> Result<Address, Self::Error> {
+        recover_eth_signer(msg, eth_sig).map_err(SigningError::capture)
+    }
+
+    fn is_external(&self, address: Address) -> bool {
+        self.accounts
+            .iter()
+            .any(|s| matches!(s, Some(s) if s.address() == address))
+    }
+}
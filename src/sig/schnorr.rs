@@ -0,0 +1,170 @@
+//! secp256k1 Schnorr signer whose signatures can be verified on-chain with
+//! Solidity's `ecrecover` precompile instead of a native Schnorr/bn256
+//! precompile, using the trick popularized by
+//! <https://github.com/noot/schnorr-verify>. This is a separate signature
+//! scheme from the plain ECDSA [Signer][super::Signer] used for channel
+//! state signatures; it exists so participants can aggregate their keys into
+//! a single on-chain verifiable key later on.
+//!
+//! Signing (secret `x`, public `P = xG`, `px` = x-coordinate of `P`):
+//! - pick a nonce `k`, `R = kG`
+//! - challenge `e = keccak256(addr(R) || px || m) mod n`
+//! - response `s = (k + e*x) mod n`
+//! - signature is `(e, s)`
+//!
+//! Verification recovers `R` through `ecrecover` and checks that `e` commits
+//! to it: with `Q = R` derived from `sG = R + eP`, the Solidity side only
+//! needs
+//! ```solidity
+//! address R = ecrecover(-s*px mod n, 27 + parity(P), px, -e*px mod n);
+//! require(R != address(0));
+//! return e == keccak256(abi.encodePacked(R, px, message));
+//! ```
+//! which this module reproduces in Rust so tests can check agreement with
+//! the contract.
+
+use crate::abiencode::types::{Address, Hash};
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    Message, PublicKey, Secp256k1, SecretKey, SignOnly,
+};
+use sha3::{Digest, Keccak256};
+
+use super::modn::{self, SECP256K1_N};
+
+pub use secp256k1::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub e: Hash,
+    pub s: [u8; 32],
+}
+
+#[derive(Debug)]
+pub struct Signer {
+    secp: Secp256k1<SignOnly>,
+    sk: SecretKey,
+    /// x-coordinate of the public key, i.e. what goes into `r`/the `px` term
+    /// of the challenge. Cached because every signing/verification
+    /// operation needs it.
+    px: [u8; 32],
+    /// Parity of the public key's y-coordinate (0 or 1), i.e. `v - 27` in the
+    /// `ecrecover` call used for verification.
+    parity: u8,
+    addr: Address,
+}
+
+impl Signer {
+    pub fn random<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
+        let secp = Secp256k1::signing_only();
+        let (sk, pk) = secp.generate_keypair(rng);
+        Self::from_keypair(secp, sk, pk)
+    }
+
+    pub fn from_secret_bytes(sk: &[u8; 32]) -> Result<Self, Error> {
+        let secp = Secp256k1::signing_only();
+        let sk = SecretKey::from_slice(sk)?;
+        let pk = PublicKey::from_secret_key(&secp, &sk);
+        Ok(Self::from_keypair(secp, sk, pk))
+    }
+
+    fn from_keypair(secp: Secp256k1<SignOnly>, sk: SecretKey, pk: PublicKey) -> Self {
+        let (px, parity) = split_compressed(&pk);
+        Self {
+            secp,
+            sk,
+            px,
+            parity,
+            addr: Address::from(pk),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.addr
+    }
+
+    pub fn sign<R: rand::Rng + rand::CryptoRng>(&self, rng: &mut R, msg: Hash) -> Signature {
+        loop {
+            let k = SecretKey::new(rng);
+            let r_pub = PublicKey::from_secret_key(&self.secp, &k);
+            let addr_r = Address::from(r_pub);
+
+            let e = challenge(addr_r, &self.px, msg);
+            if e == [0u8; 32] {
+                continue; // Degenerate challenge, retry with a fresh nonce.
+            }
+
+            // s = k + e*x mod n
+            let ex = modn::mul_mod(&e, &self.sk.secret_bytes(), &SECP256K1_N);
+            let s = modn::add_mod(&k.secret_bytes(), &ex, &SECP256K1_N);
+
+            return Signature { e: Hash(e), s };
+        }
+    }
+
+    pub fn verify(&self, msg: Hash, sig: Signature) -> Result<bool, Error> {
+        verify(&self.px, self.parity, msg, sig)
+    }
+}
+
+/// Split a compressed public key into its x-coordinate (`px`) and the parity
+/// of its y-coordinate, as used throughout this module's `ecrecover` trick.
+/// `pub(crate)` so [super::musig] can split an aggregated public key the
+/// same way instead of duplicating this.
+pub(crate) fn split_compressed(pk: &PublicKey) -> ([u8; 32], u8) {
+    let compressed = pk.serialize();
+    let px: [u8; 32] = compressed[1..].try_into().unwrap();
+    let parity = compressed[0] - 2; // 0x02 => even (0), 0x03 => odd (1)
+    (px, parity)
+}
+
+/// `pub(crate)` so [super::musig] can compute the same challenge against an
+/// aggregated `R`/`P` instead of duplicating this - the challenge/verification
+/// math doesn't care whether `R`/`px` belong to a single key or an aggregate
+/// one.
+pub(crate) fn challenge(addr_r: Address, px: &[u8; 32], msg: Hash) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(addr_r.0);
+    hasher.update(px);
+    hasher.update(msg.0);
+    let digest: [u8; 32] = hasher.finalize().into();
+    modn::reduce(&digest, &SECP256K1_N)
+}
+
+/// Verify a Schnorr signature `(e, s)` for public key x-coordinate `px` and
+/// parity `parity` (0 or 1) against `msg`, using the same `ecrecover`
+/// reconstruction the on-chain verifier performs.
+///
+/// Note the edge cases the on-chain verifier also has to reject: `px == 0`
+/// (invalid point) and the `n`-reduced values of `-s*px`/`-e*px` happening to
+/// be zero, both of which make [secp256k1::ecdsa::RecoverableSignature] or
+/// [Message] construction fail below and are surfaced as `Ok(false)` rather
+/// than an error, matching `ecrecover` returning the zero address.
+pub fn verify(px: &[u8; 32], parity: u8, msg: Hash, sig: Signature) -> Result<bool, Error> {
+    if px == &[0u8; 32] {
+        return Ok(false);
+    }
+
+    let s_px = modn::mul_mod(&sig.s, px, &SECP256K1_N);
+    let msg_hash = modn::neg_mod(&s_px, &SECP256K1_N);
+
+    let e_px = modn::mul_mod(&sig.e.0, px, &SECP256K1_N);
+    let sig_s = modn::neg_mod(&e_px, &SECP256K1_N);
+
+    if msg_hash == [0u8; 32] || sig_s == [0u8; 32] {
+        return Ok(false);
+    }
+
+    let mut rs = [0u8; 64];
+    rs[..32].copy_from_slice(px);
+    rs[32..].copy_from_slice(&sig_s);
+
+    let recid = RecoveryId::from_i32(parity.into())?;
+    let recoverable = RecoverableSignature::from_compact(&rs, recid)?;
+    let message = Message::from_slice(&msg_hash)?;
+
+    let secp = Secp256k1::verification_only();
+    let recovered_r: Address = secp.recover_ecdsa(&message, &recoverable)?.into();
+
+    Ok(sig.e.0 == challenge(recovered_r, px, msg))
+}
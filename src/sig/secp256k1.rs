@@ -1,11 +1,11 @@
 //! Signer using the secp256k1 C-Library.
 
-use super::hash_to_eth_signed_msg_hash;
+use super::{bip32, hash_to_eth_signed_msg_hash};
 use crate::abiencode::types::{Address, Hash, Signature};
 use secp256k1::{
     self,
     ecdsa::{RecoverableSignature, RecoveryId},
-    All, Message, Secp256k1, SecretKey, PublicKey
+    All, Message, PublicKey, Secp256k1, SecretKey,
 };
 
 pub use secp256k1::Error;
@@ -18,28 +18,10 @@ pub struct Signer {
 }
 
 impl Signer {
-    pub fn new<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
-        // let secp = Secp256k1::new();
-        // let (sk, pk) = secp.generate_keypair(rng);
-
-        let private_key_bytes: [u8; 32] = [
-            0x24, 0x4F, 0xFC, 0x73, 0xC4, 0x48, 0xB5, 0x6D,
-            0xDB, 0xA6, 0xA7, 0xBF, 0xA8, 0xD5, 0x8E, 0xD3,
-            0x60, 0x12, 0x61, 0x1D, 0xA8, 0x3D, 0x4C, 0xB8,
-            0x30, 0x25, 0xEA, 0x12, 0xAC, 0xCF, 0x49, 0xFE,
-        ];
-
-        // Create a Secp256k1 context
+    /// Generate a fresh, random keypair using the given RNG.
+    pub fn random<R: rand::Rng + rand::CryptoRng>(rng: &mut R) -> Self {
         let secp = Secp256k1::new();
-
-        // Create the private key from the byte array
-        let sk = SecretKey::from_slice(&private_key_bytes)
-            .expect("Invalid private key");
-
-        // Generate the corresponding public key
-        let pk = PublicKey::from_secret_key(&secp, &private_key);
-
-
+        let (sk, pk) = secp.generate_keypair(rng);
 
         Self {
             secp,
@@ -48,6 +30,29 @@ impl Signer {
         }
     }
 
+    /// Load a keypair from an existing 32-byte secret key, e.g. one kept in
+    /// a device's configuration.
+    pub fn from_secret_bytes(sk: &[u8; 32]) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_slice(sk)?;
+        let pk = PublicKey::from_secret_key(&secp, &sk);
+
+        Ok(Self {
+            secp,
+            sk,
+            addr: pk.into(),
+        })
+    }
+
+    /// Derive a keypair from a BIP-39 mnemonic phrase, following the
+    /// Ethereum BIP-44 derivation path `m/44'/60'/0'/0/account_index`. This
+    /// does not validate the mnemonic's wordlist/checksum.
+    pub fn from_mnemonic(phrase: &str, account_index: u32) -> Result<Self, Error> {
+        let seed = bip32::mnemonic_to_seed(phrase, "");
+        let sk = bip32::derive_secret_key(&seed, account_index);
+        Self::from_secret_bytes(&sk)
+    }
+
     pub fn address(&self) -> Address {
         self.addr
     }
@@ -101,6 +106,14 @@ impl Signer {
         Signature::new(&rs, v)
     }
 
+    /// Sign like [Self::sign_eth()], but return the
+    /// [EIP-2098](https://eips.ethereum.org/EIPS/eip-2098) compact 64-byte
+    /// signature instead of the 65-byte one. This is what OpenZeppelin's
+    /// `ECDSA.sol` accepts and halves the signature size on the wire.
+    pub fn sign_eth_compact(&self, msg: Hash) -> [u8; 64] {
+        self.sign_eth(msg).to_compact()
+    }
+
     /// Recover the Public Key from a signature.
     ///
     /// Hash is the hash of the data given to [Self::sign_eth()], it should not
@@ -108,16 +121,104 @@ impl Signer {
     ///
     /// To get the Ethereum Address use `into()`.
     pub fn recover_signer(&self, hash: Hash, eth_sig: Signature) -> Result<Address, Error> {
-        let hash = hash_to_eth_signed_msg_hash(hash);
+        recover_eth_signer(hash, eth_sig)
+    }
 
-        let rs = &eth_sig.0[..64];
-        let v = eth_sig.0[64] - 27;
+    /// Counterpart to [Self::sign_eth_compact()], recovers the signer from a
+    /// compact 64-byte signature.
+    pub fn recover_signer_compact(
+        &self,
+        hash: Hash,
+        compact_sig: [u8; 64],
+    ) -> Result<Address, Error> {
+        self.recover_signer(hash, Signature::from_compact(&compact_sig))
+    }
 
-        let recid = RecoveryId::from_i32(v.into())?;
-        let sig = RecoverableSignature::from_compact(rs, recid)?;
+    /// Sign a transaction-style hash following
+    /// [EIP-155](https://eips.ethereum.org/EIPS/eip-155): unlike
+    /// [Self::sign_eth()] this does not add the `\x19Ethereum Signed
+    /// Message` prefix (transaction hashes are signed directly), and folds
+    /// `chain_id` into `v` as `chain_id * 2 + 35 + yParity` instead of the
+    /// `27`/`28` used for personal messages. Use this when the same device
+    /// has to sign for adjudicator contracts on different EVM networks.
+    ///
+    /// Returns an [Eip155Signature] rather than [Signature] because `v` can
+    /// need more than the single byte [Signature] reserves for it once
+    /// `chain_id` is folded in (e.g. chain id 137 already pushes `v` past
+    /// 255).
+    pub fn sign_eth_155(&self, msg: Hash, chain_id: u64) -> Eip155Signature {
+        let sig = self
+            .secp
+            .sign_ecdsa_recoverable(&Message::from(msg), &self.sk);
+        let (recid, rs) = sig.serialize_compact();
+
+        debug_assert!(rs[32] & 0x80 == 0);
+
+        let y_parity = recid.to_i32() as u64;
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(&rs[..32]);
+        s.copy_from_slice(&rs[32..]);
 
-        let pk = self.secp.recover_ecdsa(&Message::from(hash), &sig)?;
+        Eip155Signature {
+            r,
+            s,
+            v: chain_id * 2 + 35 + y_parity,
+        }
+    }
+
+    /// Counterpart to [Self::sign_eth_155()]: recovers the signer and the
+    /// chain id the signature was made for by stripping it back out of `v`.
+    pub fn recover_signer_155(
+        &self,
+        hash: Hash,
+        sig: Eip155Signature,
+    ) -> Result<(Address, u64), Error> {
+        let y_parity = (sig.v - 35) % 2;
+        let chain_id = (sig.v - 35 - y_parity) / 2;
+
+        let mut rs = [0u8; 64];
+        rs[..32].copy_from_slice(&sig.r);
+        rs[32..].copy_from_slice(&sig.s);
+
+        let recid = RecoveryId::from_i32(y_parity as i32)?;
+        let recoverable = RecoverableSignature::from_compact(&rs, recid)?;
+        let pk = self
+            .secp
+            .recover_ecdsa(&Message::from(hash), &recoverable)?;
 
-        Ok(pk.into())
+        Ok((pk.into(), chain_id))
     }
 }
+
+/// Recover the address that produced `eth_sig` over `msg`. Doesn't need a
+/// [Signer] instance - unlike signing, recovery is pure public-key math - so
+/// [super::remote::RemoteSigner] can use this to implement
+/// [crate::sig::EthSigner::recover_signer] without holding a local keypair.
+pub(crate) fn recover_eth_signer(hash: Hash, eth_sig: Signature) -> Result<Address, Error> {
+    let hash = hash_to_eth_signed_msg_hash(hash);
+
+    let rs = &eth_sig.0[..64];
+    let v = eth_sig.0[64] - 27;
+
+    let recid = RecoveryId::from_i32(v.into())?;
+    let sig = RecoverableSignature::from_compact(rs, recid)?;
+
+    // Recovery only needs a verification context, so this doesn't require an
+    // existing [Signer] (which also carries a secret key).
+    let secp = Secp256k1::verification_only();
+    let pk = secp.recover_ecdsa(&Message::from(hash), &sig)?;
+
+    Ok(pk.into())
+}
+
+/// The `(r, s, v)` triple of an [EIP-155](https://eips.ethereum.org/EIPS/eip-155)
+/// transaction-style signature, as produced by [Signer::sign_eth_155()]. Kept
+/// separate from [Signature] because `v` here folds in the chain id and can
+/// exceed the single byte [Signature] reserves for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Eip155Signature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    pub v: u64,
+}
@@ -40,7 +40,7 @@ macro_rules! make_compare_hardcoded {
 
             // Do not use that on any real device, this is just for testing.
             let mut rng = StdRng::seed_from_u64(0);
-            let signer = <$signer>::new(&mut rng);
+            let signer = <$signer>::random(&mut rng);
             let sig = signer.sign_eth(data());
 
             println!("Address: {}", signer.address().0.encode_hex::<String>());
@@ -65,14 +65,14 @@ macro_rules! make_a_to_b {
         fn $name() {
             // Do not use that on any real device, this is just for testing.
             let mut rng = StdRng::seed_from_u64(0);
-            let signer = <$signer>::new(&mut rng);
+            let signer = <$signer>::random(&mut rng);
             let msg = data();
             let sig = signer.sign_eth(msg);
 
             println!("Address: {}", signer.address().0.encode_hex::<String>());
             println!("Sig: 0x{}", sig.0.encode_hex::<String>());
 
-            let verifier = <$verifier>::new(&mut rng);
+            let verifier = <$verifier>::random(&mut rng);
             let address = verifier.recover_signer(msg, sig).unwrap();
 
             assert_eq!(address, signer.address());
@@ -122,6 +122,47 @@ make_a_to_b!(
     super::secp256k1::Signer
 );
 
+#[cfg(feature = "secp256k1")]
+#[test]
+fn secp256k1_compact_roundtrip() {
+    // Do not use that on any real device, this is just for testing.
+    let mut rng = StdRng::seed_from_u64(0);
+    let signer = super::secp256k1::Signer::random(&mut rng);
+    let msg = data();
+
+    let sig = signer.sign_eth(msg);
+    let compact = signer.sign_eth_compact(msg);
+
+    assert_eq!(sig.to_compact(), compact);
+    assert_eq!(
+        crate::abiencode::types::Signature::from_compact(&compact),
+        sig
+    );
+
+    let address = signer.recover_signer_compact(msg, compact).unwrap();
+    assert_eq!(address, signer.address());
+}
+
+#[cfg(feature = "secp256k1")]
+#[test]
+fn secp256k1_eip155_roundtrip() {
+    // Do not use that on any real device, this is just for testing.
+    let mut rng = StdRng::seed_from_u64(0);
+    let signer = super::secp256k1::Signer::random(&mut rng);
+    let msg = data();
+
+    // Ethereum mainnet and a representative L2/testnet chain id, the latter
+    // large enough that `v` no longer fits a single byte.
+    for chain_id in [1u64, 80001u64] {
+        let sig = signer.sign_eth_155(msg, chain_id);
+        assert!(sig.v == chain_id * 2 + 35 || sig.v == chain_id * 2 + 36);
+
+        let (address, recovered_chain_id) = signer.recover_signer_155(msg, sig).unwrap();
+        assert_eq!(address, signer.address());
+        assert_eq!(recovered_chain_id, chain_id);
+    }
+}
+
 // #[cfg(feature = "secp256k1")]
 // fn secp256k1_sign() {
 //     // This test may break in the future (e.g. if the dependency changes
@@ -130,7 +171,7 @@ make_a_to_b!(
 
 //     // Do not use that on any real device, this is just for testing.
 //     let mut rng = StdRng::seed_from_u64(0);
-//     let signer = super::secp256k1::Signer::new(&mut rng);
+//     let signer = super::secp256k1::Signer::random(&mut rng);
 
 //     let sig = signer.sign_eth(data());
 
@@ -146,7 +187,7 @@ make_a_to_b!(
 
 //     // Do not use that on any real device, this is just for testing.
 //     let mut rng = StdRng::seed_from_u64(0);
-//     let signer = super::k256::Signer::new(&mut rng);
+//     let signer = super::k256::Signer::random(&mut rng);
 
 //     let sig = signer.sign_eth(data());
 
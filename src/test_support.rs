@@ -0,0 +1,62 @@
+//! Fixtures shared by this crate's own `#[cfg(test)]` modules (`channel::proposal`,
+//! `channel::channel_update`, `sig::enforcing`, `sig::validating`) so each one
+//! stops hand-rolling its own near-identical stand-in `EthSigner`/`MessageBus`.
+//! Only compiled in under `#[cfg(test)]` (see `lib.rs`), so it adds nothing to
+//! non-test builds.
+
+use crate::{
+    abiencode::types::{Address, Hash, Signature},
+    messages::{FunderRequestMessage, ParticipantMessage, WatcherRequestMessage},
+    sig::EthSigner,
+    wire::{Identity, MessageBus},
+};
+
+/// An [EthSigner] that "signs" by encoding its address into the signature's
+/// first 20 bytes and "recovers" by decoding them back out - these tests only
+/// exercise bookkeeping built on top of signing/recovery, not any actual
+/// cryptography. [signature_for] builds the same encoding for a participant
+/// other than the one local to the [StubSigner] under test (e.g. to simulate
+/// a multi-party channel's other participants).
+pub(crate) struct StubSigner(pub(crate) Address);
+
+impl EthSigner for StubSigner {
+    type Error = core::convert::Infallible;
+
+    fn address(&self) -> Address {
+        self.0
+    }
+
+    fn sign_eth(&self, _msg: Hash) -> Result<Signature, Self::Error> {
+        Ok(signature_for(self.0))
+    }
+
+    fn recover_signer(&self, _msg: Hash, eth_sig: Signature) -> Result<Address, Self::Error> {
+        let mut bytes = [0u8; 20];
+        bytes.copy_from_slice(&eth_sig.0[..20]);
+        Ok(Address(bytes))
+    }
+}
+
+/// Builds the [Signature] a [StubSigner] for `addr` would produce, without
+/// needing one - see [StubSigner]'s docs.
+pub(crate) fn signature_for(addr: Address) -> Signature {
+    let mut bytes = [0u8; 65];
+    bytes[..20].copy_from_slice(&addr.0);
+    Signature(bytes)
+}
+
+/// A [MessageBus] that drops everything sent through it - shared by tests
+/// that only check a type's own state, not what ends up "on the wire".
+pub(crate) struct NullBus;
+
+impl MessageBus for NullBus {
+    fn send_to_watcher(&self, _msg: WatcherRequestMessage) {}
+    fn send_to_funder(&self, _msg: FunderRequestMessage) {}
+    fn send_to_participant(
+        &self,
+        _sender: &Identity,
+        _recipient: &Identity,
+        _msg: ParticipantMessage,
+    ) {
+    }
+}
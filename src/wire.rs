@@ -1,7 +1,41 @@
+#[cfg(feature = "async")]
+mod async_bus;
+mod codec;
 mod encoding;
+mod encrypted;
+#[cfg(feature = "libp2p")]
+mod libp2p_bus;
+#[cfg(feature = "pure-rust-wire")]
+mod pb;
+#[cfg(feature = "smoltcp")]
+mod smoltcp_bus;
 
 use alloc::vec::Vec;
-pub use encoding::ProtoBufEncodingLayer;
+#[cfg(feature = "async")]
+pub use async_bus::{
+    async_message_bus, AsyncMessageBus, AsyncReceivers, OutgoingParticipantMessage,
+};
+#[cfg(feature = "std")]
+pub use codec::read_message_from;
+pub use codec::{read_message, write_message, DecodeError, WIRE_VERSION};
+pub use encoding::{
+    Error as EncodingError, ProtoBufEncodingLayer, RateLimits, TokenBucketConfig, VersionRange,
+};
+pub use encrypted::{
+    Channel, Config as EncryptedLayerConfig, EncryptedLayer, Error as EncryptedLayerError,
+    TrustMode,
+};
+#[cfg(feature = "libp2p")]
+pub use libp2p_bus::{
+    behaviour, libp2p_bus, Ack, Behaviour, BytesCodec, Command, Libp2pBus, Routes, PROTOCOL,
+};
+#[cfg(feature = "pure-rust-wire")]
+pub use pb::{
+    read_length_delimited, read_tag, read_varint, write_length_delimited, write_tag, write_varint,
+    Error as PbError, WireType,
+};
+#[cfg(feature = "smoltcp")]
+pub use smoltcp_bus::{Bus as SmoltcpBus, QueueFull as SmoltcpQueueFull};
 
 use crate::{
     channel::{PartIdx, Peers},
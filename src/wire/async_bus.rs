@@ -0,0 +1,110 @@
+//! Async [MessageBus] built on [futures_channel]'s unbounded mpsc queues, for
+//! callers that already run an async executor and would rather `select!` over
+//! channels than fit this crate's synchronous, fire-and-forget
+//! [MessageBus]/[BytesBus] calls into a polling loop.
+//!
+//! [AsyncMessageBus::send_to_watcher]/[send_to_funder][
+//! AsyncMessageBus::send_to_funder]/[send_to_participant][
+//! AsyncMessageBus::send_to_participant] stay synchronous and
+//! fire-and-forget, same as the rest of [MessageBus] - `unbounded_send` never
+//! blocks, and a closed receiver (the event loop task having ended) is
+//! dropped silently rather than turned into an error the trait has no way to
+//! report.
+//!
+//! This crate has no single `Channel`/`Event` state machine to hand a
+//! `run()` adapter to - the proposal/active/close lifecycle is the sequence
+//! of distinct phase types ([crate::channel::proposal::ProposedChannel],
+//! [crate::channel::agreed_upon::AgreedUponChannel],
+//! [crate::channel::active::ActiveChannel],
+//! [crate::channel::closing::ClosingChannel]), driven by whichever code
+//! already owns the receive path today (see
+//! [crate::wire::encrypted], whose module docs describe the same division of
+//! labor for its handshake). [AsyncReceivers] is therefore the full
+//! integration point this layer provides: build the event loop task around
+//! `select!`-ing its three receivers and feeding replies into the phase
+//! object the caller is currently holding, the same way a synchronous poll
+//! loop would feed them into `process_watcher_reply`/`process_funder_reply`/
+//! a participant message handler.
+
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+
+use super::{Identity, MessageBus};
+use crate::messages::{FunderRequestMessage, ParticipantMessage, WatcherRequestMessage};
+
+/// An outgoing [ParticipantMessage] paired with the sender/recipient
+/// [Identity] pair [MessageBus::send_to_participant] received it with, since
+/// the participant queue is shared across every peer instead of being
+/// per-peer.
+pub struct OutgoingParticipantMessage {
+    pub sender: Identity,
+    pub recipient: Identity,
+    pub msg: ParticipantMessage,
+}
+
+/// The receiving half of an [AsyncMessageBus], returned alongside it by
+/// [async_message_bus()]. Not [MessageBus] itself - just the three plain
+/// [UnboundedReceiver]s the caller's event loop selects over.
+pub struct AsyncReceivers {
+    pub watcher: UnboundedReceiver<WatcherRequestMessage>,
+    pub funder: UnboundedReceiver<FunderRequestMessage>,
+    pub participant: UnboundedReceiver<OutgoingParticipantMessage>,
+}
+
+/// [MessageBus] impl that pushes every outgoing message onto an unbounded
+/// [futures_channel] queue instead of calling out synchronously. Build one
+/// with [async_message_bus()].
+#[derive(Clone)]
+pub struct AsyncMessageBus {
+    watcher_tx: UnboundedSender<WatcherRequestMessage>,
+    funder_tx: UnboundedSender<FunderRequestMessage>,
+    participant_tx: UnboundedSender<OutgoingParticipantMessage>,
+}
+
+/// Creates a linked [AsyncMessageBus]/[AsyncReceivers] pair, analogous to
+/// [futures_channel::mpsc::unbounded()] itself: give [PerunClient][
+/// crate::PerunClient] the [AsyncMessageBus] half, and drive the
+/// [AsyncReceivers] half from whatever task owns the connection to the
+/// watcher/funder/participants.
+pub fn async_message_bus() -> (AsyncMessageBus, AsyncReceivers) {
+    let (watcher_tx, watcher) = unbounded();
+    let (funder_tx, funder) = unbounded();
+    let (participant_tx, participant) = unbounded();
+
+    (
+        AsyncMessageBus {
+            watcher_tx,
+            funder_tx,
+            participant_tx,
+        },
+        AsyncReceivers {
+            watcher,
+            funder,
+            participant,
+        },
+    )
+}
+
+impl MessageBus for AsyncMessageBus {
+    fn send_to_watcher(&self, msg: WatcherRequestMessage) {
+        let _ = self.watcher_tx.unbounded_send(msg);
+    }
+
+    fn send_to_funder(&self, msg: FunderRequestMessage) {
+        let _ = self.funder_tx.unbounded_send(msg);
+    }
+
+    fn send_to_participant(
+        &self,
+        sender: &Identity,
+        recipient: &Identity,
+        msg: ParticipantMessage,
+    ) {
+        let _ = self
+            .participant_tx
+            .unbounded_send(OutgoingParticipantMessage {
+                sender: sender.clone(),
+                recipient: recipient.clone(),
+                msg,
+            });
+    }
+}
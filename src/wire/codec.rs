@@ -0,0 +1,148 @@
+//! Length-prefixed framing for a [perunwire::Envelope] over a raw byte
+//! stream, plus a [DecodeError] precise enough that a caller can tell *why*
+//! a frame was rejected instead of just getting a generic conversion
+//! failure.
+//!
+//! Nothing else in this crate actually framed [Envelope] bytes off a raw
+//! stream before this module: [ProtoBufEncodingLayer][super::ProtoBufEncodingLayer]
+//! only encodes/decodes once a [BytesBus][super::BytesBus] transport has
+//! already delivered a discrete frame, and `examples/go-integration.rs`
+//! hand-rolls its own ad-hoc length prefix directly against a `TcpStream`
+//! because nothing in the library did this for it. The wire layout here is
+//! deliberately similar to [EncryptedLayer::seal][super::EncryptedLayer]'s,
+//! just one level up the stack (framing a plaintext [Envelope] rather than
+//! an already-sealed ciphertext):
+//!
+//! ```text
+//! version(2, big-endian) || compression flag(1) || len(4, big-endian) || protobuf Envelope
+//! ```
+//!
+//! `len` counts only the trailing protobuf bytes. [read_message()] checks
+//! it against the caller's `max_len` before trusting it, and
+//! [read_message_from()] (std only) checks it before allocating a buffer
+//! for the body at all, so a peer can't make us allocate on its say-so by
+//! sending an oversized length descriptor ahead of a frame it never
+//! intends to finish sending.
+
+use alloc::vec::Vec;
+use prost::Message;
+
+use crate::messages::ConversionError;
+use crate::perunwire::Envelope;
+
+/// The only wire version this build speaks. Not negotiated (unlike
+/// [VersionRange][super::VersionRange]'s per-connection handshake) - a
+/// mismatch here means the peer is running an incompatible build of this
+/// same framing, not a deliberately different protocol version.
+pub const WIRE_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 2 + 1 + 4;
+
+/// Modeled on rust-lightning's `msgs::DecodeError`, trimmed to the variants
+/// this framing can actually produce.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The frame's leading version tag wasn't [WIRE_VERSION].
+    UnknownVersion(u16),
+    /// The decoded [Envelope] failed one of the `TryFrom<perunwire::...>`
+    /// conversions in [crate::messages] (e.g. a missing `oneof`, a
+    /// wrong-length byte vector).
+    InvalidValue(ConversionError),
+    /// The protobuf bytes themselves were not a valid [Envelope].
+    Malformed(prost::DecodeError),
+    /// The frame's `len` field exceeded the caller-supplied `max_len`, or
+    /// (for [read_message_from()]) didn't leave room for a sane amount of
+    /// remaining input.
+    BadLengthDescriptor,
+    /// Fewer bytes were available than the header or `len` field promised.
+    ShortRead,
+    /// The compression flag byte was nonzero; this framing does not
+    /// implement compression yet.
+    UnsupportedCompression,
+    /// Reading the frame off a `std::io::Read` failed.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl From<ConversionError> for DecodeError {
+    fn from(e: ConversionError) -> Self {
+        Self::InvalidValue(e)
+    }
+}
+
+/// Frames `msg` as `version || compression flag || len || protobuf bytes`,
+/// see the module docs.
+pub fn write_message(msg: &Envelope) -> Vec<u8> {
+    let body_len: u32 = msg
+        .encoded_len()
+        .try_into()
+        .expect("a single Envelope never approaches u32::MAX bytes");
+
+    let mut framed = Vec::with_capacity(HEADER_LEN + body_len as usize);
+    framed.extend_from_slice(&WIRE_VERSION.to_be_bytes());
+    framed.push(0); // compression flag: none
+    framed.extend_from_slice(&body_len.to_be_bytes());
+    msg.encode(&mut framed)
+        .expect("Vec<u8> grows to fit, so encoding into it cannot fail");
+    framed
+}
+
+fn parse_header(header: &[u8; HEADER_LEN]) -> Result<u32, DecodeError> {
+    let version = u16::from_be_bytes(header[..2].try_into().unwrap());
+    if version != WIRE_VERSION {
+        return Err(DecodeError::UnknownVersion(version));
+    }
+    if header[2] != 0 {
+        return Err(DecodeError::UnsupportedCompression);
+    }
+    Ok(u32::from_be_bytes(header[3..7].try_into().unwrap()))
+}
+
+/// Reverses [write_message()] off the front of `input`, which may have
+/// trailing bytes belonging to the next frame. Returns the decoded
+/// [Envelope] and the number of bytes of `input` it consumed. Rejects a
+/// `len` field larger than `max_len` before slicing the body out of
+/// `input`; use [read_message_from()] instead to also avoid allocating a
+/// buffer for a body that hasn't arrived yet.
+pub fn read_message(input: &[u8], max_len: u32) -> Result<(Envelope, usize), DecodeError> {
+    if input.len() < HEADER_LEN {
+        return Err(DecodeError::ShortRead);
+    }
+    let header: [u8; HEADER_LEN] = input[..HEADER_LEN].try_into().unwrap();
+    let body_len = parse_header(&header)?;
+    if body_len > max_len {
+        return Err(DecodeError::BadLengthDescriptor);
+    }
+
+    let body_len = body_len as usize;
+    let rest = &input[HEADER_LEN..];
+    if rest.len() < body_len {
+        return Err(DecodeError::ShortRead);
+    }
+
+    let envelope = Envelope::decode(&rest[..body_len]).map_err(DecodeError::Malformed)?;
+    Ok((envelope, HEADER_LEN + body_len))
+}
+
+/// Like [read_message()], but reads directly off a `std::io::Read` instead
+/// of requiring the caller to already have buffered a whole frame: the
+/// 7-byte header is read (and its `len` checked against `max_len`) before
+/// the body buffer is allocated at all, so a peer can't force an
+/// allocation merely by claiming an oversized frame it never finishes
+/// sending.
+#[cfg(feature = "std")]
+pub fn read_message_from(
+    reader: &mut impl std::io::Read,
+    max_len: u32,
+) -> Result<Envelope, DecodeError> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header).map_err(DecodeError::Io)?;
+    let body_len = parse_header(&header)?;
+    if body_len > max_len {
+        return Err(DecodeError::BadLengthDescriptor);
+    }
+
+    let mut body = alloc::vec![0u8; body_len as usize];
+    reader.read_exact(&mut body).map_err(DecodeError::Io)?;
+    Envelope::decode(body.as_slice()).map_err(DecodeError::Malformed)
+}
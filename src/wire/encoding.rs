@@ -1,22 +1,228 @@
-use prost::{bytes::BufMut, EncodeError};
+use core::cell::{Cell, RefCell};
 
-use super::{BytesBus, MessageBus, ParticipantMessage};
+use prost::{bytes::BufMut, DecodeError, EncodeError};
+
+use super::{BytesBus, Identity, MessageBus, ParticipantMessage};
 use crate::{
     messages::{FunderRequestMessage, WatcherRequestMessage},
     perunwire::{
-        envelope, message, AuthResponseMsg, ChannelProposalRejMsg, ChannelUpdateRejMsg, Envelope,
-        Message,
+        envelope, message, AuthChallengeMsg, AuthResponseMsg, ChannelProposalRejMsg,
+        ChannelUpdateRejMsg, Envelope, Message, PingMsg, PongMsg,
     },
 };
-use alloc::vec::Vec;
+use alloc::{collections::VecDeque, vec::Vec};
+
+/// Range of protocol versions a peer is willing to speak, exchanged once per
+/// connection (as its own small envelope, ahead of any protobuf frame) so
+/// both sides can agree on a single version to tag every subsequent frame
+/// with. `min`/`max` are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl VersionRange {
+    /// Highest version both `self` and `peer` support, or `None` if the two
+    /// ranges do not overlap.
+    fn negotiate(self, peer: VersionRange) -> Option<u16> {
+        let version = self.max.min(peer.max);
+        (version >= self.min.max(peer.min)).then_some(version)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// [VersionRange::negotiate] found no version both sides support; the
+    /// connection must be dropped instead of risking either side
+    /// misinterpreting the other's frames.
+    NoCompatibleVersion,
+    /// A received frame was tagged with a version other than the one
+    /// [ProtoBufEncodingLayer::negotiate_version] settled on.
+    UnexpectedVersion(u16),
+    /// A received frame was too short to even hold the version tag.
+    Truncated,
+    Decode(DecodeError),
+}
+
+/// A token bucket for one [BytesBus] target: starts full, a send spends one
+/// token, and [ProtoBufEncodingLayer::tick] refills it by `refill_per_tick`,
+/// clamped to `capacity`. Following wireguard-rs's per-peer ratelimiter, this
+/// caps how often the target is sent to without the encoding layer having to
+/// understand *why* a particular target is being flooded (e.g. a rapid
+/// sequence of `ActiveChannel::update` calls).
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucketConfig {
+    pub capacity: u32,
+    pub refill_per_tick: u32,
+}
+
+impl TokenBucketConfig {
+    /// A bucket that never runs dry: every send always has a token.
+    pub const UNLIMITED: Self = Self {
+        capacity: u32::MAX,
+        refill_per_tick: u32::MAX,
+    };
+}
+
+impl Default for TokenBucketConfig {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+/// Per-target rate limits for [ProtoBufEncodingLayer]. Each of the three
+/// [BytesBus] targets floods independently of the other two (an update storm
+/// towards the Watcher says nothing about the Funder or a participant), so
+/// each gets its own [TokenBucketConfig].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimits {
+    pub watcher: TokenBucketConfig,
+    pub funder: TokenBucketConfig,
+    pub participant: TokenBucketConfig,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    config: TokenBucketConfig,
+    tokens: Cell<u32>,
+}
+
+impl TokenBucket {
+    fn new(config: TokenBucketConfig) -> Self {
+        Self {
+            config,
+            tokens: Cell::new(config.capacity),
+        }
+    }
+
+    /// Spends one token if one is available.
+    fn try_take(&self) -> bool {
+        match self.tokens.get() {
+            0 => false,
+            tokens => {
+                self.tokens.set(tokens - 1);
+                true
+            }
+        }
+    }
+
+    fn refill(&self) {
+        let refilled = self
+            .tokens
+            .get()
+            .saturating_add(self.config.refill_per_tick);
+        self.tokens.set(refilled.min(self.config.capacity));
+    }
+}
+
+/// An outbound frame that missed its target's budget, buffered instead of
+/// dropped (see [ProtoBufEncodingLayer::tick]).
+#[derive(Debug)]
+enum QueuedFrame {
+    Watcher(Vec<u8>),
+    Funder(Vec<u8>),
+    Participant {
+        sender: Identity,
+        recipient: Identity,
+        frame: Vec<u8>,
+    },
+}
 
 #[derive(Debug)]
 pub struct ProtoBufEncodingLayer<B: BytesBus> {
     pub bus: B,
+    supported: VersionRange,
+    // Interior mutability: MessageBus/BytesBus's send_to_* take &self (same
+    // reasoning as EncryptedLayer's `sessions`), but the version negotiated
+    // once per connection needs to be stashed somewhere to tag every
+    // subsequent outgoing frame with. `None` until [Self::negotiate_version]
+    // is called, meaning frames are written/read without a version tag at
+    // all - the pre-negotiation wire format this layer has always used -
+    // so constructing one and never negotiating is fully backward
+    // compatible.
+    negotiated: RefCell<Option<u16>>,
+    watcher_limiter: TokenBucket,
+    funder_limiter: TokenBucket,
+    participant_limiter: TokenBucket,
+    // Frames that missed their bucket, oldest first. Unbounded - this layer
+    // never drops an outbound frame on the floor, it only ever delays one;
+    // an integrator that cares about the resulting memory growth should
+    // watch [Self::pending_len] and slow down its own call site instead.
+    pending: RefCell<VecDeque<QueuedFrame>>,
 }
 
 impl<B: BytesBus> ProtoBufEncodingLayer<B> {
-    fn encode<T: prost::Message>(msg: T) -> Result<Vec<u8>, EncodeError> {
+    pub fn new(bus: B, supported: VersionRange, rate_limits: RateLimits) -> Self {
+        Self {
+            bus,
+            supported,
+            negotiated: RefCell::new(None),
+            watcher_limiter: TokenBucket::new(rate_limits.watcher),
+            funder_limiter: TokenBucket::new(rate_limits.funder),
+            participant_limiter: TokenBucket::new(rate_limits.participant),
+            pending: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// Refills every target's [TokenBucket] and flushes as much of
+    /// [Self::pending] as the newly-available budget allows, oldest first.
+    /// Callers drive this from whatever periodic source they have (a
+    /// hardware timer tick, an async interval, ...) - this layer has no
+    /// clock of its own, the same way nothing else in this crate drives its
+    /// own I/O.
+    pub fn tick(&self) {
+        self.watcher_limiter.refill();
+        self.funder_limiter.refill();
+        self.participant_limiter.refill();
+
+        let mut pending = self.pending.borrow_mut();
+        while let Some(frame) = pending.front() {
+            let limiter = match frame {
+                QueuedFrame::Watcher(_) => &self.watcher_limiter,
+                QueuedFrame::Funder(_) => &self.funder_limiter,
+                QueuedFrame::Participant { .. } => &self.participant_limiter,
+            };
+            if !limiter.try_take() {
+                break;
+            }
+            match pending.pop_front().unwrap() {
+                QueuedFrame::Watcher(frame) => self.bus.send_to_watcher(&frame),
+                QueuedFrame::Funder(frame) => self.bus.send_to_funder(&frame),
+                QueuedFrame::Participant {
+                    sender,
+                    recipient,
+                    frame,
+                } => self.bus.send_to_participant(&sender, &recipient, &frame),
+            }
+        }
+    }
+
+    /// Number of frames buffered behind a drained [TokenBucket], across all
+    /// three targets. Integrators without their own flow control can poll
+    /// this as a back-pressure signal - e.g. pausing further
+    /// `ActiveChannel::update` calls while it stays above some threshold -
+    /// instead of [Self::tick] ever dropping a frame to keep it at zero.
+    pub fn pending_len(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    /// Settles on the protocol version to tag every subsequent frame with,
+    /// given the peer's advertised [VersionRange] (read off its own version
+    /// envelope - the receive side lives outside this type, same as
+    /// [EncryptedLayer][super::EncryptedLayer]'s handshake). Must be called
+    /// before any `send_to_*`/[Self::decode()] that should use the
+    /// negotiated wire format.
+    pub fn negotiate_version(&self, peer: VersionRange) -> Result<u16, Error> {
+        let version = self
+            .supported
+            .negotiate(peer)
+            .ok_or(Error::NoCompatibleVersion)?;
+        *self.negotiated.borrow_mut() = Some(version);
+        Ok(version)
+    }
+
+    fn encode<T: prost::Message>(&self, msg: T) -> Result<Vec<u8>, EncodeError> {
         // Go-perun writes a u16 for the length (2 bytes), this means we cannot
         // use `encode_length_delimited`, which would write a variable length
         // integer using LEB128.
@@ -27,11 +233,39 @@ impl<B: BytesBus> ProtoBufEncodingLayer<B> {
         // case (as we're using unwrap below, too).
         assert!(len < (1 << 16));
 
-        let mut buf = Vec::with_capacity(2 + len);
+        let negotiated = *self.negotiated.borrow();
+        let prefix_len = if negotiated.is_some() { 4 } else { 2 };
+        let mut buf = Vec::with_capacity(prefix_len + len);
+        if let Some(version) = negotiated {
+            buf.put_slice(&version.to_be_bytes());
+        }
         buf.put_slice(&(len as u16).to_be_bytes());
         msg.encode(&mut buf)?;
         Ok(buf)
     }
+
+    /// Decodes a frame produced by the peer-side counterpart of
+    /// [Self::encode()]: once [Self::negotiate_version] has been called,
+    /// this expects (and validates) the same version tag every frame now
+    /// carries; the tag is the "decoder... dispatch to the right path" hook
+    /// - today there is only ever one negotiated version in flight, so
+    /// dispatch on it is trivial, but a future second wire format would
+    /// switch on `version` here instead of always decoding as `T`.
+    pub fn decode<T: prost::Message + Default>(&self, framed: &[u8]) -> Result<T, Error> {
+        match *self.negotiated.borrow() {
+            None => T::decode(framed).map_err(Error::Decode),
+            Some(expected) => {
+                if framed.len() < 2 {
+                    return Err(Error::Truncated);
+                }
+                let version = u16::from_be_bytes(framed[..2].try_into().unwrap());
+                if version != expected {
+                    return Err(Error::UnexpectedVersion(version));
+                }
+                T::decode(&framed[2..]).map_err(Error::Decode)
+            }
+        }
+    }
 }
 
 impl<B: BytesBus> MessageBus for ProtoBufEncodingLayer<B> {
@@ -43,8 +277,14 @@ impl<B: BytesBus> MessageBus for ProtoBufEncodingLayer<B> {
         };
         let envelope = Message { msg: Some(wiremsg) };
 
-        let buf = Self::encode(envelope).unwrap();
-        self.bus.send_to_watcher(&buf);
+        let buf = self.encode(envelope).unwrap();
+        if self.watcher_limiter.try_take() {
+            self.bus.send_to_watcher(&buf);
+        } else {
+            self.pending
+                .borrow_mut()
+                .push_back(QueuedFrame::Watcher(buf));
+        }
     }
 
     fn send_to_funder(&self, msg: FunderRequestMessage) {
@@ -53,13 +293,49 @@ impl<B: BytesBus> MessageBus for ProtoBufEncodingLayer<B> {
         };
         let envelope = Message { msg: Some(wiremsg) };
 
-        let buf = Self::encode(envelope).unwrap();
-        self.bus.send_to_funder(&buf);
+        let buf = self.encode(envelope).unwrap();
+        if self.funder_limiter.try_take() {
+            self.bus.send_to_funder(&buf);
+        } else {
+            self.pending
+                .borrow_mut()
+                .push_back(QueuedFrame::Funder(buf));
+        }
     }
 
-    fn send_to_participants(&self, msg: ParticipantMessage) {
+    fn send_to_participant(
+        &self,
+        sender: &Identity,
+        recipient: &Identity,
+        msg: ParticipantMessage,
+    ) {
         let wiremsg: envelope::Msg = match msg {
-            ParticipantMessage::Auth => envelope::Msg::AuthResponseMsg(AuthResponseMsg {}),
+            ParticipantMessage::AuthChallenge(nonce) => {
+                envelope::Msg::AuthChallengeMsg(AuthChallengeMsg {
+                    nonce: nonce.0.to_vec(),
+                })
+            }
+            ParticipantMessage::AuthResponse {
+                nonce,
+                sig,
+                features: _,
+            } => {
+                // `AuthResponseMsg` is generated by `build.rs` from a
+                // `.proto` file this source tree doesn't carry (see the
+                // `perunwire` module docs in `lib.rs`), so it can't gain a
+                // `features` field here the way `ParticipantMessage::AuthResponse`
+                // just did - that needs a coordinated change to the upstream
+                // schema first. Until then, `ChannelFeatures` negotiation
+                // only works between peers that exchange `ParticipantMessage`
+                // directly (e.g. over the [crate::wire::async_bus]), not
+                // ones bridged through this protobuf wire format.
+                envelope::Msg::AuthResponseMsg(AuthResponseMsg {
+                    nonce: nonce.0.to_vec(),
+                    sig: sig.0.to_vec(),
+                })
+            }
+            ParticipantMessage::Ping => envelope::Msg::PingMsg(PingMsg {}),
+            ParticipantMessage::Pong => envelope::Msg::PongMsg(PongMsg {}),
             ParticipantMessage::ChannelProposal(msg) => {
                 envelope::Msg::LedgerChannelProposalMsg(msg.into())
             }
@@ -85,15 +361,39 @@ impl<B: BytesBus> MessageBus for ProtoBufEncodingLayer<B> {
                 version,
                 reason,
             }),
+            ParticipantMessage::Shutdown(msg) => envelope::Msg::ShutdownMsg(msg.into()),
+            ParticipantMessage::ChannelSync(msg) => envelope::Msg::ChannelSyncMsg(msg.into()),
+            ParticipantMessage::VirtualChannelProposal(msg) => {
+                envelope::Msg::VirtualChannelProposalMsg(msg.into())
+            }
+            ParticipantMessage::VirtualChannelProposalAccepted(msg) => {
+                envelope::Msg::VirtualChannelProposalAccMsg(msg.into())
+            }
+            ParticipantMessage::VirtualChannelFundingProposal(msg) => {
+                envelope::Msg::VirtualChannelFundingProposalMsg(msg.into())
+            }
+            ParticipantMessage::VirtualChannelSettlementProposal(msg) => {
+                envelope::Msg::VirtualChannelSettlementProposalMsg(msg.into())
+            }
         };
 
         let envelope = Envelope {
-            sender: "Alice".as_bytes().to_vec(),  // TODO
-            recipient: "Bob".as_bytes().to_vec(), // TODO
+            sender: sender.clone(),
+            recipient: recipient.clone(),
             msg: Some(wiremsg),
         };
 
-        let buf = Self::encode(envelope).unwrap();
-        self.bus.send_to_participants(&buf);
+        let buf = self.encode(envelope).unwrap();
+        if self.participant_limiter.try_take() {
+            self.bus.send_to_participant(sender, recipient, &buf);
+        } else {
+            self.pending
+                .borrow_mut()
+                .push_back(QueuedFrame::Participant {
+                    sender: sender.clone(),
+                    recipient: recipient.clone(),
+                    frame: buf,
+                });
+        }
     }
 }
@@ -0,0 +1,436 @@
+//! Authenticated, encrypted transport layer wrapping a [BytesBus] so Perun
+//! frames are confidential and tamper-evident instead of plaintext protobuf
+//! over TCP. This sits *below* [ProtoBufEncodingLayer][super::ProtoBufEncodingLayer]
+//! in the stack (it seals raw frame bytes, not the decoded message types), so
+//! the usual composition is:
+//!
+//! ```ignore
+//! let bus = ProtoBufEncodingLayer { bus: EncryptedLayer::new(raw_bus, config) };
+//! let client = PerunClient::new(bus, signer);
+//! ```
+//!
+//! [BytesBus] only has `send_to_*` methods, the receive side lives outside of
+//! it (decoded straight off the socket, e.g. in `try_recv`). This layer can
+//! therefore only originate the handshake on send; completing it needs the
+//! bytes the peer sent back, which is why [Self::begin_handshake()] and
+//! [Self::complete_handshake()] are exposed separately instead of being
+//! driven internally - whoever owns the receive path (the poll loop) is
+//! responsible for wiring the two together per logical channel
+//! ([Channel::Watcher]/[Channel::Funder]/[Channel::Participant]).
+//!
+//! Loosely modeled on a Noise-style handshake: each node has a static X25519
+//! keypair plus a way to decide which peer public keys to trust ([TrustMode]).
+//! After an ephemeral-static ECDH handshake the transport derives a pair of
+//! directional [ChaCha20Poly1305] keys from the handshake transcript. Frames
+//! carry an explicit 64-bit counter as the AEAD nonce (instead of requiring
+//! strict ordering) and are accepted within a sliding replay window, since the
+//! embedded socket path can reorder/drop. Sessions automatically rekey after
+//! [Config::rekey_after_messages] frames (by the caller re-running the
+//! handshake - this layer only reports that it's due via [Self::needs_rekey]).
+//!
+//! Every sealed frame is also prefixed with its own big-endian `u32` length,
+//! ahead of the counter and ciphertext - see [Self::seal()] for why a
+//! message-oriented [BytesBus] still needs that.
+
+use alloc::vec::Vec;
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use core::cell::RefCell;
+use sha3::{Digest, Sha3_256};
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey, StaticSecret};
+
+use super::BytesBus;
+
+/// How a node decides which static public key to trust for its peer.
+pub enum TrustMode {
+    /// Derive this node's static keypair from a secret shared with the peer
+    /// out-of-band (e.g. provisioned at manufacturing time). Both sides
+    /// derive the same static key, so there is nothing further to trust.
+    SharedSecret([u8; 32]),
+    /// Use an explicit static keypair and trust a configured peer public key
+    /// directly instead of deriving it.
+    ExplicitTrust {
+        static_secret: StaticSecret,
+        trusted_peer: X25519PublicKey,
+    },
+}
+
+pub struct Config {
+    pub trust: TrustMode,
+    /// Rekey (run a fresh handshake) after this many sealed messages.
+    pub rekey_after_messages: u64,
+    /// How many counter values behind the newest seen one are still
+    /// accepted, to tolerate out-of-order delivery.
+    pub replay_window: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            trust: TrustMode::SharedSecret([0; 32]),
+            rekey_after_messages: 1 << 20,
+            replay_window: 64,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    HandshakeNotComplete,
+    Decryption,
+    ReplayedOrTooOld,
+    /// The frame's own embedded length field didn't match the number of
+    /// bytes actually handed to [EncryptedLayer::open()] - see
+    /// [EncryptedLayer::seal()]'s doc comment for why that field exists.
+    Framing,
+}
+
+/// Which logical peer a session is with, i.e. the three destinations
+/// [BytesBus] can send to. Each gets its own independent handshake/session
+/// since they are separate TCP connections in practice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Watcher,
+    Funder,
+    Participant,
+}
+
+fn static_secret_for(trust: &TrustMode) -> StaticSecret {
+    match trust {
+        TrustMode::SharedSecret(secret) => {
+            // Derive a static X25519 key deterministically from the shared
+            // secret, so both sides (holding the same secret) arrive at the
+            // same keypair and thus the same trusted peer public key.
+            let mut hasher = Sha3_256::new();
+            hasher.update(b"perun-encrypted-layer-static-key");
+            hasher.update(secret);
+            let derived: [u8; 32] = hasher.finalize().into();
+            StaticSecret::from(derived)
+        }
+        TrustMode::ExplicitTrust { static_secret, .. } => static_secret.clone(),
+    }
+}
+
+fn trusted_peer_for(trust: &TrustMode, own_static_public: &X25519PublicKey) -> X25519PublicKey {
+    match trust {
+        // Both sides derive the same static keypair from the shared secret,
+        // so the peer's public key is simply our own.
+        TrustMode::SharedSecret(_) => *own_static_public,
+        TrustMode::ExplicitTrust { trusted_peer, .. } => *trusted_peer,
+    }
+}
+
+struct Session {
+    send_key: ChaCha20Poly1305,
+    recv_key: ChaCha20Poly1305,
+    send_counter: u64,
+    /// Highest counter accepted so far; together with `replay_seen` forms a
+    /// sliding window of the last 64 counters.
+    recv_highest: u64,
+    replay_seen: u64,
+    messages_since_handshake: u64,
+}
+
+fn nonce_from_counter(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::clone_from_slice(&nonce)
+}
+
+/// Derive the two directional AEAD keys from the handshake transcript. Both
+/// sides compute the same pair, just swapping which one is "ours" based on
+/// the lexicographic order of the two static public keys, so the party with
+/// the lower key always sends on `key_a`/receives on `key_b` and vice versa.
+fn derive_session_keys(
+    transcript: &[u8; 32],
+    own_static_public: &X25519PublicKey,
+    peer_static_public: &X25519PublicKey,
+) -> (ChaCha20Poly1305, ChaCha20Poly1305) {
+    let kdf = |label: &[u8]| -> ChaCha20Poly1305 {
+        let mut hasher = Sha3_256::new();
+        hasher.update(transcript);
+        hasher.update(label);
+        let key: [u8; 32] = hasher.finalize().into();
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+    };
+
+    let key_a = kdf(b"perun-encrypted-layer-key-a");
+    let key_b = kdf(b"perun-encrypted-layer-key-b");
+
+    if own_static_public.as_bytes() < peer_static_public.as_bytes() {
+        (key_a, key_b) // we send on a, receive on b
+    } else {
+        (key_b, key_a) // we send on b, receive on a
+    }
+}
+
+/// Encrypting/authenticating [BytesBus] wrapper. See the module docs for how
+/// this is usually composed with [ProtoBufEncodingLayer][super::ProtoBufEncodingLayer]
+/// and for why the handshake is driven from the outside.
+pub struct EncryptedLayer<B: BytesBus> {
+    inner: B,
+    static_secret: StaticSecret,
+    peer_static_public: X25519PublicKey,
+    config: Config,
+    // Interior mutability: BytesBus::send_to_* take &self, matching the rest
+    // of the wire stack, but sealing/opening frames needs to advance the
+    // session's counters, and the handshake needs somewhere to stash our
+    // ephemeral secret between `begin_handshake` and `complete_handshake`.
+    pending: RefCell<[Option<EphemeralSecret>; 3]>,
+    sessions: RefCell<[Option<Session>; 3]>,
+}
+
+fn slot(channel: Channel) -> usize {
+    match channel {
+        Channel::Watcher => 0,
+        Channel::Funder => 1,
+        Channel::Participant => 2,
+    }
+}
+
+impl<B: BytesBus> EncryptedLayer<B> {
+    pub fn new(inner: B, config: Config) -> Self {
+        let static_secret = static_secret_for(&config.trust);
+        let own_static_public = X25519PublicKey::from(&static_secret);
+        let peer_static_public = trusted_peer_for(&config.trust, &own_static_public);
+
+        Self {
+            inner,
+            static_secret,
+            peer_static_public,
+            config,
+            pending: RefCell::new([None, None, None]),
+            sessions: RefCell::new([None, None, None]),
+        }
+    }
+
+    /// Start a handshake for `channel`: generates a fresh ephemeral keypair
+    /// and returns its public half to be sent to the peer over the
+    /// corresponding raw socket. Call [Self::complete_handshake()] with the
+    /// peer's response to finish it.
+    pub fn begin_handshake(&self, channel: Channel) -> [u8; 32] {
+        let ephemeral_secret = EphemeralSecret::new(rand_core::OsRng);
+        let ephemeral_public = X25519PublicKey::from(&ephemeral_secret);
+        self.pending.borrow_mut()[slot(channel)] = Some(ephemeral_secret);
+        *ephemeral_public.as_bytes()
+    }
+
+    /// Finish a handshake previously started with [Self::begin_handshake()],
+    /// given the peer's ephemeral public key, and install the resulting
+    /// session so `send_to_*`/[Self::open()] work for `channel`.
+    pub fn complete_handshake(&self, channel: Channel, peer_ephemeral_public: [u8; 32]) {
+        let ephemeral_secret = self.pending.borrow_mut()[slot(channel)]
+            .take()
+            .expect("complete_handshake called without a matching begin_handshake");
+        let peer_ephemeral_public = X25519PublicKey::from(peer_ephemeral_public);
+
+        let dh_ee = ephemeral_secret.diffie_hellman(&peer_ephemeral_public);
+        let dh_se = self.static_secret.diffie_hellman(&peer_ephemeral_public);
+        // We don't have the peer's ephemeral secret to compute the symmetric
+        // "es" term from their side, so we fold in their long-term static key
+        // from our own ephemeral secret instead - this still binds both
+        // parties' static identities into the transcript.
+        let dh_es = ephemeral_secret.diffie_hellman(&self.peer_static_public);
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(dh_ee.as_bytes());
+        hasher.update(dh_se.as_bytes());
+        hasher.update(dh_es.as_bytes());
+        let transcript: [u8; 32] = hasher.finalize().into();
+
+        let own_static_public = X25519PublicKey::from(&self.static_secret);
+        let (send_key, recv_key) =
+            derive_session_keys(&transcript, &own_static_public, &self.peer_static_public);
+
+        self.sessions.borrow_mut()[slot(channel)] = Some(Session {
+            send_key,
+            recv_key,
+            send_counter: 0,
+            recv_highest: 0,
+            replay_seen: 0,
+            messages_since_handshake: 0,
+        });
+    }
+
+    /// Whether `channel`'s session has sent/received enough messages that it
+    /// should be rekeyed (by calling [Self::begin_handshake()] again).
+    pub fn needs_rekey(&self, channel: Channel) -> bool {
+        match &self.sessions.borrow()[slot(channel)] {
+            Some(session) => session.messages_since_handshake >= self.config.rekey_after_messages,
+            None => false,
+        }
+    }
+
+    /// Seals `msg` into `len(4) || counter(8) || ciphertext`. The leading
+    /// length field is redundant with whatever framing [BytesBus::send_to_*]'s
+    /// concrete transport already does for a message-oriented channel, but at
+    /// least one such transport in this tree (`examples/go-integration.rs`'s
+    /// raw `TcpStream::write`) has none: without it, a short read or a write
+    /// split across two `send`/`write` calls on the raw socket leaves the
+    /// receiver unable to tell where one sealed frame ends and the next
+    /// begins, silently corrupting every following message instead of just
+    /// failing the one that was split. [Self::open()] checks the field
+    /// against the slice it's handed so that desync is at least detected as
+    /// [Error::Framing] instead of a confusing AEAD failure further in.
+    fn seal(&self, channel: Channel, msg: &[u8]) -> Vec<u8> {
+        let mut sessions = self.sessions.borrow_mut();
+        let session = sessions[slot(channel)]
+            .as_mut()
+            .expect("send_to_* called before completing the handshake for this channel");
+
+        let counter = session.send_counter;
+        session.send_counter += 1;
+        session.messages_since_handshake += 1;
+
+        let nonce = nonce_from_counter(counter);
+        let ciphertext = session
+            .send_key
+            .encrypt(&nonce, msg)
+            .expect("ChaCha20Poly1305 encryption over a bounded buffer cannot fail");
+
+        let body_len: u32 = (8 + ciphertext.len())
+            .try_into()
+            .expect("a single sealed frame never approaches u32::MAX bytes");
+
+        let mut framed = Vec::with_capacity(4 + 8 + ciphertext.len());
+        framed.extend_from_slice(&body_len.to_be_bytes());
+        framed.extend_from_slice(&counter.to_be_bytes());
+        framed.extend_from_slice(&ciphertext);
+        framed
+    }
+
+    /// Open a received frame for `channel`. Called by whoever owns the
+    /// receive path instead of through [BytesBus], since that trait is
+    /// send-only.
+    pub fn open(&self, channel: Channel, framed: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut sessions = self.sessions.borrow_mut();
+        let session = sessions[slot(channel)]
+            .as_mut()
+            .ok_or(Error::HandshakeNotComplete)?;
+
+        if framed.len() < 4 {
+            return Err(Error::Framing);
+        }
+        let body_len = u32::from_be_bytes(framed[..4].try_into().unwrap()) as usize;
+        let framed = &framed[4..];
+        if framed.len() != body_len {
+            return Err(Error::Framing);
+        }
+
+        if framed.len() < 8 {
+            return Err(Error::Decryption);
+        }
+        let counter = u64::from_be_bytes(framed[..8].try_into().unwrap());
+
+        // Sliding replay window: accept anything newer than the highest seen
+        // counter, or anything within `replay_window` of it that hasn't been
+        // seen yet.
+        if counter > session.recv_highest {
+            let shift = counter - session.recv_highest;
+            session.replay_seen = if shift >= 64 {
+                0
+            } else {
+                session.replay_seen << shift
+            };
+            // Bit 0 tracks `recv_highest` itself - set it so this counter
+            // can't be replayed even once before a newer one shifts the
+            // window further (previously only a second replay was caught,
+            // since the `else` branch's `age == 0` check found bit 0 unset).
+            session.replay_seen |= 1;
+            session.recv_highest = counter;
+        } else {
+            let age = session.recv_highest - counter;
+            if age >= self.config.replay_window || age >= 64 {
+                return Err(Error::ReplayedOrTooOld);
+            }
+            if session.replay_seen & (1 << age) != 0 {
+                return Err(Error::ReplayedOrTooOld);
+            }
+            session.replay_seen |= 1 << age;
+        }
+
+        let nonce = nonce_from_counter(counter);
+        session
+            .recv_key
+            .decrypt(&nonce, &framed[8..])
+            .map_err(|_| Error::Decryption)
+    }
+}
+
+impl<B: BytesBus> BytesBus for EncryptedLayer<B> {
+    fn send_to_watcher(&self, msg: &[u8]) {
+        let framed = self.seal(Channel::Watcher, msg);
+        self.inner.send_to_watcher(&framed);
+    }
+
+    fn send_to_funder(&self, msg: &[u8]) {
+        let framed = self.seal(Channel::Funder, msg);
+        self.inner.send_to_funder(&framed);
+    }
+
+    fn send_to_participant(
+        &self,
+        sender: &super::Identity,
+        recipient: &super::Identity,
+        msg: &[u8],
+    ) {
+        let framed = self.seal(Channel::Participant, msg);
+        self.inner.send_to_participant(sender, recipient, &framed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Never actually used to send anything - the tests below seal/open
+    /// frames directly, bypassing [BytesBus], so this only exists to satisfy
+    /// [EncryptedLayer]'s `B: BytesBus` bound.
+    struct NullBus;
+
+    impl BytesBus for NullBus {
+        fn send_to_watcher(&self, _msg: &[u8]) {}
+        fn send_to_funder(&self, _msg: &[u8]) {}
+        fn send_to_participant(
+            &self,
+            _sender: &super::Identity,
+            _recipient: &super::Identity,
+            _msg: &[u8],
+        ) {
+        }
+    }
+
+    /// Builds two [EncryptedLayer]s sharing a [TrustMode::SharedSecret] (so
+    /// they trust each other automatically) and runs a handshake between
+    /// them on `channel`, leaving both sides with an established session.
+    fn handshake_pair(channel: Channel) -> (EncryptedLayer<NullBus>, EncryptedLayer<NullBus>) {
+        let config = || Config {
+            trust: TrustMode::SharedSecret([0x42; 32]),
+            ..Config::default()
+        };
+        let a = EncryptedLayer::new(NullBus, config());
+        let b = EncryptedLayer::new(NullBus, config());
+
+        let eph_a = a.begin_handshake(channel);
+        let eph_b = b.begin_handshake(channel);
+        a.complete_handshake(channel, eph_b);
+        b.complete_handshake(channel, eph_a);
+
+        (a, b)
+    }
+
+    #[test]
+    fn replayed_frame_is_rejected() {
+        let (a, b) = handshake_pair(Channel::Watcher);
+
+        let framed = a.seal(Channel::Watcher, b"hello");
+
+        assert_eq!(b.open(Channel::Watcher, &framed).unwrap(), b"hello");
+        assert!(matches!(
+            b.open(Channel::Watcher, &framed),
+            Err(Error::ReplayedOrTooOld)
+        ));
+    }
+}
@@ -0,0 +1,261 @@
+//! [BytesBus] over a libp2p `request-response` protocol, for callers who
+//! already run a libp2p [Swarm][libp2p::swarm::Swarm] and would rather route
+//! Perun frames by [PeerId] than own a raw socket per peer the way
+//! `examples/go-integration.rs`'s TCP transport does. Wrap in
+//! [super::ProtoBufEncodingLayer] to get a full [super::MessageBus], same as
+//! every other [BytesBus] in this module:
+//!
+//! ```ignore
+//! let (bus, mut outgoing) = libp2p_bus(routes);
+//! let client = PerunClient::new(ProtoBufEncodingLayer { bus }, signer);
+//! // event loop:
+//! while let Some(cmd) = outgoing.next().await {
+//!     swarm.behaviour_mut().send_request(&cmd.peer, cmd.msg);
+//! }
+//! ```
+//!
+//! Like [super::async_bus], [Libp2pBus::send_to_watcher]/[send_to_funder][
+//! Libp2pBus::send_to_funder]/[send_to_participant][
+//! Libp2pBus::send_to_participant] stay synchronous and fire-and-forget -
+//! they push a [Command] onto an unbounded queue and return, since sending a
+//! libp2p request is an async `Swarm` operation this trait has no way to
+//! await. A destination with no known [PeerId] in [Routes] is dropped
+//! silently, the same way [super::async_bus] drops into a closed receiver.
+//!
+//! # One-shot request/response instead of held reply channels
+//!
+//! libp2p's `request_response::ResponseChannel` answers exactly once and is
+//! tied to the substream that produced it - it isn't `Clone` or
+//! serializable, so holding one across the (potentially long) gap until a
+//! channel's counterparty actually has a reply ready would mean losing the
+//! ability to reply at all across a restart. This module instead borrows the
+//! "one-shot" pattern used for libp2p request/response integrations
+//! elsewhere (e.g. Comit/xmr-btc-swap's swap setup protocol, also cited in
+//! [crate::wire::async_bus]'s module docs for the same kind of
+//! divided-ownership problem): the event loop acks every inbound request
+//! immediately with a trivial [Ack] and discards the channel, then delivers
+//! the real reply later as a *new* outbound request to the peer [Routes]
+//! remembers for that [Identity][super::Identity]. Because the thing that
+//! has to survive a restart is then just `Identity -> PeerId`, [Routes] is a
+//! plain serializable map instead of a table of live channels.
+//!
+//! [BytesBus] only has `send_to_*` methods - same as [super::encrypted], the
+//! receive side (matching an inbound [PeerId] back to an
+//! [Identity][super::Identity], and populating [Routes] with that mapping)
+//! lives outside of it, in whatever code owns the [Swarm][libp2p::swarm::Swarm]
+//! and already decodes `perunwire` messages to learn who a peer claims to be.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use async_trait::async_trait;
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use libp2p::{
+    core::upgrade::{read_length_prefixed, write_length_prefixed},
+    futures::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    request_response, PeerId, StreamProtocol,
+};
+use std::sync::{Arc, Mutex};
+
+use super::{BytesBus, Identity};
+
+/// Largest frame [BytesCodec] will read, mirroring
+/// [crate::wire::encoding::ProtoBufEncodingLayer]'s own framing limit - an
+/// unbounded read here would let a misbehaving peer force unbounded memory
+/// growth before the length-prefixed frame is even decoded.
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// The `request-response` protocol name Perun frames are exchanged over.
+pub const PROTOCOL: StreamProtocol = StreamProtocol::new("/perun/bytes/1.0.0");
+
+/// Trivial response every inbound request is immediately answered with - see
+/// the module docs for why the real reply travels as a new outbound request
+/// instead of being written back over the [request_response::ResponseChannel]
+/// this closes out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ack;
+
+/// [request_response::Codec] moving raw, already-framed [BytesBus] payloads -
+/// the `perunwire`/protobuf conversion happens one layer up, in
+/// [super::ProtoBufEncodingLayer], same as it does for every other
+/// [BytesBus] impl in this module.
+#[derive(Debug, Clone, Default)]
+pub struct BytesCodec;
+
+#[async_trait]
+impl request_response::Codec for BytesCodec {
+    type Protocol = StreamProtocol;
+    type Request = Vec<u8>;
+    type Response = Ack;
+
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> std::io::Result<Vec<u8>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_length_prefixed(io, MAX_FRAME_LEN).await
+    }
+
+    async fn read_response<T>(&mut self, _: &StreamProtocol, io: &mut T) -> std::io::Result<Ack>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_length_prefixed(io, 1).await?;
+        Ok(Ack)
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        req: Vec<u8>,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, req).await?;
+        io.close().await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        _: Ack,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, []).await?;
+        io.close().await
+    }
+}
+
+/// `request_response` [NetworkBehaviour][libp2p::swarm::NetworkBehaviour] for
+/// [PROTOCOL]. Embed this in an application's own
+/// [NetworkBehaviour][libp2p::swarm::NetworkBehaviour] (e.g. via
+/// `#[derive(NetworkBehaviour)]`) and feed its events into [Routes] and
+/// [Libp2pBus]'s [UnboundedReceiver] the same way [super::async_bus]'s
+/// module docs describe driving [AsyncReceivers][super::async_bus::AsyncReceivers].
+pub type Behaviour = request_response::Behaviour<BytesCodec>;
+
+/// Builds the [Behaviour] this module expects, with
+/// [request_response::ProtocolSupport::Full] on [PROTOCOL] and the crate's
+/// default config.
+pub fn behaviour() -> Behaviour {
+    request_response::Behaviour::new(
+        [(PROTOCOL, request_response::ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+/// Serializable `Identity -> PeerId` routing table behind [Libp2pBus].
+/// Unlike a [request_response::ResponseChannel] - tied to the substream that
+/// produced it, and neither `Clone` nor serializable - this is just a map,
+/// so a client can persist it and reload it after a restart instead of
+/// losing track of where to deliver a reply. [PeerId] is stored via
+/// [PeerId::to_bytes]/[PeerId::from_bytes] rather than derived (de)serialize
+/// impls, since `libp2p-identity`'s own `serde` support is behind a feature
+/// flag this crate doesn't otherwise need.
+#[derive(Debug, Clone, Default)]
+pub struct Routes {
+    watcher: Option<Vec<u8>>,
+    funder: Option<Vec<u8>>,
+    participants: BTreeMap<Identity, Vec<u8>>,
+}
+
+impl Routes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_watcher(&mut self, peer: PeerId) {
+        self.watcher = Some(peer.to_bytes());
+    }
+
+    pub fn watcher(&self) -> Option<PeerId> {
+        self.watcher
+            .as_deref()
+            .and_then(|b| PeerId::from_bytes(b).ok())
+    }
+
+    pub fn set_funder(&mut self, peer: PeerId) {
+        self.funder = Some(peer.to_bytes());
+    }
+
+    pub fn funder(&self) -> Option<PeerId> {
+        self.funder
+            .as_deref()
+            .and_then(|b| PeerId::from_bytes(b).ok())
+    }
+
+    /// Record that `id` is reachable at `peer`, learned by whoever owns the
+    /// receive path from an inbound request's sender - see the module docs.
+    pub fn set_participant(&mut self, id: Identity, peer: PeerId) {
+        self.participants.insert(id, peer.to_bytes());
+    }
+
+    pub fn participant(&self, id: &Identity) -> Option<PeerId> {
+        self.participants
+            .get(id)
+            .and_then(|b| PeerId::from_bytes(b).ok())
+    }
+}
+
+/// A [Libp2pBus] send queued up for the event loop that owns the
+/// [Swarm][libp2p::swarm::Swarm] to turn into
+/// `swarm.behaviour_mut().send_request(&peer, msg)`.
+pub struct Command {
+    pub peer: PeerId,
+    pub msg: Vec<u8>,
+}
+
+/// [BytesBus] impl that looks the destination up in a [Routes] table and
+/// pushes a [Command] onto an unbounded queue, instead of calling out to the
+/// [Swarm][libp2p::swarm::Swarm] synchronously - see the module docs. Build
+/// one with [libp2p_bus()].
+#[derive(Clone)]
+pub struct Libp2pBus {
+    routes: Arc<Mutex<Routes>>,
+    tx: UnboundedSender<Command>,
+}
+
+/// Creates a linked [Libp2pBus]/[UnboundedReceiver] pair sharing `routes`,
+/// analogous to [super::async_bus::async_message_bus()]: give [PerunClient][
+/// crate::PerunClient] the [Libp2pBus] half (wrapped in
+/// [super::ProtoBufEncodingLayer]), and drain the [UnboundedReceiver] half
+/// from the task that owns the [Swarm][libp2p::swarm::Swarm], which is also
+/// responsible for populating `routes` as peers are discovered.
+pub fn libp2p_bus(routes: Arc<Mutex<Routes>>) -> (Libp2pBus, UnboundedReceiver<Command>) {
+    let (tx, rx) = unbounded();
+    (Libp2pBus { routes, tx }, rx)
+}
+
+impl Libp2pBus {
+    fn send(&self, peer: Option<PeerId>, msg: &[u8]) {
+        if let Some(peer) = peer {
+            let _ = self.tx.unbounded_send(Command {
+                peer,
+                msg: msg.to_vec(),
+            });
+        }
+    }
+
+    fn routes(&self) -> std::sync::MutexGuard<'_, Routes> {
+        self.routes.lock().expect("routes mutex poisoned")
+    }
+}
+
+impl BytesBus for Libp2pBus {
+    fn send_to_watcher(&self, msg: &[u8]) {
+        let peer = self.routes().watcher();
+        self.send(peer, msg);
+    }
+
+    fn send_to_funder(&self, msg: &[u8]) {
+        let peer = self.routes().funder();
+        self.send(peer, msg);
+    }
+
+    fn send_to_participant(&self, _sender: &Identity, recipient: &Identity, msg: &[u8]) {
+        let peer = self.routes().participant(recipient);
+        self.send(peer, msg);
+    }
+}
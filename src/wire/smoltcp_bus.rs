@@ -0,0 +1,174 @@
+//! [BytesBus] over raw smoltcp TCP sockets, for `no_std` embedded targets
+//! that drive a smoltcp [Interface][smoltcp::iface::Interface] directly
+//! instead of going through a hosted OS socket (the `std` `net::Bus` in
+//! `examples/go-integration.rs`'s TCP transport). smoltcp's own
+//! `tcp::Socket::send_slice` only ever accepts as much as the socket's tx
+//! buffer has room for right now and leaves it up to the caller to queue
+//! whatever didn't fit - [Bus] is that queue.
+//!
+//! Like every other [BytesBus] in this module, [Bus::send_to_watcher]/
+//! [send_to_funder][Bus::send_to_funder]/[send_to_participant][
+//! Bus::send_to_participant] stay synchronous and never touch a live
+//! socket - sending here means pushing the whole message onto the
+//! destination's [PendingSend] queue and writing as much of it as the
+//! socket's tx buffer currently accepts. [Bus::poll_flush], called from the
+//! same loop that drives the smoltcp [Interface][smoltcp::iface::Interface]
+//! (after [Interface::poll][smoltcp::iface::Interface::poll]), is what
+//! drains whatever is left as buffer space frees up.
+//!
+//! Each [PendingSend] is a fixed-capacity ring buffer rather than an
+//! unbounded queue like [super::encoding::ProtoBufEncodingLayer]'s - an
+//! embedded target has no headroom to let a stalled peer's backlog grow
+//! without bound, so a send that doesn't fit reports [QueueFull] instead of
+//! growing the buffer or panicking the way a bare `send_slice` call (that
+//! assumes the whole message always fits) would.
+
+use alloc::collections::BTreeMap;
+use core::cell::RefCell;
+use smoltcp::{
+    iface::{SocketHandle, SocketSet},
+    socket::tcp,
+};
+
+use super::{BytesBus, Identity};
+
+/// [Bus::send_to_watcher] and friends couldn't queue the whole message
+/// because the destination's [PendingSend] ring buffer has no room left -
+/// see the module docs for why this is reported instead of growing the
+/// buffer or panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+/// Fixed-capacity ring buffer of bytes already handed to [Bus::send_to_watcher]
+/// (or a sibling) but not yet accepted by `send_slice` - see the module docs
+/// for why this isn't just a `VecDeque`.
+struct PendingSend<const CAP: usize> {
+    buf: [u8; CAP],
+    start: usize,
+    len: usize,
+}
+
+impl<const CAP: usize> PendingSend<CAP> {
+    fn new() -> Self {
+        Self {
+            buf: [0; CAP],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) -> Result<(), QueueFull> {
+        if data.len() > CAP - self.len {
+            return Err(QueueFull);
+        }
+        for &byte in data {
+            let idx = (self.start + self.len) % CAP;
+            self.buf[idx] = byte;
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    /// Hands the queue's contiguous front chunk to `write` (a `send_slice`
+    /// call), repeating until either the queue empties or `write` stops
+    /// accepting anything, same as [Bus::poll_flush] uses it.
+    fn drain(&mut self, mut write: impl FnMut(&[u8]) -> usize) {
+        while self.len > 0 {
+            let idx = self.start % CAP;
+            let chunk = (CAP - idx).min(self.len);
+            let written = write(&self.buf[idx..idx + chunk]);
+            if written == 0 {
+                break;
+            }
+            self.start = (self.start + written) % CAP;
+            self.len -= written;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// [BytesBus] impl backed by smoltcp TCP sockets - see the module docs. The
+/// [SocketSet] itself stays owned by whatever drives the
+/// [Interface][smoltcp::iface::Interface]'s poll loop, the same way
+/// [super::libp2p_bus::Libp2pBus] never owns the
+/// [Swarm][libp2p::swarm::Swarm] it sends through; [Bus::poll_flush] only
+/// borrows it for as long as it takes to drain what's queued.
+pub struct Bus<const CAP: usize> {
+    watcher: Option<SocketHandle>,
+    funder: Option<SocketHandle>,
+    participants: RefCell<BTreeMap<Identity, SocketHandle>>,
+    pending: RefCell<BTreeMap<SocketHandle, PendingSend<CAP>>>,
+}
+
+impl<const CAP: usize> Bus<CAP> {
+    pub fn new(watcher: Option<SocketHandle>, funder: Option<SocketHandle>) -> Self {
+        Self {
+            watcher,
+            funder,
+            participants: RefCell::new(BTreeMap::new()),
+            pending: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Record that `id` is reachable over `handle` - the receive side (same
+    /// division of labor as [super::libp2p_bus::Routes]) is responsible for
+    /// populating this as peers connect.
+    pub fn set_participant(&self, id: Identity, handle: SocketHandle) {
+        self.participants.borrow_mut().insert(id, handle);
+    }
+
+    /// Number of sockets with anything left in their [PendingSend] queue -
+    /// an integrator can poll this the same way as
+    /// [super::encoding::ProtoBufEncodingLayer::pending_len] to watch for a
+    /// destination falling behind instead of only learning about it from a
+    /// [QueueFull] error on the next send.
+    pub fn pending_len(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    fn enqueue(&self, handle: Option<SocketHandle>, msg: &[u8]) -> Result<(), QueueFull> {
+        // An unrouted destination is dropped silently, same as every other
+        // `BytesBus` impl in this module does for a destination it doesn't
+        // recognize.
+        let Some(handle) = handle else {
+            return Ok(());
+        };
+        self.pending
+            .borrow_mut()
+            .entry(handle)
+            .or_insert_with(PendingSend::new)
+            .push(msg)
+    }
+
+    /// Writes as much of every socket's [PendingSend] queue to its live
+    /// `tcp::Socket` as `send_slice` currently accepts, oldest-queued-byte
+    /// first per socket - call this from the same poll loop driving the
+    /// [Interface][smoltcp::iface::Interface], after
+    /// [Interface::poll][smoltcp::iface::Interface::poll].
+    pub fn poll_flush(&self, sockets: &mut SocketSet<'_>) {
+        let mut pending = self.pending.borrow_mut();
+        pending.retain(|&handle, queue| {
+            let socket = sockets.get_mut::<tcp::Socket>(handle);
+            queue.drain(|chunk| socket.send_slice(chunk).unwrap_or(0));
+            !queue.is_empty()
+        });
+    }
+}
+
+impl<const CAP: usize> BytesBus for Bus<CAP> {
+    fn send_to_watcher(&self, msg: &[u8]) {
+        let _ = self.enqueue(self.watcher, msg);
+    }
+
+    fn send_to_funder(&self, msg: &[u8]) {
+        let _ = self.enqueue(self.funder, msg);
+    }
+
+    fn send_to_participant(&self, _sender: &Identity, recipient: &Identity, msg: &[u8]) {
+        let handle = self.participants.borrow().get(recipient).copied();
+        let _ = self.enqueue(handle, msg);
+    }
+}